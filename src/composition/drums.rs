@@ -0,0 +1,152 @@
+//! Drum patterns on a step grid
+//!
+//! ```
+//! use mutheors::{DrumPattern, DrumSound};
+//! let measures = DrumPattern::new(16, 4)
+//!     .grid(DrumSound::ClosedHiHat, "x.x.x.x.x.x.x.x.")
+//!     .grid(DrumSound::AcousticSnare, "....X.......X..f")
+//!     .grid(DrumSound::BassDrum, "x.....x.x.......")
+//!     .measures(4);
+//! assert_eq!(measures.len(), 1);
+//! ```
+//!
+//! Grid characters, one per step:
+//! - `x` hit, `X` accented hit, `o` ghost note, `f` flam
+//! - `.` or `-` no hit
+//! - spaces and `|` are ignored, they only help reading
+
+use crate::{DrumSound, Measure, PercussionNote};
+
+/// Velocity of an accented hit
+const ACCENT: f32 = 1.0;
+/// Velocity of a ghost note
+const GHOST: f32 = 0.3;
+
+/// Step sequencer style drum pattern
+#[derive(Debug, Clone)]
+pub struct DrumPattern {
+    steps: usize,
+    steps_per_beat: usize,
+    /// Hits with their step index
+    hits: Vec<(usize, PercussionNote)>,
+}
+
+impl DrumPattern {
+    /// Empty pattern of `steps` steps, `steps_per_beat` of them in a beat
+    pub fn new(steps: usize, steps_per_beat: usize) -> Self {
+        DrumPattern {
+            steps,
+            steps_per_beat: steps_per_beat.max(1),
+            hits: vec![],
+        }
+    }
+
+    /// Add a hit, `velocity` in [0.0, 1.0] with `0.0` for the default dynamic.
+    /// Steps outside the pattern are ignored.
+    pub fn hit(self, sound: DrumSound, step: usize, velocity: f32) -> Self {
+        self.push(step, PercussionNote::new(sound).with_velocity(velocity))
+    }
+
+    /// Add a flammed hit
+    pub fn flam(self, sound: DrumSound, step: usize, velocity: f32) -> Self {
+        self.push(
+            step,
+            PercussionNote::new(sound)
+                .with_velocity(velocity)
+                .with_flam(),
+        )
+    }
+
+    /// Add the hits of one instrument written as a grid, see the module documentation
+    pub fn grid(self, sound: DrumSound, row: &str) -> Self {
+        row.chars()
+            .filter(|c| !c.is_whitespace() && *c != '|')
+            .enumerate()
+            .fold(self, |pattern, (step, c)| match c {
+                'x' => pattern.hit(sound, step, 0.0),
+                'X' => pattern.hit(sound, step, ACCENT),
+                'o' => pattern.hit(sound, step, GHOST),
+                'f' => pattern.flam(sound, step, 0.0),
+                _ => pattern,
+            })
+    }
+
+    fn push(mut self, step: usize, note: PercussionNote) -> Self {
+        if step < self.steps {
+            // A sound is struck once per step
+            self.hits
+                .retain(|(s, n)| *s != step || n.sound() != note.sound());
+            self.hits.push((step, note));
+            self.hits.sort_by_key(|(s, n)| (*s, n.sound()));
+        }
+        self
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn steps_per_beat(&self) -> usize {
+        self.steps_per_beat
+    }
+
+    /// Hits of the pattern with their step index
+    pub fn hits(&self) -> &[(usize, PercussionNote)] {
+        &self.hits
+    }
+
+    /// Split the pattern into measures of `beats_per_measure` beats
+    pub fn measures(&self, beats_per_measure: u8) -> Vec<Measure> {
+        let steps_per_measure = (self.steps_per_beat * beats_per_measure as usize).max(1);
+        let measure_count = self.steps.div_ceil(steps_per_measure);
+
+        (0..measure_count)
+            .map(|measure| {
+                let range = measure * steps_per_measure..(measure + 1) * steps_per_measure;
+                let notes = self
+                    .hits
+                    .iter()
+                    .filter(|(step, _)| range.contains(step))
+                    .map(|(step, note)| {
+                        let position = (step - range.start) as f32 / self.steps_per_beat as f32;
+                        note.at(position)
+                    })
+                    .collect::<Vec<_>>();
+                match notes.is_empty() {
+                    true => Measure::Rest,
+                    false => Measure::Percussion(notes),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_pattern() {
+        let pattern = DrumPattern::new(8, 2)
+            .grid(DrumSound::ClosedHiHat, "xxxx|xxxx")
+            .grid(DrumSound::AcousticSnare, "..X. ..of")
+            .hit(DrumSound::CrashCymbal, 0, 0.8)
+            .hit(DrumSound::CrashCymbal, 9, 0.8);
+        assert_eq!(pattern.hits().len(), 12);
+
+        let measures = pattern.measures(2);
+        assert_eq!(measures.len(), 2);
+        let Measure::Percussion(second) = &measures[1] else {
+            panic!("expected percussion");
+        };
+        let snares = second
+            .iter()
+            .filter(|n| n.sound() == DrumSound::AcousticSnare)
+            .collect::<Vec<_>>();
+        assert_eq!(snares.len(), 2);
+        assert_eq!(snares[0].position(), 1.0);
+        assert_eq!(snares[0].velocity(), GHOST);
+        assert!(snares[1].is_flam());
+        assert_eq!(snares[1].position(), 1.5);
+    }
+}
@@ -0,0 +1,363 @@
+//! Groove templates
+//!
+//! A `Groove` moves straight notated positions and reshapes velocities when a score is
+//! rendered, the notated `Duration`s stay untouched:
+//! - swing: the first note of each pair of eighths (or sixteenths) gets a longer share
+//! - shuffle: triplet swing on eighths with lighter off-beats
+//! - push / laid-back: every note a little ahead of or behind the beat
+//! - humanize: seeded random timing and velocity jitter
+//! - groove maps: per-step timing and velocity profiles, e.g. extracted from a performance
+//!
+//! Positions are in beats of the score. The groove is part of the `Interpretation`, so the
+//! `Timeline` and MIDI playback apply it.
+
+use crate::{Duration, DurationBase, DurationGenerator};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Swing feel on a subdivision
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Swing {
+    /// Swung note value, usually eighths or sixteenths
+    pub unit: DurationBase,
+    /// Share of a pair of units given to the first one, 0.5 is straight, 2/3 triplet swing
+    pub ratio: f32,
+}
+
+/// Seeded timing and velocity jitter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Humanize {
+    /// Largest timing deviation in beats
+    pub timing: f32,
+    /// Largest velocity deviation
+    pub velocity: u8,
+    pub seed: u64,
+}
+
+/// Timing and velocity profile over a grid, repeating every `timing.len()` steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrooveMap {
+    grid: DurationBase,
+    /// Offset of each step, in fractions of a grid step
+    timing: Vec<f32>,
+    /// Velocity factor of each step
+    velocity: Vec<f32>,
+}
+
+/// Groove applied at render time, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groove {
+    swing: Option<Swing>,
+    /// Offset of every note in beats, negative to push, positive to lay back
+    offset: f32,
+    /// Velocity factor of off-beat swung notes
+    off_beat_velocity: f32,
+    humanize: Option<Humanize>,
+    map: Option<GrooveMap>,
+}
+
+impl Default for Groove {
+    fn default() -> Self {
+        Groove {
+            swing: None,
+            offset: 0.0,
+            off_beat_velocity: 1.0,
+            humanize: None,
+            map: None,
+        }
+    }
+}
+
+impl Groove {
+    /// Straight, no groove
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Triplet swing on eighths with lighter off-beats
+    pub fn shuffle() -> Self {
+        Groove {
+            off_beat_velocity: 0.85,
+            ..Groove::new().with_swing_ratio(DurationBase::Eighth, 2, 1)
+        }
+    }
+
+    /// Swing with the first unit of each pair taking `ratio` of it (0.6 for 60%)
+    pub fn with_swing(self, unit: DurationBase, ratio: f32) -> Self {
+        Groove {
+            swing: Some(Swing {
+                unit,
+                ratio: ratio.clamp(0.05, 0.95),
+            }),
+            ..self
+        }
+    }
+
+    /// Swing given as a ratio of the long and short notes, e.g. 2:1
+    pub fn with_swing_ratio(self, unit: DurationBase, long: u8, short: u8) -> Self {
+        let ratio = long as f32 / (long as f32 + short as f32).max(1.0);
+        self.with_swing(unit, ratio)
+    }
+
+    /// Play ahead of the beat by `beats`
+    pub fn with_push(self, beats: f32) -> Self {
+        Groove {
+            offset: -beats.abs(),
+            ..self
+        }
+    }
+
+    /// Play behind the beat by `beats`
+    pub fn with_laid_back(self, beats: f32) -> Self {
+        Groove {
+            offset: beats.abs(),
+            ..self
+        }
+    }
+
+    pub fn with_humanize(self, timing: f32, velocity: u8, seed: u64) -> Self {
+        Groove {
+            humanize: Some(Humanize {
+                timing: timing.abs(),
+                velocity,
+                seed,
+            }),
+            ..self
+        }
+    }
+
+    pub fn with_map(self, map: GrooveMap) -> Self {
+        Groove {
+            map: Some(map),
+            ..self
+        }
+    }
+
+    pub fn swing(&self) -> Option<Swing> {
+        self.swing
+    }
+
+    pub fn is_straight(&self) -> bool {
+        self == &Groove::default()
+    }
+
+    /// Position of a notated position once swung and mapped, without random jitter
+    pub fn warp(&self, beat: f32, dg: &DurationGenerator) -> f32 {
+        let mut position = beat;
+
+        if let Some(swing) = self.swing {
+            let pair = 2.0 * dg.in_beats(&Duration::new(swing.unit));
+            let start = (beat / pair).floor() * pair;
+            let t = (beat - start) / pair;
+            let swung = if t <= 0.5 {
+                t / 0.5 * swing.ratio
+            } else {
+                swing.ratio + (t - 0.5) / 0.5 * (1.0 - swing.ratio)
+            };
+            position = start + swung * pair;
+        }
+
+        if let Some(map) = &self.map {
+            if let Some(step) = map.step_at(beat, dg) {
+                position += map.timing[step] * map.step_beats(dg);
+            }
+        }
+
+        (position + self.offset).max(0.0)
+    }
+
+    /// Apply the groove to a rendered note, `key` seeds its humanization.
+    /// Returns the new onset, duration and velocity.
+    pub fn apply(
+        &self,
+        onset: f32,
+        duration: f32,
+        velocity: u8,
+        dg: &DurationGenerator,
+        key: u64,
+    ) -> (f32, f32, u8) {
+        let mut start = self.warp(onset, dg);
+        let mut end = self.warp(onset + duration, dg).max(start);
+        let mut velocity = velocity as f32;
+
+        if let Some(swing) = self.swing {
+            let unit = dg.in_beats(&Duration::new(swing.unit));
+            let on_off_beat = ((onset / unit).round() as i64).rem_euclid(2) == 1
+                && (onset / unit - (onset / unit).round()).abs() < 1e-3;
+            if on_off_beat {
+                velocity *= self.off_beat_velocity;
+            }
+        }
+
+        if let Some(map) = &self.map {
+            if let Some(step) = map.step_at(onset, dg) {
+                velocity *= map.velocity[step];
+            }
+        }
+
+        if let Some(humanize) = self.humanize {
+            let mut rng = StdRng::seed_from_u64(humanize.seed ^ key);
+            if humanize.timing > 0.0 {
+                let shift = rng.random_range(-humanize.timing..=humanize.timing);
+                start = (start + shift).max(0.0);
+                end = (end + shift).max(start);
+            }
+            if humanize.velocity > 0 {
+                let jitter = humanize.velocity as f32;
+                velocity += rng.random_range(-jitter..=jitter);
+            }
+        }
+
+        (start, end - start, velocity.round().clamp(1.0, 127.0) as u8)
+    }
+}
+
+impl GrooveMap {
+    /// `timing` in fractions of a grid step, `velocity` as factors. Missing velocity
+    /// factors are 1.0.
+    pub fn new(grid: DurationBase, timing: Vec<f32>, velocity: Vec<f32>) -> Self {
+        let mut velocity = velocity;
+        velocity.resize(timing.len(), 1.0);
+        GrooveMap {
+            grid,
+            timing,
+            velocity,
+        }
+    }
+
+    /// Average deviation from the grid and relative velocity of each step of a performance.
+    ///
+    /// `performance` holds performed onsets in beats with their velocities, the profile
+    /// repeats every `steps` grid steps.
+    pub fn extract(
+        performance: &[(f32, u8)],
+        grid: DurationBase,
+        steps: usize,
+        dg: &DurationGenerator,
+    ) -> Self {
+        let steps = steps.max(1);
+        let step_beats = dg.in_beats(&Duration::new(grid));
+        let mut timing = vec![(0.0f32, 0usize); steps];
+        let mut velocity = vec![0.0f32; steps];
+
+        for (beat, v) in performance {
+            let nearest = (beat / step_beats).round();
+            let step = (nearest as i64).rem_euclid(steps as i64) as usize;
+            timing[step].0 += beat / step_beats - nearest;
+            timing[step].1 += 1;
+            velocity[step] += *v as f32;
+        }
+
+        let mean = performance.iter().map(|(_, v)| *v as f32).sum::<f32>()
+            / performance.len().max(1) as f32;
+        GrooveMap {
+            grid,
+            velocity: timing
+                .iter()
+                .zip(&velocity)
+                .map(|((_, count), sum)| match *count {
+                    0 => 1.0,
+                    n => sum / n as f32 / mean.max(1.0),
+                })
+                .collect(),
+            timing: timing
+                .iter()
+                .map(|(sum, count)| sum / (*count).max(1) as f32)
+                .collect(),
+        }
+    }
+
+    pub fn timing(&self) -> &[f32] {
+        &self.timing
+    }
+
+    pub fn velocity(&self) -> &[f32] {
+        &self.velocity
+    }
+
+    fn step_beats(&self, dg: &DurationGenerator) -> f32 {
+        dg.in_beats(&Duration::new(self.grid))
+    }
+
+    /// Step of the profile a notated position falls on, if it is on the grid
+    fn step_at(&self, beat: f32, dg: &DurationGenerator) -> Option<usize> {
+        if self.timing.is_empty() {
+            return None;
+        }
+        let position = beat / self.step_beats(dg);
+        let nearest = position.round();
+        ((position - nearest).abs() < 1e-3)
+            .then(|| (nearest as i64).rem_euclid(self.timing.len() as i64) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dg() -> DurationGenerator {
+        DurationGenerator::new(DurationBase::Quarter)
+    }
+
+    #[test]
+    fn test_swing() {
+        let groove = Groove::new().with_swing_ratio(DurationBase::Eighth, 2, 1);
+        assert!((groove.warp(0.5, &dg()) - 2.0 / 3.0).abs() < 1e-5);
+        assert_eq!(groove.warp(1.0, &dg()), 1.0);
+        assert!((groove.warp(3.5, &dg()) - (3.0 + 2.0 / 3.0)).abs() < 1e-5);
+
+        // Sixteenths at 60%
+        let groove = Groove::new().with_swing(DurationBase::Sixteenth, 0.6);
+        assert!((groove.warp(0.25, &dg()) - 0.3).abs() < 1e-5);
+        assert_eq!(groove.warp(0.5, &dg()), 0.5);
+
+        // A straight eighth pair becomes long-short, the notated length is kept
+        let (start, duration, _) = groove.apply(0.0, 0.25, 80, &dg(), 0);
+        assert_eq!(start, 0.0);
+        assert!((duration - 0.3).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_shuffle_push_and_humanize() {
+        let shuffle = Groove::shuffle();
+        let (_, _, on_beat) = shuffle.apply(1.0, 0.5, 100, &dg(), 0);
+        let (_, _, off_beat) = shuffle.apply(1.5, 0.5, 100, &dg(), 0);
+        assert_eq!(on_beat, 100);
+        assert_eq!(off_beat, 85);
+
+        let pushed = Groove::new().with_push(0.05);
+        assert!((pushed.warp(2.0, &dg()) - 1.95).abs() < 1e-5);
+        assert_eq!(pushed.warp(0.0, &dg()), 0.0);
+        let laid_back = Groove::new().with_laid_back(0.05);
+        assert!((laid_back.warp(2.0, &dg()) - 2.05).abs() < 1e-5);
+
+        let human = Groove::new().with_humanize(0.02, 10, 42);
+        let a = human.apply(1.0, 1.0, 80, &dg(), 7);
+        let b = human.apply(1.0, 1.0, 80, &dg(), 7);
+        assert_eq!(a, b);
+        assert!((a.0 - 1.0).abs() <= 0.02 + 1e-6);
+        assert!((a.2 as i32 - 80).abs() <= 10);
+    }
+
+    #[test]
+    fn test_groove_map() {
+        // Performed eighths, off-beats late and soft
+        let performance = (0..8)
+            .map(|i| {
+                let beat = i as f32 * 0.5;
+                match i % 2 {
+                    0 => (beat, 100),
+                    _ => (beat + 0.05, 60),
+                }
+            })
+            .collect::<Vec<_>>();
+        let map = GrooveMap::extract(&performance, DurationBase::Eighth, 2, &dg());
+        assert!((map.timing()[1] - 0.1).abs() < 1e-4);
+        assert!(map.velocity()[0] > 1.0 && map.velocity()[1] < 1.0);
+
+        let groove = Groove::new().with_map(map);
+        let (start, _, velocity) = groove.apply(2.5, 0.5, 80, &dg(), 0);
+        assert!((start - 2.55).abs() < 1e-4);
+        assert_eq!(velocity, 60);
+    }
+}
@@ -0,0 +1,479 @@
+//! Interpretation layer
+//!
+//! Maps the expression markings of notes (articulations, dynamics, hairpins, slurs,
+//! fermatas, grace notes) onto effective playback parameters: velocity, sounding
+//! duration and onset. MIDI playback and audio rendering both consume the result
+//! through the `Timeline`.
+
+use crate::{
+    Articulation, DurationGenerator, Dynamic, Grace, Groove, Hairpin, HairpinMark, Note,
+    PercussionNote, SlurMark,
+};
+
+/// Configurable mapping from notation to performance
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpretation {
+    /// Fraction of the notated value that sounds for an unmarked note
+    gate: f32,
+    /// Fraction of the notated value that sounds for a staccato note
+    staccato_gate: f32,
+    /// Fraction of the notated value that sounds for a tenuto note
+    tenuto_gate: f32,
+    /// Fraction of the notated value that sounds for a note inside a slur
+    legato_gate: f32,
+    /// Velocity added by an accent
+    accent_boost: u8,
+    /// Velocity added by a marcato
+    marcato_boost: u8,
+    /// Dynamic in effect before the first marking
+    default_dynamic: Dynamic,
+    /// MIDI velocity of each dynamic level, pp to ff
+    dynamic_velocities: [u8; 6],
+    /// How much longer a note under a fermata lasts
+    fermata_stretch: f32,
+    /// Length of an acciaccatura in beats
+    grace_beats: f32,
+    /// Swing, feel and humanization applied to the rendered positions
+    groove: Groove,
+}
+
+/// Effective playback parameters of a note. Times are in beats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rendition {
+    /// Onset, relative to the same origin as the notated positions
+    pub onset: f32,
+    /// Sounding duration
+    pub duration: f32,
+    /// MIDI velocity [1, 127]
+    pub velocity: u8,
+    /// Extra time the whole performance pauses after this note (fermata)
+    pub hold: f32,
+}
+
+impl Default for Interpretation {
+    fn default() -> Self {
+        Self {
+            gate: 0.9,
+            staccato_gate: 0.5,
+            tenuto_gate: 1.0,
+            legato_gate: 1.0,
+            accent_boost: 16,
+            marcato_boost: 24,
+            default_dynamic: Dynamic::MezzoForte,
+            dynamic_velocities: [33, 49, 64, 80, 96, 112],
+            fermata_stretch: 2.0,
+            grace_beats: 0.125,
+            groove: Groove::default(),
+        }
+    }
+}
+
+impl Interpretation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_gate(mut self, gate: f32) -> Self {
+        self.gate = gate;
+        self
+    }
+
+    pub fn with_staccato_gate(mut self, gate: f32) -> Self {
+        self.staccato_gate = gate;
+        self
+    }
+
+    pub fn with_tenuto_gate(mut self, gate: f32) -> Self {
+        self.tenuto_gate = gate;
+        self
+    }
+
+    pub fn with_legato_gate(mut self, gate: f32) -> Self {
+        self.legato_gate = gate;
+        self
+    }
+
+    pub fn with_accent_boost(mut self, accent: u8, marcato: u8) -> Self {
+        self.accent_boost = accent;
+        self.marcato_boost = marcato;
+        self
+    }
+
+    pub fn with_default_dynamic(mut self, dynamic: Dynamic) -> Self {
+        self.default_dynamic = dynamic;
+        self
+    }
+
+    pub fn with_dynamic_velocity(mut self, dynamic: Dynamic, velocity: u8) -> Self {
+        self.dynamic_velocities[dynamic as usize] = velocity.clamp(1, 127);
+        self
+    }
+
+    pub fn with_fermata_stretch(mut self, stretch: f32) -> Self {
+        self.fermata_stretch = stretch.max(1.0);
+        self
+    }
+
+    pub fn with_grace_beats(mut self, beats: f32) -> Self {
+        self.grace_beats = beats.max(0.0);
+        self
+    }
+
+    pub fn with_groove(mut self, groove: Groove) -> Self {
+        self.groove = groove;
+        self
+    }
+
+    pub fn groove(&self) -> &Groove {
+        &self.groove
+    }
+
+    pub fn default_dynamic(&self) -> Dynamic {
+        self.default_dynamic
+    }
+
+    /// MIDI velocity of a dynamic level
+    pub fn velocity_of(&self, dynamic: Dynamic) -> u8 {
+        self.dynamic_velocities[dynamic as usize]
+    }
+
+    /// Render a percussion hit placed at `onset`, preceded by its grace stroke when flammed.
+    ///
+    /// Percussion sounds are one-shot, the rendered duration is only the length of the
+    /// MIDI note.
+    pub fn render_percussion(&self, onset: f32, note: &PercussionNote) -> Vec<Rendition> {
+        /// Length of a percussion hit in beats
+        const HIT_BEATS: f32 = 0.25;
+
+        let velocity = match note.velocity() {
+            v if v > 0.0 => (v * 127.0).round().clamp(1.0, 127.0) as u8,
+            _ => self.velocity_of(self.default_dynamic),
+        };
+        let hit = Rendition {
+            onset,
+            duration: HIT_BEATS,
+            velocity,
+            hold: 0.0,
+        };
+
+        if !note.is_flam() {
+            return vec![hit];
+        }
+        // The grace stroke is softer and falls just before the beat
+        let offset = (self.grace_beats / 2.0).min(onset);
+        let grace = Rendition {
+            onset: onset - offset,
+            duration: offset.max(f32::EPSILON),
+            velocity: ((velocity as f32 * 0.6).round() as u8).max(1),
+            hold: 0.0,
+        };
+        vec![grace, hit]
+    }
+
+    /// Render a sequence of notes of one voice.
+    ///
+    /// `notes` holds each note with its notated onset in beats. Grace notes share the onset
+    /// of their principal note. Dynamics carry over from note to note, so the whole voice
+    /// should be passed at once.
+    pub fn render(&self, notes: &[(f32, &Note)], dg: &DurationGenerator) -> Vec<Rendition> {
+        let velocities = self.velocities(notes);
+        let in_slur = Self::slurred(notes);
+
+        let mut renditions: Vec<Rendition> = notes
+            .iter()
+            .enumerate()
+            .map(|(i, (onset, note))| {
+                let expression = note.expression();
+                let length = if note.is_grace() {
+                    0.0
+                } else {
+                    note.duration().in_beats(dg)
+                };
+
+                let gate = if expression.fermata {
+                    1.0
+                } else if expression.has_articulation(Articulation::Staccato) {
+                    self.staccato_gate
+                } else if expression.has_articulation(Articulation::Tenuto) {
+                    self.tenuto_gate
+                } else if in_slur[i] {
+                    self.legato_gate
+                } else {
+                    self.gate
+                };
+
+                let hold = if expression.fermata {
+                    length * (self.fermata_stretch - 1.0)
+                } else {
+                    0.0
+                };
+
+                Rendition {
+                    onset: *onset,
+                    duration: length * gate,
+                    velocity: velocities[i],
+                    hold,
+                }
+            })
+            .collect();
+
+        self.place_grace_notes(notes, dg, &mut renditions);
+        renditions
+    }
+
+    /// Dynamic level and hairpins resolved to a velocity per note
+    fn velocities(&self, notes: &[(f32, &Note)]) -> Vec<u8> {
+        let mut current = self.default_dynamic;
+        let mut velocities: Vec<f32> = notes
+            .iter()
+            .map(|(_, note)| {
+                if let Some(dynamic) = note.expression().dynamic {
+                    current = dynamic;
+                }
+                self.velocity_of(current) as f32
+            })
+            .collect();
+
+        let mut i = 0;
+        while i < notes.len() {
+            let Some(HairpinMark::Start(hairpin)) = notes[i].1.expression().hairpin else {
+                i += 1;
+                continue;
+            };
+
+            let stop = (i..notes.len())
+                .find(|&j| j > i && notes[j].1.expression().hairpin == Some(HairpinMark::Stop))
+                .unwrap_or(notes.len() - 1);
+
+            let start_dynamic = notes[..=i]
+                .iter()
+                .rev()
+                .find_map(|(_, n)| n.expression().dynamic)
+                .unwrap_or(self.default_dynamic);
+            let target_dynamic = notes
+                .get(stop + 1)
+                .and_then(|(_, n)| n.expression().dynamic)
+                .unwrap_or(match hairpin {
+                    Hairpin::Crescendo => start_dynamic.louder(),
+                    Hairpin::Diminuendo => start_dynamic.softer(),
+                });
+
+            let from = self.velocity_of(start_dynamic) as f32;
+            let to = self.velocity_of(target_dynamic) as f32;
+            let steps = (stop - i + 1) as f32;
+            for (k, velocity) in velocities[i..=stop].iter_mut().enumerate() {
+                *velocity = from + (to - from) * k as f32 / steps;
+            }
+            // The level reached holds until the next dynamic marking
+            for (velocity, (_, note)) in velocities[stop + 1..].iter_mut().zip(&notes[stop + 1..]) {
+                if note.expression().dynamic.is_some() {
+                    break;
+                }
+                *velocity = to;
+            }
+
+            i = stop + 1;
+        }
+
+        notes
+            .iter()
+            .zip(velocities)
+            .map(|((_, note), velocity)| {
+                let expression = note.expression();
+                let mut velocity = if note.velocity() > 0.0 {
+                    note.velocity() * 127.0
+                } else {
+                    velocity
+                };
+                if expression.has_articulation(Articulation::Marcato) {
+                    velocity += self.marcato_boost as f32;
+                } else if expression.has_articulation(Articulation::Accent) {
+                    velocity += self.accent_boost as f32;
+                }
+                velocity.round().clamp(1.0, 127.0) as u8
+            })
+            .collect()
+    }
+
+    /// Whether each note is inside a slur and connects legato to the next one
+    fn slurred(notes: &[(f32, &Note)]) -> Vec<bool> {
+        let mut open = false;
+        notes
+            .iter()
+            .map(|(_, note)| match note.expression().slur {
+                Some(SlurMark::Start) => {
+                    open = true;
+                    true
+                }
+                Some(SlurMark::Stop) => {
+                    open = false;
+                    false
+                }
+                None => open,
+            })
+            .collect()
+    }
+
+    /// Grace notes borrow their time from the following principal note
+    fn place_grace_notes(
+        &self,
+        notes: &[(f32, &Note)],
+        dg: &DurationGenerator,
+        renditions: &mut [Rendition],
+    ) {
+        let mut i = 0;
+        while i < notes.len() {
+            if !notes[i].1.is_grace() {
+                i += 1;
+                continue;
+            }
+
+            let first = i;
+            while i < notes.len() && notes[i].1.is_grace() {
+                i += 1;
+            }
+            let Some(principal) = renditions.get(i).copied() else {
+                // Trailing grace notes without a principal note are played as written
+                for (k, rendition) in renditions[first..].iter_mut().enumerate() {
+                    rendition.onset += k as f32 * self.grace_beats;
+                    rendition.duration = self.grace_beats;
+                }
+                break;
+            };
+
+            let mut cursor = principal.onset;
+            for (k, rendition) in renditions[first..i].iter_mut().enumerate() {
+                let length = match notes[first + k].1.expression().grace {
+                    Some(Grace::Appoggiatura) => notes[i].1.duration().in_beats(dg) / 2.0,
+                    _ => self.grace_beats,
+                };
+                // Never eat more than most of the principal note
+                let length = length.min((principal.onset + principal.duration - cursor) * 0.75);
+                rendition.onset = cursor;
+                rendition.duration = length;
+                cursor += length;
+            }
+
+            let stolen = cursor - principal.onset;
+            renditions[i].onset += stolen;
+            renditions[i].duration = (renditions[i].duration - stolen).max(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Duration, DurationBase, PitchClass, Tuning};
+
+    fn notes_at(notes: &[Note], dg: &DurationGenerator) -> Vec<(f32, Note)> {
+        let mut onset = 0.0;
+        notes
+            .iter()
+            .map(|n| {
+                let at = onset;
+                if !n.is_grace() {
+                    onset += n.duration().in_beats(dg);
+                }
+                (at, n.clone())
+            })
+            .collect()
+    }
+
+    fn render(notes: &[Note]) -> Vec<Rendition> {
+        let dg = DurationGenerator::new(DurationBase::Quarter);
+        let placed = notes_at(notes, &dg);
+        let refs = placed.iter().map(|(o, n)| (*o, n)).collect::<Vec<_>>();
+        Interpretation::default().render(&refs, &dg)
+    }
+
+    fn c4() -> Note {
+        Note::new(Tuning::new(PitchClass::C, 4))
+    }
+
+    #[test]
+    fn test_articulation_gates() {
+        let r = render(&[
+            c4(),
+            c4().with_articulation(Articulation::Staccato),
+            c4().with_articulation(Articulation::Tenuto),
+        ]);
+        assert_eq!(r[0].duration, 0.9);
+        assert_eq!(r[1].duration, 0.5);
+        assert_eq!(r[2].duration, 1.0);
+        assert_eq!(r[2].onset, 2.0);
+    }
+
+    #[test]
+    fn test_dynamics_and_accents() {
+        let r = render(&[
+            c4().with_dynamic(Dynamic::Piano),
+            c4(),
+            c4().with_articulation(Articulation::Accent),
+            c4().with_dynamic(Dynamic::Forte)
+                .with_articulation(Articulation::Marcato),
+            c4().with_velocity(0.5),
+        ]);
+        assert_eq!(r[0].velocity, 49);
+        assert_eq!(r[1].velocity, 49);
+        assert_eq!(r[2].velocity, 65);
+        assert_eq!(r[3].velocity, 120);
+        assert_eq!(r[4].velocity, 64);
+    }
+
+    #[test]
+    fn test_crescendo() {
+        let r = render(&[
+            c4().with_dynamic(Dynamic::Piano)
+                .with_hairpin(HairpinMark::Start(Hairpin::Crescendo)),
+            c4(),
+            c4().with_hairpin(HairpinMark::Stop),
+            c4().with_dynamic(Dynamic::Forte),
+        ]);
+        assert_eq!(r[0].velocity, 49);
+        assert!(r[0].velocity < r[1].velocity && r[1].velocity < r[2].velocity);
+        assert!(r[2].velocity < r[3].velocity);
+        assert_eq!(r[3].velocity, 96);
+
+        let r = render(&[
+            c4().with_hairpin(HairpinMark::Start(Hairpin::Diminuendo)),
+            c4().with_hairpin(HairpinMark::Stop),
+            c4(),
+        ]);
+        assert!(r[1].velocity < r[0].velocity);
+    }
+
+    #[test]
+    fn test_slur_is_legato() {
+        let r = render(&[
+            c4().with_slur(SlurMark::Start),
+            c4(),
+            c4().with_slur(SlurMark::Stop),
+        ]);
+        assert_eq!(r[0].duration, 1.0);
+        assert_eq!(r[1].duration, 1.0);
+        assert_eq!(r[2].duration, 0.9);
+    }
+
+    #[test]
+    fn test_fermata_and_grace() {
+        let r = render(&[
+            c4().with_grace(Grace::Acciaccatura),
+            c4(),
+            c4().with_fermata(),
+        ]);
+        assert_eq!(r[0].onset, 0.0);
+        assert_eq!(r[0].duration, 0.125);
+        assert_eq!(r[1].onset, 0.125);
+        assert_eq!(r[2].onset, 1.0);
+        assert_eq!(r[2].duration, 1.0);
+        assert_eq!(r[2].hold, 1.0);
+
+        let r = render(&[
+            c4().with_grace(Grace::Appoggiatura),
+            c4().with_duration(Duration::new(DurationBase::Half)),
+        ]);
+        assert_eq!(r[0].duration, 1.0);
+        assert_eq!(r[1].onset, 1.0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Key signature
+//!
+//! Counted in fifths from C major, positive for sharps and negative for flats, as in
+//! MusicXML and most notation formats.
+
+use crate::{MusicError, PitchClass, Scale, ScaleType, Tuning};
+use std::fmt::Display;
+
+/// Order in which sharps are added, flats are added in reverse
+const SHARP_ORDER: [char; 7] = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeySignature {
+    fifths: i8,
+    minor: bool,
+}
+
+impl KeySignature {
+    /// Major key with `fifths` sharps (positive) or flats (negative), in [-7, 7]
+    pub fn new(fifths: i8) -> Self {
+        KeySignature {
+            fifths: fifths.clamp(-7, 7),
+            minor: false,
+        }
+    }
+
+    /// Minor key with `fifths` sharps (positive) or flats (negative), in [-7, 7]
+    pub fn minor(fifths: i8) -> Self {
+        KeySignature {
+            minor: true,
+            ..KeySignature::new(fifths)
+        }
+    }
+
+    /// Key signature of a major or minor tonic, e.g. Eb major or F# minor
+    pub fn from_tonic(tonic: Tuning, minor: bool) -> Result<Self, MusicError> {
+        let fifths = major_fifths(tonic) - if minor { 3 } else { 0 };
+        if !(-7..=7).contains(&fifths) {
+            return Err(MusicError::TheoryViolation(format!(
+                "{tonic} {} needs {fifths} fifths",
+                if minor { "minor" } else { "major" }
+            )));
+        }
+        Ok(KeySignature { fifths, minor })
+    }
+
+    /// Key signature of a diatonic scale. Church modes keep the signature of their
+    /// parent major key.
+    pub fn from_scale(scale: &Scale) -> Result<Self, MusicError> {
+        let shift = match scale.scale_type() {
+            ScaleType::Major | ScaleType::Ionian | ScaleType::PentatonicMajor => 0,
+            ScaleType::NaturalMinor
+            | ScaleType::Aeolian
+            | ScaleType::HarmonicMinor
+            | ScaleType::MelodicMinor
+            | ScaleType::PentatonicMinor
+            | ScaleType::Blues => return KeySignature::from_tonic(scale.root(), true),
+            ScaleType::Dorian => -2,
+            ScaleType::Phrygian => -4,
+            ScaleType::Lydian => 1,
+            ScaleType::Mixolydian => -1,
+            ScaleType::Locrian => -5,
+            other => {
+                return Err(MusicError::TheoryViolation(format!(
+                    "{other:?} has no key signature"
+                )))
+            }
+        };
+        match major_fifths(scale.root()) + shift {
+            fifths @ -7..=7 => Ok(KeySignature::new(fifths)),
+            fifths => Err(MusicError::TheoryViolation(format!(
+                "{fifths} fifths is not a key signature"
+            ))),
+        }
+    }
+
+    pub fn fifths(&self) -> i8 {
+        self.fifths
+    }
+
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Tonic of the key, in octave 4
+    pub fn tonic(&self) -> Tuning {
+        let fifths = self.fifths + if self.minor { 3 } else { 0 } + 1;
+        let letter = SHARP_ORDER[fifths.rem_euclid(7) as usize];
+        let alter = fifths.div_euclid(7);
+        Tuning::from_spelling(letter, alter, 4).unwrap_or(Tuning::new(PitchClass::C, 4))
+    }
+
+    /// Alteration the signature gives to a letter, e.g. `-1` for B in F major
+    pub fn alter(&self, letter: char) -> i8 {
+        let letter = letter.to_ascii_uppercase();
+        let count = self.fifths.unsigned_abs() as usize;
+        match self.fifths.signum() {
+            1 if SHARP_ORDER[..count].contains(&letter) => 1,
+            -1 if SHARP_ORDER[7 - count..].contains(&letter) => -1,
+            _ => 0,
+        }
+    }
+}
+
+/// Fifths from C major to the major key of `tonic`, outside [-7, 7] for theoretical keys
+fn major_fifths(tonic: Tuning) -> i8 {
+    let (letter, alter, _) = tonic.spelling();
+    let index = SHARP_ORDER.iter().position(|l| *l == letter).unwrap_or(1) as i8;
+    index - 1 + 7 * alter
+}
+
+impl Display for KeySignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode = if self.minor { "minor" } else { "major" };
+        write!(f, "{} {}", self.tonic(), mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_signature() {
+        let eb = KeySignature::from_tonic(Tuning::new(PitchClass::Eb, 4), false).unwrap();
+        assert_eq!(eb.fifths(), -3);
+        assert_eq!(eb.alter('A'), -1);
+        assert_eq!(eb.alter('D'), 0);
+        assert_eq!(eb.to_string(), "Eb major");
+
+        let fs_minor = KeySignature::from_tonic(Tuning::new(PitchClass::Fs, 4), true).unwrap();
+        assert_eq!(fs_minor.fifths(), 3);
+        assert_eq!(fs_minor.alter('G'), 1);
+        assert_eq!(fs_minor.to_string(), "F# minor");
+
+        assert_eq!(KeySignature::default().to_string(), "C major");
+        assert!(KeySignature::from_tonic(Tuning::new(PitchClass::Gs, 4), false).is_err());
+
+        let dorian = Scale::new(Tuning::new(PitchClass::D, 4), ScaleType::Dorian).unwrap();
+        assert_eq!(KeySignature::from_scale(&dorian).unwrap().fifths(), 0);
+        let dorian = Scale::new(Tuning::new(PitchClass::Gs, 4), ScaleType::Dorian).unwrap();
+        assert_eq!(KeySignature::from_scale(&dorian).unwrap().fifths(), 6);
+        let minor = Scale::new(Tuning::new(PitchClass::G, 4), ScaleType::NaturalMinor).unwrap();
+        assert_eq!(
+            KeySignature::from_scale(&minor).unwrap(),
+            KeySignature::minor(-2)
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! Chord-over-lyrics text rendering
+//!
+//! Walks a melody track carrying lyrics alongside a chord track and prints each chord
+//! symbol above the syllable sung when it changes:
+//!
+//! ```text
+//! C       G
+//! Hap-py birth-day to you
+//! ```
+
+use crate::{Lyric, Measure, MusicError, Score};
+
+/// Chord-over-lyrics renderer
+#[derive(Debug, Clone)]
+pub struct LyricSheet {
+    verse: u8,
+    measures_per_line: usize,
+}
+
+/// Chord change and/or syllable at the same position
+struct Slot<'a> {
+    /// Position in beats from the start of the line
+    onset: f32,
+    chord: Option<String>,
+    lyric: Option<&'a Lyric>,
+}
+
+impl Default for LyricSheet {
+    fn default() -> Self {
+        LyricSheet {
+            verse: 1,
+            measures_per_line: 4,
+        }
+    }
+}
+
+impl LyricSheet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_verse(self, verse: u8) -> Self {
+        LyricSheet { verse, ..self }
+    }
+
+    pub fn with_measures_per_line(self, measures_per_line: usize) -> Self {
+        LyricSheet {
+            measures_per_line: measures_per_line.max(1),
+            ..self
+        }
+    }
+
+    /// Render the lyrics of `melody` with the chords of `chords`, two text lines per
+    /// group of `measures_per_line` measures
+    pub fn render<const TRACK_COUNT: usize>(
+        &self,
+        score: &Score<TRACK_COUNT>,
+        melody: usize,
+        chords: usize,
+    ) -> Result<String, MusicError> {
+        let tracks = score.get_tracks();
+        let melody_measures = tracks
+            .get(melody)
+            .ok_or(MusicError::InvalidTrack(melody))?
+            .get_measures();
+        let chord_measures = tracks
+            .get(chords)
+            .ok_or(MusicError::InvalidTrack(chords))?
+            .get_measures();
+
+        let dg = score.duration_generator();
+        let beats_per_measure = score.time_signature().beats_per_measure() as f32;
+        let measure_count = melody_measures.len().max(chord_measures.len());
+
+        let mut output = String::new();
+        for line_start in (0..measure_count).step_by(self.measures_per_line) {
+            let mut slots: Vec<Slot> = vec![];
+            let line_end = (line_start + self.measures_per_line).min(measure_count);
+
+            for measure_idx in line_start..line_end {
+                let measure_start = (measure_idx - line_start) as f32 * beats_per_measure;
+
+                if let Some(Measure::Chords(measure_chords)) = chord_measures.get(measure_idx) {
+                    let length = beats_per_measure / measure_chords.len() as f32;
+                    for (i, chord) in measure_chords.iter().enumerate() {
+                        slot_at(&mut slots, measure_start + length * i as f32).chord =
+                            Some(chord.to_string());
+                    }
+                }
+
+                if let Some(Measure::Note(notes)) = melody_measures.get(measure_idx) {
+                    let mut offset = 0.0;
+                    for note in notes.iter().filter(|note| !note.is_grace()) {
+                        if let Some(lyric) = note.lyric(self.verse) {
+                            slot_at(&mut slots, measure_start + offset).lyric = Some(lyric);
+                        }
+                        offset += note.duration().in_beats(&dg);
+                    }
+                }
+            }
+
+            slots.sort_by(|a, b| a.onset.total_cmp(&b.onset));
+            let (chord_line, lyric_line) = layout(&slots);
+            for line in [chord_line, lyric_line] {
+                let line = line.trim_end();
+                if !line.is_empty() {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+fn slot_at<'a, 'b>(slots: &'b mut Vec<Slot<'a>>, onset: f32) -> &'b mut Slot<'a> {
+    const EPSILON: f32 = 1e-4;
+    match slots.iter().position(|s| (s.onset - onset).abs() < EPSILON) {
+        Some(i) => &mut slots[i],
+        None => {
+            slots.push(Slot {
+                onset,
+                chord: None,
+                lyric: None,
+            });
+            slots.last_mut().unwrap()
+        }
+    }
+}
+
+/// Align chord symbols above the syllables, hyphens or extenders fill the gaps
+fn layout(slots: &[Slot]) -> (String, String) {
+    let width = |s: &String| s.chars().count();
+    let pad = |s: &mut String, to: usize, fill: char| {
+        let len = s.chars().count();
+        s.extend(std::iter::repeat_n(fill, to.saturating_sub(len)));
+    };
+
+    let mut chord_line = String::new();
+    let mut lyric_line = String::new();
+    // Filling character when the lyric line has to be stretched
+    let mut fill = ' ';
+
+    for slot in slots {
+        if let Some(chord) = &slot.chord {
+            // Keep chord symbols apart
+            let min = if chord_line.is_empty() {
+                0
+            } else {
+                width(&chord_line) + 1
+            };
+            if width(&lyric_line) < min {
+                // Stretching before the separator keeps the word readable
+                let separator = lyric_line.pop().filter(|c| *c == ' ');
+                pad(&mut lyric_line, min - separator.iter().count(), fill);
+                lyric_line.extend(separator);
+            }
+            let column = width(&lyric_line);
+            pad(&mut chord_line, column, ' ');
+            chord_line.push_str(chord);
+        }
+
+        if let Some(lyric) = slot.lyric {
+            lyric_line.push_str(&lyric.text);
+            if lyric.syllabic.continues() {
+                lyric_line.push('-');
+                fill = '-';
+            } else if lyric.extend {
+                lyric_line.push('_');
+                lyric_line.push(' ');
+                fill = '_';
+            } else {
+                lyric_line.push(' ');
+                fill = ' ';
+            }
+        }
+    }
+
+    (chord_line, lyric_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chord, Note, PitchClass, Tuning};
+
+    fn notes(count: usize) -> Measure {
+        Measure::from(
+            (0..count)
+                .map(|_| Note::new(Tuning::new(PitchClass::C, 4)))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_chords_over_lyrics() {
+        let mut score = Score::<2>::new();
+        score.push_measures([notes(4), Chord::from_symbol("C").unwrap().into()]);
+        score.push_measures([notes(4), Chord::from_symbol("G7").unwrap().into()]);
+        score
+            .set_lyrics(0, 1, "Hap-py birth-day to you, hap-py")
+            .unwrap();
+        score
+            .set_lyrics(0, 2, "Ma-ny hap-py _ re-turns to you")
+            .unwrap();
+
+        let sheet = LyricSheet::new().render(&score, 0, 1).unwrap();
+        assert_eq!(
+            sheet,
+            "C                G7\nHap-py birth-day to you, hap-py\n"
+        );
+
+        let sheet = LyricSheet::new()
+            .with_verse(2)
+            .with_measures_per_line(1)
+            .render(&score, 0, 1)
+            .unwrap();
+        assert_eq!(sheet, "C\nMa-ny hap-py_\nG7\nre-turns to\n");
+
+        assert!(LyricSheet::new().render(&score, 0, 2).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Chord, Note};
+use crate::{Chord, Note, PercussionNote};
 use std::fmt::Display;
 
 #[derive(Clone)]
@@ -6,6 +6,8 @@ pub enum Measure {
     Rest,
     Chords(Vec<Chord>),
     Note(Vec<Note>),
+    /// Unpitched hits, each placed at its own position in the measure
+    Percussion(Vec<PercussionNote>),
 }
 
 impl Measure {
@@ -28,6 +30,16 @@ impl Measure {
     pub fn note(&mut self, notes: Vec<Note>) {
         *self = Self::Note(notes);
     }
+
+    pub fn percussion(&mut self, notes: Vec<PercussionNote>) {
+        *self = Self::Percussion(notes);
+    }
+}
+
+impl Default for Measure {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Display for Measure {
@@ -47,6 +59,10 @@ impl Display for Measure {
                 let notes_str: Vec<String> = notes.iter().map(|n| n.to_string()).collect();
                 write!(f, "{}", notes_str.join(" "))
             }
+            Measure::Percussion(notes) => {
+                let notes_str: Vec<String> = notes.iter().map(|n| n.to_string()).collect();
+                write!(f, "{}", notes_str.join(" "))
+            }
         }
     }
 }
@@ -82,3 +98,9 @@ impl From<Vec<Chord>> for Measure {
         m
     }
 }
+
+impl From<Vec<PercussionNote>> for Measure {
+    fn from(value: Vec<PercussionNote>) -> Self {
+        Measure::Percussion(value)
+    }
+}
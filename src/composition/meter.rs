@@ -0,0 +1,334 @@
+//! Meter
+//!
+//! A `TimeSignature` groups its beats (2+2 in 4/4, 3+3 in 6/8, 2+3 in 5/8 unless told
+//! otherwise) and splits any span into notatable tied values that keep the metric
+//! structure visible, so that barlines, beat groups and beats are not hidden inside a
+//! single long value.
+//!
+//! This is for writing only. The exporters tie the values through the layout, but a
+//! `Note` has no tie of its own, so a note held over a barline is still imported as one
+//! note per measure.
+//!
+//! ```
+//! use mutheors::{Duration, DurationBase, TimeSignature};
+//! // Five eighths from the second half of beat 2 in 4/4
+//! let ts = TimeSignature::new(4, DurationBase::Quarter);
+//! assert_eq!(
+//!     ts.decompose(1.5, 2.5).unwrap(),
+//!     vec![Duration::new(DurationBase::Eighth), Duration::new(DurationBase::Half)]
+//! );
+//! ```
+
+use crate::{Duration, DurationBase, MusicError};
+
+/// Ticks in a quarter note, enough for a dotted sixty-fourth
+const TICKS_PER_QUARTER: u32 = 32;
+
+#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
+pub struct TimeSignature {
+    beats_per_measure: u8,
+    beat_type: DurationBase,
+    /// Bit `i` is set when a beat group starts on beat `i`
+    groups: u32,
+}
+
+impl TimeSignature {
+    pub fn new(beats_per_measure: u8, beat_type: DurationBase) -> Self {
+        TimeSignature {
+            beats_per_measure,
+            beat_type,
+            groups: group_bits(&default_grouping(beats_per_measure)),
+        }
+    }
+
+    /// Group the beats of a measure, e.g. `[3, 3, 2]` for 8/8.
+    /// The groups must add up to the beats of the measure.
+    pub fn with_grouping(self, groups: &[u8]) -> Result<Self, MusicError> {
+        let total = groups.iter().map(|g| *g as u32).sum::<u32>();
+        if total != self.beats_per_measure as u32 || groups.contains(&0) {
+            return Err(MusicError::TheoryViolation(format!(
+                "grouping {groups:?} does not fill {} beats",
+                self.beats_per_measure
+            )));
+        }
+        Ok(TimeSignature {
+            groups: group_bits(groups),
+            ..self
+        })
+    }
+
+    pub fn beats_per_measure(&self) -> u8 {
+        self.beats_per_measure
+    }
+
+    pub fn beat_type(&self) -> DurationBase {
+        self.beat_type
+    }
+
+    /// Number of beats in each group
+    pub fn grouping(&self) -> Vec<u8> {
+        let starts = (0..self.beats_per_measure.min(32))
+            .filter(|beat| self.groups & (1 << beat) != 0)
+            .chain([self.beats_per_measure])
+            .collect::<Vec<_>>();
+        starts.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    /// Length of a measure in quarter notes
+    pub fn measure_quarters(&self) -> f32 {
+        self.beats_per_measure as f32 * self.beat_type.in_quarters()
+    }
+
+    /// Split `length` beats starting `start` beats after a barline into tied notatable
+    /// values.
+    ///
+    /// No value crosses a barline. A value crosses a group, beat or subdivision boundary
+    /// only when it starts on a boundary at least as strong, except a dotted value
+    /// whose dot lasts at most half a beat (a dotted quarter on beat 2 of 4/4).
+    /// Among the valid splits the one with the fewest values wins.
+    ///
+    /// The ties are left to the caller, the values carry none.
+    pub fn decompose(&self, start: f32, length: f32) -> Result<Vec<Duration>, MusicError> {
+        let beat = self.beat_ticks();
+        let to_ticks = |beats: f32| {
+            let ticks = beats * beat as f32;
+            (ticks >= 0.0 && (ticks - ticks.round()).abs() < 1e-2).then_some(ticks.round() as u32)
+        };
+        let (Some(start), Some(ticks)) = (to_ticks(start), to_ticks(length)) else {
+            return Err(MusicError::InvalidDuration(length));
+        };
+
+        let measure = self.measure_ticks();
+        let end = start + ticks;
+        let mut position = start;
+        let mut durations = vec![];
+        while position < end {
+            let bar = position - position % measure;
+            let to = end.min(bar + measure);
+            durations.extend(
+                self.decompose_in_measure(position - bar, to - bar)
+                    .ok_or(MusicError::InvalidDuration(length))?,
+            );
+            position = to;
+        }
+        Ok(durations)
+    }
+
+    fn beat_ticks(&self) -> u32 {
+        ((self.beat_type.in_quarters() * TICKS_PER_QUARTER as f32) as u32).max(1)
+    }
+
+    fn measure_ticks(&self) -> u32 {
+        self.beat_ticks() * self.beats_per_measure.max(1) as u32
+    }
+
+    /// Strength of a position in a measure: 0 barline, 1 group, 2 beat, then subdivisions
+    fn level(&self, tick: u32) -> u32 {
+        let beat = self.beat_ticks();
+        if tick.is_multiple_of(self.measure_ticks()) {
+            return 0;
+        }
+        if tick.is_multiple_of(beat) {
+            let index = tick / beat;
+            return match index < 32 && self.groups & (1 << index) != 0 {
+                true => 1,
+                false => 2,
+            };
+        }
+        let mut level = 3;
+        let mut sub = beat / 2;
+        while sub > 1 && !tick.is_multiple_of(sub) {
+            sub /= 2;
+            level += 1;
+        }
+        level
+    }
+
+    /// Fewest values covering `[start, end)` of one measure, in ticks
+    fn decompose_in_measure(&self, start: u32, end: u32) -> Option<Vec<Duration>> {
+        let crosses_weaker =
+            |from: u32, to: u32, level: u32| (from + 1..to).any(|t| self.level(t) < level);
+        let valid = |s: u32, ticks: u32, plain: u32| {
+            let level = self.level(s);
+            !crosses_weaker(s, s + ticks, level)
+                || (ticks > plain
+                    && ticks - plain <= self.beat_ticks() / 2
+                    && !crosses_weaker(s, s + plain, level)
+                    && !crosses_weaker(s + plain, s + ticks, level))
+        };
+
+        let candidates = [
+            DurationBase::Breve,
+            DurationBase::Whole,
+            DurationBase::Half,
+            DurationBase::Quarter,
+            DurationBase::Eighth,
+            DurationBase::Sixteenth,
+            DurationBase::ThirtySecond,
+            DurationBase::SixtyFourth,
+        ]
+        .into_iter()
+        .flat_map(|base| {
+            let plain = (base.in_quarters() * TICKS_PER_QUARTER as f32) as u32;
+            [
+                (Duration::new(base).dotted(1), plain + plain / 2, plain),
+                (Duration::new(base), plain, plain),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+        // Cost of the best split from each position to the end: one per value, a little
+        // more for dots and for values ending inside a beat group they cross into
+        let length = (end - start) as usize;
+        let mut best: Vec<Option<(f32, usize)>> = vec![None; length + 1];
+        best[length] = Some((0.0, 0));
+        for offset in (0..length).rev() {
+            let s = start + offset as u32;
+            best[offset] = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, ticks, plain))| {
+                    offset + *ticks as usize <= length && valid(s, *ticks, *plain)
+                })
+                .filter_map(|(i, (duration, ticks, _))| {
+                    let (rest, _) = best[offset + *ticks as usize]?;
+                    // Prefer values that cover whole beat groups
+                    let hides_group = crosses_weaker(s, s + ticks, 2) && self.level(s + ticks) > 1;
+                    let cost =
+                        1.0 + duration.dots as f32 * 0.1 + if hides_group { 0.2 } else { 0.0 };
+                    Some((cost + rest, i))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+        }
+
+        let mut offset = 0;
+        let mut durations = vec![];
+        while offset < length {
+            let (_, i) = best[offset]?;
+            durations.push(candidates[i].0);
+            offset += candidates[i].1 as usize;
+        }
+        Some(durations)
+    }
+}
+
+/// 2+2 for four beats, threes for compound meters, otherwise twos ending with a three
+fn default_grouping(beats: u8) -> Vec<u8> {
+    match beats {
+        0..=3 => vec![beats],
+        4 => vec![2, 2],
+        b if b.is_multiple_of(3) => vec![3; b as usize / 3],
+        b if b.is_multiple_of(2) => vec![2; b as usize / 2],
+        b => {
+            let mut groups = vec![2; (b as usize - 3) / 2];
+            groups.push(3);
+            groups
+        }
+    }
+}
+
+fn group_bits(groups: &[u8]) -> u32 {
+    let mut bits = 0u32;
+    let mut beat = 0u32;
+    for group in groups {
+        if beat < 32 {
+            bits |= 1 << beat;
+        }
+        beat += *group as u32;
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(base: DurationBase) -> Duration {
+        Duration::new(base)
+    }
+
+    #[test]
+    fn test_grouping() {
+        let grouping = |n| TimeSignature::new(n, DurationBase::Eighth).grouping();
+        assert_eq!(grouping(4), vec![2, 2]);
+        assert_eq!(grouping(6), vec![3, 3]);
+        assert_eq!(grouping(5), vec![2, 3]);
+        assert_eq!(grouping(7), vec![2, 2, 3]);
+        assert_eq!(grouping(3), vec![3]);
+
+        let ts = TimeSignature::new(8, DurationBase::Eighth)
+            .with_grouping(&[3, 3, 2])
+            .unwrap();
+        assert_eq!(ts.grouping(), vec![3, 3, 2]);
+        assert!(ts.with_grouping(&[3, 3]).is_err());
+        assert_eq!(
+            TimeSignature::new(4, DurationBase::Quarter),
+            TimeSignature::new(4, DurationBase::Quarter)
+                .with_grouping(&[2, 2])
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decompose_simple_meter() {
+        let ts = TimeSignature::new(4, DurationBase::Quarter);
+        let q = DurationBase::Quarter;
+
+        assert_eq!(
+            ts.decompose(0.0, 4.0).unwrap(),
+            vec![d(DurationBase::Whole)]
+        );
+        assert_eq!(
+            ts.decompose(0.0, 3.0).unwrap(),
+            vec![d(DurationBase::Half).dotted(1)]
+        );
+        // The middle of the bar stays visible
+        assert_eq!(ts.decompose(1.0, 2.0).unwrap(), vec![d(q), d(q)]);
+        // but a dotted quarter on beat 2 is fine
+        assert_eq!(ts.decompose(1.0, 1.5).unwrap(), vec![d(q).dotted(1)]);
+        // Syncopation inside a beat shows the half beat
+        assert_eq!(
+            ts.decompose(0.25, 0.5).unwrap(),
+            vec![d(DurationBase::Sixteenth), d(DurationBase::Sixteenth)]
+        );
+        // Across the barline
+        assert_eq!(
+            ts.decompose(3.0, 3.0).unwrap(),
+            vec![d(q), d(DurationBase::Half)]
+        );
+        assert!(ts.decompose(0.0, 1.0 / 3.0).is_err());
+
+        let three = TimeSignature::new(3, DurationBase::Quarter);
+        assert_eq!(
+            three.decompose(1.0, 2.0).unwrap(),
+            vec![d(DurationBase::Half)]
+        );
+    }
+
+    #[test]
+    fn test_decompose_compound_and_irregular() {
+        let six = TimeSignature::new(6, DurationBase::Eighth);
+        let e = DurationBase::Eighth;
+        assert_eq!(
+            six.decompose(0.0, 3.0).unwrap(),
+            vec![d(DurationBase::Quarter).dotted(1)]
+        );
+        // Beat groups are not hidden
+        assert_eq!(six.decompose(2.0, 2.0).unwrap(), vec![d(e), d(e)]);
+        assert_eq!(
+            six.decompose(1.0, 3.0).unwrap(),
+            vec![d(DurationBase::Quarter), d(e)]
+        );
+
+        let five = TimeSignature::new(5, DurationBase::Eighth);
+        assert_eq!(
+            five.decompose(0.0, 5.0).unwrap(),
+            vec![d(DurationBase::Quarter), d(DurationBase::Quarter).dotted(1)]
+        );
+        let five = five.with_grouping(&[3, 2]).unwrap();
+        assert_eq!(
+            five.decompose(0.0, 5.0).unwrap(),
+            vec![d(DurationBase::Quarter).dotted(1), d(DurationBase::Quarter)]
+        );
+    }
+}
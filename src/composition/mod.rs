@@ -1,11 +1,29 @@
+mod drums;
+mod groove;
+mod interpretation;
+mod key_signature;
+mod lyric_sheet;
 mod measure;
+mod meter;
+mod navigation;
+mod quantize;
 mod score;
 mod tempo;
+mod timeline;
 mod track;
 
+pub use drums::*;
+pub use groove::*;
+pub use interpretation::*;
+pub use key_signature::*;
+pub use lyric_sheet::*;
 pub use measure::*;
+pub use meter::*;
+pub use navigation::*;
+pub use quantize::*;
 pub use score::*;
 pub use tempo::*;
+pub use timeline::*;
 pub use track::*;
 
 #[cfg(test)]
@@ -0,0 +1,326 @@
+//! Repeats and navigation marks
+//!
+//! - Repeat barlines with first, second... endings (voltas)
+//! - Segno, Coda, "To Coda" and Fine
+//! - D.C. / D.S. jumps, optionally "al Fine" or "al Coda"
+//!
+//! Marks live on the score measures and keep the notation compact. `Score::unroll` expands
+//! them into the linear performance order used by playback and the `Timeline`.
+//!
+//! As usual in printed music, repeats are not taken again after a D.C./D.S. jump, and only
+//! the last ending of a volta group is played on the way back.
+
+use crate::Score;
+use std::collections::HashMap;
+
+/// Where a D.C./D.S. jump goes and where the return pass stops
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jump {
+    /// D.C. - back to the beginning, play to the end
+    DaCapo,
+    /// D.C. al Fine - back to the beginning, stop at Fine
+    DaCapoAlFine,
+    /// D.C. al Coda - back to the beginning, at "To Coda" jump to the Coda
+    DaCapoAlCoda,
+    /// D.S. - back to the Segno, play to the end
+    DalSegno,
+    /// D.S. al Fine - back to the Segno, stop at Fine
+    DalSegnoAlFine,
+    /// D.S. al Coda - back to the Segno, at "To Coda" jump to the Coda
+    DalSegnoAlCoda,
+}
+
+impl Jump {
+    fn to_segno(self) -> bool {
+        matches!(
+            self,
+            Jump::DalSegno | Jump::DalSegnoAlFine | Jump::DalSegnoAlCoda
+        )
+    }
+
+    fn al_fine(self) -> bool {
+        matches!(self, Jump::DaCapoAlFine | Jump::DalSegnoAlFine)
+    }
+
+    fn al_coda(self) -> bool {
+        matches!(self, Jump::DaCapoAlCoda | Jump::DalSegnoAlCoda)
+    }
+}
+
+/// Mark attached to a measure of the score
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum BarMark {
+    /// `|:` at the beginning of the measure
+    RepeatStart,
+    /// `:|` at the end of the measure, the section is played this many times in total
+    RepeatEnd(u8),
+    /// The measure belongs to the ending(s) with these numbers
+    Volta(Vec<u8>),
+    /// 𝄋 at the beginning of the measure
+    Segno,
+    /// 𝄌 the coda section starts at this measure
+    Coda,
+    /// "To Coda" at the end of the measure
+    ToCoda,
+    /// Fine at the end of the measure
+    Fine,
+    /// D.C./D.S. instruction at the end of the measure
+    Jump(Jump),
+}
+
+impl BarMark {
+    /// Plain `:|`, the section is played twice
+    pub fn repeat_end() -> Self {
+        BarMark::RepeatEnd(2)
+    }
+
+    /// Single numbered ending
+    pub fn volta(number: u8) -> Self {
+        BarMark::Volta(vec![number])
+    }
+}
+
+impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
+    /// Attach a mark to a measure
+    pub fn mark(&mut self, measure: usize, mark: BarMark) {
+        let marks = self.marks.entry(measure).or_default();
+        if !marks.contains(&mark) {
+            marks.push(mark);
+        }
+    }
+
+    pub fn marks(&self, measure: usize) -> &[BarMark] {
+        self.marks.get(&measure).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn has_marks(&self) -> bool {
+        self.marks.values().any(|m| !m.is_empty())
+    }
+
+    /// Measure indices in performance order, with every repeat and jump expanded
+    pub fn unroll(&self) -> Vec<usize> {
+        unroll(self.measure_count(), &self.marks)
+    }
+
+    /// Linear copy of the score in performance order, without marks
+    pub fn unrolled(&self) -> Self {
+        let order = self.unroll();
+        let mut score = self.clone();
+        for track in score.tracks.iter_mut() {
+            track.measures = order
+                .iter()
+                .map(|&i| track.measures.get(i).cloned().unwrap_or_default())
+                .collect();
+        }
+        score.marks.clear();
+        score
+    }
+}
+
+fn volta_of(marks: &HashMap<usize, Vec<BarMark>>, measure: usize) -> Option<&[u8]> {
+    marks.get(&measure)?.iter().find_map(|m| match m {
+        BarMark::Volta(numbers) => Some(numbers.as_slice()),
+        _ => None,
+    })
+}
+
+/// Highest ending number of the volta group containing `measure`
+fn last_ending(marks: &HashMap<usize, Vec<BarMark>>, measure: usize) -> u8 {
+    let mut first = measure;
+    while first > 0 && volta_of(marks, first - 1).is_some() {
+        first -= 1;
+    }
+    (first..)
+        .map_while(|i| volta_of(marks, i))
+        .flat_map(|numbers| numbers.iter().copied())
+        .max()
+        .unwrap_or(1)
+}
+
+fn unroll(measure_count: usize, marks: &HashMap<usize, Vec<BarMark>>) -> Vec<usize> {
+    let has = |i: usize, mark: &BarMark| marks.get(&i).is_some_and(|m| m.contains(mark));
+    let find = |mark: &BarMark| (0..measure_count).find(|&i| has(i, mark));
+
+    let mut order = vec![];
+    // Guards against marks that would loop forever
+    let limit = measure_count * 64 + 64;
+
+    let mut i = 0;
+    let mut pass = 1;
+    let mut section_start = 0;
+    let mut jumped: Option<Jump> = None;
+    // Set when `i` was reached by going back to the start of a repeated section
+    let mut repeating = false;
+
+    while i < measure_count && order.len() < limit {
+        if has(i, &BarMark::RepeatStart) && !repeating {
+            section_start = i;
+            pass = 1;
+        }
+
+        match volta_of(marks, i) {
+            Some(numbers) => {
+                let ending = if jumped.is_some() {
+                    last_ending(marks, i)
+                } else {
+                    pass
+                };
+                if !numbers.contains(&ending) {
+                    i += 1;
+                    continue;
+                }
+            }
+            None => {
+                // Leaving a volta group closes the repeated section
+                if !repeating && i > 0 && volta_of(marks, i - 1).is_some() {
+                    section_start = i;
+                    pass = 1;
+                }
+            }
+        }
+
+        order.push(i);
+        repeating = false;
+        let measure_marks = marks.get(&i).map(Vec::as_slice).unwrap_or(&[]);
+
+        if let Some(jump) = jumped {
+            if jump.al_fine() && measure_marks.contains(&BarMark::Fine) {
+                break;
+            }
+            if jump.al_coda() && measure_marks.contains(&BarMark::ToCoda) {
+                match (i + 1..measure_count).find(|&c| has(c, &BarMark::Coda)) {
+                    Some(coda) => {
+                        i = coda;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+        } else {
+            let repeat = measure_marks.iter().find_map(|m| match m {
+                BarMark::RepeatEnd(times) => Some(*times),
+                _ => None,
+            });
+            if let Some(times) = repeat {
+                if pass < times {
+                    pass += 1;
+                    i = section_start;
+                    repeating = true;
+                    continue;
+                }
+                section_start = i + 1;
+                pass = 1;
+            }
+
+            let jump = measure_marks.iter().find_map(|m| match m {
+                BarMark::Jump(jump) => Some(*jump),
+                _ => None,
+            });
+            if let Some(jump) = jump {
+                jumped = Some(jump);
+                i = if jump.to_segno() {
+                    find(&BarMark::Segno).unwrap_or(0)
+                } else {
+                    0
+                };
+                section_start = i;
+                pass = 1;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Measure, Note, PitchClass, Tuning};
+
+    fn score(measures: usize) -> Score<1> {
+        let mut score = Score::<1>::new();
+        for i in 0..measures {
+            let tuning = Tuning::new(PitchClass::from_degree(i as i8 + 1), 4);
+            score.push_measures([Measure::from(vec![Note::new(tuning)])]);
+        }
+        score
+    }
+
+    #[test]
+    fn test_plain_repeat() {
+        let mut s = score(4);
+        s.mark(1, BarMark::RepeatStart);
+        s.mark(2, BarMark::repeat_end());
+        assert_eq!(s.unroll(), vec![0, 1, 2, 1, 2, 3]);
+
+        let mut s = score(3);
+        s.mark(1, BarMark::RepeatEnd(3));
+        assert_eq!(s.unroll(), vec![0, 1, 0, 1, 0, 1, 2]);
+    }
+
+    #[test]
+    fn test_voltas() {
+        let mut s = score(5);
+        s.mark(0, BarMark::RepeatStart);
+        s.mark(2, BarMark::volta(1));
+        s.mark(2, BarMark::repeat_end());
+        s.mark(3, BarMark::volta(2));
+        assert_eq!(s.unroll(), vec![0, 1, 2, 0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_two_repeated_sections() {
+        let mut s = score(6);
+        s.mark(1, BarMark::volta(1));
+        s.mark(1, BarMark::repeat_end());
+        s.mark(2, BarMark::volta(2));
+        s.mark(4, BarMark::repeat_end());
+        assert_eq!(s.unroll(), vec![0, 1, 0, 2, 3, 4, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_da_capo_al_fine() {
+        let mut s = score(4);
+        s.mark(0, BarMark::RepeatStart);
+        s.mark(1, BarMark::repeat_end());
+        s.mark(1, BarMark::Fine);
+        s.mark(3, BarMark::Jump(Jump::DaCapoAlFine));
+        assert_eq!(s.unroll(), vec![0, 1, 0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn test_dal_segno_al_coda() {
+        let mut s = score(6);
+        s.mark(1, BarMark::Segno);
+        s.mark(2, BarMark::ToCoda);
+        s.mark(3, BarMark::Jump(Jump::DalSegnoAlCoda));
+        s.mark(4, BarMark::Coda);
+        assert_eq!(s.unroll(), vec![0, 1, 2, 3, 1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn test_last_ending_after_jump() {
+        let mut s = score(4);
+        s.mark(1, BarMark::volta(1));
+        s.mark(1, BarMark::repeat_end());
+        s.mark(2, BarMark::volta(2));
+        s.mark(2, BarMark::Jump(Jump::DaCapo));
+        assert_eq!(s.unroll(), vec![0, 1, 0, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn test_unrolled_score() {
+        let mut s = score(3);
+        s.mark(1, BarMark::repeat_end());
+        let unrolled = s.unrolled();
+        assert!(!unrolled.has_marks());
+        assert_eq!(unrolled.measure_count(), 5);
+        assert_eq!(
+            unrolled.get_tracks()[0].get_measures()[2].to_string(),
+            s.get_tracks()[0].get_measures()[0].to_string()
+        );
+    }
+}
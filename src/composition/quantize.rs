@@ -0,0 +1,523 @@
+//! Rhythm quantization
+//!
+//! Turns performed onset / offset times (MIDI input, `OnsetDetector` results, imported
+//! files) into measures of notated values.
+//!
+//! Each beat is snapped to the grid among the candidates that best balances the timing
+//! error against the complexity of the notation, so a beat may be straight sixteenths and
+//! the next one a triplet. Notes crossing a barline or a tuplet beat are split and tied.
+//!
+//! ```
+//! use mutheors::{Duration, DurationBase, Quantizer, RhythmValue, TimeSignature};
+//! let quantizer = Quantizer::new(TimeSignature::new(4, DurationBase::Quarter));
+//! let measures = quantizer
+//!     .quantize_beats(&[(0.02, 0.98), (0.97, 2.51), (2.49, 4.0)])
+//!     .unwrap();
+//! assert_eq!(
+//!     measures[0],
+//!     vec![
+//!         RhythmValue::Note(Duration::new(DurationBase::Quarter)),
+//!         RhythmValue::Note(Duration::new(DurationBase::Quarter).dotted(1)),
+//!         RhythmValue::Note(Duration::new(DurationBase::Eighth)),
+//!         RhythmValue::Tied(Duration::new(DurationBase::Quarter)),
+//!     ]
+//! );
+//! ```
+
+use crate::{Duration, DurationBase, MusicError, RhythmValue, TimeSignature, Tuplet};
+
+/// Resolution of a beat, divisible by every supported grid
+const TICKS_PER_BEAT: u32 = 240;
+/// Cost of two performed onsets merging into one grid position, in beats
+const COLLISION_COST: f32 = 0.5;
+
+/// Grid based rhythm quantizer, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantizer {
+    time_signature: TimeSignature,
+    /// Candidate subdivisions of a beat
+    grids: Vec<u8>,
+    dots: bool,
+    tuplets: bool,
+    /// Weight of the notation complexity against the timing error
+    simplicity: f32,
+}
+
+impl Quantizer {
+    /// Quantizer with straight grids down to sixteenths of a quarter beat, triplets
+    /// and sextuplets
+    pub fn new(time_signature: TimeSignature) -> Self {
+        Quantizer {
+            time_signature,
+            grids: vec![1, 2, 4, 3, 6],
+            dots: true,
+            tuplets: true,
+            simplicity: 0.02,
+        }
+    }
+
+    /// Candidate subdivisions of a beat, among 1, 2, 3, 4, 5, 6, 8, 12 and 16
+    pub fn with_grids(self, grids: &[u8]) -> Self {
+        let grids = grids
+            .iter()
+            .copied()
+            .filter(|g| *g > 0 && TICKS_PER_BEAT.is_multiple_of(*g as u32))
+            .collect::<Vec<_>>();
+        Quantizer {
+            grids: match grids.is_empty() {
+                true => vec![1],
+                false => grids,
+            },
+            ..self
+        }
+    }
+
+    /// Allow dotted values
+    pub fn with_dots(self, dots: bool) -> Self {
+        Quantizer { dots, ..self }
+    }
+
+    /// Allow tuplet grids (subdivisions that are not a power of two)
+    pub fn with_tuplets(self, tuplets: bool) -> Self {
+        Quantizer { tuplets, ..self }
+    }
+
+    /// How strongly simpler grids are preferred, `0.0` picks the most accurate grid
+    pub fn with_simplicity(self, simplicity: f32) -> Self {
+        Quantizer {
+            simplicity: simplicity.max(0.0),
+            ..self
+        }
+    }
+
+    pub fn time_signature(&self) -> &TimeSignature {
+        &self.time_signature
+    }
+
+    /// Quantize `(onset, offset)` pairs in seconds at `tempo` beats per minute
+    pub fn quantize_seconds(
+        &self,
+        events: &[(f32, f32)],
+        tempo: f32,
+    ) -> Result<Vec<Vec<RhythmValue>>, MusicError> {
+        if tempo <= 0.0 || !tempo.is_finite() {
+            return Err(MusicError::TheoryViolation(format!(
+                "invalid tempo {tempo}"
+            )));
+        }
+        let beats = events
+            .iter()
+            .map(|(on, off)| (on * tempo / 60.0, off * tempo / 60.0))
+            .collect::<Vec<_>>();
+        self.quantize_beats(&beats)
+    }
+
+    /// Quantize onsets in seconds, such as `OnsetResult::onsets`, each note lasting until
+    /// the next one and the last one a beat
+    pub fn quantize_onsets(
+        &self,
+        onsets: &[f32],
+        tempo: f32,
+    ) -> Result<Vec<Vec<RhythmValue>>, MusicError> {
+        let mut onsets = onsets.to_vec();
+        onsets.sort_by(f32::total_cmp);
+        let events = onsets
+            .iter()
+            .enumerate()
+            .map(|(i, on)| {
+                let off = onsets.get(i + 1).copied().unwrap_or(on + 60.0 / tempo);
+                (*on, off)
+            })
+            .collect::<Vec<_>>();
+        self.quantize_seconds(&events, tempo)
+    }
+
+    /// Quantize `(onset, offset)` pairs in beats from the start of the first measure.
+    /// Returns the values of each measure, overlapping notes are cut at the next onset.
+    pub fn quantize_beats(
+        &self,
+        events: &[(f32, f32)],
+    ) -> Result<Vec<Vec<RhythmValue>>, MusicError> {
+        if let Some((on, off)) = events
+            .iter()
+            .find(|(on, off)| !on.is_finite() || !off.is_finite() || *on < 0.0)
+        {
+            return Err(MusicError::TheoryViolation(format!(
+                "cannot quantize the note from {on} to {off}"
+            )));
+        }
+
+        let mut events = events.to_vec();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let grids = self.choose_grids(&events);
+        let snap = |t: f32| -> u32 {
+            let beat = (t.floor() as usize).min(grids.len() - 1);
+            let grid = grids[beat] as f32;
+            let step = TICKS_PER_BEAT / grids[beat] as u32;
+            beat as u32 * TICKS_PER_BEAT + ((t - beat as f32) * grid).round() as u32 * step
+        };
+
+        // Snapped notes, monophonic
+        let mut notes: Vec<(u32, u32)> = vec![];
+        for (on, off) in &events {
+            let start = snap(*on);
+            if notes.last().is_some_and(|(s, _)| *s == start) {
+                continue;
+            }
+            if let Some(last) = notes.last_mut() {
+                last.1 = last.1.min(start);
+            }
+            let beat = (start / TICKS_PER_BEAT) as usize;
+            let step = TICKS_PER_BEAT / grids[beat.min(grids.len() - 1)] as u32;
+            notes.push((start, snap(*off).max(start + step)));
+        }
+        for i in 1..notes.len() {
+            let next = notes[i].0;
+            let previous = &mut notes[i - 1];
+            previous.1 = previous.1.min(next);
+        }
+
+        let measure_ticks = self.time_signature.beats_per_measure().max(1) as u32 * TICKS_PER_BEAT;
+        let end = notes.last().map(|n| n.1).unwrap_or(0);
+        let measure_count = end.div_ceil(measure_ticks).max(1);
+
+        // Notes and rests covering every measure
+        let mut segments = vec![];
+        let mut position = 0;
+        for (start, end) in notes {
+            if start > position {
+                segments.push((position, start, false));
+            }
+            segments.push((start, end, true));
+            position = end;
+        }
+        if position < measure_count * measure_ticks {
+            segments.push((position, measure_count * measure_ticks, false));
+        }
+
+        let mut measures = vec![vec![]; measure_count as usize];
+        for (start, end, sounding) in segments {
+            let mut first = true;
+            for (from, to) in self.split_points(start, end, measure_ticks, &grids) {
+                let measure = (from / measure_ticks) as usize;
+                for duration in self.durations(from, to, &grids) {
+                    measures[measure].push(match (sounding, first) {
+                        (false, _) => RhythmValue::Rest(duration),
+                        (true, true) => RhythmValue::Note(duration),
+                        (true, false) => RhythmValue::Tied(duration),
+                    });
+                    first = false;
+                }
+            }
+        }
+        Ok(measures)
+    }
+
+    /// The candidate subdivision of each beat with the lowest cost
+    fn choose_grids(&self, events: &[(f32, f32)]) -> Vec<u8> {
+        let beats = events
+            .iter()
+            .map(|(on, off)| on.max(*off).ceil() as usize + 1)
+            .max()
+            .unwrap_or(1);
+        let candidates = self
+            .grids
+            .iter()
+            .copied()
+            .filter(|g| self.tuplets || g.is_power_of_two())
+            .collect::<Vec<_>>();
+
+        (0..beats)
+            .map(|beat| {
+                let in_beat = |t: &f32| t.floor() as usize == beat;
+                let onsets = events
+                    .iter()
+                    .map(|(on, _)| *on)
+                    .filter(in_beat)
+                    .collect::<Vec<_>>();
+                let times = events
+                    .iter()
+                    .flat_map(|(on, off)| [*on, *off])
+                    .filter(in_beat)
+                    .collect::<Vec<_>>();
+                if times.is_empty() {
+                    return candidates.iter().copied().min().unwrap_or(1);
+                }
+
+                let cost = |grid: u8| {
+                    let snap = |t: f32| (t * grid as f32).round();
+                    let error = times
+                        .iter()
+                        .map(|t| (t - snap(*t) / grid as f32).abs())
+                        .sum::<f32>();
+                    let mut snapped = onsets.iter().map(|t| snap(*t) as i64).collect::<Vec<_>>();
+                    snapped.dedup();
+                    let collisions = onsets.len() - snapped.len();
+                    error + collisions as f32 * COLLISION_COST + self.simplicity * complexity(grid)
+                };
+                candidates
+                    .iter()
+                    .copied()
+                    .min_by(|a, b| cost(*a).total_cmp(&cost(*b)))
+                    .unwrap_or(1)
+            })
+            .collect()
+    }
+
+    /// Split a span at barlines and at the borders of tuplet beats
+    fn split_points(
+        &self,
+        start: u32,
+        end: u32,
+        measure_ticks: u32,
+        grids: &[u8],
+    ) -> Vec<(u32, u32)> {
+        let is_tuplet = |beat: u32| {
+            grids
+                .get(beat as usize)
+                .is_some_and(|g| !g.is_power_of_two())
+        };
+        let mut cuts = vec![start];
+        for tick in (start / TICKS_PER_BEAT + 1) * TICKS_PER_BEAT..end {
+            if !tick.is_multiple_of(TICKS_PER_BEAT) {
+                continue;
+            }
+            let beat = tick / TICKS_PER_BEAT;
+            if tick.is_multiple_of(measure_ticks) || is_tuplet(beat) || is_tuplet(beat - 1) {
+                cuts.push(tick);
+            }
+        }
+        cuts.push(end);
+        cuts.windows(2).map(|w| (w[0], w[1])).collect()
+    }
+
+    /// Notated values of a span that stays inside a measure and a tuplet beat
+    fn durations(&self, start: u32, end: u32, grids: &[u8]) -> Vec<Duration> {
+        let grid = grids
+            .get((start / TICKS_PER_BEAT) as usize)
+            .copied()
+            .unwrap_or(1);
+
+        if grid.is_power_of_two() {
+            let measure_ticks =
+                self.time_signature.beats_per_measure().max(1) as u32 * TICKS_PER_BEAT;
+            let origin = start - start % measure_ticks;
+            let beats = |ticks: u32| ticks as f32 / TICKS_PER_BEAT as f32;
+            return match self
+                .time_signature
+                .decompose(beats(start - origin), beats(end - start))
+            {
+                Ok(durations) if self.dots => durations,
+                Ok(durations) => durations.into_iter().flat_map(undotted).collect(),
+                // Finer than the meter resolves
+                Err(_) => self.split(start - origin, end - origin, TICKS_PER_BEAT, None),
+            };
+        }
+
+        // Tuplet beat: `grid` values in the time of `normal` ones
+        let normal = 1u8 << (7 - grid.leading_zeros());
+        let origin = start - start % TICKS_PER_BEAT;
+        let nominal_beat = normal as u32 * TICKS_PER_BEAT / grid as u32;
+        self.split(
+            start - origin,
+            end - origin,
+            nominal_beat,
+            Some((grid, normal)),
+        )
+    }
+
+    /// Split into the fewest plain or dotted values, each aligned on its own length.
+    /// Positions are ticks from the start of the measure or of the tuplet beat, where a
+    /// beat of the time signature lasts `beat_ticks`.
+    fn split(
+        &self,
+        start: u32,
+        end: u32,
+        beat_ticks: u32,
+        tuplet: Option<(u8, u8)>,
+    ) -> Vec<Duration> {
+        let beat_quarters = self.time_signature.beat_type().in_quarters();
+        let ticks_of = |d: &Duration| {
+            let ticks = d.in_quarters() / beat_quarters * beat_ticks as f32;
+            (ticks.fract().abs() < 1e-3).then_some(ticks.round() as u32)
+        };
+        let candidates = [
+            DurationBase::Whole,
+            DurationBase::Half,
+            DurationBase::Quarter,
+            DurationBase::Eighth,
+            DurationBase::Sixteenth,
+            DurationBase::ThirtySecond,
+            DurationBase::SixtyFourth,
+        ]
+        .into_iter()
+        .flat_map(|base| {
+            let plain = Duration::new(base);
+            let align = ticks_of(&plain);
+            [plain.dotted(1), plain]
+                .into_iter()
+                .filter(|d| self.dots || d.dots == 0)
+                .filter_map(move |d| Some((d, ticks_of(&d)?, align?)))
+        })
+        .filter(|(_, ticks, align)| *ticks > 0 && *align > 0)
+        .collect::<Vec<_>>();
+
+        // Fewest values, dotted ones cost a little more, values off their own grid a lot
+        let length = (end - start) as usize;
+        let mut best: Vec<Option<(f32, usize)>> = vec![None; length + 1];
+        best[length] = Some((0.0, 0));
+        for offset in (0..length).rev() {
+            let position = start + offset as u32;
+            best[offset] = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, ticks, _))| offset + *ticks as usize <= length)
+                .filter_map(|(i, (duration, ticks, align))| {
+                    let (rest, _) = best[offset + *ticks as usize]?;
+                    let misaligned = !position.is_multiple_of(*align);
+                    let cost = 1.0
+                        + duration.dots as f32 * 0.1
+                        + if misaligned { 10.0 } else { 0.0 }
+                        + rest;
+                    Some((cost, i))
+                })
+                .min_by(|a, b| a.0.total_cmp(&b.0));
+        }
+
+        let mut offset = 0;
+        let mut durations = vec![];
+        while let Some((_, i)) = best[offset].filter(|_| offset < length) {
+            let (duration, ticks, _) = candidates[i];
+            durations.push(match tuplet {
+                Some((actual, normal)) => match Tuplet::new(actual, normal, duration.base) {
+                    Ok(t) => duration.with_tuplet(t),
+                    Err(_) => duration,
+                },
+                None => duration,
+            });
+            offset += ticks as usize;
+        }
+        durations
+    }
+}
+
+/// A dotted value as its plain value tied to the next shorter one
+fn undotted(duration: Duration) -> Vec<Duration> {
+    let shorter = DurationBase::from_quarters(duration.base.in_quarters() / 2.0);
+    match (duration.dots, shorter) {
+        (0, _) | (_, Err(_)) => vec![duration],
+        (_, Ok(shorter)) => vec![Duration::new(duration.base), Duration::new(shorter)],
+    }
+}
+
+/// Notation complexity of a beat subdivision
+fn complexity(grid: u8) -> f32 {
+    match grid {
+        1 => 0.0,
+        2 => 1.0,
+        4 => 2.0,
+        3 => 2.5,
+        8 => 3.0,
+        6 => 3.5,
+        16 => 4.0,
+        5 => 5.0,
+        _ => 6.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quantizer() -> Quantizer {
+        Quantizer::new(TimeSignature::new(4, DurationBase::Quarter))
+    }
+
+    fn note(base: DurationBase) -> RhythmValue {
+        RhythmValue::Note(Duration::new(base))
+    }
+
+    #[test]
+    fn test_quantize_straight_and_dotted() {
+        let measures = quantizer()
+            .quantize_beats(&[(0.03, 0.48), (0.52, 1.0), (1.01, 2.48), (3.0, 4.0)])
+            .unwrap();
+        assert_eq!(measures.len(), 1);
+        assert_eq!(
+            measures[0],
+            vec![
+                note(DurationBase::Eighth),
+                note(DurationBase::Eighth),
+                RhythmValue::Note(Duration::new(DurationBase::Quarter).dotted(1)),
+                RhythmValue::Rest(Duration::new(DurationBase::Eighth)),
+                note(DurationBase::Quarter),
+            ]
+        );
+
+        // Held notes are tied over the barline
+        let measures = quantizer().quantize_beats(&[(2.0, 6.0)]).unwrap();
+        assert_eq!(measures.len(), 2);
+        assert_eq!(
+            measures[0],
+            vec![
+                RhythmValue::Rest(Duration::new(DurationBase::Half)),
+                note(DurationBase::Half),
+            ]
+        );
+        assert_eq!(
+            measures[1],
+            vec![
+                RhythmValue::Tied(Duration::new(DurationBase::Half)),
+                RhythmValue::Rest(Duration::new(DurationBase::Half)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quantize_triplets() {
+        let triplet = Duration::new(DurationBase::Eighth)
+            .with_tuplet(Tuplet::new(3, 2, DurationBase::Eighth).unwrap());
+        let measures = quantizer()
+            .quantize_beats(&[(0.0, 0.3), (0.34, 0.65), (0.68, 1.0), (1.0, 4.0)])
+            .unwrap();
+        assert_eq!(
+            measures[0][..3],
+            [RhythmValue::Note(triplet); 3],
+            "{:?}",
+            measures[0]
+        );
+        let total = measures[0]
+            .iter()
+            .map(|v| v.duration().in_quarters())
+            .sum::<f32>();
+        assert!((total - 4.0).abs() < 1e-4);
+
+        // Without tuplets the same beat falls on the straight grid
+        let measures = quantizer()
+            .with_tuplets(false)
+            .quantize_beats(&[(0.0, 0.3), (0.34, 0.65), (0.68, 1.0)])
+            .unwrap();
+        assert!(measures[0].iter().all(|v| v.duration().tuplet.is_none()));
+    }
+
+    #[test]
+    fn test_quantize_seconds() {
+        // 120 bpm, a beat every half second
+        let measures = quantizer()
+            .quantize_onsets(&[0.0, 0.51, 0.74, 1.0], 120.0)
+            .unwrap();
+        assert_eq!(
+            measures[0],
+            vec![
+                note(DurationBase::Quarter),
+                note(DurationBase::Eighth),
+                note(DurationBase::Eighth),
+                note(DurationBase::Quarter),
+                RhythmValue::Rest(Duration::new(DurationBase::Quarter)),
+            ]
+        );
+        assert!(quantizer().quantize_seconds(&[(0.0, 1.0)], 0.0).is_err());
+        assert!(quantizer().quantize_beats(&[(-1.0, 1.0)]).is_err());
+    }
+}
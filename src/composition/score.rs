@@ -1,20 +1,20 @@
 use crate::composition::measure::Measure;
 use crate::composition::track::Track;
-use crate::{DurationBase, DurationGenerator, TempoLike};
+use crate::{
+    BarMark, DurationBase, DurationGenerator, KeySignature, MusicError, TempoLike, TimeSignature,
+};
 use std::array;
+use std::collections::HashMap;
 use std::fmt::Display;
 use std::rc::Rc;
 
-#[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
-pub struct TimeSignature {
-    beats_per_measure: u8,
-    beat_type: DurationBase,
-}
-
+#[derive(Clone)]
 pub struct Score<const TRACK_COUNT: usize> {
-    tracks: [Track; TRACK_COUNT],
+    pub(crate) tracks: [Track; TRACK_COUNT],
     tempo: f32,
     time_signature: TimeSignature,
+    key_signature: KeySignature,
+    pub(crate) marks: HashMap<usize, Vec<BarMark>>,
 
     duration_generator: Rc<DurationGenerator>,
     current_measure: usize,
@@ -26,6 +26,8 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
             tracks: array::from_fn(|_| Track::new()),
             tempo: 120.0,
             time_signature: TimeSignature::new(4, DurationBase::Quarter),
+            key_signature: KeySignature::default(),
+            marks: HashMap::new(),
             duration_generator: Rc::new(DurationGenerator::new(DurationBase::Quarter)),
             current_measure: 0,
         }
@@ -46,6 +48,22 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
         }
     }
 
+    /// Same as `with_time_signature`, keeping the beat grouping of the time signature
+    pub fn with_meter(self, time_signature: TimeSignature) -> Self {
+        Score {
+            time_signature,
+            duration_generator: Rc::new(DurationGenerator::new(time_signature.beat_type())),
+            ..self
+        }
+    }
+
+    pub fn with_key_signature(self, key_signature: KeySignature) -> Self {
+        Score {
+            key_signature,
+            ..self
+        }
+    }
+
     pub fn push_measures(&mut self, measures: [Measure; TRACK_COUNT]) {
         self.tracks
             .iter_mut()
@@ -63,16 +81,23 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
         let mut new_measure: [Measure; TRACK_COUNT] = array::from_fn(|_| Measure::new());
         f(&mut new_measure);
 
+        // Each track is checked against its own meter
         let measure_check = new_measure.iter().enumerate().filter_map(|(i, measure)| {
+            let time_signature = self.track_time_signature(i);
+            let dg = DurationGenerator::new(time_signature.beat_type());
             return match measure {
                 Measure::Note(notes) => {
-                    let total = notes.iter().fold(0.0f32, |acc, note| {
-                        let beats = note.duration().in_beats(&self.duration_generator());
-                        beats + acc
-                    });
-
-                    if total > self.time_signature.beats_per_measure as f32 {
-                        Some((i, total))
+                    let total =
+                        notes
+                            .iter()
+                            .filter(|note| !note.is_grace())
+                            .fold(0.0f32, |acc, note| {
+                                let beats = note.duration().in_beats(&dg);
+                                beats + acc
+                            });
+
+                    if total > time_signature.beats_per_measure() as f32 {
+                        Some((i, total, time_signature.beats_per_measure()))
                     } else {
                         None
                     }
@@ -84,7 +109,7 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
         measure_check.for_each(|track| {
             eprintln!(
                 "Track {}: measure [{}] in [{} beats] that exceeds the time signature [{}] please check the measures ",
-                track.0, self.current_measure, track.1, self.time_signature.beats_per_measure
+                track.0, self.current_measure, track.1, track.2
             );
         });
         self.push_measures(new_measure);
@@ -95,6 +120,24 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
         &self.tracks
     }
 
+    /// Spread a lyric line over the notes of a track, see `Track::set_lyrics`
+    pub fn set_lyrics(&mut self, track: usize, verse: u8, line: &str) -> Result<(), MusicError> {
+        self.tracks
+            .get_mut(track)
+            .ok_or(MusicError::InvalidTrack(track))?
+            .set_lyrics(verse, line);
+        Ok(())
+    }
+
+    /// Number of measures of the longest track
+    pub fn measure_count(&self) -> usize {
+        self.tracks
+            .iter()
+            .map(|t| t.get_measures().len())
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn tempo(&self) -> f32 {
         self.tempo
     }
@@ -103,6 +146,52 @@ impl<const TRACK_COUNT: usize> Score<TRACK_COUNT> {
         &self.time_signature
     }
 
+    pub fn key_signature(&self) -> &KeySignature {
+        &self.key_signature
+    }
+
+    /// Run a track in its own meter, see `Track::set_time_signature`
+    pub fn set_track_time_signature(
+        &mut self,
+        track: usize,
+        time_signature: TimeSignature,
+    ) -> Result<(), MusicError> {
+        self.tracks
+            .get_mut(track)
+            .ok_or(MusicError::InvalidTrack(track))?
+            .set_time_signature(time_signature);
+        Ok(())
+    }
+
+    /// Play a track in a tuplet against the score, see `Track::set_tuplet`
+    pub fn set_track_tuplet(
+        &mut self,
+        track: usize,
+        actual: u8,
+        normal: u8,
+    ) -> Result<(), MusicError> {
+        self.tracks
+            .get_mut(track)
+            .ok_or(MusicError::InvalidTrack(track))?
+            .set_tuplet(actual, normal);
+        Ok(())
+    }
+
+    /// Meter of a track, its own or the score's
+    pub fn track_time_signature(&self, track: usize) -> TimeSignature {
+        self.tracks
+            .get(track)
+            .and_then(|t| t.time_signature().copied())
+            .unwrap_or(self.time_signature)
+    }
+
+    /// Length of a measure of a track in beats of the score
+    pub fn track_measure_beats(&self, track: usize) -> f32 {
+        let time_signature = self.track_time_signature(track);
+        let stretch = self.tracks.get(track).map_or(1.0, |t| t.stretch());
+        time_signature.measure_quarters() / self.time_signature.beat_type().in_quarters() * stretch
+    }
+
     pub fn duration_generator(&self) -> Rc<DurationGenerator> {
         self.duration_generator.clone()
     }
@@ -113,7 +202,9 @@ impl<const TRACK_COUNT: usize> Display for Score<TRACK_COUNT> {
         writeln!(
             f,
             "Tempo: {}\n {}\n---\n {}",
-            self.tempo, self.time_signature.beats_per_measure, self.time_signature.beat_type as u8
+            self.tempo,
+            self.time_signature.beats_per_measure(),
+            self.time_signature.beat_type() as u8
         )?;
 
         for (_i, track) in self.tracks.iter().enumerate() {
@@ -126,23 +217,6 @@ impl<const TRACK_COUNT: usize> Display for Score<TRACK_COUNT> {
     }
 }
 
-impl TimeSignature {
-    pub fn new(beats_per_measure: u8, beat_type: DurationBase) -> Self {
-        TimeSignature {
-            beats_per_measure,
-            beat_type,
-        }
-    }
-
-    pub fn beats_per_measure(&self) -> u8 {
-        self.beats_per_measure
-    }
-
-    pub fn beat_type(&self) -> DurationBase {
-        self.beat_type
-    }
-}
-
 impl From<Measure> for Score<1> {
     fn from(value: Measure) -> Self {
         let mut score = Score::new();
@@ -0,0 +1,416 @@
+//! Score timeline
+//!
+//! Flattens a `Score` into absolutely timed events (in seconds) with effective velocities.
+//! This is the common input of every renderer: MIDI playback, audio synthesis, exporters.
+
+use crate::{Chord, Interpretation, Measure, Note, PercussionNote, Score, Tuning};
+
+/// Where a timeline event comes from
+#[derive(Debug, Clone)]
+pub enum EventSource {
+    Chord(Chord),
+    Note(Note),
+    Percussion(PercussionNote),
+}
+
+/// A sounding event
+#[derive(Debug, Clone)]
+pub struct TimelineEvent {
+    /// Index of the track in the score
+    pub track: usize,
+    /// Index of the notated measure in the track (repeats are unrolled, so several
+    /// events may come from the same measure)
+    pub measure: usize,
+    /// Start time in seconds
+    pub start: f64,
+    /// Sounding duration in seconds
+    pub duration: f64,
+    /// Pitches sounding together, empty for percussion
+    pub tunings: Vec<Tuning>,
+    /// MIDI velocity [1, 127]
+    pub velocity: u8,
+    pub source: EventSource,
+}
+
+impl TimelineEvent {
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+
+    /// Unpitched event, played on the General MIDI percussion channel
+    pub fn is_percussion(&self) -> bool {
+        matches!(self.source, EventSource::Percussion(_))
+    }
+}
+
+/// Time-ordered events of a whole score
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    events: Vec<TimelineEvent>,
+}
+
+/// Event positioned in beats, before tempo and fermatas are applied
+struct BeatEvent {
+    track: usize,
+    measure: usize,
+    onset: f32,
+    duration: f32,
+    tunings: Vec<Tuning>,
+    velocity: u8,
+    source: EventSource,
+}
+
+impl Timeline {
+    pub fn new<const TRACK_COUNT: usize>(score: &Score<TRACK_COUNT>) -> Self {
+        Self::with_interpretation(score, &Interpretation::default())
+    }
+
+    pub fn with_interpretation<const TRACK_COUNT: usize>(
+        score: &Score<TRACK_COUNT>,
+        interpretation: &Interpretation,
+    ) -> Self {
+        let dg = score.duration_generator();
+        let chord_velocity = interpretation.velocity_of(interpretation.default_dynamic());
+
+        // Repeats and jumps are played out
+        let order = score.unroll();
+
+        let mut beat_events = vec![];
+        // (position in beats, extra beats) pauses caused by fermatas
+        let mut holds: Vec<(f32, f32)> = vec![];
+
+        for (track_idx, track) in score.get_tracks().iter().enumerate() {
+            let mut voice: Vec<(usize, f32, &Note)> = vec![];
+            // Tracks in their own meter or tuplet have their own measure length, and
+            // their notated beats may be stretched against the score
+            let beats_per_measure = score.track_measure_beats(track_idx);
+            let stretch = track.stretch();
+            let track_beat = beats_per_measure
+                / score
+                    .track_time_signature(track_idx)
+                    .beats_per_measure()
+                    .max(1) as f32;
+
+            for (position, &measure_idx) in order.iter().enumerate() {
+                let Some(measure) = track.get_measures().get(measure_idx) else {
+                    continue;
+                };
+                let measure_start = position as f32 * beats_per_measure;
+                match measure {
+                    Measure::Rest => {}
+                    Measure::Chords(chords) => {
+                        let length = beats_per_measure / chords.len() as f32;
+                        for (i, chord) in chords.iter().enumerate() {
+                            beat_events.push(BeatEvent {
+                                track: track_idx,
+                                measure: measure_idx,
+                                onset: measure_start + length * i as f32,
+                                duration: length,
+                                tunings: chord.components(),
+                                velocity: chord_velocity,
+                                source: EventSource::Chord(chord.clone()),
+                            });
+                        }
+                    }
+                    Measure::Percussion(notes) => {
+                        for note in notes {
+                            let onset = measure_start + note.position() * track_beat;
+                            for rendition in interpretation.render_percussion(onset, note) {
+                                beat_events.push(BeatEvent {
+                                    track: track_idx,
+                                    measure: measure_idx,
+                                    onset: rendition.onset,
+                                    duration: rendition.duration,
+                                    tunings: vec![],
+                                    velocity: rendition.velocity,
+                                    source: EventSource::Percussion(*note),
+                                });
+                            }
+                        }
+                    }
+                    Measure::Note(notes) => {
+                        let mut offset = 0.0;
+                        for note in notes {
+                            if !note.is_rest() {
+                                voice.push((measure_idx, measure_start + offset, note));
+                            }
+                            if !note.is_grace() {
+                                offset += note.duration().in_beats(&dg) * stretch;
+                            }
+                        }
+                    }
+                }
+            }
+
+            let placed = voice
+                .iter()
+                .map(|(_, onset, note)| (*onset, *note))
+                .collect::<Vec<_>>();
+            let renditions = interpretation.render(&placed, &dg);
+
+            for ((measure_idx, onset, note), rendition) in voice.into_iter().zip(renditions) {
+                if rendition.hold > 0.0 {
+                    let end = onset + note.duration().in_beats(&dg) * stretch;
+                    holds.push((end, rendition.hold * stretch));
+                }
+                beat_events.push(BeatEvent {
+                    track: track_idx,
+                    measure: measure_idx,
+                    onset: rendition.onset,
+                    duration: rendition.duration * stretch,
+                    tunings: note.tuning().into_iter().collect(),
+                    velocity: rendition.velocity,
+                    source: EventSource::Note(note.clone()),
+                });
+            }
+        }
+
+        // Swing, feel and humanization move the rendered notes, not the notation
+        let groove = interpretation.groove();
+        if !groove.is_straight() {
+            for e in &mut beat_events {
+                let key = ((e.track as u64) << 32) ^ e.onset.to_bits() as u64;
+                (e.onset, e.duration, e.velocity) =
+                    groove.apply(e.onset, e.duration, e.velocity, &dg, key);
+            }
+            for hold in &mut holds {
+                hold.0 = groove.warp(hold.0, &dg);
+            }
+        }
+
+        Self::schedule(beat_events, holds, score.tempo())
+    }
+
+    /// Apply fermata pauses and tempo
+    fn schedule(beat_events: Vec<BeatEvent>, mut holds: Vec<(f32, f32)>, tempo: f32) -> Self {
+        const EPSILON: f32 = 1e-4;
+
+        // Simultaneous fermatas in several tracks pause only once
+        holds.sort_by(|a, b| a.0.total_cmp(&b.0));
+        holds.dedup_by(|b, a| {
+            if (a.0 - b.0).abs() < EPSILON {
+                a.1 = a.1.max(b.1);
+                true
+            } else {
+                false
+            }
+        });
+
+        let warp = |beat: f32| -> f32 {
+            beat + holds
+                .iter()
+                .take_while(|(position, _)| *position <= beat + EPSILON)
+                .map(|(_, extra)| extra)
+                .sum::<f32>()
+        };
+        let seconds_per_beat = 60.0 / tempo as f64;
+
+        let mut events = beat_events
+            .into_iter()
+            .map(|e| {
+                let start = warp(e.onset);
+                let end = warp(e.onset + e.duration);
+                TimelineEvent {
+                    track: e.track,
+                    measure: e.measure,
+                    start: start as f64 * seconds_per_beat,
+                    duration: (end - start) as f64 * seconds_per_beat,
+                    tunings: e.tunings,
+                    velocity: e.velocity,
+                    source: e.source,
+                }
+            })
+            .collect::<Vec<_>>();
+        events.sort_by(|a, b| a.start.total_cmp(&b.start).then(a.track.cmp(&b.track)));
+
+        Timeline { events }
+    }
+
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    /// Time at which the last event stops sounding, in seconds
+    pub fn duration(&self) -> f64 {
+        self.events.iter().map(|e| e.end()).fold(0.0, f64::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BarMark, DrumPattern, DrumSound, DurationBase, Dynamic, Grace, Groove, PitchClass,
+    };
+
+    fn c4() -> Note {
+        Note::new(Tuning::new(PitchClass::C, 4))
+    }
+
+    #[test]
+    fn test_timeline_positions() {
+        let mut score = Score::<2>::new().with_tempo(60.0);
+        score.new_measures(|m| {
+            m[0].note(vec![c4(), c4().with_dynamic(Dynamic::Forte), c4(), c4()]);
+            m[1].chord(Chord::from_symbol("C").unwrap());
+        });
+
+        let timeline = Timeline::new(&score);
+        let events = timeline.events();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].start, 0.0);
+        assert_eq!(events[0].track, 0);
+        assert_eq!(events[1].track, 1);
+        assert_eq!(events[1].duration, 4.0);
+        assert_eq!(events[1].tunings.len(), 3);
+        assert_eq!(events[2].start, 1.0);
+        assert_eq!(events[2].velocity, 96);
+        assert!((events[0].duration - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_timeline_fermata_pauses_all_tracks() {
+        let mut score = Score::<2>::new()
+            .with_tempo(60.0)
+            .with_time_signature(2, DurationBase::Quarter);
+        score.push_measures([
+            Measure::from(vec![c4(), c4().with_fermata()]),
+            Measure::from(vec![c4(), c4()]),
+        ]);
+        score.push_measures([Measure::from(vec![c4(), c4()]), Measure::Rest]);
+
+        let timeline = Timeline::new(&score);
+        let fermata = &timeline.events()[2];
+        assert_eq!(fermata.start, 1.0);
+        assert_eq!(fermata.duration, 2.0);
+
+        let after = timeline.events().iter().find(|e| e.measure == 1).unwrap();
+        assert_eq!(after.start, 3.0);
+        assert!((timeline.duration() - 4.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_timeline_plays_repeats() {
+        let mut score = Score::<1>::new().with_tempo(60.0);
+        score.push_measures([Measure::from(vec![
+            c4().with_duration(crate::Duration::new(DurationBase::Whole))
+        ])]);
+        score.push_measures([Measure::Rest]);
+        score.mark(0, BarMark::repeat_end());
+
+        let events = Timeline::new(&score).events().to_vec();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].measure, 0);
+        assert_eq!(events[1].start, 4.0);
+    }
+
+    #[test]
+    fn test_timeline_percussion() {
+        let mut score = Score::<1>::new().with_tempo(60.0);
+        score.push_measures([DrumPattern::new(4, 1)
+            .grid(DrumSound::BassDrum, "x.X.")
+            .flam(DrumSound::AcousticSnare, 1, 0.5)
+            .measures(4)
+            .remove(0)]);
+
+        let events = Timeline::new(&score).events().to_vec();
+        assert_eq!(events.len(), 4);
+        assert!(events
+            .iter()
+            .all(|e| e.is_percussion() && e.tunings.is_empty()));
+        assert_eq!(events[0].velocity, 80);
+        // Grace stroke of the flam
+        assert!(events[1].start < 1.0);
+        assert!(events[1].velocity < events[2].velocity);
+        assert_eq!(events[2].start, 1.0);
+        assert_eq!(events[3].velocity, 127);
+    }
+
+    #[test]
+    fn test_timeline_grace_takes_no_time() {
+        let mut score = Score::<1>::new().with_tempo(60.0);
+        score.new_measures(|m| {
+            m[0].note(vec![c4().with_grace(Grace::Acciaccatura), c4(), c4()]);
+        });
+        let events = Timeline::new(&score).events().to_vec();
+        assert_eq!(events[0].start, 0.0);
+        assert_eq!(events[1].start, 0.125);
+        assert_eq!(events[2].start, 1.0);
+    }
+
+    #[test]
+    fn test_timeline_rests_are_silent() {
+        let rest = Note::rest(crate::Duration::new(DurationBase::Half));
+        let mut score = Score::<1>::new().with_tempo(60.0);
+        score.new_measures(|m| m[0].note(vec![c4(), rest, c4()]));
+        let events = Timeline::new(&score).events().to_vec();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].start, 3.0);
+    }
+
+    #[test]
+    fn test_timeline_swing() {
+        let eighth = || c4().with_duration(crate::Duration::new(DurationBase::Eighth));
+        let mut score = Score::<1>::new().with_tempo(60.0);
+        score.new_measures(|m| m[0].note(vec![eighth(), eighth(), c4()]));
+
+        let interpretation = Interpretation::new()
+            .with_gate(1.0)
+            .with_groove(Groove::new().with_swing_ratio(DurationBase::Eighth, 2, 1));
+        let events = Timeline::with_interpretation(&score, &interpretation)
+            .events()
+            .to_vec();
+        assert!((events[1].start - 2.0 / 3.0).abs() < 1e-5);
+        assert!((events[0].duration - 2.0 / 3.0).abs() < 1e-5);
+        assert_eq!(events[2].start, 1.0);
+        // The notation is untouched
+        let EventSource::Note(note) = &events[1].source else {
+            panic!("expected a note");
+        };
+        assert_eq!(note.duration(), crate::Duration::new(DurationBase::Eighth));
+    }
+
+    #[test]
+    fn test_timeline_polymeter() {
+        use crate::TimeSignature;
+
+        let quarter = || c4().with_duration(crate::Duration::new(DurationBase::Quarter));
+        let mut score = Score::<3>::new().with_tempo(60.0);
+        score
+            .set_track_time_signature(1, TimeSignature::new(5, DurationBase::Quarter))
+            .unwrap();
+        // Three quarters in the time of four
+        score
+            .set_track_time_signature(2, TimeSignature::new(3, DurationBase::Quarter))
+            .unwrap();
+        score.set_track_tuplet(2, 3, 4).unwrap();
+        assert!(score.set_track_tuplet(3, 3, 2).is_err());
+        for _ in 0..2 {
+            score.new_measures(|m| {
+                m[0].note(vec![quarter(); 4]);
+                m[1].note(vec![quarter(); 5]);
+                m[2].note(vec![quarter(); 3]);
+            });
+        }
+        assert_eq!(score.track_measure_beats(1), 5.0);
+        assert_eq!(score.track_measure_beats(2), 4.0);
+
+        let events = Timeline::new(&score).events().to_vec();
+        let starts = |track: usize| {
+            events
+                .iter()
+                .filter(|e| e.track == track)
+                .map(|e| e.start)
+                .collect::<Vec<_>>()
+        };
+        // The 5/4 track drifts against the 4/4 barlines
+        assert_eq!(starts(0)[4], 4.0);
+        assert_eq!(starts(1)[5], 5.0);
+        // The triplet track meets the 4/4 track on every barline
+        let triplet = starts(2);
+        assert!((triplet[1] - 4.0 / 3.0).abs() < 1e-5);
+        assert_eq!(triplet[3], 4.0);
+        let last = events.iter().rfind(|e| e.track == 2).unwrap();
+        assert!((last.duration - 0.9 * 4.0 / 3.0).abs() < 1e-5);
+    }
+}
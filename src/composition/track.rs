@@ -1,13 +1,50 @@
 use crate::composition::measure::Measure;
+use crate::{Lyric, TimeSignature};
 
 #[derive(Clone)]
 pub struct Track {
     pub(crate) measures: Vec<Measure>,
+    /// Own meter of the track, the score's one when `None`
+    time_signature: Option<TimeSignature>,
+    /// `actual` beats of the track in the time of `normal` beats of the score
+    tuplet: Option<(u8, u8)>,
 }
 
 impl Track {
     pub fn new() -> Self {
-        Track { measures: vec![] }
+        Track {
+            measures: vec![],
+            time_signature: None,
+            tuplet: None,
+        }
+    }
+
+    /// Run the track in its own meter, e.g. 5/4 over a 4/4 score. Beats keep the
+    /// score's pace, so the barlines drift apart (polymeter).
+    pub fn set_time_signature(&mut self, time_signature: TimeSignature) {
+        self.time_signature = Some(time_signature);
+    }
+
+    /// Play `actual` beats of the track in the time of `normal` beats of the score, e.g.
+    /// 3 against 4 (polyrhythm).
+    pub fn set_tuplet(&mut self, actual: u8, normal: u8) {
+        self.tuplet = (actual > 0 && normal > 0 && actual != normal).then_some((actual, normal));
+    }
+
+    pub fn time_signature(&self) -> Option<&TimeSignature> {
+        self.time_signature.as_ref()
+    }
+
+    pub fn tuplet(&self) -> Option<(u8, u8)> {
+        self.tuplet
+    }
+
+    /// Score time taken by the track's notated time
+    pub(crate) fn stretch(&self) -> f32 {
+        match self.tuplet {
+            Some((actual, normal)) => normal as f32 / actual as f32,
+            None => 1.0,
+        }
     }
 
     pub fn push(&mut self, measure: Measure) {
@@ -17,4 +54,28 @@ impl Track {
     pub fn get_measures(&self) -> &[Measure] {
         &self.measures
     }
+
+    /// Spread a lyric line (see `Lyric::parse_line`) over the notes of the track, grace
+    /// notes and rests excluded. Extra syllables are dropped.
+    pub fn set_lyrics(&mut self, verse: u8, line: &str) {
+        let mut syllables = Lyric::parse_line(line, verse).into_iter();
+
+        let notes = self
+            .measures
+            .iter_mut()
+            .filter_map(|measure| match measure {
+                Measure::Note(notes) => Some(notes),
+                _ => None,
+            })
+            .flatten()
+            .filter(|note| !note.is_grace() && !note.is_rest());
+
+        for note in notes {
+            match syllables.next() {
+                Some(Some(lyric)) => *note = note.clone().with_lyric(lyric),
+                Some(None) => {}
+                None => break,
+            }
+        }
+    }
 }
@@ -21,7 +21,7 @@ pub enum DurationBase {
 }
 
 #[cfg_attr(feature = "bindgen", derive(uniffi::Object))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Duration {
     pub base: DurationBase,
     pub dots: u8, // [0, 3]
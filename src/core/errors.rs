@@ -47,4 +47,10 @@ pub enum MusicError {
 
     #[error("Invalid chord quality")]
     InvalidChordQuality,
+
+    #[error("Invalid track index {0}")]
+    InvalidTrack(usize),
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
 }
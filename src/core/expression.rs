@@ -0,0 +1,190 @@
+//! Expression markings carried by a `Note`
+//!
+//! - Articulations: staccato, tenuto, accent, marcato
+//! - Dynamics: pp, p, mp, mf, f, ff and crescendo/diminuendo hairpins
+//! - Legato slurs, fermatas and grace notes
+//!
+//! Markings that span several notes (slurs, hairpins) are stored as start/stop marks
+//! on the first and last note of the span, the same way engraving formats do.
+
+use std::fmt::Display;
+
+/// Articulation applied to a single note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Articulation {
+    /// Staccato - short and detached
+    ///
+    /// 断音 - 短促、分离
+    Staccato,
+    /// Tenuto - held for the full value
+    ///
+    /// 保持音 - 奏满时值
+    Tenuto,
+    /// Accent - played louder
+    ///
+    /// 重音 - 加强力度
+    Accent,
+    /// Marcato - strongly accented
+    ///
+    /// 强调音 - 更强的重音
+    Marcato,
+}
+
+/// Dynamic level, from pianissimo to fortissimo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Dynamic {
+    /// pp - very soft
+    Pianissimo,
+    /// p - soft
+    Piano,
+    /// mp - moderately soft
+    MezzoPiano,
+    /// mf - moderately loud
+    MezzoForte,
+    /// f - loud
+    Forte,
+    /// ff - very loud
+    Fortissimo,
+}
+
+impl Dynamic {
+    pub fn iter() -> impl Iterator<Item = Dynamic> {
+        [
+            Dynamic::Pianissimo,
+            Dynamic::Piano,
+            Dynamic::MezzoPiano,
+            Dynamic::MezzoForte,
+            Dynamic::Forte,
+            Dynamic::Fortissimo,
+        ]
+        .into_iter()
+    }
+
+    /// One level louder, saturating at ff
+    pub fn louder(&self) -> Self {
+        match self {
+            Dynamic::Pianissimo => Dynamic::Piano,
+            Dynamic::Piano => Dynamic::MezzoPiano,
+            Dynamic::MezzoPiano => Dynamic::MezzoForte,
+            Dynamic::MezzoForte => Dynamic::Forte,
+            Dynamic::Forte | Dynamic::Fortissimo => Dynamic::Fortissimo,
+        }
+    }
+
+    /// One level softer, saturating at pp
+    pub fn softer(&self) -> Self {
+        match self {
+            Dynamic::Pianissimo | Dynamic::Piano => Dynamic::Pianissimo,
+            Dynamic::MezzoPiano => Dynamic::Piano,
+            Dynamic::MezzoForte => Dynamic::MezzoPiano,
+            Dynamic::Forte => Dynamic::MezzoForte,
+            Dynamic::Fortissimo => Dynamic::Forte,
+        }
+    }
+
+    /// Standard marking, e.g. `mf`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Dynamic::Pianissimo => "pp",
+            Dynamic::Piano => "p",
+            Dynamic::MezzoPiano => "mp",
+            Dynamic::MezzoForte => "mf",
+            Dynamic::Forte => "f",
+            Dynamic::Fortissimo => "ff",
+        }
+    }
+
+    /// Parse a standard marking, e.g. `mf`
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        Dynamic::iter().find(|d| d.symbol() == symbol)
+    }
+}
+
+impl Display for Dynamic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+/// Direction of a dynamic hairpin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hairpin {
+    /// Crescendo - gradually louder
+    ///
+    /// 渐强
+    Crescendo,
+    /// Diminuendo - gradually softer
+    ///
+    /// 渐弱
+    Diminuendo,
+}
+
+/// Boundary of a hairpin spanning several notes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HairpinMark {
+    Start(Hairpin),
+    Stop,
+}
+
+/// Boundary of a legato slur spanning several notes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlurMark {
+    Start,
+    Stop,
+}
+
+/// Grace note kind. Grace notes take no time in the measure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Grace {
+    /// Acciaccatura - a short "crushed" note
+    ///
+    /// 短倚音
+    Acciaccatura,
+    /// Appoggiatura - leans on the principal note and takes part of its value
+    ///
+    /// 长倚音
+    Appoggiatura,
+}
+
+/// All expression markings of a note
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expression {
+    pub articulations: Vec<Articulation>,
+    pub dynamic: Option<Dynamic>,
+    pub hairpin: Option<HairpinMark>,
+    pub slur: Option<SlurMark>,
+    pub fermata: bool,
+    pub grace: Option<Grace>,
+}
+
+impl Expression {
+    pub fn has_articulation(&self, articulation: Articulation) -> bool {
+        self.articulations.contains(&articulation)
+    }
+
+    pub fn is_grace(&self) -> bool {
+        self.grace.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dynamic_steps() {
+        assert_eq!(Dynamic::MezzoForte.louder(), Dynamic::Forte);
+        assert_eq!(Dynamic::Fortissimo.louder(), Dynamic::Fortissimo);
+        assert_eq!(Dynamic::Piano.softer(), Dynamic::Pianissimo);
+        assert!(Dynamic::Piano < Dynamic::Forte);
+    }
+
+    #[test]
+    fn test_dynamic_symbol() {
+        for d in Dynamic::iter() {
+            assert_eq!(Dynamic::from_symbol(d.symbol()), Some(d));
+        }
+        assert_eq!(Dynamic::Fortissimo.to_string(), "ff");
+        assert_eq!(Dynamic::from_symbol("fff"), None);
+    }
+}
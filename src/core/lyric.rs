@@ -0,0 +1,159 @@
+//! Lyrics carried by a `Note`
+//!
+//! Every note can hold one syllable per verse. Words split across several notes are
+//! hyphenated with `Syllabic`, and a syllable held over several notes (melisma) carries an
+//! extender line.
+//!
+//! Lyric lines use the usual text convention:
+//! - `-` splits a word into syllables: `Hap-py`
+//! - `_` on its own continues the previous syllable on the next note (melisma)
+//! - `*` on its own leaves the note without lyric
+
+use std::fmt::Display;
+
+/// Position of a syllable in its word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Syllabic {
+    /// The whole word
+    #[default]
+    Single,
+    /// First syllable, followed by a hyphen
+    Begin,
+    /// Inner syllable, between hyphens
+    Middle,
+    /// Last syllable of the word
+    End,
+}
+
+impl Syllabic {
+    /// Another syllable of the same word follows
+    pub fn continues(&self) -> bool {
+        matches!(self, Syllabic::Begin | Syllabic::Middle)
+    }
+}
+
+/// One syllable of a verse
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Lyric {
+    /// Verse number, starting at 1
+    pub verse: u8,
+    pub text: String,
+    pub syllabic: Syllabic,
+    /// The syllable is held over the following notes (melisma extender line)
+    pub extend: bool,
+}
+
+impl Lyric {
+    pub fn new(text: impl Into<String>) -> Self {
+        Lyric {
+            verse: 1,
+            text: text.into(),
+            syllabic: Syllabic::Single,
+            extend: false,
+        }
+    }
+
+    pub fn with_verse(self, verse: u8) -> Self {
+        Lyric { verse, ..self }
+    }
+
+    pub fn with_syllabic(self, syllabic: Syllabic) -> Self {
+        Lyric { syllabic, ..self }
+    }
+
+    pub fn with_extend(self) -> Self {
+        Lyric {
+            extend: true,
+            ..self
+        }
+    }
+
+    /// Split a lyric line into one entry per note, `None` for notes without a new syllable
+    ///
+    /// ```
+    /// use mutheors::{Lyric, Syllabic};
+    /// let line = Lyric::parse_line("Hap-py _ birth-day", 1);
+    /// assert_eq!(line.len(), 5);
+    /// assert_eq!(line[0].as_ref().unwrap().syllabic, Syllabic::Begin);
+    /// assert!(line[1].as_ref().unwrap().extend);
+    /// assert!(line[2].is_none());
+    /// ```
+    pub fn parse_line(line: &str, verse: u8) -> Vec<Option<Lyric>> {
+        let mut result: Vec<Option<Lyric>> = vec![];
+
+        for word in line.split_whitespace() {
+            match word {
+                "_" => {
+                    // Extend the last sung syllable
+                    if let Some(last) = result.iter_mut().rev().find_map(Option::as_mut) {
+                        last.extend = true;
+                    }
+                    result.push(None);
+                }
+                "*" => result.push(None),
+                _ => {
+                    let syllables = word
+                        .split('-')
+                        .filter(|s| !s.is_empty())
+                        .collect::<Vec<_>>();
+                    let count = syllables.len();
+                    for (i, text) in syllables.into_iter().enumerate() {
+                        let syllabic = match (i, count) {
+                            (_, 1) => Syllabic::Single,
+                            (0, _) => Syllabic::Begin,
+                            (i, n) if i + 1 == n => Syllabic::End,
+                            _ => Syllabic::Middle,
+                        };
+                        result.push(Some(
+                            Lyric::new(text).with_verse(verse).with_syllabic(syllabic),
+                        ));
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl Display for Lyric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)?;
+        if self.syllabic.continues() {
+            write!(f, "-")?;
+        }
+        if self.extend {
+            write!(f, "_")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let line = Lyric::parse_line("A-ma-zing grace _ * how", 2);
+        let texts = line
+            .iter()
+            .map(|l| l.as_ref().map(|l| l.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            vec![
+                Some("A-".to_owned()),
+                Some("ma-".to_owned()),
+                Some("zing".to_owned()),
+                Some("grace_".to_owned()),
+                None,
+                None,
+                Some("how".to_owned()),
+            ]
+        );
+        assert_eq!(line[1].as_ref().unwrap().syllabic, Syllabic::Middle);
+        assert_eq!(line[2].as_ref().unwrap().syllabic, Syllabic::End);
+        assert!(line.iter().flatten().all(|l| l.verse == 2));
+    }
+}
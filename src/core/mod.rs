@@ -1,9 +1,12 @@
 pub mod chord;
 pub mod duration;
 pub mod errors;
+pub mod expression;
 pub mod interval;
+pub mod lyric;
 pub mod macros;
 pub mod note;
+pub mod percussion;
 pub mod scale;
 pub mod tonality;
 pub mod tuning;
@@ -11,8 +14,11 @@ pub mod tuning;
 pub use chord::*;
 pub use duration::*;
 pub use errors::*;
+pub use expression::*;
 pub use interval::*;
+pub use lyric::*;
 pub use note::*;
+pub use percussion::*;
 pub use scale::*;
 pub use tonality::*;
 pub use tuning::*;
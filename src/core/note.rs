@@ -1,26 +1,49 @@
-use crate::{Duration, Tuning};
+use crate::{
+    Articulation, Duration, Dynamic, Expression, Grace, HairpinMark, Lyric, SlurMark, Tuning,
+};
 use std::fmt::Display;
 
+/// A pitched note or a rest. Notes carry their markings and lyrics, so they are `Clone`
+/// but no longer `Copy`.
 #[cfg_attr(feature = "bindgen", derive(uniffi::Object))]
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Note {
-    tuning: Tuning,
+    /// `None` for a rest
+    tuning: Option<Tuning>,
     duration: Duration,
     velocity: f32,
+    expression: Expression,
+    lyrics: Vec<Lyric>,
 }
 
 impl Display for Note {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}[{}]", self.tuning, self.duration)
+        match self.tuning {
+            Some(tuning) => write!(f, "{}[{}]", tuning, self.duration),
+            None => write!(f, "r[{}]", self.duration),
+        }
     }
 }
 
 impl Note {
     pub fn new(tuning: Tuning) -> Self {
         Note {
-            tuning,
+            tuning: Some(tuning),
             duration: Duration::from_quarters(1.0),
             velocity: 0.0,
+            expression: Expression::default(),
+            lyrics: vec![],
+        }
+    }
+
+    /// Silence inside a melody, it takes its duration in the measure but does not sound
+    pub fn rest(duration: Duration) -> Self {
+        Note {
+            tuning: None,
+            duration,
+            velocity: 0.0,
+            expression: Expression::default(),
+            lyrics: vec![],
         }
     }
 
@@ -28,11 +51,13 @@ impl Note {
         Note { duration, ..self }
     }
 
+    /// Explicit velocity in [0.0, 1.0]. `0.0` means unset, the dynamic markings decide.
     pub fn with_velocity(self, velocity: f32) -> Note {
         Note { velocity, ..self }
     }
 
-    pub fn tuning(&self) -> Tuning {
+    /// Pitch of the note, `None` for a rest
+    pub fn tuning(&self) -> Option<Tuning> {
         self.tuning
     }
 
@@ -43,6 +68,77 @@ impl Note {
     pub fn velocity(&self) -> f32 {
         self.velocity
     }
+
+    pub fn is_rest(&self) -> bool {
+        self.tuning.is_none()
+    }
+}
+
+/// Expression markings
+impl Note {
+    pub fn with_articulation(mut self, articulation: Articulation) -> Note {
+        if !self.expression.has_articulation(articulation) {
+            self.expression.articulations.push(articulation);
+        }
+        self
+    }
+
+    pub fn with_dynamic(mut self, dynamic: Dynamic) -> Note {
+        self.expression.dynamic = Some(dynamic);
+        self
+    }
+
+    pub fn with_hairpin(mut self, hairpin: HairpinMark) -> Note {
+        self.expression.hairpin = Some(hairpin);
+        self
+    }
+
+    pub fn with_slur(mut self, slur: SlurMark) -> Note {
+        self.expression.slur = Some(slur);
+        self
+    }
+
+    pub fn with_fermata(mut self) -> Note {
+        self.expression.fermata = true;
+        self
+    }
+
+    /// Turn the note into a grace note, it takes no time in the measure
+    pub fn with_grace(mut self, grace: Grace) -> Note {
+        self.expression.grace = Some(grace);
+        self
+    }
+
+    pub fn with_expression(self, expression: Expression) -> Note {
+        Note { expression, ..self }
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn is_grace(&self) -> bool {
+        self.expression.is_grace()
+    }
+}
+
+/// Lyrics, at most one syllable per verse
+impl Note {
+    /// Attach a syllable, replacing the one of the same verse
+    pub fn with_lyric(mut self, lyric: Lyric) -> Note {
+        self.lyrics.retain(|l| l.verse != lyric.verse);
+        self.lyrics.push(lyric);
+        self.lyrics.sort_by_key(|l| l.verse);
+        self
+    }
+
+    pub fn lyrics(&self) -> &[Lyric] {
+        &self.lyrics
+    }
+
+    pub fn lyric(&self, verse: u8) -> Option<&Lyric> {
+        self.lyrics.iter().find(|l| l.verse == verse)
+    }
 }
 
 impl From<Tuning> for Note {
@@ -0,0 +1,188 @@
+//! Unpitched percussion
+//!
+//! A `PercussionNote` names a drum sound of the General MIDI percussion map instead of a
+//! `Tuning`, and is placed at a position inside its measure, so several hits can sound
+//! together (kick and hi-hat on the same step).
+
+use std::fmt::Display;
+
+macro_rules! drum_sounds {
+    ($($sound:ident = $number:literal, $name:literal;)*) => {
+        /// Drum sound of the General MIDI percussion map (channel 10)
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum DrumSound {
+            $(
+                #[doc = $name]
+                $sound = $number,
+            )*
+        }
+
+        impl DrumSound {
+            pub fn iter() -> impl Iterator<Item = DrumSound> {
+                [$(DrumSound::$sound),*].into_iter()
+            }
+
+            /// General MIDI name, e.g. `Closed Hi-Hat`
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(DrumSound::$sound => $name,)*
+                }
+            }
+        }
+    };
+}
+
+drum_sounds! {
+    AcousticBassDrum = 35, "Acoustic Bass Drum";
+    BassDrum = 36, "Bass Drum 1";
+    SideStick = 37, "Side Stick";
+    AcousticSnare = 38, "Acoustic Snare";
+    HandClap = 39, "Hand Clap";
+    ElectricSnare = 40, "Electric Snare";
+    LowFloorTom = 41, "Low Floor Tom";
+    ClosedHiHat = 42, "Closed Hi-Hat";
+    HighFloorTom = 43, "High Floor Tom";
+    PedalHiHat = 44, "Pedal Hi-Hat";
+    LowTom = 45, "Low Tom";
+    OpenHiHat = 46, "Open Hi-Hat";
+    LowMidTom = 47, "Low-Mid Tom";
+    HighMidTom = 48, "Hi-Mid Tom";
+    CrashCymbal = 49, "Crash Cymbal 1";
+    HighTom = 50, "High Tom";
+    RideCymbal = 51, "Ride Cymbal 1";
+    ChineseCymbal = 52, "Chinese Cymbal";
+    RideBell = 53, "Ride Bell";
+    Tambourine = 54, "Tambourine";
+    SplashCymbal = 55, "Splash Cymbal";
+    Cowbell = 56, "Cowbell";
+    CrashCymbal2 = 57, "Crash Cymbal 2";
+    Vibraslap = 58, "Vibraslap";
+    RideCymbal2 = 59, "Ride Cymbal 2";
+    HighBongo = 60, "Hi Bongo";
+    LowBongo = 61, "Low Bongo";
+    MuteHighConga = 62, "Mute Hi Conga";
+    OpenHighConga = 63, "Open Hi Conga";
+    LowConga = 64, "Low Conga";
+    HighTimbale = 65, "High Timbale";
+    LowTimbale = 66, "Low Timbale";
+    HighAgogo = 67, "High Agogo";
+    LowAgogo = 68, "Low Agogo";
+    Cabasa = 69, "Cabasa";
+    Maracas = 70, "Maracas";
+    ShortWhistle = 71, "Short Whistle";
+    LongWhistle = 72, "Long Whistle";
+    ShortGuiro = 73, "Short Guiro";
+    LongGuiro = 74, "Long Guiro";
+    Claves = 75, "Claves";
+    HighWoodBlock = 76, "Hi Wood Block";
+    LowWoodBlock = 77, "Low Wood Block";
+    MuteCuica = 78, "Mute Cuica";
+    OpenCuica = 79, "Open Cuica";
+    MuteTriangle = 80, "Mute Triangle";
+    OpenTriangle = 81, "Open Triangle";
+}
+
+impl DrumSound {
+    /// Key number of the sound on the General MIDI percussion channel
+    pub fn midi_number(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_midi_number(number: u8) -> Option<Self> {
+        DrumSound::iter().find(|d| d.midi_number() == number)
+    }
+}
+
+impl Display for DrumSound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A percussion hit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PercussionNote {
+    sound: DrumSound,
+    /// Position in beats from the start of the measure
+    position: f32,
+    velocity: f32,
+    flam: bool,
+}
+
+impl PercussionNote {
+    pub fn new(sound: DrumSound) -> Self {
+        PercussionNote {
+            sound,
+            position: 0.0,
+            velocity: 0.0,
+            flam: false,
+        }
+    }
+
+    /// Position in beats from the start of the measure
+    pub fn at(self, position: f32) -> Self {
+        PercussionNote { position, ..self }
+    }
+
+    /// Explicit velocity in [0.0, 1.0]. `0.0` means unset, the default dynamic decides.
+    pub fn with_velocity(self, velocity: f32) -> Self {
+        PercussionNote { velocity, ..self }
+    }
+
+    /// Precede the hit with a soft grace stroke
+    pub fn with_flam(self) -> Self {
+        PercussionNote { flam: true, ..self }
+    }
+
+    pub fn sound(&self) -> DrumSound {
+        self.sound
+    }
+
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    pub fn velocity(&self) -> f32 {
+        self.velocity
+    }
+
+    pub fn is_flam(&self) -> bool {
+        self.flam
+    }
+}
+
+impl Display for PercussionNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.sound, self.position)
+    }
+}
+
+impl From<DrumSound> for PercussionNote {
+    fn from(sound: DrumSound) -> Self {
+        PercussionNote::new(sound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_general_midi_map() {
+        assert_eq!(DrumSound::iter().count(), 47);
+        assert_eq!(DrumSound::BassDrum.midi_number(), 36);
+        assert_eq!(DrumSound::ClosedHiHat.midi_number(), 42);
+        assert_eq!(
+            DrumSound::from_midi_number(49),
+            Some(DrumSound::CrashCymbal)
+        );
+        assert_eq!(DrumSound::from_midi_number(34), None);
+        for sound in DrumSound::iter() {
+            assert_eq!(
+                DrumSound::from_midi_number(sound.midi_number()),
+                Some(sound)
+            );
+        }
+        assert_eq!(DrumSound::OpenHiHat.to_string(), "Open Hi-Hat");
+    }
+}
@@ -435,6 +435,32 @@ impl Tuning {
     }
 }
 
+impl Tuning {
+    /// Written letter, alteration in semitones and octave, e.g. `('E', -1, 4)` for Eb4,
+    /// the way notation formats spell a pitch
+    pub fn spelling(&self) -> (char, i8, i8) {
+        let natural = PitchClass::from_degree(self.class.degree());
+        let letter = natural.to_string().chars().next().unwrap_or('C');
+        let alter = self.class.semitones() - natural.semitones() + self.accidentals;
+        (letter, alter, self.octave)
+    }
+
+    /// Tuning of a spelled pitch, see `Tuning::spelling`
+    pub fn from_spelling(letter: char, alter: i8, octave: i8) -> Result<Self, MusicError> {
+        let degree = match letter.to_ascii_uppercase() {
+            'C' => 1,
+            'D' => 2,
+            'E' => 3,
+            'F' => 4,
+            'G' => 5,
+            'A' => 6,
+            'B' => 7,
+            _ => return Err(MusicError::InvalidPitch),
+        };
+        Ok(Tuning::new(PitchClass::from_degree(degree), octave).with_accidentals(alter))
+    }
+}
+
 impl Tuning {
     pub fn dom(&self, n: u8) -> Vec<Self> {
         let scale_root = self.add_interval(&-Interval::perfect_fifth()).unwrap();
@@ -505,6 +531,25 @@ mod tests {
     use crate::*;
     use std::str::FromStr;
 
+    #[test]
+    fn test_spelling() {
+        let eb4 = Tuning::new(PitchClass::Eb, 4);
+        assert_eq!(eb4.spelling(), ('E', -1, 4));
+        assert_eq!(
+            Tuning::new(PitchClass::Fs, 3).sharp().spelling(),
+            ('F', 2, 3)
+        );
+        assert_eq!(
+            Tuning::new(PitchClass::B, 3).sharp().spelling(),
+            ('B', 1, 3)
+        );
+
+        let spelled = Tuning::from_spelling('e', -1, 4).unwrap();
+        assert_eq!(spelled.number(), eb4.number());
+        assert_eq!(spelled.spelling(), ('E', -1, 4));
+        assert!(Tuning::from_spelling('H', 0, 4).is_err());
+    }
+
     #[test]
     fn test_tuning_01() {
         let tuning1 = Tuning::new(PitchClass::C.sharp(), 3) * 2;
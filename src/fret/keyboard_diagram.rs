@@ -0,0 +1,457 @@
+//! Piano keyboard diagrams
+//!
+//! ASCII and SVG keyboards with the keys of a chord, scale or fingering highlighted,
+//! finger numbers and hands written on the keys.
+
+use super::{
+    errors::{FretboardError, FretboardResult},
+    svg::SvgWriter,
+    types::{Fingering, Hand, KeyboardPosition},
+    KeyboardFretboard, SvgDiagramConfig,
+};
+use crate::{Chord, PitchClass, Scale, Tuning};
+use std::fmt::Write;
+
+/// Width of a white key in ASCII, border included
+const ASCII_KEY: usize = 5;
+
+/// Key to highlight, by pitch or by index on the keyboard
+#[derive(Clone, Debug)]
+enum KeyTarget {
+    Tuning(Tuning),
+    Key(usize),
+}
+
+#[derive(Clone, Debug)]
+struct KeyMark {
+    target: KeyTarget,
+    finger: Option<u8>,
+    hand: Option<Hand>,
+}
+
+/// One key of the drawn range
+struct DrawnKey {
+    tuning: Tuning,
+    black: bool,
+    /// Index of the white key it sits on or right of
+    white: usize,
+    mark: Option<usize>,
+}
+
+/// Piano keyboard diagram generator
+///
+/// Keys are highlighted with `with_chord`, `with_scale` and `with_fingering`, which can
+/// be combined, e.g. a left hand fingering under a right hand one.
+#[derive(Clone, Debug)]
+pub struct KeyboardDiagram {
+    marks: Vec<KeyMark>,
+    /// Lowest and highest key drawn, the octaves around the highlights when `None`
+    range: Option<(Tuning, Tuning)>,
+    show_note_names: bool,
+    /// White key width and height in pixels
+    key_size: (f32, f32),
+    svg: SvgDiagramConfig,
+    highlight_color: String,
+    left_hand_color: String,
+    right_hand_color: String,
+}
+
+impl Default for KeyboardDiagram {
+    fn default() -> Self {
+        Self {
+            marks: vec![],
+            range: None,
+            show_note_names: true,
+            key_size: (24.0, 110.0),
+            svg: SvgDiagramConfig::default(),
+            highlight_color: "#f6c945".to_string(),
+            left_hand_color: "#2e86de".to_string(),
+            right_hand_color: "#c0392b".to_string(),
+        }
+    }
+}
+
+impl KeyboardDiagram {
+    /// Create a new keyboard diagram without highlights
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlight the tones of a chord as voiced
+    pub fn with_chord(mut self, chord: &Chord) -> Self {
+        let tunings = chord.components();
+        self.mark_tunings(&tunings);
+        self
+    }
+
+    /// Highlight one octave of a scale from its root
+    pub fn with_scale(mut self, scale: &Scale) -> Self {
+        let tunings = scale.generate_tunings(0).unwrap_or_default();
+        self.mark_tunings(&tunings);
+        self
+    }
+
+    /// Highlight the keys of a fingering with their finger numbers, played by `hand`
+    pub fn with_fingering(mut self, fingering: &Fingering<KeyboardPosition>, hand: Hand) -> Self {
+        for position in &fingering.positions {
+            self.marks.push(KeyMark {
+                target: KeyTarget::Key(position.position.key as usize),
+                finger: position.finger.map(|f| f.piano_number()),
+                hand: Some(hand),
+            });
+        }
+        self
+    }
+
+    /// Draw the keys from `lowest` to `highest`, widened to start and end on white keys
+    pub fn with_range(mut self, lowest: Tuning, highest: Tuning) -> Self {
+        self.range = Some((lowest, highest));
+        self
+    }
+
+    /// Enable or disable note names on highlighted keys without a finger
+    pub fn with_note_names(mut self, show: bool) -> Self {
+        self.show_note_names = show;
+        self
+    }
+
+    /// Set the size of a white key in the SVG keyboard
+    pub fn with_key_size(mut self, width: f32, height: f32) -> Self {
+        self.key_size = (width, height);
+        self
+    }
+
+    /// Use the fonts, line colour and margins of a chord diagram configuration
+    pub fn with_svg_config(mut self, config: SvgDiagramConfig) -> Self {
+        self.svg = config;
+        self
+    }
+
+    /// Set the fill of highlighted keys and the colours of the two hands
+    pub fn with_colors(
+        mut self,
+        highlight: impl Into<String>,
+        left_hand: impl Into<String>,
+        right_hand: impl Into<String>,
+    ) -> Self {
+        self.highlight_color = highlight.into();
+        self.left_hand_color = left_hand.into();
+        self.right_hand_color = right_hand.into();
+        self
+    }
+
+    fn mark_tunings(&mut self, tunings: &[Tuning]) {
+        for tuning in tunings {
+            self.marks.push(KeyMark {
+                target: KeyTarget::Tuning(*tuning),
+                finger: None,
+                hand: None,
+            });
+        }
+    }
+
+    /// Render the keyboard as ASCII, black keys as `#` and highlighted black keys as `*`
+    pub fn render_ascii(&self, keyboard: &KeyboardFretboard) -> FretboardResult<String> {
+        let keys = self.keys(keyboard)?;
+        let whites = keys.iter().filter(|k| !k.black).count();
+        let width = whites * ASCII_KEY + 1;
+        let mut rows = vec![vec![' '; width]; 7];
+        for white in 0..=whites {
+            for row in &mut rows[1..] {
+                row[white * ASCII_KEY] = '|';
+            }
+        }
+        for white in 0..whites {
+            let start = white * ASCII_KEY + 1;
+            rows[6][start..start + ASCII_KEY - 1].fill('_');
+        }
+
+        for key in &keys {
+            let mark = key.mark.map(|m| &self.marks[m]);
+            let label = mark.map(|m| self.label(m, &key.tuning));
+            let hand = mark.and_then(|m| m.hand).map(|h| h.to_string());
+            if key.black {
+                let start = key.white * ASCII_KEY + ASCII_KEY - 1;
+                let fill = if mark.is_some() { '*' } else { '#' };
+                for row in &mut rows[1..4] {
+                    row[start..start + 3].fill(fill);
+                }
+                put(&mut rows[2], start, 3, label.as_deref(), fill);
+                put(&mut rows[3], start, 3, hand.as_deref(), fill);
+            } else {
+                let start = key.white * ASCII_KEY + 1;
+                put(&mut rows[4], start, ASCII_KEY - 1, label.as_deref(), ' ');
+                put(&mut rows[5], start, ASCII_KEY - 1, hand.as_deref(), ' ');
+                if key.tuning.class() == PitchClass::C {
+                    let octave = format!("{:#}", key.tuning);
+                    for (i, c) in octave.chars().take(ASCII_KEY).enumerate() {
+                        rows[0][start - 1 + i] = c;
+                    }
+                }
+            }
+        }
+
+        let mut out = String::new();
+        for row in rows {
+            let _ = writeln!(out, "{}", row.into_iter().collect::<String>().trim_end());
+        }
+        Ok(out)
+    }
+
+    /// Render the keyboard as SVG, with a numbered dot in the colour of the hand on each key
+    pub fn render_svg(&self, keyboard: &KeyboardFretboard) -> FretboardResult<String> {
+        let keys = self.keys(keyboard)?;
+        let svg = &self.svg;
+        let (key_width, key_height) = self.key_size;
+        let (black_width, black_height) = (key_width * 0.6, key_height * 0.62);
+        let whites = keys.iter().filter(|k| !k.black).count();
+        let left = svg.margin;
+        let top = svg.margin + svg.font_size * 1.5;
+        let width = left * 2.0 + whites as f32 * key_width;
+        let height = top + key_height + svg.margin;
+
+        let mut out = SvgWriter::new(width, height);
+        if let Some(background) = &svg.background {
+            out.rect(0.0, 0.0, width, height, background);
+        }
+        // Keys first, white under black, then the dots on top of all of them
+        let mut dots = vec![];
+        for black in [false, true] {
+            for key in keys.iter().filter(|k| k.black == black) {
+                let (x, w, h) = match black {
+                    false => (left + key.white as f32 * key_width, key_width, key_height),
+                    true => (
+                        left + (key.white + 1) as f32 * key_width - black_width / 2.0,
+                        black_width,
+                        black_height,
+                    ),
+                };
+                let fill = match (key.mark, black) {
+                    (Some(_), _) => self.highlight_color.as_str(),
+                    (None, false) => "#ffffff",
+                    (None, true) => svg.line_color.as_str(),
+                };
+                out.framed_rect((x, top), (w, h), fill, &svg.line_color, svg.line_width);
+                if let Some(mark) = key.mark {
+                    dots.push((x + w / 2.0, top + h - w / 2.0 - 2.0, &self.marks[mark], key));
+                }
+                if !black && key.tuning.class() == PitchClass::C {
+                    let label = format!("{:#}", key.tuning);
+                    out.text(
+                        x + w / 2.0,
+                        top - svg.font_size * 0.5,
+                        &label,
+                        svg.font_size,
+                        &svg.font_family,
+                        &svg.text_color,
+                        "middle",
+                    );
+                }
+            }
+        }
+        for (x, y, mark, key) in dots {
+            let radius = (key_width * 0.6).min(svg.dot_radius * 2.0) / 2.0 + 2.0;
+            let color = match mark.hand {
+                Some(Hand::Left) => &self.left_hand_color,
+                Some(Hand::Right) => &self.right_hand_color,
+                None => &svg.dot_color,
+            };
+            out.circle(x, y, radius, color, "none", 0.0);
+            let label = self.label(mark, &key.tuning);
+            out.text(
+                x,
+                y + svg.font_size * 0.3,
+                &label,
+                svg.font_size * 0.8,
+                &svg.font_family,
+                &svg.finger_color,
+                "middle",
+            );
+            if let Some(hand) = mark.hand {
+                let y = y - radius - svg.font_size * 0.4;
+                let color = if key.black {
+                    &svg.finger_color
+                } else {
+                    &svg.text_color
+                };
+                out.text(
+                    x,
+                    y,
+                    &hand.to_string(),
+                    svg.font_size * 0.7,
+                    &svg.font_family,
+                    color,
+                    "middle",
+                );
+            }
+        }
+        Ok(out.finish())
+    }
+
+    fn label(&self, mark: &KeyMark, tuning: &Tuning) -> String {
+        match (mark.finger, self.show_note_names) {
+            (Some(finger), _) => finger.to_string(),
+            (None, true) => tuning.to_string(),
+            (None, false) => "*".to_string(),
+        }
+    }
+
+    /// Keys of the drawn range, each with the mark highlighting it
+    fn keys(&self, keyboard: &KeyboardFretboard) -> FretboardResult<Vec<DrawnKey>> {
+        let mut marked = vec![];
+        for (i, mark) in self.marks.iter().enumerate() {
+            let key = match &mark.target {
+                KeyTarget::Tuning(tuning) => keyboard
+                    .key_index_for_tuning(tuning)
+                    .ok_or_else(|| FretboardError::tuning_out_of_range(tuning))?,
+                KeyTarget::Key(key) if keyboard.is_key_valid(*key) => *key,
+                KeyTarget::Key(key) => {
+                    return Err(FretboardError::invalid_position(KeyboardPosition::new(
+                        *key as u32,
+                    )))
+                }
+            };
+            marked.push((key, i));
+        }
+
+        let last = keyboard.key_count() - 1;
+        let index = |tuning: &Tuning| {
+            let offset = tuning.number() as i32 - keyboard.lowest_key().number() as i32;
+            offset.clamp(0, last as i32) as usize
+        };
+        let (mut low, mut high) = match (&self.range, marked.is_empty()) {
+            (Some((low, high)), _) => (index(low), index(high)),
+            (None, true) => (0, last),
+            // Whole octaves from the C below the lowest highlight to the B above the highest
+            (None, false) => {
+                let low = marked.iter().map(|m| m.0).min().unwrap();
+                let high = marked.iter().map(|m| m.0).max().unwrap();
+                let class =
+                    |key: usize| keyboard.key_tuning(key).unwrap().class_semitones() as usize;
+                (
+                    low.saturating_sub(class(low)),
+                    (high + 11 - class(high)).min(last),
+                )
+            }
+        };
+        let black = |key: usize| keyboard.is_black_key(key) == Some(true);
+        while low > 0 && black(low) {
+            low -= 1;
+        }
+        while high < last && black(high) {
+            high += 1;
+        }
+
+        let mut keys: Vec<DrawnKey> = vec![];
+        let mut whites = 0;
+        for key in low..=high {
+            let is_black = black(key) && !keys.is_empty() && key != high;
+            keys.push(DrawnKey {
+                tuning: *keyboard.key_tuning(key).unwrap(),
+                black: is_black,
+                white: if is_black { whites - 1 } else { whites },
+                // Fingerings win over plain highlights of the same key
+                mark: marked
+                    .iter()
+                    .filter(|m| m.0 == key)
+                    .max_by_key(|m| self.marks[m.1].hand.is_some())
+                    .map(|m| m.1),
+            });
+            if !is_black {
+                whites += 1;
+            }
+        }
+        Ok(keys)
+    }
+}
+
+/// Write `text` centered in `width` cells of a row starting at `start`, padded with `fill`
+fn put(row: &mut [char], start: usize, width: usize, text: Option<&str>, fill: char) {
+    let Some(text) = text else { return };
+    let text = format!("{:^width$}", text.chars().take(width).collect::<String>());
+    for (i, c) in text.chars().enumerate() {
+        row[start + i] = if c == ' ' { fill } else { c };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChordQuality, Finger, FingerPosition, InstrumentPresets, PlayingTechnique};
+
+    fn piano() -> KeyboardFretboard {
+        KeyboardFretboard::new(InstrumentPresets::piano_88_key()).unwrap()
+    }
+
+    #[test]
+    fn test_ascii_chord() {
+        let chord = Chord::new(Tuning::new(PitchClass::D, 4), ChordQuality::Major).unwrap();
+        let ascii = KeyboardDiagram::new()
+            .with_chord(&chord)
+            .render_ascii(&piano())
+            .unwrap();
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 7);
+        // One octave from C4, the F# black key highlighted between F and G
+        assert!(lines[0].starts_with("C4"), "{ascii}");
+        assert_eq!(lines[6], "|____|____|____|____|____|____|____|");
+        assert_eq!(lines[2], "|   ###  ###   |   F#*  ###  ###   |");
+        assert_eq!(lines[4], "|    | D  |    |    |    | A  |    |");
+    }
+
+    #[test]
+    fn test_fingering_with_hands() {
+        let keyboard = piano();
+        let key = |tuning: Tuning| {
+            KeyboardPosition::new(keyboard.key_index_for_tuning(&tuning).unwrap() as u32)
+        };
+        let right = Fingering::new(
+            vec![
+                FingerPosition::pressed(key(Tuning::new(PitchClass::C, 4)), Finger::Thumb),
+                FingerPosition::pressed(key(Tuning::new(PitchClass::Ds, 4)), Finger::Middle),
+            ],
+            PlayingTechnique::Standard,
+            0.2,
+        );
+        let left = Fingering::new(
+            vec![FingerPosition::pressed(
+                key(Tuning::new(PitchClass::C, 3)),
+                Finger::Pinky,
+            )],
+            PlayingTechnique::Standard,
+            0.1,
+        );
+        let diagram = KeyboardDiagram::new()
+            .with_fingering(&right, Hand::Right)
+            .with_fingering(&left, Hand::Left);
+        let ascii = diagram.render_ascii(&keyboard).unwrap();
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert!(
+            lines[0].starts_with("C3") && lines[0].contains("C4"),
+            "{ascii}"
+        );
+        assert!(lines[4].starts_with("| 5  |") && lines[5].starts_with("| L  |"));
+        assert!(lines[2].contains("*3*") && lines[3].contains("*R*"));
+
+        let svg = diagram.render_svg(&keyboard).unwrap();
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // 14 white and 10 black keys, three of them highlighted with dots
+        assert_eq!(svg.matches("<rect").count(), 24);
+        assert_eq!(svg.matches("fill=\"#f6c945\"").count(), 3);
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert!(svg.contains("fill=\"#2e86de\"") && svg.contains("fill=\"#c0392b\""));
+
+        let outside = Fingering::new(
+            vec![FingerPosition::pressed(
+                KeyboardPosition::new(100),
+                Finger::Index,
+            )],
+            PlayingTechnique::Standard,
+            0.0,
+        );
+        assert!(KeyboardDiagram::new()
+            .with_fingering(&outside, Hand::Right)
+            .render_ascii(&keyboard)
+            .is_err());
+    }
+}
@@ -0,0 +1,499 @@
+//! Full-neck fretboard maps
+//!
+//! Every position of a scale or chord tones across the whole neck, labelled with note
+//! names, degrees or intervals, with inlays and frets spaced like the real instrument.
+
+use super::{svg::SvgWriter, traits::Fretboard, types::StringedPosition, SvgDiagramConfig};
+use crate::{Chord, Interval, Scale, StringedInstrumentConfig, Tuning};
+use std::fmt::Write;
+
+/// Frets shown on unfretted necks
+const UNFRETTED_SPAN: usize = 12;
+
+/// Text printed on each highlighted position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MapLabel {
+    /// Note name spelled as in the scale or chord, e.g. `F#`
+    #[default]
+    NoteName,
+    /// Scale degree, or chord degree such as `3` or `7`
+    Degree,
+    /// Interval above the root, e.g. `m3`, the root itself is `R`
+    Interval,
+}
+
+/// One tone highlighted on the neck
+#[derive(Clone, Debug)]
+struct MapTone {
+    tuning: Tuning,
+    degree: i8,
+    interval: Interval,
+}
+
+/// Full-neck map of a scale or chord with ASCII and SVG backends
+///
+/// Works on any fretboard configured by a `StringedInstrumentConfig`; necks without
+/// frets (`ContinuousFretboard`) show the first octave in semitone steps.
+#[derive(Clone, Debug)]
+pub struct FretboardMap {
+    tones: Vec<MapTone>,
+    label: MapLabel,
+    /// Last fret shown, all frets when `None`
+    frets: Option<usize>,
+    /// Columns of the ASCII neck
+    width: usize,
+    /// Pixels per millimetre of the SVG neck
+    scale: f32,
+    svg: SvgDiagramConfig,
+    root_color: String,
+    inlay_color: String,
+}
+
+impl FretboardMap {
+    fn new(tones: Vec<MapTone>) -> Self {
+        Self {
+            tones,
+            label: MapLabel::default(),
+            frets: None,
+            width: 120,
+            scale: 1.5,
+            svg: SvgDiagramConfig::default().with_spacing(22.0, 0.0),
+            root_color: "#c0392b".to_string(),
+            inlay_color: "#d0d0d0".to_string(),
+        }
+    }
+
+    /// Map every tone of a scale
+    pub fn from_scale(scale: &Scale) -> Self {
+        let root = scale.root();
+        let tunings = scale.generate_tunings(0).unwrap_or_else(|_| vec![root]);
+        let tones = tunings
+            .into_iter()
+            .take(scale.interval_count().max(1) as usize)
+            .enumerate()
+            .map(|(i, tuning)| MapTone {
+                tuning,
+                degree: i as i8 + 1,
+                interval: Interval::from_semitones_unchecked(
+                    (tuning.number() - root.number()).rem_euclid(12),
+                ),
+            })
+            .collect();
+        Self::new(tones)
+    }
+
+    /// Map the tones of a chord
+    pub fn from_chord(chord: &Chord) -> Self {
+        let root = chord.root();
+        let tones = std::iter::once(Interval::unison())
+            .chain(chord.intervals())
+            .filter_map(|interval| {
+                let tuning = root.add_interval(&interval).ok()?;
+                Some(MapTone {
+                    tuning,
+                    degree: interval.degree(),
+                    interval,
+                })
+            })
+            .collect();
+        Self::new(tones)
+    }
+
+    /// Set the text printed on each position
+    pub fn with_label(mut self, label: MapLabel) -> Self {
+        self.label = label;
+        self
+    }
+
+    /// Show the neck up to a fret instead of all of it
+    pub fn with_frets(mut self, last_fret: usize) -> Self {
+        self.frets = Some(last_fret.max(1));
+        self
+    }
+
+    /// Set the number of columns of the ASCII neck, frets never get narrower than their labels
+    pub fn with_width(mut self, columns: usize) -> Self {
+        self.width = columns;
+        self
+    }
+
+    /// Set the size of the SVG neck in pixels per millimetre of the instrument's scale
+    /// length
+    pub fn with_scale(mut self, pixels_per_mm: f32) -> Self {
+        self.scale = pixels_per_mm;
+        self
+    }
+
+    /// Use the sizes, fonts and colours of a chord diagram configuration for the SVG neck
+    pub fn with_svg_config(mut self, config: SvgDiagramConfig) -> Self {
+        self.svg = config;
+        self
+    }
+
+    /// Set the colours of root positions and inlays in the SVG neck
+    pub fn with_colors(
+        mut self,
+        root_color: impl Into<String>,
+        inlay_color: impl Into<String>,
+    ) -> Self {
+        self.root_color = root_color.into();
+        self.inlay_color = inlay_color.into();
+        self
+    }
+
+    /// Every highlighted position on the neck with its label, lowest string first
+    pub fn positions<F>(&self, fretboard: &F) -> Vec<(StringedPosition, String)>
+    where
+        F: Fretboard<Config = StringedInstrumentConfig>,
+    {
+        let config = fretboard.get_config();
+        let last = self.last_fret(config);
+        let mut positions = vec![];
+        for (string, open) in config.strings.iter().enumerate() {
+            for fret in 0..=last {
+                if let Some(tone) = self.tone_at(open, fret) {
+                    positions.push((
+                        StringedPosition::new(string as u32, fret as u32),
+                        self.text(tone),
+                    ));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Render the neck as ASCII, highest string on top like tablature
+    pub fn render_ascii<F>(&self, fretboard: &F) -> String
+    where
+        F: Fretboard<Config = StringedInstrumentConfig>,
+    {
+        let config = fretboard.get_config();
+        let fretted = config.fret_count > 0;
+        let last = self.last_fret(config);
+        let label = self
+            .tones
+            .iter()
+            .map(|t| self.text(t).len())
+            .max()
+            .unwrap_or(1);
+        let cell = label + 2;
+
+        // Cells as wide as the real frets, scaled to the requested width
+        let total = fret_distance(last);
+        let widths = (1..=last)
+            .map(|fret| {
+                let share = (fret_distance(fret) - fret_distance(fret - 1)) / total;
+                ((share * self.width as f32).round() as usize).max(cell)
+            })
+            .collect::<Vec<_>>();
+        let names = config
+            .strings
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>();
+        let name_width = names.iter().map(String::len).max().unwrap_or(1);
+        let separator = if fretted { '|' } else { ':' };
+
+        let mut out = String::new();
+        let mut header = " ".repeat(name_width + 1 + label + 2);
+        for (fret, width) in (1..=last).zip(&widths) {
+            let _ = write!(header, "{:^width$} ", fret);
+        }
+        let _ = writeln!(out, "{}", header.trim_end());
+        for (string, open) in config.strings.iter().enumerate().rev() {
+            let open_text = self
+                .tone_at(open, 0)
+                .map(|t| self.text(t))
+                .unwrap_or_default();
+            let _ = write!(
+                out,
+                "{:<name_width$} {:^label$}||",
+                names[string], open_text
+            );
+            for (fret, width) in (1..=last).zip(&widths) {
+                let text = self.tone_at(open, fret).map(|t| self.text(t));
+                let _ = write!(out, "{}{}", centered(text.as_deref(), *width), separator);
+            }
+            out.push('\n');
+        }
+        if fretted {
+            let mut inlays = " ".repeat(name_width + 1 + label + 2);
+            for (fret, width) in (1..=last).zip(&widths) {
+                let _ = write!(inlays, "{:^width$} ", inlay(fret).unwrap_or(""));
+            }
+            let _ = writeln!(out, "{}", inlays.trim_end());
+        }
+        out
+    }
+
+    /// Render the neck as SVG, nut on the left and highest string on top
+    pub fn render_svg<F>(&self, fretboard: &F) -> String
+    where
+        F: Fretboard<Config = StringedInstrumentConfig>,
+    {
+        let config = fretboard.get_config();
+        let svg = &self.svg;
+        let fretted = config.fret_count > 0;
+        let last = self.last_fret(config);
+        let strings = config.strings.len();
+        let spacing = svg.string_spacing;
+        let radius = svg.dot_radius;
+
+        let left = svg.margin + svg.font_size * 2.0 + radius * 2.0 + 4.0;
+        let top = svg.margin;
+        let across = (strings.max(1) - 1) as f32 * spacing;
+        // Frets at their distance from the nut on the instrument's scale length
+        let length = config.scale_length * fret_distance(last) * self.scale;
+        let width = left + length + svg.margin;
+        let height = top + across + svg.font_size * 2.0 + svg.margin;
+        let x = |fret: usize| left + config.scale_length * fret_distance(fret) * self.scale;
+        let y = |string: usize| top + (strings - 1 - string) as f32 * spacing;
+        // Middle of a fret, ahead of the nut for open strings
+        let center = |fret: usize| match fret {
+            0 => left - radius - 4.0,
+            fret => (x(fret - 1) + x(fret)) / 2.0,
+        };
+
+        let mut out = SvgWriter::new(width, height);
+        if let Some(background) = &svg.background {
+            out.rect(0.0, 0.0, width, height, background);
+        }
+        if fretted {
+            for fret in 1..=last {
+                let double = fret % 12 == 0;
+                match inlay(fret) {
+                    Some(_) if double => {
+                        out.circle(
+                            center(fret),
+                            top + across / 4.0,
+                            radius * 0.6,
+                            &self.inlay_color,
+                            "none",
+                            0.0,
+                        );
+                        out.circle(
+                            center(fret),
+                            top + across * 3.0 / 4.0,
+                            radius * 0.6,
+                            &self.inlay_color,
+                            "none",
+                            0.0,
+                        );
+                    }
+                    Some(_) => out.circle(
+                        center(fret),
+                        top + across / 2.0,
+                        radius * 0.6,
+                        &self.inlay_color,
+                        "none",
+                        0.0,
+                    ),
+                    None => {}
+                }
+                if inlay(fret).is_some() || fret == 1 {
+                    out.text(
+                        center(fret),
+                        top + across + svg.font_size * 1.5,
+                        &fret.to_string(),
+                        svg.font_size,
+                        &svg.font_family,
+                        &svg.text_color,
+                        "middle",
+                    );
+                }
+                out.line(
+                    x(fret),
+                    top,
+                    x(fret),
+                    top + across,
+                    &svg.line_color,
+                    svg.line_width,
+                );
+            }
+        }
+        out.line(
+            left,
+            top,
+            left,
+            top + across,
+            &svg.line_color,
+            svg.nut_width,
+        );
+        for (string, open) in config.strings.iter().enumerate() {
+            out.line(
+                left,
+                y(string),
+                x(last),
+                y(string),
+                &svg.line_color,
+                svg.line_width,
+            );
+            if svg.show_string_labels {
+                out.text(
+                    svg.margin,
+                    y(string) + svg.font_size * 0.35,
+                    &open.to_string(),
+                    svg.font_size,
+                    &svg.font_family,
+                    &svg.text_color,
+                    "start",
+                );
+            }
+        }
+
+        for (string, open) in config.strings.iter().enumerate() {
+            for fret in 0..=last {
+                let Some(tone) = self.tone_at(open, fret) else {
+                    continue;
+                };
+                let root = tone.degree == 1;
+                let fill = if root {
+                    &self.root_color
+                } else {
+                    &svg.dot_color
+                };
+                out.circle(center(fret), y(string), radius, fill, "none", 0.0);
+                out.text(
+                    center(fret),
+                    y(string) + svg.font_size * 0.3,
+                    &self.text(tone),
+                    svg.font_size * 0.8,
+                    &svg.font_family,
+                    &svg.finger_color,
+                    "middle",
+                );
+            }
+        }
+        out.finish()
+    }
+
+    fn last_fret(&self, config: &StringedInstrumentConfig) -> usize {
+        let frets = match config.fret_count {
+            0 => UNFRETTED_SPAN,
+            frets => frets as usize,
+        };
+        self.frets.unwrap_or(frets)
+    }
+
+    fn tone_at(&self, open: &Tuning, fret: usize) -> Option<&MapTone> {
+        let class = (open.number() as i32 + fret as i32).rem_euclid(12);
+        self.tones
+            .iter()
+            .find(|t| (t.tuning.number() as i32).rem_euclid(12) == class)
+    }
+
+    fn text(&self, tone: &MapTone) -> String {
+        match self.label {
+            MapLabel::NoteName => tone.tuning.to_string(),
+            MapLabel::Degree => tone.degree.to_string(),
+            MapLabel::Interval if tone.degree == 1 => "R".to_string(),
+            MapLabel::Interval => tone.interval.name(),
+        }
+    }
+}
+
+/// Distance of a fret from the nut as a fraction of the scale length
+fn fret_distance(fret: usize) -> f32 {
+    1.0 - 2f32.powf(-(fret as f32) / 12.0)
+}
+
+/// Inlay marking a fret, double dots at the octaves
+fn inlay(fret: usize) -> Option<&'static str> {
+    match fret % 12 {
+        0 => Some("**"),
+        3 | 5 | 7 | 9 => Some("*"),
+        _ => None,
+    }
+}
+
+fn centered(text: Option<&str>, width: usize) -> String {
+    match text {
+        Some(text) => format!("{:-^width$}", text),
+        None => "-".repeat(width),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ChordQuality, ContinuousFretboard, InstrumentPresets, PitchClass, ScaleType,
+        StringedFretboard,
+    };
+
+    #[test]
+    fn test_scale_positions_and_labels() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let scale = Scale::new(Tuning::new(PitchClass::A, 3), ScaleType::PentatonicMinor).unwrap();
+        let map = FretboardMap::from_scale(&scale).with_frets(12);
+        let positions = map.positions(&guitar);
+        // Two of the five pitch classes on every string per octave, plus the 12th fret
+        assert!(positions.contains(&(StringedPosition::new(0, 5), "A".to_string())));
+        assert!(positions.contains(&(StringedPosition::new(0, 0), "E".to_string())));
+        assert!(!positions.iter().any(|(p, _)| p.string == 0 && p.fret == 1));
+
+        let degrees = map.clone().with_label(MapLabel::Degree).positions(&guitar);
+        assert!(degrees.contains(&(StringedPosition::new(0, 5), "1".to_string())));
+        let intervals = map.with_label(MapLabel::Interval).positions(&guitar);
+        assert!(intervals.contains(&(StringedPosition::new(0, 5), "R".to_string())));
+        assert!(intervals.contains(&(StringedPosition::new(0, 8), "m3".to_string())));
+    }
+
+    #[test]
+    fn test_ascii_neck() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let chord = Chord::new(Tuning::new(PitchClass::C, 3), ChordQuality::Major).unwrap();
+        let ascii = FretboardMap::from_chord(&chord)
+            .with_frets(12)
+            .render_ascii(&guitar);
+        let lines = ascii.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].trim_start().starts_with('1'));
+        // High E first, open E is a chord tone and the 1st fret is F
+        assert!(lines[1].starts_with("E E||-------------|"), "{ascii}");
+        assert_eq!(lines[6], lines[1]);
+        assert!(lines[7].contains('*') && lines[7].ends_with("**"));
+        // Lower frets are wider than higher ones
+        let cells = lines[1]
+            .split(['|', ':'])
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        assert!(cells[1].len() > cells[12].len());
+
+        let violin = ContinuousFretboard::new(InstrumentPresets::violin_standard()).unwrap();
+        let ascii = FretboardMap::from_chord(&chord).render_ascii(&violin);
+        assert_eq!(ascii.lines().count(), 5);
+        assert!(ascii.contains(':') && !ascii.contains('*'));
+    }
+
+    #[test]
+    fn test_svg_neck() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let scale = Scale::new(Tuning::new(PitchClass::G, 3), ScaleType::Major).unwrap();
+        let svg = FretboardMap::from_scale(&scale)
+            .with_frets(15)
+            .with_colors("#ff0000", "#eeeeee")
+            .render_svg(&guitar);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        // Inlays at 3, 5, 7, 9, 15 and two at 12
+        assert_eq!(svg.matches("fill=\"#eeeeee\"").count(), 7);
+        // Seven notes over 15 frets and open strings: roots in their own colour
+        assert_eq!(svg.matches("fill=\"#ff0000\"").count(), 6 * 16 / 12 + 1);
+        assert!(svg.contains(">F#</text>"));
+
+        // A shorter scale length brings the frets closer
+        let mut short = InstrumentPresets::guitar_standard();
+        short.scale_length = 540.0;
+        let short = StringedFretboard::new(short).unwrap();
+        let width = |svg: &str| {
+            let start = svg.find("width=\"").unwrap() + 7;
+            svg[start..start + svg[start..].find('"').unwrap()]
+                .parse::<f32>()
+                .unwrap()
+        };
+        let map = FretboardMap::from_scale(&scale).with_frets(12);
+        let (long, short) = (map.render_svg(&guitar), map.render_svg(&short));
+        let left = width(&long) - 648.0 * 0.5 * 1.5;
+        assert!((width(&short) - left - 540.0 * 0.5 * 1.5).abs() < 1.0);
+    }
+}
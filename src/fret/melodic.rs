@@ -0,0 +1,167 @@
+//! Fingering optimization for single-note lines
+
+use super::{
+    errors::{FretboardError, FretboardResult},
+    traits::Fretboard,
+    types::StringedPosition,
+    StringedFretboard,
+};
+use crate::Tuning;
+
+/// Melodic fingering optimizer
+///
+/// Picks a string for every note of a melody so that the hand moves as little as
+/// possible, using dynamic programming over all positions of each note.
+#[derive(Clone, Debug)]
+pub struct MelodicFingeringOptimizer {
+    /// Cost of moving the hand by one fret
+    shift_weight: f32,
+    /// Cost of crossing one string
+    string_weight: f32,
+    /// Cost per fret of playing high up the neck
+    height_weight: f32,
+}
+
+impl MelodicFingeringOptimizer {
+    /// Create a new melodic fingering optimizer with default weights
+    pub fn new() -> Self {
+        Self {
+            shift_weight: 1.0,
+            string_weight: 0.3,
+            height_weight: 0.05,
+        }
+    }
+
+    /// Set the weights for hand shifts, string crossings and neck height
+    pub fn with_weights(
+        mut self,
+        shift_weight: f32,
+        string_weight: f32,
+        height_weight: f32,
+    ) -> Self {
+        self.shift_weight = shift_weight;
+        self.string_weight = string_weight;
+        self.height_weight = height_weight;
+        self
+    }
+
+    /// Find the positions of a melody with the lowest total cost
+    ///
+    /// Fails with `TuningOutOfRange` when a note cannot be played on the fretboard.
+    pub fn optimize_melody(
+        &self,
+        fretboard: &StringedFretboard,
+        melody: &[Tuning],
+    ) -> FretboardResult<Vec<StringedPosition>> {
+        let mut candidates = Vec::with_capacity(melody.len());
+        for tuning in melody {
+            let positions = fretboard.positions_for_tuning(tuning);
+            if positions.is_empty() {
+                return Err(FretboardError::tuning_out_of_range(tuning));
+            }
+            candidates.push(positions);
+        }
+        if candidates.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // costs[i][j] = (total cost ending on candidate j of note i, previous candidate)
+        let mut costs: Vec<Vec<(f32, usize)>> = vec![candidates[0]
+            .iter()
+            .map(|p| (self.height_cost(p), 0))
+            .collect()];
+        for i in 1..candidates.len() {
+            let row = candidates[i]
+                .iter()
+                .map(|current| {
+                    candidates[i - 1]
+                        .iter()
+                        .enumerate()
+                        .map(|(j, previous)| {
+                            let cost = costs[i - 1][j].0
+                                + self.transition_cost(previous, current)
+                                + self.height_cost(current);
+                            (cost, j)
+                        })
+                        .min_by(|a, b| a.0.total_cmp(&b.0))
+                        .unwrap()
+                })
+                .collect();
+            costs.push(row);
+        }
+
+        let last = costs.len() - 1;
+        let (mut index, _) = costs[last]
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1 .0.total_cmp(&b.1 .0))
+            .unwrap();
+        let mut positions = Vec::with_capacity(candidates.len());
+        for i in (0..=last).rev() {
+            positions.push(candidates[i][index].clone());
+            index = costs[i][index].1;
+        }
+        positions.reverse();
+        Ok(positions)
+    }
+
+    /// Cost of moving from one position to the next, open strings need no hand
+    fn transition_cost(&self, from: &StringedPosition, to: &StringedPosition) -> f32 {
+        let shift = match from.is_open() || to.is_open() {
+            true => 0,
+            false => from.fret.abs_diff(to.fret),
+        };
+        shift as f32 * self.shift_weight
+            + from.string.abs_diff(to.string) as f32 * self.string_weight
+    }
+
+    fn height_cost(&self, position: &StringedPosition) -> f32 {
+        position.fret as f32 * self.height_weight
+    }
+}
+
+impl Default for MelodicFingeringOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InstrumentPresets, PitchClass};
+
+    #[test]
+    fn test_scale_stays_in_position() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let scale = [
+            Tuning::new(PitchClass::C, 4),
+            Tuning::new(PitchClass::D, 4),
+            Tuning::new(PitchClass::E, 4),
+            Tuning::new(PitchClass::F, 4),
+        ];
+        let positions = MelodicFingeringOptimizer::new()
+            .optimize_melody(&guitar, &scale)
+            .unwrap();
+        let frets = positions.iter().map(|p| p.fret).collect::<Vec<_>>();
+        assert_eq!(frets, vec![1, 3, 0, 1]);
+        for (position, tuning) in positions.iter().zip(&scale) {
+            assert_eq!(guitar.tuning_at_position(position).unwrap(), *tuning);
+        }
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let result = MelodicFingeringOptimizer::new()
+            .optimize_melody(&guitar, &[Tuning::new(PitchClass::C, 1)]);
+        assert!(matches!(
+            result,
+            Err(FretboardError::TuningOutOfRange { .. })
+        ));
+        assert!(MelodicFingeringOptimizer::new()
+            .optimize_melody(&guitar, &[])
+            .unwrap()
+            .is_empty());
+    }
+}
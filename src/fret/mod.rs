@@ -8,8 +8,12 @@ pub mod continuous;
 pub mod errors;
 pub mod fingering;
 pub mod keyboard;
+pub mod keyboard_diagram;
+pub mod map;
+pub mod melodic;
 pub mod presets;
 pub mod stringed;
+pub mod svg;
 pub mod traits;
 pub mod types;
 pub mod visualization;
@@ -25,8 +29,12 @@ pub use fingering::{
     ChordFingeringConfig, ChordFingeringGenerator, DifficultyEvaluator, DifficultyWeights,
 };
 pub use keyboard::KeyboardFretboard;
+pub use keyboard_diagram::KeyboardDiagram;
+pub use map::{FretboardMap, MapLabel};
+pub use melodic::MelodicFingeringOptimizer;
 pub use presets::InstrumentPresets;
 pub use stringed::StringedFretboard;
+pub use svg::{SvgDiagramConfig, SvgDiagramGenerator};
 pub use traits::*;
 pub use types::*;
 pub use visualization::{DiagramConfig, FretboardDiagramGenerator};
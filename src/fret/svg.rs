@@ -0,0 +1,709 @@
+//! SVG chord diagrams
+//!
+//! Vector counterpart of the ASCII diagrams: chord boxes with the nut or a position
+//! marker, finger numbers, open and muted strings, barre arcs and technique annotations,
+//! drawn in any `DiagramOrientation`.
+
+use super::{
+    errors::{FretboardError, FretboardResult},
+    types::{Fingering, PlayingTechnique, StringedPosition},
+    visualization::DiagramOrientation,
+    StringedFretboard,
+};
+use std::fmt::Write;
+
+/// Colours and sizes of SVG chord diagrams
+#[derive(Clone, Debug)]
+pub struct SvgDiagramConfig {
+    /// Distance between two strings in pixels
+    pub string_spacing: f32,
+    /// Distance between two frets in pixels
+    pub fret_spacing: f32,
+    /// Least number of frets drawn
+    pub frets: usize,
+    /// Space around the chord box in pixels
+    pub margin: f32,
+    /// Width of the string and fret lines
+    pub line_width: f32,
+    /// Width of the nut line
+    pub nut_width: f32,
+    /// Radius of the finger dots
+    pub dot_radius: f32,
+    pub font_family: String,
+    pub font_size: f32,
+    /// Colour of strings, frets and the nut
+    pub line_color: String,
+    /// Colour of the finger dots and barres
+    pub dot_color: String,
+    /// Colour of the finger numbers on the dots
+    pub finger_color: String,
+    /// Colour of names, labels and annotations
+    pub text_color: String,
+    /// Background fill, transparent when `None`
+    pub background: Option<String>,
+    /// Show finger numbers in the dots
+    pub show_finger_numbers: bool,
+    /// Show the tuning of each string
+    pub show_string_labels: bool,
+}
+
+impl Default for SvgDiagramConfig {
+    fn default() -> Self {
+        Self {
+            string_spacing: 20.0,
+            fret_spacing: 24.0,
+            frets: 4,
+            margin: 24.0,
+            line_width: 1.5,
+            nut_width: 5.0,
+            dot_radius: 7.5,
+            font_family: "sans-serif".to_string(),
+            font_size: 12.0,
+            line_color: "#000000".to_string(),
+            dot_color: "#000000".to_string(),
+            finger_color: "#ffffff".to_string(),
+            text_color: "#000000".to_string(),
+            background: None,
+            show_finger_numbers: true,
+            show_string_labels: true,
+        }
+    }
+}
+
+impl SvgDiagramConfig {
+    /// Create a new SVG diagram configuration with default values
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the distances between strings and between frets
+    pub fn with_spacing(mut self, string_spacing: f32, fret_spacing: f32) -> Self {
+        self.string_spacing = string_spacing;
+        self.fret_spacing = fret_spacing;
+        self
+    }
+
+    /// Set the least number of frets drawn
+    pub fn with_frets(mut self, frets: usize) -> Self {
+        self.frets = frets.max(1);
+        self
+    }
+
+    /// Set the radius of the finger dots
+    pub fn with_dot_radius(mut self, radius: f32) -> Self {
+        self.dot_radius = radius;
+        self
+    }
+
+    /// Set the font of names, labels and finger numbers
+    pub fn with_font(mut self, family: impl Into<String>, size: f32) -> Self {
+        self.font_family = family.into();
+        self.font_size = size;
+        self
+    }
+
+    /// Set the colours of lines, dots, finger numbers and text
+    pub fn with_colors(
+        mut self,
+        line_color: impl Into<String>,
+        dot_color: impl Into<String>,
+        finger_color: impl Into<String>,
+        text_color: impl Into<String>,
+    ) -> Self {
+        self.line_color = line_color.into();
+        self.dot_color = dot_color.into();
+        self.finger_color = finger_color.into();
+        self.text_color = text_color.into();
+        self
+    }
+
+    /// Fill the background with a colour
+    pub fn with_background(mut self, color: impl Into<String>) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+
+    /// Enable or disable finger numbers
+    pub fn with_finger_numbers(mut self, show: bool) -> Self {
+        self.show_finger_numbers = show;
+        self
+    }
+
+    /// Enable or disable string labels
+    pub fn with_string_labels(mut self, show: bool) -> Self {
+        self.show_string_labels = show;
+        self
+    }
+}
+
+/// SVG chord diagram generator
+#[derive(Clone, Debug, Default)]
+pub struct SvgDiagramGenerator {
+    config: SvgDiagramConfig,
+}
+
+impl SvgDiagramGenerator {
+    /// Create a new SVG diagram generator with default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new SVG diagram generator with custom configuration
+    pub fn with_config(config: SvgDiagramConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &SvgDiagramConfig {
+        &self.config
+    }
+
+    /// Generate an SVG chord box for a fingering, titled with `name` if given
+    ///
+    /// Standard draws the strings upright with the lowest one on the left, left-handed
+    /// mirrors it, and vertical lays the strings across with the highest one on top.
+    pub fn generate_svg(
+        &self,
+        fretboard: &StringedFretboard,
+        fingering: &Fingering<StringedPosition>,
+        name: Option<&str>,
+        orientation: DiagramOrientation,
+    ) -> FretboardResult<String> {
+        let strings = fretboard.string_count();
+        if let Some(p) = fingering
+            .positions
+            .iter()
+            .find(|p| p.position.string as usize >= strings)
+        {
+            return Err(FretboardError::invalid_position_with_context(
+                p.position.to_string(),
+                "String index out of range for the diagram",
+            ));
+        }
+        if let PlayingTechnique::Barre {
+            start_string,
+            end_string,
+            fret,
+        } = fingering.technique
+        {
+            if start_string.max(end_string) as usize >= strings {
+                return Err(FretboardError::invalid_position_with_context(
+                    format!("barre {start_string}-{end_string} at fret {fret}"),
+                    "String index out of range for the diagram",
+                ));
+            }
+        }
+
+        let config = &self.config;
+        let fretted = fingering
+            .positions
+            .iter()
+            .map(|p| p.position.fret as usize)
+            .filter(|&f| f > 0);
+        let (low, high) = fretted.fold((usize::MAX, 0), |(lo, hi), f| (lo.min(f), hi.max(f)));
+        // First fret drawn, the nut when the chord fits below `frets`
+        let first = if high <= config.frets { 1 } else { low };
+        let frets = config.frets.max(high + 1 - first.min(high.max(1)));
+
+        let box_across = (strings - 1) as f32 * config.string_spacing;
+        let box_along = frets as f32 * config.fret_spacing;
+        let marker = config.dot_radius * 2.0 + 4.0;
+        let title = if name.is_some() {
+            config.font_size * 1.6
+        } else {
+            0.0
+        };
+        let label = if config.show_string_labels {
+            config.font_size * 1.5
+        } else {
+            0.0
+        };
+        let footer = match annotation(&fingering.technique) {
+            Some(_) => config.font_size * 1.5,
+            None => 0.0,
+        };
+
+        // Offsets of the box and of the diagram's size, depending on the orientation
+        let vertical = orientation == DiagramOrientation::Vertical;
+        let (left, top, width, height) = match vertical {
+            false => (
+                config.margin + label.max(config.font_size * 2.0),
+                config.margin + title + marker,
+                box_across + 2.0 * config.margin + label.max(config.font_size * 2.0),
+                box_along + 2.0 * config.margin + title + marker + footer,
+            ),
+            true => (
+                config.margin + marker + label,
+                config.margin + title,
+                box_along + 2.0 * config.margin + marker + label,
+                box_across + 2.0 * config.margin + title + config.font_size * 1.5 + footer,
+            ),
+        };
+        // Point of a string (0 is the lowest) at a distance along the neck in frets
+        let point = |string: usize, along: f32| -> (f32, f32) {
+            let across = match orientation {
+                DiagramOrientation::Standard => string,
+                DiagramOrientation::LeftHanded | DiagramOrientation::Vertical => {
+                    strings - 1 - string
+                }
+            } as f32
+                * config.string_spacing;
+            match vertical {
+                false => (left + across, top + along * config.fret_spacing),
+                true => (left + along * config.fret_spacing, top + across),
+            }
+        };
+
+        let mut svg = SvgWriter::new(width, height);
+        if let Some(background) = &config.background {
+            svg.rect(0.0, 0.0, width, height, background);
+        }
+        let text = |svg: &mut SvgWriter, x: f32, y: f32, size: f32, content: &str| {
+            svg.text(
+                x,
+                y,
+                content,
+                size,
+                &config.font_family,
+                &config.text_color,
+                "middle",
+            );
+        };
+        if let Some(name) = name {
+            let x = match vertical {
+                false => left + box_across / 2.0,
+                true => left + box_along / 2.0,
+            };
+            text(
+                &mut svg,
+                x,
+                config.margin + config.font_size,
+                config.font_size * 1.4,
+                name,
+            );
+        }
+
+        // Frets, the nut or the position marker, then strings
+        for fret in 0..=frets {
+            let (x1, y1) = point(0, fret as f32);
+            let (x2, y2) = point(strings - 1, fret as f32);
+            let width = match fret == 0 && first == 1 {
+                true => config.nut_width,
+                false => config.line_width,
+            };
+            svg.line(x1, y1, x2, y2, &config.line_color, width);
+        }
+        if first > 1 {
+            let (x, y) = match vertical {
+                false => {
+                    let (x, y) = point(0, 0.5);
+                    let x = x.min(point(strings - 1, 0.5).0);
+                    (x - config.font_size * 1.2, y + config.font_size * 0.35)
+                }
+                true => {
+                    let (x, y) = point(0, 0.5);
+                    (x, y + config.font_size * 1.3)
+                }
+            };
+            text(&mut svg, x, y, config.font_size, &format!("{first}fr"));
+        }
+        for string in 0..strings {
+            let (x1, y1) = point(string, 0.0);
+            let (x2, y2) = point(string, frets as f32);
+            svg.line(x1, y1, x2, y2, &config.line_color, config.line_width);
+        }
+
+        if config.show_string_labels {
+            for string in 0..strings {
+                let tuning = fretboard
+                    .string_tuning(string)
+                    .map(|t| t.to_string())
+                    .unwrap_or_default();
+                let (x, y) = match vertical {
+                    false => {
+                        let (x, y) = point(string, frets as f32);
+                        (x, y + config.font_size * 1.3)
+                    }
+                    true => {
+                        let (x, y) = point(string, 0.0);
+                        (
+                            x - marker - config.font_size * 0.6,
+                            y + config.font_size * 0.35,
+                        )
+                    }
+                };
+                text(&mut svg, x, y, config.font_size, &tuning);
+            }
+        }
+
+        // Open and muted strings ahead of the nut
+        let ahead = -(marker / 2.0) / config.fret_spacing;
+        let radius = config.dot_radius * 0.6;
+        for string in 0..strings {
+            let (x, y) = point(string, ahead);
+            let played = fingering
+                .positions
+                .iter()
+                .filter(|p| p.position.string as usize == string);
+            match played.map(|p| p.position.fret).min() {
+                Some(0) => svg.circle(x, y, radius, "none", &config.line_color, config.line_width),
+                Some(_) => {}
+                None => {
+                    svg.line(
+                        x - radius,
+                        y - radius,
+                        x + radius,
+                        y + radius,
+                        &config.line_color,
+                        config.line_width,
+                    );
+                    svg.line(
+                        x - radius,
+                        y + radius,
+                        x + radius,
+                        y - radius,
+                        &config.line_color,
+                        config.line_width,
+                    );
+                }
+            }
+        }
+
+        // Barres: the technique's and every finger holding one fret over several strings
+        let mut barres = vec![];
+        if let PlayingTechnique::Barre {
+            start_string,
+            end_string,
+            fret,
+        } = fingering.technique
+        {
+            barres.push((
+                start_string.min(end_string),
+                start_string.max(end_string),
+                fret,
+            ));
+        }
+        for p in &fingering.positions {
+            let Some(finger) = p.finger else { continue };
+            let held = fingering
+                .positions
+                .iter()
+                .filter(|q| q.finger == Some(finger) && q.position.fret == p.position.fret)
+                .map(|q| q.position.string);
+            let (lo, hi) = held.fold((u32::MAX, 0), |(lo, hi), s| (lo.min(s), hi.max(s)));
+            if hi > lo && p.position.fret > 0 && !barres.contains(&(lo, hi, p.position.fret)) {
+                barres.push((lo, hi, p.position.fret));
+            }
+        }
+        for &(lo, hi, fret) in &barres {
+            if (fret as usize) < first {
+                continue;
+            }
+            let along = (fret as usize - first) as f32 + 0.5;
+            let (x1, y1) = point(lo as usize, along);
+            let (x2, y2) = point(hi as usize, along);
+            // Bowed away from the fret's dots, towards the nut
+            let bow = config.fret_spacing * 0.45;
+            let (cx, cy) = match vertical {
+                false => ((x1 + x2) / 2.0, (y1 + y2) / 2.0 - bow),
+                true => ((x1 + x2) / 2.0 - bow, (y1 + y2) / 2.0),
+            };
+            svg.path(
+                &format!("M {x1:.1} {y1:.1} Q {cx:.1} {cy:.1} {x2:.1} {y2:.1}"),
+                &config.dot_color,
+                config.dot_radius,
+            );
+        }
+
+        // Finger dots, diamonds for harmonics
+        for p in fingering.positions.iter().filter(|p| p.position.fret > 0) {
+            let fret = p.position.fret as usize;
+            if fret < first {
+                continue;
+            }
+            let (x, y) = point(p.position.string as usize, (fret - first) as f32 + 0.5);
+            let r = config.dot_radius;
+            match fingering.technique {
+                PlayingTechnique::Harmonic => svg.polygon(
+                    &[(x, y - r), (x + r, y), (x, y + r), (x - r, y)],
+                    &config.dot_color,
+                ),
+                _ => svg.circle(x, y, r, &config.dot_color, "none", 0.0),
+            }
+            if let Some(finger) = p.finger.filter(|_| config.show_finger_numbers) {
+                svg.text(
+                    x,
+                    y + config.font_size * 0.35,
+                    &finger.to_string(),
+                    config.font_size * 0.9,
+                    &config.font_family,
+                    &config.finger_color,
+                    "middle",
+                );
+            }
+        }
+
+        if let Some(annotation) = annotation(&fingering.technique) {
+            text(
+                &mut svg,
+                width / 2.0,
+                height - config.margin / 2.0,
+                config.font_size,
+                &annotation,
+            );
+        }
+        Ok(svg.finish())
+    }
+}
+
+/// Text under the diagram for techniques the drawing does not show
+fn annotation(technique: &PlayingTechnique) -> Option<String> {
+    match technique {
+        PlayingTechnique::Standard | PlayingTechnique::Barre { .. } => None,
+        technique => Some(technique.to_string()),
+    }
+}
+
+/// Minimal SVG document builder
+pub(crate) struct SvgWriter {
+    out: String,
+}
+
+impl SvgWriter {
+    pub fn new(width: f32, height: f32) -> Self {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width:.0}" height="{height:.0}" viewBox="0 0 {width:.0} {height:.0}">"#
+        );
+        SvgWriter { out }
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: &str, width: f32) {
+        let _ = writeln!(
+            self.out,
+            r#"  <line x1="{x1:.1}" y1="{y1:.1}" x2="{x2:.1}" y2="{y2:.1}" stroke="{color}" stroke-width="{width:.1}" stroke-linecap="round"/>"#
+        );
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, fill: &str) {
+        let _ = writeln!(
+            self.out,
+            r#"  <rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{fill}"/>"#
+        );
+    }
+
+    /// Rectangle with an outline
+    pub fn framed_rect(
+        &mut self,
+        (x, y): (f32, f32),
+        (width, height): (f32, f32),
+        fill: &str,
+        stroke: &str,
+        stroke_width: f32,
+    ) {
+        let _ = writeln!(
+            self.out,
+            r#"  <rect x="{x:.1}" y="{y:.1}" width="{width:.1}" height="{height:.1}" fill="{fill}" stroke="{stroke}" stroke-width="{stroke_width:.1}"/>"#
+        );
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, r: f32, fill: &str, stroke: &str, width: f32) {
+        let _ = writeln!(
+            self.out,
+            r#"  <circle cx="{x:.1}" cy="{y:.1}" r="{r:.1}" fill="{fill}" stroke="{stroke}" stroke-width="{width:.1}"/>"#
+        );
+    }
+
+    pub fn polygon(&mut self, points: &[(f32, f32)], fill: &str) {
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(self.out, r#"  <polygon points="{points}" fill="{fill}"/>"#);
+    }
+
+    /// Ellipse turned by `angle` degrees, hollow when `fill` is `none`
+    #[allow(clippy::too_many_arguments)]
+    pub fn ellipse(
+        &mut self,
+        x: f32,
+        y: f32,
+        rx: f32,
+        ry: f32,
+        angle: f32,
+        fill: &str,
+        stroke: &str,
+        width: f32,
+    ) {
+        let _ = writeln!(
+            self.out,
+            r#"  <ellipse cx="{x:.1}" cy="{y:.1}" rx="{rx:.1}" ry="{ry:.1}" transform="rotate({angle:.0} {x:.1} {y:.1})" fill="{fill}" stroke="{stroke}" stroke-width="{width:.1}"/>"#
+        );
+    }
+
+    /// Unfilled path
+    pub fn path(&mut self, d: &str, stroke: &str, width: f32) {
+        let _ = writeln!(
+            self.out,
+            r#"  <path d="{d}" fill="none" stroke="{stroke}" stroke-width="{width:.1}" stroke-linecap="round"/>"#
+        );
+    }
+
+    /// Text anchored at `anchor` (`start`, `middle` or `end`) of its baseline
+    #[allow(clippy::too_many_arguments)]
+    pub fn text(
+        &mut self,
+        x: f32,
+        y: f32,
+        content: &str,
+        size: f32,
+        family: &str,
+        color: &str,
+        anchor: &str,
+    ) {
+        let content = content
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        let _ = writeln!(
+            self.out,
+            r#"  <text x="{x:.1}" y="{y:.1}" font-family="{family}" font-size="{size:.1}" fill="{color}" text-anchor="{anchor}">{content}</text>"#
+        );
+    }
+
+    pub fn finish(mut self) -> String {
+        self.out.push_str("</svg>\n");
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fret::presets::InstrumentPresets;
+    use crate::fret::types::{Finger, FingerPosition};
+
+    fn guitar() -> StringedFretboard {
+        StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap()
+    }
+
+    #[test]
+    fn test_open_chord() {
+        // C major: x32010
+        let fingering = Fingering::new(
+            vec![
+                FingerPosition::pressed(StringedPosition::new(1, 3), Finger::Ring),
+                FingerPosition::pressed(StringedPosition::new(2, 2), Finger::Middle),
+                FingerPosition::open(StringedPosition::new(3, 0)),
+                FingerPosition::pressed(StringedPosition::new(4, 1), Finger::Index),
+                FingerPosition::open(StringedPosition::new(5, 0)),
+            ],
+            PlayingTechnique::Standard,
+            0.2,
+        );
+        let svg = SvgDiagramGenerator::new()
+            .generate_svg(
+                &guitar(),
+                &fingering,
+                Some("C"),
+                DiagramOrientation::Standard,
+            )
+            .unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">C</text>"));
+        // Nut, three dots with their fingers, two open strings and one muted string
+        assert!(svg.contains("stroke-width=\"5.0\""));
+        assert_eq!(svg.matches("fill=\"#000000\" stroke=\"none\"").count(), 3);
+        assert!(svg.contains(">3</text>") && svg.contains(">2</text>"));
+        assert_eq!(svg.matches("fill=\"none\" stroke=\"#000000\"").count(), 2);
+        assert!(!svg.contains("fr</text>"));
+
+        // Mirrored for left-handed players: the muted low E moves to the right
+        let left = SvgDiagramGenerator::new()
+            .generate_svg(&guitar(), &fingering, None, DiagramOrientation::LeftHanded)
+            .unwrap();
+        assert_ne!(left, svg);
+        assert!(!left.contains(">C</text>"));
+    }
+
+    #[test]
+    fn test_barre_and_orientation() {
+        // B minor barre at the 7th fret, past the nut
+        let barre =
+            |string| FingerPosition::pressed(StringedPosition::new(string, 7), Finger::Index);
+        let fingering = Fingering::new(
+            vec![
+                barre(1),
+                FingerPosition::pressed(StringedPosition::new(2, 9), Finger::Ring),
+                FingerPosition::pressed(StringedPosition::new(3, 9), Finger::Pinky),
+                FingerPosition::pressed(StringedPosition::new(4, 8), Finger::Middle),
+                barre(5),
+            ],
+            PlayingTechnique::Barre {
+                start_string: 1,
+                end_string: 5,
+                fret: 7,
+            },
+            0.6,
+        );
+        let config = SvgDiagramConfig::new()
+            .with_colors("#333333", "#aa0000", "#ffffff", "#333333")
+            .with_background("#fafafa");
+        for orientation in [
+            DiagramOrientation::Standard,
+            DiagramOrientation::LeftHanded,
+            DiagramOrientation::Vertical,
+        ] {
+            let svg = SvgDiagramGenerator::with_config(config.clone())
+                .generate_svg(&guitar(), &fingering, Some("Bm"), orientation)
+                .unwrap();
+            assert!(svg.contains(">7fr</text>"), "{svg}");
+            assert_eq!(svg.matches("<path").count(), 1);
+            assert!(svg.contains("fill=\"#fafafa\""));
+            assert!(!svg.contains("stroke-width=\"5.0\""));
+        }
+
+        let slide = Fingering::new(
+            vec![FingerPosition::pressed(
+                StringedPosition::new(0, 5),
+                Finger::Index,
+            )],
+            PlayingTechnique::Slide,
+            0.1,
+        );
+        let svg = SvgDiagramGenerator::new()
+            .generate_svg(&guitar(), &slide, None, DiagramOrientation::Vertical)
+            .unwrap();
+        assert!(svg.contains(">Slide</text>"));
+        let wrong = Fingering::new(
+            vec![FingerPosition::open(StringedPosition::new(6, 0))],
+            PlayingTechnique::Standard,
+            0.0,
+        );
+        assert!(SvgDiagramGenerator::new()
+            .generate_svg(&guitar(), &wrong, None, DiagramOrientation::Standard)
+            .is_err());
+
+        // A barre past the last string is refused in every orientation
+        let wide = Fingering::new(
+            fingering.positions.clone(),
+            PlayingTechnique::Barre {
+                start_string: 0,
+                end_string: 6,
+                fret: 7,
+            },
+            0.6,
+        );
+        for orientation in [
+            DiagramOrientation::Standard,
+            DiagramOrientation::LeftHanded,
+            DiagramOrientation::Vertical,
+        ] {
+            assert!(SvgDiagramGenerator::new()
+                .generate_svg(&guitar(), &wide, None, orientation)
+                .is_err());
+        }
+    }
+}
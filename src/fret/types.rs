@@ -121,6 +121,38 @@ impl Display for Finger {
     }
 }
 
+impl Finger {
+    /// Finger number in piano fingering, thumb is 1 and pinky is 5
+    pub fn piano_number(&self) -> u8 {
+        match self {
+            Finger::Thumb => 1,
+            Finger::Index => 2,
+            Finger::Middle => 3,
+            Finger::Ring => 4,
+            Finger::Pinky => 5,
+        }
+    }
+}
+
+/// Hand playing a position on instruments played with both hands
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "bindgen", derive(uniffi::Enum))]
+pub enum Hand {
+    /// Left hand (L)
+    Left,
+    /// Right hand (R)
+    Right,
+}
+
+impl Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Hand::Left => write!(f, "L"),
+            Hand::Right => write!(f, "R"),
+        }
+    }
+}
+
 /// Playing technique for a fingering
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "bindgen", derive(uniffi::Enum))]
@@ -144,6 +176,11 @@ pub enum PlayingTechnique {
     Slide,
     /// Harmonic technique
     Harmonic,
+    /// String bend raising the pitch
+    Bend {
+        /// Semitones the string is bent up
+        semitones: u32,
+    },
 }
 
 impl Display for PlayingTechnique {
@@ -165,6 +202,7 @@ impl Display for PlayingTechnique {
             PlayingTechnique::Pull => write!(f, "Pull-off"),
             PlayingTechnique::Slide => write!(f, "Slide"),
             PlayingTechnique::Harmonic => write!(f, "Harmonic"),
+            PlayingTechnique::Bend { semitones } => write!(f, "Bend ({} semitones)", semitones),
         }
     }
 }
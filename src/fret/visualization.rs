@@ -33,6 +33,8 @@ pub enum ExportFormat {
     Json,
     /// YAML format
     Yaml,
+    /// SVG chord diagram
+    Svg,
 }
 
 /// Metadata about the fingering and instrument
@@ -654,6 +656,7 @@ impl FretboardDiagramGenerator {
             PlayingTechnique::Pull => "Pull-off".to_string(),
             PlayingTechnique::Slide => "Slide".to_string(),
             PlayingTechnique::Harmonic => "Harmonic".to_string(),
+            PlayingTechnique::Bend { semitones } => format!("Bend ({} semitones)", semitones),
         }
     }
 
@@ -852,6 +855,12 @@ impl FretboardDiagramGenerator {
                     format!("YAML serialization failed: {}", e),
                 )
             }),
+            ExportFormat::Svg => super::svg::SvgDiagramGenerator::new().generate_svg(
+                fretboard,
+                fingering,
+                None,
+                orientation,
+            ),
         }
     }
 }
@@ -1236,6 +1245,16 @@ mod tests {
         assert!(vertical_result.is_ok());
         let vertical_yaml = vertical_result.unwrap();
         assert!(vertical_yaml.contains("orientation: vertical"));
+
+        let svg = generator
+            .export_with_orientation(
+                &fretboard,
+                &fingering,
+                DiagramOrientation::Vertical,
+                ExportFormat::Svg,
+            )
+            .unwrap();
+        assert!(svg.starts_with("<svg"));
     }
 
     #[test]
@@ -1419,6 +1438,9 @@ mod property_tests {
                 PlayingTechnique::Harmonic => {
                     prop_assert!(diagram.contains("Harmonic"), "Harmonic technique should be indicated");
                 }
+                PlayingTechnique::Bend { .. } => {
+                    prop_assert!(diagram.contains("Bend"), "Bend technique should be indicated");
+                }
             }
 
             // Verify finger positions are represented
@@ -0,0 +1,428 @@
+//! Bassline generation from chord measures
+//!
+//! Turns a track of `Measure::Chords` into a bass track. Every chord of a measure lasts an
+//! equal share of it, as in playback. Styles:
+//! - root–fifth: root, then fifth on the second half of the chord
+//! - walking: quarter notes through chord tones with a chromatic approach to the next root
+//! - tumbao: Afro-Cuban pattern, fifth on the "and" of two and the next root anticipated
+//!   on four. The anticipated root is sounded again on the downbeat, measures cannot tie.
+//!   The pattern needs a single chord over four beats, other measures are played
+//!   root–fifth.
+//! - pedal: repeated quarter notes on a single pitch
+//!
+//! Notes stay within the range of a stringed instrument, by default
+//! `InstrumentPresets::bass_4_string`, so they always have a position on the fretboard.
+
+use crate::{
+    Chord, DurationBase, DurationGenerator, InstrumentPresets, Measure, MusicError, Note,
+    StringedInstrumentConfig, TimeSignature, Track, Tuning,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Bassline style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BassStyle {
+    RootFifth,
+    Walking,
+    Tumbao,
+    Pedal,
+}
+
+/// Bassline generator, see the module documentation
+#[derive(Debug, Clone)]
+pub struct BassGenerator {
+    style: BassStyle,
+    /// Lowest and highest MIDI numbers
+    range: (i8, i8),
+    time_signature: TimeSignature,
+    /// Pitch of the pedal point, the first root when unset or out of range
+    pedal: Option<Tuning>,
+    seed: u64,
+}
+
+/// Chord sounding for a number of beats
+struct Span<'a> {
+    chord: &'a Chord,
+    beats: f32,
+}
+
+impl BassGenerator {
+    pub fn new(style: BassStyle) -> Self {
+        BassGenerator {
+            style,
+            range: (0, 0),
+            time_signature: TimeSignature::new(4, DurationBase::Quarter),
+            pedal: None,
+            seed: 0,
+        }
+        .with_instrument(&InstrumentPresets::bass_4_string())
+    }
+
+    /// Keep the notes between the lowest open string and the highest fret
+    pub fn with_instrument(self, config: &StringedInstrumentConfig) -> Self {
+        let numbers = config.strings.iter().map(|s| s.number());
+        let low = numbers.clone().min().unwrap_or(0);
+        let high = numbers.max().unwrap_or(0) as i32 + config.fret_count as i32;
+        BassGenerator {
+            range: (low, high.min(i8::MAX as i32) as i8),
+            ..self
+        }
+    }
+
+    pub fn with_time_signature(self, beats_per_measure: u8, beat_type: DurationBase) -> Self {
+        BassGenerator {
+            time_signature: TimeSignature::new(beats_per_measure, beat_type),
+            ..self
+        }
+    }
+
+    pub fn with_pedal(self, pedal: Tuning) -> Self {
+        BassGenerator {
+            pedal: Some(pedal),
+            ..self
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        BassGenerator { seed, ..self }
+    }
+
+    /// Lowest and highest MIDI numbers of the bassline
+    pub fn range(&self) -> (i8, i8) {
+        self.range
+    }
+
+    /// One bass measure per measure of `chords`, measures without chords become rests
+    pub fn generate(&self, chords: &Track) -> Result<Track, MusicError> {
+        let beats_per_measure = self.time_signature.beats_per_measure() as f32;
+        let dg = DurationGenerator::new(self.time_signature.beat_type());
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let measures = chords
+            .get_measures()
+            .iter()
+            .map(|m| match m {
+                Measure::Chords(chords) if !chords.is_empty() => chords
+                    .iter()
+                    .map(|chord| Span {
+                        chord,
+                        beats: beats_per_measure / chords.len() as f32,
+                    })
+                    .collect(),
+                _ => vec![],
+            })
+            .collect::<Vec<Vec<Span>>>();
+        let all_spans = measures.iter().flatten().collect::<Vec<_>>();
+
+        let first_root = all_spans
+            .first()
+            .map(|span| span.chord.root())
+            .ok_or(MusicError::UnsupportedChord)?;
+        // Start an octave above the lowest note, leaving room for the line to move
+        let mut previous = self
+            .place(first_root, self.range.0.saturating_add(12))
+            .number();
+        let pedal = match self.pedal {
+            Some(pedal) if (self.range.0..=self.range.1).contains(&pedal.number()) => pedal,
+            pedal => self.place(pedal.unwrap_or(first_root), previous),
+        };
+
+        let mut track = Track::new();
+        let mut span_idx = 0;
+        for spans in &measures {
+            if spans.is_empty() {
+                track.push(Measure::Rest);
+                continue;
+            }
+
+            let mut notes: Vec<(Tuning, f32)> = vec![];
+            for span in spans {
+                let next = all_spans.get(span_idx + 1).map(|s| s.chord);
+                span_idx += 1;
+
+                let line = match self.style {
+                    BassStyle::RootFifth => self.root_fifth(span, previous),
+                    BassStyle::Walking => self.walking(&mut rng, span, next, previous),
+                    BassStyle::Tumbao if spans.len() == 1 && span.beats == 4.0 => {
+                        self.tumbao(span, next, previous)
+                    }
+                    // Split measures and other meters have no tumbao pattern
+                    BassStyle::Tumbao => self.root_fifth(span, previous),
+                    BassStyle::Pedal => beats(span.beats)
+                        .into_iter()
+                        .map(|beats| (pedal, beats))
+                        .collect(),
+                };
+                if let Some((last, _)) = line.last() {
+                    previous = last.number();
+                }
+                notes.extend(line);
+            }
+
+            track.push(Measure::Note(
+                notes
+                    .into_iter()
+                    .map(|(tuning, beats)| dg.beat(beats).with_note(Note::new(tuning)))
+                    .collect(),
+            ));
+        }
+
+        Ok(track)
+    }
+
+    /// Octave of `tuning` inside the range closest to `near`
+    fn place(&self, tuning: Tuning, near: i8) -> Tuning {
+        let (low, high) = self.range;
+        (-1..=9)
+            .map(|octave| tuning.with_octave(octave))
+            .filter(|t| (low..=high).contains(&t.number()))
+            .min_by_key(|t| (t.number() - near).abs())
+            .unwrap_or(tuning)
+    }
+
+    fn root_fifth(&self, span: &Span, previous: i8) -> Vec<(Tuning, f32)> {
+        let root = self.place(span.chord.root(), previous);
+        if span.beats < 2.0 {
+            return vec![(root, span.beats)];
+        }
+        let fifth = self.place(chord_tone(span.chord, 5), root.number());
+        let first = (span.beats / 2.0).ceil();
+        vec![(root, first), (fifth, span.beats - first)]
+    }
+
+    fn walking(
+        &self,
+        rng: &mut StdRng,
+        span: &Span,
+        next: Option<&Chord>,
+        previous: i8,
+    ) -> Vec<(Tuning, f32)> {
+        let lengths = beats(span.beats);
+        let root = self.place(span.chord.root(), previous);
+        let mut line = vec![root];
+        if lengths.len() == 1 {
+            return vec![(root, lengths[0])];
+        }
+
+        // Chromatic approach to the next root, from above or below
+        let approach = next.map(|next| {
+            let target = self.place(next.root(), root.number());
+            let (low, high) = self.range;
+            let below = target.with_accidentals(target.accidentals() - 1);
+            let above = target.with_accidentals(target.accidentals() + 1);
+            match (
+                rng.random_bool(0.5),
+                below.number() >= low,
+                above.number() <= high,
+            ) {
+                (true, true, _) | (false, true, false) => below,
+                _ => above,
+            }
+        });
+        let walk_to = approach.map(|t| t.number()).unwrap_or(root.number());
+
+        let tones = [3, 5, 7]
+            .into_iter()
+            .filter_map(|degree| span.chord_tone_of(degree))
+            .chain([span.chord.root()])
+            .collect::<Vec<_>>();
+        let inner = lengths.len() - 1 - approach.is_some() as usize;
+        for i in 1..=inner {
+            // Head towards the approach tone
+            let progress = i as f32 / (inner + 1) as f32;
+            let aim = root.number() as f32 + (walk_to - root.number()) as f32 * progress;
+            let last = line.last().unwrap().number();
+            let mut candidates = tones
+                .iter()
+                .map(|t| self.place(*t, aim.round() as i8))
+                .filter(|t| t.number() != last)
+                .collect::<Vec<_>>();
+            candidates.sort_by_key(|t| (t.number() as f32 - aim).abs() as i32);
+            candidates.truncate(2);
+            match candidates.len() {
+                0 => line.push(self.place(chord_tone(span.chord, 5), last)),
+                n => line.push(candidates[rng.random_range(0..n)]),
+            }
+        }
+        line.extend(approach);
+
+        line.into_iter().zip(lengths).collect()
+    }
+
+    /// Four beats: the anticipated root, fifth on the "and" of two, next root on four
+    fn tumbao(&self, span: &Span, next: Option<&Chord>, previous: i8) -> Vec<(Tuning, f32)> {
+        let root = self.place(span.chord.root(), previous);
+        let fifth = self.place(chord_tone(span.chord, 5), root.number());
+        let anticipation = self.place(next.unwrap_or(span.chord).root(), fifth.number());
+        vec![(root, 1.5), (fifth, 1.5), (anticipation, 1.0)]
+    }
+}
+
+impl Span<'_> {
+    fn chord_tone_of(&self, degree: i8) -> Option<Tuning> {
+        self.chord
+            .intervals()
+            .iter()
+            .find(|i| i.degree() == degree)
+            .and_then(|i| self.chord.root().add_interval(i).ok())
+    }
+}
+
+/// Chord tone of the given degree, the root when the chord has none
+fn chord_tone(chord: &Chord, degree: i8) -> Tuning {
+    Span { chord, beats: 0.0 }
+        .chord_tone_of(degree)
+        .unwrap_or(chord.root())
+}
+
+/// Whole beats, the remainder as a last shorter note
+fn beats(total: f32) -> Vec<f32> {
+    let mut beats = vec![1.0; total.floor() as usize];
+    let rest = total - total.floor();
+    if rest > 0.0 {
+        beats.push(rest);
+    }
+    beats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fretboard, PitchClass, StringedFretboard};
+
+    fn chords(symbols: &[&[&str]]) -> Track {
+        let mut track = Track::new();
+        for measure in symbols {
+            track.push(Measure::from(
+                measure
+                    .iter()
+                    .map(|s| Chord::from_symbol(s).unwrap())
+                    .collect::<Vec<_>>(),
+            ));
+        }
+        track
+    }
+
+    fn notes(track: &Track) -> Vec<Note> {
+        track
+            .get_measures()
+            .iter()
+            .flat_map(|m| match m {
+                Measure::Note(notes) => notes.clone(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_root_fifth() {
+        let bass = BassGenerator::new(BassStyle::RootFifth)
+            .generate(&chords(&[&["C"], &["G", "Am"]]))
+            .unwrap();
+        let notes = notes(&bass);
+        let classes = notes
+            .iter()
+            .map(|n| n.tuning().unwrap().class())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                PitchClass::C,
+                PitchClass::G,
+                PitchClass::G,
+                PitchClass::D,
+                PitchClass::A,
+                PitchClass::E
+            ]
+        );
+        assert_eq!(notes[0].duration().in_quarters(), 2.0);
+        assert_eq!(notes[2].duration().in_quarters(), 1.0);
+    }
+
+    #[test]
+    fn test_walking_bass() {
+        let progression = chords(&[&["Dm7"], &["G7"], &["CM7"], &["A7"]]);
+        for seed in 0..10 {
+            let bass = BassGenerator::new(BassStyle::Walking)
+                .with_seed(seed)
+                .generate(&progression)
+                .unwrap();
+            let notes = notes(&bass);
+            assert_eq!(notes.len(), 16);
+            assert!(notes.iter().all(|n| n.duration().in_quarters() == 1.0));
+
+            // Every measure starts on its root, approached chromatically
+            for (measure, root) in [PitchClass::G, PitchClass::C, PitchClass::A]
+                .into_iter()
+                .enumerate()
+            {
+                let downbeat = &notes[(measure + 1) * 4];
+                let approach = &notes[(measure + 1) * 4 - 1];
+                assert_eq!(downbeat.tuning().unwrap().class(), root);
+                assert_eq!(
+                    (downbeat.tuning().unwrap().number() - approach.tuning().unwrap().number())
+                        .abs(),
+                    1
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tumbao_and_pedal() {
+        let progression = chords(&[&["Am"], &["E7"]]);
+        let bass = BassGenerator::new(BassStyle::Tumbao)
+            .generate(&progression)
+            .unwrap();
+        let tumbao = notes(&bass);
+        assert_eq!(tumbao[1].tuning().unwrap().class(), PitchClass::E);
+        assert_eq!(tumbao[1].duration().in_quarters(), 1.5);
+        assert_eq!(tumbao[2].tuning().unwrap().class(), PitchClass::E);
+
+        // A measure of two chords falls back to root–fifth
+        let split = chords(&[&["Am", "Dm"]]);
+        let line = |style| {
+            notes(&BassGenerator::new(style).generate(&split).unwrap())
+                .iter()
+                .map(|n| (n.tuning().unwrap().number(), n.duration().in_quarters()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(line(BassStyle::Tumbao), line(BassStyle::RootFifth));
+
+        let bass = BassGenerator::new(BassStyle::Pedal)
+            .with_pedal(Tuning::new(PitchClass::A, 1))
+            .generate(&progression)
+            .unwrap();
+        let pedal = notes(&bass);
+        assert_eq!(pedal.len(), 8);
+        assert!(pedal.iter().all(|n| n.tuning().unwrap().number() == 33));
+    }
+
+    #[test]
+    fn test_bass_is_playable() {
+        let progression = chords(&[&["C", "Am"], &["F"], &["G7"], &["C"]]);
+        for config in [
+            InstrumentPresets::bass_4_string(),
+            InstrumentPresets::bass_5_string(),
+        ] {
+            let fretboard = StringedFretboard::new(config.clone()).unwrap();
+            for style in [
+                BassStyle::RootFifth,
+                BassStyle::Walking,
+                BassStyle::Tumbao,
+                BassStyle::Pedal,
+            ] {
+                let generator = BassGenerator::new(style).with_instrument(&config);
+                let (low, high) = generator.range();
+                for note in notes(&generator.generate(&progression).unwrap()) {
+                    let number = note.tuning().unwrap().number();
+                    assert!((low..=high).contains(&number));
+                    assert!(!fretboard
+                        .positions_for_tuning(&note.tuning().unwrap())
+                        .is_empty());
+                }
+            }
+        }
+    }
+}
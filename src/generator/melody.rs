@@ -0,0 +1,540 @@
+//! Constraint-based melody generation
+//!
+//! Builds a melody over a chord progression (one chord per measure) inside a `Scale` and a
+//! pitch range. Pitches are drawn from weighted candidates that satisfy:
+//! - chord tones on strong beats
+//! - non-chord tones only as passing or neighbour tones (approached and left by step)
+//! - a preference for stepwise motion, leaps limited and recovered by a step back
+//! - a cadence target on the last note
+//!
+//! The generator is driven by a seed, the same settings always produce the same melody.
+
+use crate::{
+    Chord, DurationBase, DurationGenerator, Measure, MusicError, Note, Scale, TimeSignature, Tuning,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Rhythmic vocabulary of the melody, in beats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RhythmStyle {
+    /// Quarters and halves
+    Simple,
+    /// Eighths and quarters
+    #[default]
+    Flowing,
+    /// Dotted figures
+    Dotted,
+    /// Sixteenths and eighths
+    Busy,
+}
+
+impl RhythmStyle {
+    /// Rhythmic cells, each filling whole beats
+    fn cells(&self) -> &'static [&'static [f32]] {
+        match self {
+            RhythmStyle::Simple => &[&[1.0], &[1.0, 1.0], &[2.0]],
+            RhythmStyle::Flowing => &[&[1.0], &[0.5, 0.5], &[0.5, 0.5], &[2.0]],
+            RhythmStyle::Dotted => &[&[1.0], &[1.5, 0.5], &[0.5, 0.5], &[0.75, 0.25]],
+            RhythmStyle::Busy => &[&[0.25, 0.25, 0.25, 0.25], &[0.5, 0.5], &[0.5, 0.25, 0.25]],
+        }
+    }
+}
+
+/// How the melody ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cadence {
+    /// Long note on the tonic
+    #[default]
+    Authentic,
+    /// Long note on the dominant
+    Half,
+    /// No cadence target
+    Open,
+}
+
+/// Melody generator, see the module documentation
+#[derive(Debug, Clone)]
+pub struct MelodyGenerator {
+    scale: Scale,
+    low: Tuning,
+    high: Tuning,
+    style: RhythmStyle,
+    cadence: Cadence,
+    time_signature: TimeSignature,
+    /// Largest interval allowed between two notes, in semitones
+    max_leap: u8,
+    seed: u64,
+}
+
+/// Intervals above this many semitones are leaps to be recovered
+const LEAP: i8 = 4;
+/// Largest step for passing and neighbour tones
+const STEP: i8 = 2;
+
+impl MelodyGenerator {
+    /// Generator in the given scale, within `low..=high`
+    pub fn new(scale: Scale, low: Tuning, high: Tuning) -> Self {
+        MelodyGenerator {
+            scale,
+            low,
+            high,
+            style: RhythmStyle::default(),
+            cadence: Cadence::default(),
+            time_signature: TimeSignature::new(4, DurationBase::Quarter),
+            max_leap: 7,
+            seed: 0,
+        }
+    }
+
+    pub fn with_style(self, style: RhythmStyle) -> Self {
+        MelodyGenerator { style, ..self }
+    }
+
+    pub fn with_cadence(self, cadence: Cadence) -> Self {
+        MelodyGenerator { cadence, ..self }
+    }
+
+    pub fn with_time_signature(self, beats_per_measure: u8, beat_type: DurationBase) -> Self {
+        MelodyGenerator {
+            time_signature: TimeSignature::new(beats_per_measure, beat_type),
+            ..self
+        }
+    }
+
+    pub fn with_max_leap(self, max_leap: u8) -> Self {
+        MelodyGenerator {
+            max_leap: max_leap.max(STEP as u8),
+            ..self
+        }
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        MelodyGenerator { seed, ..self }
+    }
+
+    /// Scale tones inside the range, from low to high
+    pub fn pitches(&self) -> Result<Vec<Tuning>, MusicError> {
+        let (low, high) = (self.low.number(), self.high.number());
+        let mut pitches = self
+            .scale
+            .generate_tunings(0)?
+            .into_iter()
+            .flat_map(|t| (-1..=9).map(move |octave| t.with_octave(octave)))
+            .filter(|t| (low..=high).contains(&t.number()))
+            .collect::<Vec<_>>();
+        pitches.sort_by_key(|t| t.number());
+        pitches.dedup_by_key(|t| t.number());
+        Ok(pitches)
+    }
+
+    /// One measure of melody per chord
+    pub fn generate(&self, progression: &[Chord]) -> Result<Vec<Measure>, MusicError> {
+        let pitches = self.pitches()?;
+        if pitches.is_empty() {
+            return Err(MusicError::TheoryViolation(
+                "No scale tone in the melody range".to_owned(),
+            ));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let dg = DurationGenerator::new(self.time_signature.beat_type());
+        let mut melody: Vec<i8> = vec![];
+        // The last note is a non-chord tone
+        let mut resolving = false;
+        let mut measures = vec![];
+
+        for (measure_idx, chord) in progression.iter().enumerate() {
+            let last_measure = measure_idx + 1 == progression.len();
+            let rhythm = self.rhythm(&mut rng, last_measure);
+            let chord_classes = chord
+                .components()
+                .iter()
+                .map(|t| t.class_semitones())
+                .collect::<Vec<_>>();
+
+            let mut notes = vec![];
+            let mut onset = 0.0;
+            for (i, beats) in rhythm.iter().enumerate() {
+                let last_note = i + 1 == rhythm.len();
+                let cadence = last_note && last_measure;
+                let approaching_cadence = last_measure && i + 2 == rhythm.len();
+                let context = Context {
+                    chord: &chord_classes,
+                    // Non-chord tones resolve within the measure, on the same chord
+                    passing: !self.is_strong(onset) && !last_note && !approaching_cadence,
+                    resolving,
+                    cadence: cadence.then(|| self.cadence_target()).flatten(),
+                };
+                let choice = self.choose(&mut rng, &pitches, &melody, &context);
+                let number = pitches[choice].number();
+                resolving = !chord_classes.contains(&number.rem_euclid(12));
+                melody.push(number);
+                notes.push(dg.beat(*beats).with_note(Note::new(pitches[choice])));
+                onset += beats;
+            }
+            measures.push(Measure::Note(notes));
+        }
+
+        Ok(measures)
+    }
+
+    /// Note lengths of a measure, the last measure ends on a long note when there is a cadence
+    fn rhythm(&self, rng: &mut StdRng, last_measure: bool) -> Vec<f32> {
+        let beats_per_measure = self.time_signature.beats_per_measure() as f32;
+        let tail = if last_measure && self.cadence != Cadence::Open {
+            self.cadence_tail()
+        } else {
+            vec![]
+        };
+
+        let mut rhythm = vec![];
+        let mut remaining = beats_per_measure - tail.iter().sum::<f32>();
+        while remaining > 0.0 {
+            let cells = self
+                .style
+                .cells()
+                .iter()
+                .filter(|cell| cell.iter().sum::<f32>() <= remaining)
+                .collect::<Vec<_>>();
+            let cell: &[f32] = match cells.len() {
+                0 => &[1.0],
+                n => cells[rng.random_range(0..n)],
+            };
+            rhythm.extend_from_slice(cell);
+            remaining -= cell.iter().sum::<f32>();
+        }
+        rhythm.extend(tail);
+        rhythm
+    }
+
+    /// Lengths of the cadence note, over the last beat group, or over the second half of a
+    /// measure of a single group, split so that it ends on the barline
+    fn cadence_tail(&self) -> Vec<f32> {
+        let beats = self.time_signature.beats_per_measure();
+        let length = match self.time_signature.grouping().as_slice() {
+            [.., last] if *last < beats => *last,
+            _ => beats.div_ceil(2),
+        };
+        let beat = self.time_signature.beat_type().in_quarters();
+        match self
+            .time_signature
+            .decompose((beats - length) as f32, length as f32)
+        {
+            Ok(values) => values.iter().map(|d| d.in_quarters() / beat).collect(),
+            Err(_) => vec![length as f32],
+        }
+    }
+
+    /// Start of a beat group: beats 1 and 3 in 4/4, beats 1 and 4 in 6/8
+    fn is_strong(&self, onset: f32) -> bool {
+        let mut start = 0.0;
+        self.time_signature.grouping().iter().any(|group| {
+            let strong = onset == start;
+            start += *group as f32;
+            strong
+        })
+    }
+
+    /// Pitch class of the final note
+    fn cadence_target(&self) -> Option<i8> {
+        match self.cadence {
+            Cadence::Authentic => Some(self.scale.root().class_semitones()),
+            Cadence::Half => self.scale.degree(5).ok().map(|t| t.class_semitones()),
+            Cadence::Open => None,
+        }
+    }
+
+    /// Index of the next pitch. Constraints are relaxed one after another when nothing
+    /// satisfies all of them.
+    fn choose(
+        &self,
+        rng: &mut StdRng,
+        pitches: &[Tuning],
+        melody: &[i8],
+        context: &Context,
+    ) -> usize {
+        let is_chord_tone = |n: i8| context.chord.contains(&n.rem_euclid(12));
+        let previous = melody.last().copied();
+
+        let Some(previous) = previous else {
+            // Start on a chord tone around the middle of the range
+            let center = (self.low.number() as i32 + self.high.number() as i32) / 2;
+            let start = pitches
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| match context.cadence {
+                    Some(target) => t.class_semitones() == target,
+                    None => is_chord_tone(t.number()),
+                })
+                .map(|(i, t)| (i, 1.0 / (1.0 + (t.number() as i32 - center).abs() as f32)))
+                .collect::<Vec<_>>();
+            return weighted(rng, &start).unwrap_or(pitches.len() / 2);
+        };
+
+        // Motion of the last two notes
+        let last_motion = match melody {
+            [.., before, last] => last - before,
+            _ => 0,
+        };
+
+        let constraints: [&dyn Fn(i8) -> bool; 5] = [
+            // Cadence target
+            &|n| {
+                context
+                    .cadence
+                    .is_none_or(|target| n.rem_euclid(12) == target)
+            },
+            // Leap size
+            &|n| (n - previous).unsigned_abs() <= self.max_leap,
+            // Chord tones on strong beats, non-chord tones approached by step and next to
+            // a chord tone to resolve to
+            &|n| {
+                is_chord_tone(n)
+                    || (context.passing
+                        && (n - previous).abs() <= STEP
+                        && (n - STEP..=n + STEP).any(|m| m != n && is_chord_tone(m)))
+            },
+            // Non-chord tones resolve by step to a chord tone
+            &|n| !context.resolving || ((n - previous).abs() <= STEP && is_chord_tone(n)),
+            // Leaps are recovered by a step in the opposite direction
+            &|n| {
+                let motion = n - previous;
+                last_motion.abs() <= LEAP
+                    || (motion.abs() <= STEP
+                        && motion != 0
+                        && motion.signum() != last_motion.signum())
+            },
+        ];
+
+        for relaxed in 0..=constraints.len() {
+            let active = &constraints[..constraints.len() - relaxed];
+            let candidates = pitches
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| active.iter().all(|c| c(t.number())))
+                .map(|(i, t)| (i, motion_weight(t.number() - previous)))
+                .collect::<Vec<_>>();
+            if let Some(choice) = weighted(rng, &candidates) {
+                return choice;
+            }
+        }
+
+        // Closest pitch to the previous one
+        (0..pitches.len())
+            .min_by_key(|&i| (pitches[i].number() - previous).abs())
+            .unwrap_or(0)
+    }
+}
+
+/// What the next note has to fit
+struct Context<'a> {
+    /// Pitch classes of the current chord, C = 0
+    chord: &'a [i8],
+    /// A non-chord tone may be placed here
+    passing: bool,
+    /// The previous note is a non-chord tone
+    resolving: bool,
+    /// Required pitch class of the final note
+    cadence: Option<i8>,
+}
+
+/// Stepwise motion is preferred, repeated notes and leaps are rarer
+fn motion_weight(motion: i8) -> f32 {
+    match motion.abs() {
+        0 => 2.0,
+        1..=2 => 8.0,
+        3..=4 => 4.0,
+        5..=7 => 2.0,
+        _ => 1.0,
+    }
+}
+
+fn weighted(rng: &mut StdRng, candidates: &[(usize, f32)]) -> Option<usize> {
+    let total = candidates.iter().map(|(_, w)| w).sum::<f32>();
+    if candidates.is_empty() || total <= 0.0 {
+        return None;
+    }
+    let mut pick = rng.random_range(0.0..total);
+    for (i, weight) in candidates {
+        if pick < *weight {
+            return Some(*i);
+        }
+        pick -= weight;
+    }
+    candidates.last().map(|(i, _)| *i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PitchClass, ScaleType};
+
+    fn generator() -> MelodyGenerator {
+        let scale = Scale::new(Tuning::new(PitchClass::C, 4), ScaleType::Major).unwrap();
+        MelodyGenerator::new(
+            scale,
+            Tuning::new(PitchClass::C, 4),
+            Tuning::new(PitchClass::C, 6),
+        )
+    }
+
+    fn progression() -> Vec<Chord> {
+        ["C", "Am", "F", "G", "C", "F", "G7", "C"]
+            .iter()
+            .map(|s| Chord::from_symbol(s).unwrap())
+            .collect()
+    }
+
+    fn notes(measures: &[Measure]) -> Vec<Note> {
+        measures
+            .iter()
+            .flat_map(|m| match m {
+                Measure::Note(notes) => notes.clone(),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_melody_is_reproducible() {
+        let a = notes(&generator().with_seed(7).generate(&progression()).unwrap());
+        let b = notes(&generator().with_seed(7).generate(&progression()).unwrap());
+        let c = notes(&generator().with_seed(8).generate(&progression()).unwrap());
+        let numbers = |n: &[Note]| {
+            n.iter()
+                .map(|n| n.tuning().unwrap().number())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(numbers(&a), numbers(&b));
+        assert_ne!(numbers(&a), numbers(&c));
+    }
+
+    #[test]
+    fn test_melody_constraints() {
+        let progression = progression();
+        let styles = [
+            RhythmStyle::Simple,
+            RhythmStyle::Flowing,
+            RhythmStyle::Dotted,
+            RhythmStyle::Busy,
+        ];
+        for (seed, style) in (0..40).zip(styles.into_iter().cycle()) {
+            let g = generator().with_seed(seed).with_style(style);
+            let measures = g.generate(&progression).unwrap();
+            assert_eq!(measures.len(), progression.len());
+
+            for (measure, chord) in measures.iter().zip(&progression) {
+                let Measure::Note(notes) = measure else {
+                    panic!("melody measure without notes");
+                };
+                let classes = chord
+                    .components()
+                    .iter()
+                    .map(|t| t.class_semitones())
+                    .collect::<Vec<_>>();
+                let beats = notes
+                    .iter()
+                    .map(|n| n.duration().in_quarters())
+                    .sum::<f32>();
+                assert_eq!(beats, 4.0);
+                // Downbeat on a chord tone
+                assert!(classes.contains(&notes[0].tuning().unwrap().class_semitones()));
+            }
+
+            // Non-chord tones are passing or neighbour tones
+            let placed = measures
+                .iter()
+                .zip(&progression)
+                .flat_map(|(m, chord)| {
+                    notes(std::slice::from_ref(m))
+                        .into_iter()
+                        .map(move |n| (n, chord))
+                })
+                .collect::<Vec<_>>();
+            for window in placed.windows(3) {
+                let [(before, _), (note, chord), (after, _)] = window else {
+                    unreachable!()
+                };
+                let class = note.tuning().unwrap().class_semitones();
+                if !chord
+                    .components()
+                    .iter()
+                    .any(|t| t.class_semitones() == class)
+                {
+                    let number = note.tuning().unwrap().number();
+                    assert!((number - before.tuning().unwrap().number()).abs() <= 2);
+                    assert!((after.tuning().unwrap().number() - number).abs() <= 2);
+                }
+            }
+
+            let melody = notes(&measures);
+            for pair in melody.windows(2) {
+                let leap =
+                    (pair[1].tuning().unwrap().number() - pair[0].tuning().unwrap().number()).abs();
+                assert!(leap <= 7, "seed {seed}: leap of {leap} semitones");
+            }
+            assert!(melody.iter().all(|n| {
+                let number = n.tuning().unwrap().number();
+                (60..=84).contains(&number)
+            }));
+
+            let last = melody.last().unwrap();
+            assert_eq!(last.tuning().unwrap().class(), PitchClass::C);
+            assert_eq!(last.duration().in_quarters(), 2.0);
+        }
+    }
+
+    #[test]
+    fn test_half_cadence() {
+        let measures = generator()
+            .with_cadence(Cadence::Half)
+            .with_time_signature(3, DurationBase::Quarter)
+            .generate(&progression()[..4])
+            .unwrap();
+        let melody = notes(&measures);
+        assert_eq!(
+            melody.last().unwrap().tuning().unwrap().class(),
+            PitchClass::G
+        );
+    }
+
+    #[test]
+    fn test_measures_fill_the_meter() {
+        let meters = [
+            (6, DurationBase::Eighth),
+            (9, DurationBase::Eighth),
+            (5, DurationBase::Quarter),
+            (7, DurationBase::Eighth),
+        ];
+        let styles = [
+            RhythmStyle::Simple,
+            RhythmStyle::Flowing,
+            RhythmStyle::Dotted,
+            RhythmStyle::Busy,
+        ];
+        for (beats, beat_type) in meters {
+            let bar = TimeSignature::new(beats, beat_type).measure_quarters();
+            for (seed, style) in (0..12).zip(styles.into_iter().cycle()) {
+                let measures = generator()
+                    .with_seed(seed)
+                    .with_style(style)
+                    .with_time_signature(beats, beat_type)
+                    .generate(&progression())
+                    .unwrap();
+                for measure in &measures {
+                    let length = notes(std::slice::from_ref(measure))
+                        .iter()
+                        .map(|n| n.duration().in_quarters())
+                        .sum::<f32>();
+                    assert_eq!(length, bar, "{beats}/{beat_type:?} seed {seed}");
+                }
+            }
+        }
+
+        // The cadence holds the last group of 9/8 and the strong beats follow the groups
+        let g = generator().with_time_signature(9, DurationBase::Eighth);
+        assert_eq!(g.cadence_tail(), vec![3.0]);
+        assert!(g.is_strong(3.0) && g.is_strong(6.0) && !g.is_strong(2.0));
+    }
+}
@@ -0,0 +1,7 @@
+mod bass;
+mod melody;
+mod rhythm;
+
+pub use bass::*;
+pub use melody::*;
+pub use rhythm::*;
@@ -0,0 +1,422 @@
+//! Rhythm patterns
+//!
+//! A `Rhythm` is an onset mask over a grid of equal steps. It can be built as:
+//! - a Euclidean rhythm (Bjorklund's algorithm), optionally rotated
+//! - a clave pattern
+//! - an additive rhythm such as 3+3+2
+//! - a random rhythm weighted by the metric position of each step
+//!
+//! and turned into notated values with `Rhythm::values`.
+
+use crate::{Duration, DurationBase, MusicError, TimeSignature};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Display;
+
+/// Clave patterns, one 4/4 measure of sixteenths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clave {
+    /// Son clave, three side first
+    Son32,
+    /// Son clave, two side first
+    Son23,
+    /// Rumba clave, three side first
+    Rumba32,
+    /// Rumba clave, two side first
+    Rumba23,
+    /// Bossa nova clave
+    Bossa,
+}
+
+impl Clave {
+    fn grid(&self) -> &'static str {
+        match self {
+            Clave::Son32 => "x..x..x...x.x...",
+            Clave::Son23 => "..x.x...x..x..x.",
+            Clave::Rumba32 => "x..x...x..x.x...",
+            Clave::Rumba23 => "..x.x...x..x...x",
+            Clave::Bossa => "x..x..x...x..x..",
+        }
+    }
+}
+
+/// Notated value of a rhythm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RhythmValue {
+    /// A new onset
+    Note(Duration),
+    /// Continuation of the previous note, tied to it
+    Tied(Duration),
+    Rest(Duration),
+}
+
+impl RhythmValue {
+    pub fn duration(&self) -> Duration {
+        match self {
+            RhythmValue::Note(d) | RhythmValue::Tied(d) | RhythmValue::Rest(d) => *d,
+        }
+    }
+}
+
+/// Onset mask over a grid of equal steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rhythm {
+    mask: Vec<bool>,
+    step: DurationBase,
+}
+
+impl Rhythm {
+    pub fn new(mask: Vec<bool>, step: DurationBase) -> Self {
+        Rhythm { mask, step }
+    }
+
+    /// Parse a grid such as `x..x..x.`, `x` being an onset. Spaces and `|` are ignored.
+    pub fn from_grid(grid: &str, step: DurationBase) -> Self {
+        let mask = grid
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '|')
+            .map(|c| matches!(c, 'x' | 'X'))
+            .collect();
+        Rhythm { mask, step }
+    }
+
+    /// `pulses` onsets spread as evenly as possible over `steps` steps
+    ///
+    /// ```
+    /// use mutheors::{DurationBase, Rhythm};
+    /// let tresillo = Rhythm::euclidean(3, 8, DurationBase::Eighth).unwrap();
+    /// assert_eq!(tresillo.to_string(), "x..x..x.");
+    /// ```
+    pub fn euclidean(pulses: usize, steps: usize, step: DurationBase) -> Result<Self, MusicError> {
+        if pulses > steps {
+            return Err(MusicError::TheoryViolation(format!(
+                "{pulses} pulses do not fit in {steps} steps"
+            )));
+        }
+
+        // Bjorklund: repeatedly pair the onset groups with the remainder groups
+        let mut groups = vec![vec![true]; pulses];
+        let mut remainder = vec![vec![false]; steps - pulses];
+        while remainder.len() > 1 && !groups.is_empty() {
+            let paired = groups.len().min(remainder.len());
+            let rest = if groups.len() > paired {
+                groups.split_off(paired)
+            } else {
+                remainder.split_off(paired)
+            };
+            for (group, tail) in groups.iter_mut().zip(remainder) {
+                group.extend(tail);
+            }
+            remainder = rest;
+        }
+
+        let mask = groups.into_iter().chain(remainder).flatten().collect();
+        Ok(Rhythm { mask, step })
+    }
+
+    pub fn clave(clave: Clave) -> Self {
+        Rhythm::from_grid(clave.grid(), DurationBase::Sixteenth)
+    }
+
+    /// Groups of steps each starting with an onset, e.g. `[3, 3, 2]`
+    pub fn additive(groups: &[usize], step: DurationBase) -> Self {
+        let mask = groups
+            .iter()
+            .filter(|g| **g > 0)
+            .flat_map(|g| (0..*g).map(|i| i == 0))
+            .collect();
+        Rhythm { mask, step }
+    }
+
+    /// One measure of random onsets, more likely on strong metric positions.
+    /// `density` is the expected fraction of steps with an onset.
+    pub fn weighted_random(
+        time_signature: &TimeSignature,
+        step: DurationBase,
+        density: f32,
+        seed: u64,
+    ) -> Self {
+        let weights = metric_weights(time_signature, step);
+        let mean = weights.iter().sum::<f32>() / weights.len().max(1) as f32;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mask = weights
+            .iter()
+            .map(|w| rng.random::<f32>() < (density.clamp(0.0, 1.0) * w / mean).min(1.0))
+            .collect();
+        Rhythm { mask, step }
+    }
+
+    /// Start the pattern `steps` steps later
+    pub fn rotate(mut self, steps: usize) -> Self {
+        if !self.mask.is_empty() {
+            let steps = steps % self.mask.len();
+            self.mask.rotate_left(steps);
+        }
+        self
+    }
+
+    pub fn mask(&self) -> &[bool] {
+        &self.mask
+    }
+
+    pub fn step(&self) -> DurationBase {
+        self.step
+    }
+
+    /// Step indices of the onsets
+    pub fn onsets(&self) -> Vec<usize> {
+        (0..self.mask.len()).filter(|&i| self.mask[i]).collect()
+    }
+
+    pub fn in_quarters(&self) -> f32 {
+        self.mask.len() as f32 * self.step.in_quarters()
+    }
+
+    /// The pattern fills a whole number of measures
+    pub fn fits(&self, time_signature: &TimeSignature) -> bool {
+        let measure =
+            time_signature.beats_per_measure() as f32 * time_signature.beat_type().in_quarters();
+        let measures = self.in_quarters() / measure;
+        measures >= 1.0 && (measures - measures.round()).abs() < 1e-4
+    }
+
+    /// Notes (held until the next onset) and rests, split into notatable values
+    pub fn values(&self) -> Vec<RhythmValue> {
+        let mut values = vec![];
+        for (_, length, note) in self.spans() {
+            let durations = self.split(length);
+            values.extend(tie(durations, note));
+        }
+        values
+    }
+
+    /// Notes and rests laid out in measures of `time_signature`, split and tied so that
+    /// barlines and beat groups stay visible
+    pub fn values_in(
+        &self,
+        time_signature: &TimeSignature,
+    ) -> Result<Vec<RhythmValue>, MusicError> {
+        let step = self.step.in_quarters() / time_signature.beat_type().in_quarters();
+        let mut values = vec![];
+        for (start, length, note) in self.spans() {
+            let durations = time_signature.decompose(start as f32 * step, length as f32 * step)?;
+            values.extend(tie(durations, note));
+        }
+        Ok(values)
+    }
+
+    /// `(first step, steps, is a note)` of the leading rest and of each note
+    fn spans(&self) -> Vec<(usize, usize, bool)> {
+        let onsets = self.onsets();
+        let leading = onsets.first().copied().unwrap_or(self.mask.len());
+        let mut spans = vec![];
+        if leading > 0 {
+            spans.push((0, leading, false));
+        }
+        for (i, onset) in onsets.iter().enumerate() {
+            let end = onsets.get(i + 1).copied().unwrap_or(self.mask.len());
+            spans.push((*onset, end - onset, true));
+        }
+        spans
+    }
+
+    /// Largest plain or dotted values first
+    fn split(&self, steps: usize) -> Vec<Duration> {
+        let step = self.step.in_quarters();
+        let candidates = [
+            DurationBase::Whole,
+            DurationBase::Half,
+            DurationBase::Quarter,
+            DurationBase::Eighth,
+            DurationBase::Sixteenth,
+            DurationBase::ThirtySecond,
+            DurationBase::SixtyFourth,
+        ]
+        .into_iter()
+        .flat_map(|base| [Duration::new(base).dotted(1), Duration::new(base)])
+        .collect::<Vec<_>>();
+
+        let mut remaining = steps as f32 * step;
+        let mut durations = vec![];
+        while remaining > 1e-4 {
+            match candidates
+                .iter()
+                .find(|d| d.in_quarters() <= remaining + 1e-4)
+            {
+                Some(d) => {
+                    durations.push(*d);
+                    remaining -= d.in_quarters();
+                }
+                None => break,
+            }
+        }
+        durations
+    }
+}
+
+impl Display for Rhythm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for onset in &self.mask {
+            write!(f, "{}", if *onset { 'x' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+/// A note as its first value tied to the others, or rests
+fn tie(durations: Vec<Duration>, note: bool) -> impl Iterator<Item = RhythmValue> {
+    durations
+        .into_iter()
+        .enumerate()
+        .map(move |(i, duration)| match (note, i) {
+            (false, _) => RhythmValue::Rest(duration),
+            (true, 0) => RhythmValue::Note(duration),
+            (true, _) => RhythmValue::Tied(duration),
+        })
+}
+
+/// Weight of each step of a measure: downbeat, strong beat, beats, half beats, the rest
+fn metric_weights(time_signature: &TimeSignature, step: DurationBase) -> Vec<f32> {
+    let beats = time_signature.beats_per_measure() as usize;
+    let steps_per_beat = (time_signature.beat_type().in_quarters() / step.in_quarters())
+        .round()
+        .max(1.0) as usize;
+
+    (0..beats * steps_per_beat)
+        .map(|i| {
+            let (beat, sub) = (i / steps_per_beat, i % steps_per_beat);
+            match (beat, sub) {
+                (0, 0) => 1.0,
+                (b, 0) if beats.is_multiple_of(2) && b == beats / 2 => 0.8,
+                (_, 0) => 0.6,
+                (_, s) if steps_per_beat.is_multiple_of(2) && s == steps_per_beat / 2 => 0.4,
+                _ => 0.2,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_euclidean() {
+        let e = |p, s| {
+            Rhythm::euclidean(p, s, DurationBase::Sixteenth)
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(e(3, 8), "x..x..x.");
+        assert_eq!(e(5, 8), "x.xx.xx.");
+        assert_eq!(e(4, 16), "x...x...x...x...");
+        assert_eq!(e(5, 12), "x..x.x..x.x.");
+        assert_eq!(e(0, 4), "....");
+        assert_eq!(e(4, 4), "xxxx");
+        assert!(Rhythm::euclidean(5, 4, DurationBase::Eighth).is_err());
+
+        let rotated = Rhythm::euclidean(3, 8, DurationBase::Eighth)
+            .unwrap()
+            .rotate(3);
+        assert_eq!(rotated.to_string(), "x..x.x..");
+    }
+
+    #[test]
+    fn test_clave_and_additive() {
+        let son = Rhythm::clave(Clave::Son32);
+        assert_eq!(son.onsets(), vec![0, 3, 6, 10, 12]);
+        assert!(son.fits(&TimeSignature::new(4, DurationBase::Quarter)));
+        assert_eq!(Rhythm::clave(Clave::Son23), son.clone().rotate(8));
+        assert_eq!(
+            Rhythm::clave(Clave::Rumba23),
+            Rhythm::clave(Clave::Rumba32).rotate(8)
+        );
+
+        let additive = Rhythm::additive(&[3, 3, 2], DurationBase::Eighth);
+        assert_eq!(additive.to_string(), "x..x..x.");
+        assert!(!additive.fits(&TimeSignature::new(3, DurationBase::Quarter)));
+    }
+
+    #[test]
+    fn test_values() {
+        let values = Rhythm::from_grid("..x.....x...", DurationBase::Sixteenth).values();
+        assert_eq!(
+            values,
+            vec![
+                RhythmValue::Rest(Duration::new(DurationBase::Eighth)),
+                RhythmValue::Note(Duration::new(DurationBase::Quarter).dotted(1)),
+                RhythmValue::Note(Duration::new(DurationBase::Quarter)),
+            ]
+        );
+
+        let values = Rhythm::from_grid("x....", DurationBase::Sixteenth).values();
+        assert_eq!(
+            values,
+            vec![
+                RhythmValue::Note(Duration::new(DurationBase::Quarter)),
+                RhythmValue::Tied(Duration::new(DurationBase::Sixteenth)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_values_in_meter() {
+        let d = |base| Duration::new(base);
+        // Son clave in 4/4, beat 3 stays visible
+        let values = Rhythm::clave(Clave::Son32)
+            .values_in(&TimeSignature::new(4, DurationBase::Quarter))
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                RhythmValue::Note(d(DurationBase::Eighth).dotted(1)),
+                RhythmValue::Note(d(DurationBase::Sixteenth)),
+                RhythmValue::Tied(d(DurationBase::Eighth)),
+                RhythmValue::Note(d(DurationBase::Eighth)),
+                RhythmValue::Tied(d(DurationBase::Eighth)),
+                RhythmValue::Note(d(DurationBase::Eighth)),
+                RhythmValue::Note(d(DurationBase::Quarter)),
+            ]
+        );
+
+        // Held over the barline
+        let values = Rhythm::from_grid("...x....", DurationBase::Eighth)
+            .values_in(&TimeSignature::new(2, DurationBase::Quarter))
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                RhythmValue::Rest(d(DurationBase::Quarter).dotted(1)),
+                RhythmValue::Note(d(DurationBase::Eighth)),
+                RhythmValue::Tied(d(DurationBase::Half)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weighted_random() {
+        let ts = TimeSignature::new(4, DurationBase::Quarter);
+        let a = Rhythm::weighted_random(&ts, DurationBase::Sixteenth, 0.4, 3);
+        let b = Rhythm::weighted_random(&ts, DurationBase::Sixteenth, 0.4, 3);
+        assert_eq!(a, b);
+        assert!(a.fits(&ts));
+
+        // Onsets gather on the beats
+        let mut on_beat = 0;
+        let mut off_beat = 0;
+        for seed in 0..200 {
+            let r = Rhythm::weighted_random(&ts, DurationBase::Sixteenth, 0.3, seed);
+            for onset in r.onsets() {
+                match onset % 4 {
+                    0 => on_beat += 1,
+                    _ => off_beat += 1,
+                }
+            }
+        }
+        assert!(on_beat as f32 / 4.0 > off_beat as f32 / 12.0);
+
+        let full = Rhythm::weighted_random(&ts, DurationBase::Eighth, 1.0, 0);
+        assert_eq!(full.onsets()[0], 0);
+    }
+}
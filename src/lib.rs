@@ -7,13 +7,37 @@
 //! - Tuning: C4/C#4/D4/E4/F4/G4/A4/B4...
 //! - Scale: C major, C minor, C# pentatonic...
 //! - Duration: quarter, eighth, half...
-//! - Note: C4 quarter, C4 eighth, C4 half...
+//! - Note: C4 quarter, C4 eighth, C4 half..., or a rest inside a melody
+//! - Expression: articulations, dynamics, slurs, fermatas, grace notes on a Note
+//! - Lyric: syllables with hyphenation and melisma, one per verse on a Note
+//! - PercussionNote: unpitched hit of the General MIDI drum map, DrumPattern: step grid builder
 //! - Chord: C major, C minor, C7...
 //! - Measure: bundle of notes and chords
-//! - Track: bundle of measures
+//! - Track: bundle of measures, optionally in its own meter or tuplet against the score
 //! - Score: bundle of tracks
+//! - LyricSheet: chords printed over the lyrics of a melody
+//! - Timeline: score flattened into timed events, repeats unrolled and expression markings interpreted
+//! - TimeSignature: beat grouping, any span split into tied notatable values along the meter
+//! - Quantizer: performed onset / offset times snapped to notated values, tuplets and ties included
+//! - Groove: swing, shuffle, push / laid-back feel, humanization and groove maps at render time
+//!
+//! - MelodyGenerator: seeded melodies over a chord progression
+//! - BassGenerator: root–fifth, walking, tumbao and pedal basslines from chords
+//! - Rhythm: Euclidean, clave, additive and weighted random onset patterns
 //!
 //! - Midi: play the score using midi
+//! - MusicXml: score export to MusicXML 4.0 for notation editors, import of partwise, timewise and .mxl files
+//! - Abc: ABC tunes read into a score with chord symbols, repeats and lyrics, and written back
+//! - LilyPond: engravable .ly export with chord names, drum staves and tablature from a fretboard
+//! - Tab: ASCII guitar tab of melody and chord tracks, and tabs in any tuning read into fingerings and notes
+//! - ChordPro: lead sheets read and written back transposed, renamed for a capo and with chord diagrams
+//! - SvgDiagram: vector chord boxes with barres, open / muted strings and technique marks in any orientation
+//! - FretboardMap: every position of a scale or chord across the neck, true fret spacing, ASCII or SVG
+//! - KeyboardDiagram: piano keys of a chord, scale or two-handed fingering with finger numbers, ASCII or SVG
+//! - StaffSvg: measures, tracks and scores engraved on five-line staves as self-contained SVG
+//! - Jianpu: numbered notation (简谱) relative to the tonic, export and import
+//! - Kern: Humdrum **kern spines to and from a `Score`, for corpora such as the Bach chorales
+//! - IReal: iReal Pro chart links decoded into sections, repeats and chord symbols, and a chord-track `Score`
 //!
 //! Other Abilities:
 //! - Interval: describe the distance between two `Tuning`s
@@ -29,6 +53,8 @@ pub mod audio;
 mod composition;
 mod core;
 mod fret;
+mod generator;
+mod notation;
 mod tests;
 
 pub use composition::*;
@@ -52,13 +78,18 @@ pub use fret::{
     FretboardDiagramGenerator,
     // Errors
     FretboardError,
+    FretboardMap,
     FretboardResult,
+    Hand,
     // Presets
     InstrumentPresets,
     KeyLayout,
     KeyboardConfig,
+    KeyboardDiagram,
     KeyboardFretboard,
     KeyboardPosition,
+    MapLabel,
+    MelodicFingeringOptimizer,
     PlayingTechnique,
     SequenceAnalysis,
     SkillLevel,
@@ -66,8 +97,12 @@ pub use fret::{
     StringedInstrumentConfig,
     // Types
     StringedPosition,
+    SvgDiagramConfig,
+    SvgDiagramGenerator,
     VoiceLeadingOptimizer,
 };
+pub use generator::*;
+pub use notation::*;
 pub use tests::*;
 
 #[cfg(feature = "bindgen")]
@@ -1,9 +1,14 @@
-use crate::{Chord, Measure, MusicError, Note, Score, Tuning};
+use crate::{
+    Chord, EventSource, Interpretation, Measure, MusicError, Note, Score, Timeline, Tuning,
+};
 use midir::{MidiOutput, MidiOutputConnection, MidiOutputPort};
 use std::array;
 use std::cell::RefCell;
 use std::rc::Rc;
 
+/// General MIDI percussion channel (channel 10)
+const PERCUSSION_CHANNEL: usize = 9;
+
 pub struct MidiPlayer {
     name: String,
     midi_out: Option<MidiOutput>,
@@ -11,6 +16,8 @@ pub struct MidiPlayer {
     midi_out_conn: Rc<RefCell<Option<MidiOutputConnection>>>,
 
     midi_player_channels: Option<[RefCell<MidiPlayerChannel>; 16]>,
+
+    interpretation: Interpretation,
 }
 
 pub struct MidiPlayerChannel {
@@ -26,6 +33,7 @@ impl MidiPlayer {
             port: None,
             midi_out_conn: Rc::new(RefCell::new(None)),
             midi_player_channels: None,
+            interpretation: Interpretation::default(),
         };
 
         let midi_out = MidiOutput::new(&midi_player.name).ok();
@@ -113,12 +121,16 @@ impl<'a> MidiPlayerChannel {
     }
 
     pub fn play_notes(&mut self, notes: &[u8]) {
+        self.play_notes_with_velocity(notes, 0x64);
+    }
+
+    pub fn play_notes_with_velocity(&mut self, notes: &[u8], velocity: u8) {
         self.midi_out_conn.borrow_mut().as_mut().map(|conn| {
             notes.iter().for_each(|note| {
                 if *note == 0 {
                     return;
                 }
-                let _ = conn.send(&[0x90 | (self.channel & 0xF), *note, 0x64]);
+                let _ = conn.send(&[0x90 | (self.channel & 0xF), *note, velocity & 0x7F]);
             })
         });
     }
@@ -156,8 +168,17 @@ impl Tuning {
 }
 
 impl MidiPlayer {
+    /// Interpretation used to turn expression markings into velocity and timing
+    pub fn with_interpretation(mut self, interpretation: Interpretation) -> Self {
+        self.interpretation = interpretation;
+        self
+    }
+
     /// Play a score
     ///
+    /// Tracks are played on channels in order, skipping the percussion channel 10 where
+    /// every percussion measure goes.
+    ///
     /// TODO: optimize the performance
     pub fn play_score<const TRACK_COUNT: usize>(
         &mut self,
@@ -172,95 +193,57 @@ impl MidiPlayer {
             .first()
             .ok_or("No MIDI output ports available".to_owned())?;
         self.select_port(0)?;
+        let timeline = Timeline::with_interpretation(score, &self.interpretation);
         let channels = self.connect("Mutheors Port 0")?;
-        let max_track_count = TRACK_COUNT.min(channels.len());
-        let dg = score.duration_generator();
-
-        let tempo = score.tempo();
-        let beats_per_measure = score.time_signature().beats_per_measure();
-        let beat_duration = time::Duration::from_secs_f64(60.0 / tempo as f64);
-        let measure_duration = beat_duration * beats_per_measure as u32;
+        // Channel 10 is kept for percussion
+        let max_track_count = TRACK_COUNT.min(channels.len() - 1);
 
         struct TimedEvent {
             trigger_time: time::Duration,
-            track_idx: usize,
-            chord: Option<Chord>,
-            original_notes: Option<Vec<Note>>,
+            channel: usize,
+            source: Option<EventSource>,
             /// MIDI note numbers
             notes: Vec<u8>,
+            velocity: u8,
             is_start: bool,
         }
 
         let mut events = Vec::new();
-        for (track_idx, track) in tracks[..max_track_count].iter().enumerate() {
-            for (measure_idx, measure) in track.get_measures().iter().enumerate() {
-                match measure {
-                    Measure::Rest => {}
-                    Measure::Chords(chords) => {
-                        let avg_measure_duration = measure_duration / chords.len() as u32;
-                        let start_time = measure_duration * measure_idx as u32;
-
-                        for (i, chord) in chords.iter().enumerate() {
-                            let start_time = start_time + avg_measure_duration * i as u32;
-                            let end_time = start_time + avg_measure_duration;
-
-                            let chord_notes: Vec<u8> = chord
-                                .components()
-                                .iter()
-                                .map(|t| t.midi_number().unwrap())
-                                .collect();
-
-                            events.push(TimedEvent {
-                                trigger_time: start_time,
-                                track_idx,
-                                chord: Some(chord.clone()),
-                                original_notes: None,
-                                notes: chord_notes.clone(),
-                                is_start: true,
-                            });
-                            events.push(TimedEvent {
-                                trigger_time: end_time,
-                                track_idx,
-                                chord: Some(chord.clone()),
-                                original_notes: None,
-                                notes: chord_notes,
-                                is_start: false,
-                            });
-                        }
-                    }
-                    Measure::Note(notes) => {
-                        let mut current_start = 0.0;
-                        for note in notes {
-                            let duration = note.duration();
-                            let start = current_start;
-                            current_start += duration.in_beats(&dg);
-                            let note_start = measure_duration * measure_idx as u32
-                                + beat_duration.mul_f64(start as f64);
-                            let note_end =
-                                note_start + beat_duration.mul_f64(duration.in_beats(&dg) as f64);
-
-                            let midi_num = note.tuning().midi_number().unwrap();
-
-                            events.push(TimedEvent {
-                                trigger_time: note_start,
-                                track_idx,
-                                chord: None,
-                                original_notes: Some(vec![note.clone()]),
-                                notes: vec![midi_num],
-                                is_start: true,
-                            });
-                            events.push(TimedEvent {
-                                trigger_time: note_end,
-                                track_idx,
-                                chord: None,
-                                original_notes: Some(vec![note.clone()]),
-                                notes: vec![midi_num],
-                                is_start: false,
-                            });
-                        }
-                    }
-                }
+        for event in timeline.events() {
+            if event.track >= max_track_count {
+                continue;
             }
+
+            let notes: Vec<u8> = match &event.source {
+                EventSource::Percussion(note) => vec![note.sound().midi_number()],
+                _ => event
+                    .tunings
+                    .iter()
+                    .map(|t| t.midi_number().unwrap())
+                    .collect(),
+            };
+            let channel = match event.is_percussion() {
+                true => PERCUSSION_CHANNEL,
+                false if event.track < PERCUSSION_CHANNEL => event.track,
+                false => event.track + 1,
+            };
+
+            events.push(TimedEvent {
+                trigger_time: time::Duration::from_secs_f64(event.start),
+                channel,
+                source: Some(event.source.clone()),
+                notes: notes.clone(),
+                velocity: event.velocity,
+                is_start: true,
+            });
+            events.push(TimedEvent {
+                trigger_time: time::Duration::from_secs_f64(event.end()),
+                channel,
+                source: None,
+                notes,
+                velocity: event.velocity,
+                is_start: false,
+            });
         }
 
         events.sort_by(|a, b| a.trigger_time.cmp(&b.trigger_time));
@@ -272,22 +255,18 @@ impl MidiPlayer {
             if let Ok(wait_duration) = trigger_moment.duration_since(now) {
                 std::thread::sleep(wait_duration);
             }
-            let channel = &channels[event.track_idx];
+            let channel = &channels[event.channel];
 
             if event.is_start {
-                if let Some(chord) = event.chord {
-                    println!("{}", chord);
-                } else if let Some(original_notes) = event.original_notes {
-                    println!(
-                        "{}",
-                        original_notes
-                            .iter()
-                            .map(|note| { note.to_string() })
-                            .collect::<Vec<_>>()
-                            .join(" ")
-                    );
+                match event.source {
+                    Some(EventSource::Chord(chord)) => println!("{}", chord),
+                    Some(EventSource::Note(note)) => println!("{}", note),
+                    Some(EventSource::Percussion(note)) => println!("{}", note),
+                    None => {}
                 }
-                channel.borrow_mut().play_notes(&event.notes);
+                channel
+                    .borrow_mut()
+                    .play_notes_with_velocity(&event.notes, event.velocity);
             } else {
                 channel.borrow_mut().stop_notes(&event.notes);
             }
@@ -0,0 +1,1928 @@
+//! ABC notation
+//!
+//! Reads and writes tunes in ABC 2.1, the text format of most folk tune collections.
+//! The reader takes the header fields `X:`, `T:`, `C:`, `M:`, `L:`, `Q:` and `K:`, and in
+//! the body notes, octaves, accidentals, lengths, broken rhythms, rests, ties, tuplets,
+//! grace notes, slurs, decorations, bar lines, repeats, endings, chord symbols in quotes,
+//! voices (`V:`) and lyrics (`w:`). As for MusicXML, the score model is smaller than the
+//! format:
+//! - tied notes are merged when their sum is a single value, otherwise kept as two notes
+//! - stacked notes `[CEG]` keep their top note, unless the whole measure is stacked under
+//!   chord symbols, which makes a `Measure::Chords`
+//! - chord symbols over a melody are listed in `AbcTune::chord_symbols`, and fill the
+//!   first track without a voice as a chord track
+//! - the first key and meter of each voice are kept
+//!
+//! Everything else is reported as an `ImportWarning`. The writer emits one voice per
+//! track, chord measures as symbols over stacked notes. Drum tracks are written as rests.
+
+use super::layout::{layout, Content, Slot};
+use super::musicxml::{beat_type_number, letter_index};
+use super::musicxml_import::{pitch_height, Warnings};
+use crate::{
+    Articulation, BarMark, Chord, ChordQuality, ChordSymbol, Duration, DurationBase, Dynamic,
+    Expression, Grace, Hairpin, HairpinMark, ImportWarning, ImportWarningKind, Jump, KeySignature,
+    Lyric, Measure, MusicError, Note, Scale, ScaleType, Score, SlurMark, Syllabic, TimeSignature,
+    Tuning, Tuplet,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// ABC writer and reader, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Abc {
+    reference: u32,
+    title: Option<String>,
+    unit: DurationBase,
+    measures_per_line: usize,
+}
+
+impl Default for Abc {
+    fn default() -> Self {
+        Abc {
+            reference: 1,
+            title: None,
+            unit: DurationBase::Eighth,
+            measures_per_line: 4,
+        }
+    }
+}
+
+/// Result of reading an ABC tune
+#[derive(Clone)]
+pub struct AbcTune<const N: usize> {
+    pub score: Score<N>,
+    /// Reference number from `X:`
+    pub reference: Option<u32>,
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub chord_symbols: Vec<ChordSymbol>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl Abc {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reference number written in `X:`, 1 by default
+    pub fn with_reference(self, reference: u32) -> Self {
+        Abc { reference, ..self }
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        Abc {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Unit note length of `L:`, an eighth by default
+    pub fn with_unit(self, unit: DurationBase) -> Self {
+        Abc { unit, ..self }
+    }
+
+    pub fn with_measures_per_line(self, measures_per_line: usize) -> Self {
+        Abc {
+            measures_per_line: measures_per_line.max(1),
+            ..self
+        }
+    }
+
+    /// ABC tune of a score
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let ts = score.time_signature();
+        let mut out = String::new();
+        let _ = writeln!(out, "X:{}", self.reference);
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "T:{title}");
+        }
+        let _ = writeln!(out, "M:{}", meter_text(ts));
+        let _ = writeln!(out, "L:1/{}", beat_type_number(self.unit));
+        let _ = writeln!(
+            out,
+            "Q:1/{}={}",
+            beat_type_number(ts.beat_type()),
+            score.tempo()
+        );
+        if N > 1 {
+            for track in 0..N {
+                let _ = writeln!(out, "V:{}", track + 1);
+            }
+        }
+        let key = score.key_signature();
+        let _ = writeln!(
+            out,
+            "K:{}{}",
+            key.tonic(),
+            if key.is_minor() { "m" } else { "" }
+        );
+        for track in 0..N {
+            if N > 1 {
+                let _ = writeln!(out, "V:{}", track + 1);
+            }
+            let meter = score.track_time_signature(track);
+            if &meter != ts {
+                let _ = writeln!(out, "M:{}", meter_text(&meter));
+            }
+            self.write_voice(&mut out, score, track);
+        }
+        out
+    }
+
+    fn write_voice<const N: usize>(&self, out: &mut String, score: &Score<N>, track: usize) {
+        let ts = score.track_time_signature(track);
+        let rest = Measure::Rest;
+        let measures = score.get_tracks()[track].get_measures();
+        let verses = measures
+            .iter()
+            .flat_map(|m| match m {
+                Measure::Note(notes) => notes.as_slice(),
+                _ => &[],
+            })
+            .flat_map(|n| n.lyrics().iter().map(|l| l.verse))
+            .collect::<BTreeSet<_>>();
+        let mut writer = VoiceWriter {
+            text: String::new(),
+            key: ['C', 'D', 'E', 'F', 'G', 'A', 'B'].map(|l| score.key_signature().alter(l)),
+            altered: HashMap::new(),
+            tuplet: None,
+            unit: self.unit.in_quarters(),
+            ts,
+            hairpin: Hairpin::Crescendo,
+            sung: vec![],
+        };
+
+        let count = score.measure_count();
+        if score.marks(0).contains(&BarMark::RepeatStart) {
+            writer.text.push_str("|:");
+        }
+        if let Some(numbers) = volta(score, 0) {
+            let _ = write!(writer.text, "[{}", join_numbers(&numbers));
+        }
+        for m in 0..count {
+            let marks = score.marks(m);
+            for mark in marks {
+                match mark {
+                    BarMark::Segno => writer.text.push_str("!segno!"),
+                    BarMark::Coda => writer.text.push_str("!coda!"),
+                    _ => {}
+                }
+            }
+            writer.measure(&layout(measures.get(m).unwrap_or(&rest), &ts));
+            for mark in marks {
+                let decoration = match mark {
+                    BarMark::ToCoda => "dacoda",
+                    BarMark::Fine => "fine",
+                    BarMark::Jump(jump) => jump_decoration(*jump),
+                    _ => continue,
+                };
+                let _ = write!(writer.text, "!{decoration}!");
+            }
+            writer.text.push_str(&bar_after(score, m));
+
+            if (m + 1) % self.measures_per_line == 0 || m + 1 == count {
+                out.push_str(writer.text.trim_start());
+                out.push('\n');
+                for verse in &verses {
+                    if let Some(line) = lyric_line(&writer.sung, *verse) {
+                        let _ = writeln!(out, "w:{line}");
+                    }
+                }
+                writer.text.clear();
+                writer.sung.clear();
+            }
+        }
+    }
+
+    /// Tunes of a collection, each starting at its `X:` field. Text before the first tune
+    /// (the file header) is left out.
+    pub fn tunes(text: &str) -> Vec<&str> {
+        let mut starts = vec![];
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if line.starts_with("X:") {
+                starts.push(offset);
+            }
+            offset += line.len();
+        }
+        if starts.is_empty() {
+            return match text.trim().is_empty() {
+                true => vec![],
+                false => vec![text],
+            };
+        }
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, start)| &text[*start..starts.get(i + 1).copied().unwrap_or(text.len())])
+            .collect()
+    }
+
+    /// Read one ABC tune into a score of `N` tracks, one per voice. Voices beyond `N` are
+    /// dropped with a warning.
+    pub fn import<const N: usize>(tune: &str) -> Result<AbcTune<N>, MusicError> {
+        let mut reader = Reader::default();
+        for line in tune.trim_start_matches('\u{feff}').lines() {
+            if reader.ended {
+                break;
+            }
+            reader.line(line);
+        }
+        reader.finish()
+    }
+}
+
+/// Writes the measures of one voice, keeping accidental and tuplet state
+struct VoiceWriter<'a> {
+    text: String,
+    /// Alteration of each letter in the key signature
+    key: [i8; 7],
+    /// Alterations already shown in the measure, by letter and octave
+    altered: HashMap<(char, i8), i8>,
+    /// Quarters left in the open tuplet
+    tuplet: Option<f32>,
+    /// Unit note length in quarters
+    unit: f32,
+    ts: TimeSignature,
+    /// Kind of the open hairpin
+    hairpin: Hairpin,
+    /// Sung notes of the current line, for the `w:` lines
+    sung: Vec<&'a Note>,
+}
+
+impl<'a> VoiceWriter<'a> {
+    fn measure(&mut self, slots: &[Slot<'a>]) {
+        self.altered.clear();
+        // Eighths are beamed by the quarter, shorter beats by their group (3+3 in 6/8)
+        let beat = self.ts.beat_type().in_quarters();
+        let groups = match beat >= 1.0 {
+            true => vec![1; self.ts.beats_per_measure() as usize],
+            false => self.ts.grouping(),
+        };
+        let groups = groups
+            .iter()
+            .scan(0.0, |end, g| {
+                *end += *g as f32 * beat;
+                Some(*end)
+            })
+            .collect::<Vec<_>>();
+
+        let mut position = 0.0;
+        let mut grace = false;
+        for (i, slot) in slots.iter().enumerate() {
+            if slot.is_grace() != grace {
+                if grace {
+                    self.text.push('}');
+                } else {
+                    self.space(position, &groups);
+                    self.text.push('{');
+                    if let Content::Note(note, _) = slot.content {
+                        if note.expression().grace == Some(Grace::Acciaccatura) {
+                            self.text.push('/');
+                        }
+                    }
+                }
+                grace = slot.is_grace();
+            } else if !grace {
+                self.space(position, &groups);
+            }
+            if !grace {
+                self.tuplet_mark(&slots[i..]);
+            }
+            self.slot(slot);
+            if !grace {
+                position += slot.duration.in_quarters();
+            }
+        }
+        if grace {
+            self.text.push('}');
+        }
+    }
+
+    /// Notes are beamed within a beat group, a space separates the groups
+    fn space(&mut self, position: f32, groups: &[f32]) {
+        if position > 1e-3 && groups.iter().any(|g| (g - position).abs() < 1e-3) {
+            self.text.push(' ');
+        }
+    }
+
+    fn slot(&mut self, slot: &Slot<'a>) {
+        let length = length_text(slot.duration, self.unit);
+        match &slot.content {
+            Content::Note(note, tuning) => {
+                let expression = note.expression();
+                if let Some(dynamic) = expression.dynamic {
+                    let _ = write!(self.text, "!{}!", dynamic.symbol());
+                }
+                match expression.hairpin {
+                    Some(HairpinMark::Start(hairpin)) => {
+                        self.hairpin = hairpin;
+                        self.text.push_str(match hairpin {
+                            Hairpin::Crescendo => "!<(!",
+                            Hairpin::Diminuendo => "!>(!",
+                        });
+                    }
+                    Some(HairpinMark::Stop) => self.text.push_str(match self.hairpin {
+                        Hairpin::Crescendo => "!<)!",
+                        Hairpin::Diminuendo => "!>)!",
+                    }),
+                    None => {}
+                }
+                for articulation in &expression.articulations {
+                    self.text.push_str(match articulation {
+                        Articulation::Staccato => ".",
+                        Articulation::Tenuto => "!tenuto!",
+                        Articulation::Accent => "!accent!",
+                        Articulation::Marcato => "!marcato!",
+                    });
+                }
+                if expression.fermata {
+                    self.text.push_str("!fermata!");
+                }
+                if expression.slur == Some(SlurMark::Start) {
+                    self.text.push('(');
+                }
+                let pitch = self.pitch(*tuning);
+                self.text.push_str(&pitch);
+                self.text.push_str(&length);
+                if expression.slur == Some(SlurMark::Stop) {
+                    self.text.push(')');
+                }
+                if !note.is_grace() {
+                    self.sung.push(note);
+                }
+            }
+            Content::Chord(chord) => {
+                if !slot.continued {
+                    let _ = write!(self.text, "\"{}{}\"", chord.root(), chord.quality());
+                }
+                let pitches = chord
+                    .components()
+                    .iter()
+                    .map(|t| self.pitch(*t))
+                    .collect::<String>();
+                let _ = write!(self.text, "[{pitches}]{length}");
+                if slot.tie {
+                    self.text.push('-');
+                }
+            }
+            Content::Drums(_) | Content::Rest => {
+                let _ = write!(self.text, "z{length}");
+            }
+            Content::MeasureRest => self.text.push('Z'),
+        }
+    }
+
+    /// Note name with the accidental it needs at this point of the measure
+    fn pitch(&mut self, tuning: Tuning) -> String {
+        let (letter, alter, octave) = tuning.spelling();
+        let expected = self
+            .altered
+            .get(&(letter, octave))
+            .copied()
+            .unwrap_or(self.key[letter_index(letter)]);
+        let mut text = String::new();
+        if alter != expected {
+            text.push_str(match alter {
+                i8::MIN..=-2 => "__",
+                -1 => "_",
+                0 => "=",
+                1 => "^",
+                _ => "^^",
+            });
+            self.altered.insert((letter, octave), alter);
+        }
+        if octave >= 5 {
+            text.push(letter.to_ascii_lowercase());
+            text.push_str(&"'".repeat((octave - 5) as usize));
+        } else {
+            text.push(letter);
+            text.push_str(&",".repeat((4 - octave) as usize));
+        }
+        text
+    }
+
+    /// `(3` before the first note of a tuplet, `(p:q:r` when the ratio or the note count
+    /// is not the default one
+    fn tuplet_mark(&mut self, slots: &[Slot]) {
+        let Some(tuplet) = slots[0].duration.tuplet else {
+            return;
+        };
+        let remaining = match self.tuplet {
+            Some(remaining) => remaining,
+            None => {
+                let span = tuplet.base_notes as f32 * tuplet.base_duration.in_quarters();
+                let mut filled = 0.0;
+                let count = slots
+                    .iter()
+                    .filter(|slot| !slot.is_grace())
+                    .take_while(|slot| {
+                        let inside = filled < span - 1e-3;
+                        filled += slot.duration.in_quarters();
+                        inside
+                    })
+                    .count();
+                let (p, q) = (tuplet.actual_notes, tuplet.base_notes);
+                match q == default_ratio(p, &self.ts) && count == p as usize {
+                    true => {
+                        let _ = write!(self.text, "({p}");
+                    }
+                    false => {
+                        let _ = write!(self.text, "({p}:{q}:{count}");
+                    }
+                }
+                span
+            }
+        };
+        let remaining = remaining - slots[0].duration.in_quarters();
+        self.tuplet = (remaining > 1e-3).then_some(remaining);
+    }
+}
+
+/// Bar line after measure `m`, with the ending that starts after it
+fn bar_after<const N: usize>(score: &Score<N>, m: usize) -> String {
+    let last = m + 1 == score.measure_count();
+    let end = score
+        .marks(m)
+        .iter()
+        .any(|mark| matches!(mark, BarMark::RepeatEnd(_)));
+    let start = !last && score.marks(m + 1).contains(&BarMark::RepeatStart);
+    let ending = volta(score, m);
+    let next = volta(score, m + 1).filter(|_| !last);
+    let mut bar = match (end, start) {
+        (true, true) => "::",
+        (true, false) => ":|",
+        (false, true) => "|:",
+        _ if last => "|]",
+        _ if ending.is_some() && ending != next => "||",
+        _ => "|",
+    }
+    .to_string();
+    if let Some(numbers) = next.filter(|n| Some(n) != ending.as_ref()) {
+        bar.push_str(&join_numbers(&numbers));
+    }
+    bar
+}
+
+fn volta<const N: usize>(score: &Score<N>, m: usize) -> Option<Vec<u8>> {
+    score.marks(m).iter().find_map(|mark| match mark {
+        BarMark::Volta(numbers) => Some(numbers.clone()),
+        _ => None,
+    })
+}
+
+fn join_numbers(numbers: &[u8]) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn jump_decoration(jump: Jump) -> &'static str {
+    match jump {
+        Jump::DaCapo => "D.C.",
+        Jump::DaCapoAlFine => "D.C.alfine",
+        Jump::DaCapoAlCoda => "D.C.alcoda",
+        Jump::DalSegno => "D.S.",
+        Jump::DalSegnoAlFine => "D.S.alfine",
+        Jump::DalSegnoAlCoda => "D.S.alcoda",
+    }
+}
+
+/// `6/8`, or `3+3+2/8` for an irregular grouping
+pub(crate) fn meter_text(ts: &TimeSignature) -> String {
+    let default = TimeSignature::new(ts.beats_per_measure(), ts.beat_type());
+    let beats = match ts.grouping() == default.grouping() {
+        true => ts.beats_per_measure().to_string(),
+        false => ts
+            .grouping()
+            .iter()
+            .map(|g| g.to_string())
+            .collect::<Vec<_>>()
+            .join("+"),
+    };
+    format!("{beats}/{}", beat_type_number(ts.beat_type()))
+}
+
+/// Written length of a value in units, e.g. `3/2` for a dotted eighth in eighths. Tuplet
+/// values are written at their nominal length.
+fn length_text(duration: Duration, unit: f32) -> String {
+    let mut value = Duration {
+        tuplet: None,
+        ..duration
+    }
+    .in_quarters()
+        / unit;
+    let mut denominator = 1;
+    while (value - value.round()).abs() > 1e-3 && denominator < 256 {
+        value *= 2.0;
+        denominator *= 2;
+    }
+    match (value.round() as u32, denominator) {
+        (1, 1) => String::new(),
+        (n, 1) => n.to_string(),
+        (1, 2) => "/".to_string(),
+        (1, d) => format!("/{d}"),
+        (n, d) => format!("{n}/{d}"),
+    }
+}
+
+/// `w:` line of a verse for the sung notes of a line, `None` when the verse has no text
+/// there
+fn lyric_line(notes: &[&Note], verse: u8) -> Option<String> {
+    if notes.iter().all(|n| n.lyric(verse).is_none()) {
+        return None;
+    }
+    let mut line = String::new();
+    let mut joined = false;
+    let mut held = false;
+    for note in notes {
+        if !joined && !line.is_empty() {
+            line.push(' ');
+        }
+        joined = false;
+        match note.lyric(verse) {
+            Some(lyric) => {
+                line.push_str(&lyric.text.replace('-', "\\-").replace(' ', "~"));
+                if matches!(lyric.syllabic, Syllabic::Begin | Syllabic::Middle) {
+                    line.push('-');
+                    joined = !lyric.extend;
+                }
+                if lyric.extend {
+                    line.push_str(" _");
+                }
+                held = lyric.extend;
+            }
+            None => line.push(if held { '_' } else { '*' }),
+        }
+    }
+    Some(line)
+}
+
+/// Second number of a tuplet `(p` written without it
+pub(crate) fn default_ratio(p: u8, ts: &TimeSignature) -> u8 {
+    let compound = ts.beats_per_measure().is_multiple_of(3) && ts.beats_per_measure() > 3;
+    match p {
+        2 | 4 | 8 => 3,
+        3 | 6 => 2,
+        _ if compound => 3,
+        _ => 2,
+    }
+}
+
+/// A note, stacked chord or rest of a voice
+#[derive(Clone)]
+struct Event {
+    /// Empty for a rest
+    pitches: Vec<Tuning>,
+    /// Written length in quarters, before the tuplet ratio
+    length: f32,
+    tuplet: Option<(u8, u8)>,
+    note: Note,
+    /// Tied to the next event
+    tie: bool,
+}
+
+impl Event {
+    fn actual_length(&self) -> f32 {
+        match self.tuplet {
+            Some((p, q)) => self.length * q as f32 / p as f32,
+            None => self.length,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Bar {
+    events: Vec<Event>,
+    /// Chord symbols and their position in quarters
+    chords: Vec<(f32, Chord)>,
+    /// Quarters written so far
+    position: f32,
+    /// Measures of a multi-measure rest `Z4`
+    rests: usize,
+}
+
+#[derive(Default)]
+struct Voice {
+    id: String,
+    meter: Option<TimeSignature>,
+    bars: Vec<Bar>,
+    bar: Bar,
+    marks: Vec<(usize, BarMark)>,
+    /// Numbers of the open ending
+    volta: Option<Vec<u8>>,
+    /// Alterations written earlier in the bar, by letter and octave
+    altered: HashMap<(char, i8), i8>,
+    /// Decorations waiting for the next note
+    expression: Expression,
+    grace: Option<Grace>,
+    /// Ratio of the open tuplet and the notes left in it
+    tuplet: Option<(u8, u8, u8)>,
+    /// Length factor a broken rhythm gives to the next note
+    broken: Option<f32>,
+    tie: bool,
+    /// Inside a `&` overlay, which is skipped
+    overlay: bool,
+    /// Sung notes of the last music line, by bar and event index
+    line: Vec<(usize, usize)>,
+    verse: u8,
+}
+
+impl Voice {
+    fn close_bar(&mut self) {
+        let bar = std::mem::take(&mut self.bar);
+        self.altered.clear();
+        let first = self.bars.len();
+        if bar.rests > 0 {
+            self.bars.extend((0..bar.rests).map(|_| Bar::default()));
+        } else if !bar.events.is_empty() || !bar.chords.is_empty() {
+            self.bars.push(bar);
+        }
+        if let Some(numbers) = &self.volta {
+            for index in first..self.bars.len() {
+                self.marks.push((index, BarMark::Volta(numbers.clone())));
+            }
+        }
+    }
+
+    fn event_mut(&mut self, bar: usize, event: usize) -> Option<&mut Event> {
+        match bar == self.bars.len() {
+            true => self.bar.events.get_mut(event),
+            false => self.bars.get_mut(bar)?.events.get_mut(event),
+        }
+    }
+
+    /// Measures of the voice and the chord symbols written over its melody
+    fn measures(
+        &self,
+        track: usize,
+        meter: &TimeSignature,
+        warnings: &mut Warnings,
+    ) -> (Vec<Measure>, Vec<ChordSymbol>) {
+        let mut measures = vec![];
+        let mut symbols = vec![];
+        for (index, bar) in self.bars.iter().enumerate() {
+            let number = (index + 1).to_string();
+            let mut warn = |kind, element: &str, message: &str| {
+                warnings.push(kind, &self.id, &number, element, message)
+            };
+            let sounding = bar
+                .events
+                .iter()
+                .filter(|e| !e.pitches.is_empty() && e.note.expression().grace.is_none())
+                .collect::<Vec<_>>();
+            if sounding.is_empty() && bar.chords.is_empty() {
+                measures.push(Measure::Rest);
+                continue;
+            }
+            if !bar.chords.is_empty() && sounding.iter().all(|e| e.pitches.len() > 1) {
+                measures.push(Measure::Chords(
+                    bar.chords.iter().map(|(_, c)| c.clone()).collect(),
+                ));
+                continue;
+            }
+
+            // Tied notes become one note when the sum is a single value
+            let mut events: Vec<Event> = vec![];
+            for event in &bar.events {
+                if let Some(last) = events.last_mut() {
+                    let mergeable = last.tie
+                        && last.pitches == event.pitches
+                        && last.tuplet.is_none()
+                        && event.tuplet.is_none()
+                        && last.note.expression().grace.is_none();
+                    if mergeable && notated(last.length + event.length, None).is_some() {
+                        last.length += event.length;
+                        last.tie = event.tie;
+                        continue;
+                    }
+                }
+                events.push(event.clone());
+            }
+            let mut notes = vec![];
+            for event in events {
+                if event.tie {
+                    warn(
+                        ImportWarningKind::Approximated,
+                        "-",
+                        "tied notes are kept as separate notes",
+                    );
+                }
+                if event.pitches.len() > 1 {
+                    warn(
+                        ImportWarningKind::Approximated,
+                        "[",
+                        "only the top note of a chord is kept",
+                    );
+                }
+                let duration = notated(event.length, event.tuplet).unwrap_or_else(|| {
+                    warn(
+                        ImportWarningKind::Approximated,
+                        "length",
+                        "a length that is no single value is rounded",
+                    );
+                    Duration::from_quarters(event.actual_length())
+                });
+                notes.push(event.note.with_duration(duration));
+            }
+            measures.push(Measure::Note(notes));
+            symbols.extend(bar.chords.iter().map(|(position, chord)| ChordSymbol {
+                track,
+                measure: index,
+                position: position / meter.beat_type().in_quarters(),
+                chord: chord.clone(),
+            }));
+        }
+        (measures, symbols)
+    }
+}
+
+/// Header and body state of a tune being read
+#[derive(Default)]
+struct Reader {
+    warnings: Warnings,
+    reference: Option<u32>,
+    title: Option<String>,
+    composer: Option<String>,
+    meter: Option<TimeSignature>,
+    /// Unit note length in quarters
+    unit: Option<f32>,
+    /// Quarter notes per minute
+    tempo: Option<f32>,
+    key: KeySignature,
+    body: bool,
+    ended: bool,
+    voices: Vec<Voice>,
+    current: usize,
+    /// The last music line ended with `\`
+    continues: bool,
+}
+
+impl Reader {
+    fn line(&mut self, line: &str) {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            // A blank line ends the tune
+            self.ended = self.body;
+            return;
+        }
+        if line.starts_with('%') {
+            return;
+        }
+        let mut chars = line.chars();
+        if let (Some(name), Some(':')) = (chars.next(), chars.next()) {
+            let field = match self.body {
+                true => "IKLMmNPQRrsTUVWw".contains(name),
+                false => name.is_ascii_alphabetic(),
+            };
+            if field {
+                return self.field(name, &line[2..]);
+            }
+        }
+        if !self.body {
+            self.warn(
+                ImportWarningKind::Invalid,
+                "K",
+                "the header has no K: field",
+            );
+            self.body = true;
+        }
+        self.music(line);
+    }
+
+    fn field(&mut self, name: char, value: &str) {
+        let value = value.split('%').next().unwrap_or_default().trim();
+        match name {
+            'X' if !self.body => self.reference = value.parse().ok(),
+            'T' if !self.body && self.title.is_none() => self.title = Some(value.to_string()),
+            'C' if !self.body && self.composer.is_none() => self.composer = Some(value.to_string()),
+            'M' => self.meter_field(value),
+            'L' => match fraction(value) {
+                Some(whole) => self.unit = Some(whole * 4.0),
+                None => self.warn(ImportWarningKind::Invalid, "L", "unit note length"),
+            },
+            'Q' if self.tempo.is_none() => {
+                self.tempo = tempo(value, self.unit());
+                if self.tempo.is_none() {
+                    self.warn(ImportWarningKind::Invalid, "Q", "tempo");
+                }
+            }
+            'K' => match key(value) {
+                Some((key, extra)) => {
+                    if extra {
+                        self.warn(
+                            ImportWarningKind::Unsupported,
+                            "K",
+                            "explicit key accidentals are not kept",
+                        );
+                    }
+                    if !self.body {
+                        self.key = key;
+                        self.body = true;
+                        self.current = 0;
+                    } else if key != self.key {
+                        self.warn(
+                            ImportWarningKind::Unsupported,
+                            "K",
+                            "key changes are not kept",
+                        );
+                    }
+                }
+                None => {
+                    self.warn(ImportWarningKind::Invalid, "K", format!("key {value}"));
+                    self.body = true;
+                }
+            },
+            'V' => {
+                let id = value.split_whitespace().next().unwrap_or("1");
+                self.current = match self.voices.iter().position(|v| v.id == id) {
+                    Some(index) => index,
+                    None => {
+                        self.voices.push(Voice {
+                            id: id.to_string(),
+                            ..Voice::default()
+                        });
+                        self.voices.len() - 1
+                    }
+                };
+            }
+            'w' => self.lyrics(value),
+            'P' | 's' => self.warn(
+                ImportWarningKind::Unsupported,
+                &name.to_string(),
+                "parts and symbol lines are not kept",
+            ),
+            _ => {}
+        }
+    }
+
+    fn meter_field(&mut self, value: &str) {
+        let Some(ts) = meter(value) else {
+            return self.warn(
+                ImportWarningKind::Unsupported,
+                "M",
+                format!("meter {value} is read as 4/4"),
+            );
+        };
+        if !self.body {
+            self.meter = Some(ts);
+            return;
+        }
+        let default = self.meter;
+        let voice = self.voice();
+        if voice.bars.is_empty() && voice.bar.events.is_empty() {
+            voice.meter = Some(ts);
+        } else if voice.meter.or(default).as_ref() != Some(&ts) {
+            self.warn(
+                ImportWarningKind::Unsupported,
+                "M",
+                "meter changes are not kept",
+            );
+        }
+    }
+
+    /// Unit note length in quarters, from `L:` or else from the meter
+    fn unit(&self) -> f32 {
+        self.unit.unwrap_or_else(|| {
+            let ts = self
+                .meter
+                .unwrap_or(TimeSignature::new(4, DurationBase::Quarter));
+            match ts.measure_quarters() < 3.0 {
+                true => 0.25,
+                false => 0.5,
+            }
+        })
+    }
+
+    fn voice(&mut self) -> &mut Voice {
+        if self.voices.is_empty() {
+            self.voices.push(Voice {
+                id: "1".to_string(),
+                ..Voice::default()
+            });
+        }
+        let current = self.current.min(self.voices.len() - 1);
+        &mut self.voices[current]
+    }
+
+    fn warn(&mut self, kind: ImportWarningKind, element: &str, message: impl Into<String>) {
+        let (part, measure) = match self.voices.get(self.current) {
+            Some(voice) => (voice.id.clone(), (voice.bars.len() + 1).to_string()),
+            None => (String::new(), String::new()),
+        };
+        self.warnings.push(kind, &part, &measure, element, message);
+    }
+
+    fn music(&mut self, line: &str) {
+        if !self.continues {
+            let voice = self.voice();
+            voice.line.clear();
+            voice.verse = 0;
+        }
+        self.continues = line.ends_with('\\');
+
+        let chars = line.chars().collect::<Vec<_>>();
+        let mut i = 0;
+        while let Some(&c) = chars.get(i) {
+            match c {
+                '%' => break,
+                ' ' | '\t' | '`' | '\\' | 'y' | ']' => i += 1,
+                '"' | '!' | '+' => {
+                    let Some(end) = chars[i + 1..].iter().position(|x| *x == c) else {
+                        self.warn(ImportWarningKind::Invalid, &c.to_string(), "unclosed");
+                        break;
+                    };
+                    let text = chars[i + 1..i + 1 + end].iter().collect::<String>();
+                    i += end + 2;
+                    match c {
+                        '"' => self.symbol(&text),
+                        _ => self.decoration(&text),
+                    }
+                }
+                '.' | '~' | 'H' | 'L' | 'M' | 'O' | 'P' | 'S' | 'T' | 'u' | 'v' => {
+                    self.decoration(&c.to_string());
+                    i += 1;
+                }
+                '{' => {
+                    i += 1;
+                    let grace = match chars.get(i) == Some(&'/') {
+                        true => {
+                            i += 1;
+                            Grace::Acciaccatura
+                        }
+                        false => Grace::Appoggiatura,
+                    };
+                    self.voice().grace = Some(grace);
+                }
+                '}' => {
+                    self.voice().grace = None;
+                    i += 1;
+                }
+                '(' if chars.get(i + 1).is_some_and(char::is_ascii_digit) => {
+                    i += 1;
+                    let p = number(&chars, &mut i).unwrap_or(3);
+                    let mut q = None;
+                    let mut r = None;
+                    if chars.get(i) == Some(&':') {
+                        i += 1;
+                        q = number(&chars, &mut i);
+                        if chars.get(i) == Some(&':') {
+                            i += 1;
+                            r = number(&chars, &mut i);
+                        }
+                    }
+                    let meter = self.current_meter();
+                    let p = p.min(255) as u8;
+                    let q = q.map_or(default_ratio(p, &meter), |q| q.min(255) as u8);
+                    let r = r.map_or(p, |r| r.min(255) as u8);
+                    self.voice().tuplet = Some((p, q, r));
+                }
+                '(' => {
+                    self.voice().expression.slur = Some(SlurMark::Start);
+                    i += 1;
+                }
+                ')' => {
+                    let voice = self.voice();
+                    if let Some(event) = voice
+                        .bar
+                        .events
+                        .iter_mut()
+                        .rev()
+                        .find(|e| !e.pitches.is_empty())
+                    {
+                        if event.note.expression().slur.is_none() {
+                            event.note = event.note.clone().with_slur(SlurMark::Stop);
+                        }
+                    }
+                    i += 1;
+                }
+                '-' => {
+                    let voice = self.voice();
+                    if let Some(event) = voice.bar.events.last_mut() {
+                        event.tie = true;
+                        voice.tie = true;
+                    }
+                    i += 1;
+                }
+                '>' | '<' => {
+                    let count = chars[i..].iter().take_while(|x| **x == c).count();
+                    i += count;
+                    let factor = 0.5f32.powi(count as i32);
+                    let (this, next) = match c {
+                        '>' => (2.0 - factor, factor),
+                        _ => (factor, 2.0 - factor),
+                    };
+                    let voice = self.voice();
+                    if let Some(event) = voice.bar.events.last_mut() {
+                        let before = event.actual_length();
+                        event.length *= this;
+                        voice.bar.position += event.actual_length() - before;
+                        voice.broken = Some(next);
+                    }
+                }
+                '[' => match (chars.get(i + 1), chars.get(i + 2)) {
+                    (Some(d), _) if d.is_ascii_digit() => {
+                        i += 1;
+                        self.voice().volta = Some(numbers(&chars, &mut i));
+                    }
+                    (Some('|'), _) => self.bar_line(&chars, &mut i),
+                    (Some(name), Some(':')) if name.is_ascii_alphabetic() => {
+                        let end = chars[i..]
+                            .iter()
+                            .position(|x| *x == ']')
+                            .map_or(chars.len(), |e| i + e);
+                        let value = chars[i + 3..end].iter().collect::<String>();
+                        self.field(*name, &value);
+                        i = end + 1;
+                    }
+                    _ => self.stack(&chars, &mut i),
+                },
+                '|' | ':' => self.bar_line(&chars, &mut i),
+                'z' | 'x' => {
+                    i += 1;
+                    let length = length(&chars, &mut i);
+                    self.event(vec![], length);
+                }
+                'Z' | 'X' => {
+                    i += 1;
+                    let count = number(&chars, &mut i).unwrap_or(1) as usize;
+                    self.voice().bar.rests += count;
+                }
+                '&' => {
+                    self.warn(
+                        ImportWarningKind::Unsupported,
+                        "&",
+                        "voice overlays are not kept",
+                    );
+                    self.voice().overlay = true;
+                    i += 1;
+                }
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' => {
+                    let Some(pitch) = pitch(&chars, &mut i) else {
+                        self.warn(ImportWarningKind::Invalid, &c.to_string(), "no note name");
+                        i += 1;
+                        continue;
+                    };
+                    let length = length(&chars, &mut i);
+                    if let Some(tuning) = self.tuning(pitch) {
+                        self.event(vec![tuning], length);
+                    }
+                }
+                _ => {
+                    self.warn(ImportWarningKind::Invalid, &c.to_string(), "unknown symbol");
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    /// Stacked notes `[CEG]2`
+    fn stack(&mut self, chars: &[char], i: &mut usize) {
+        *i += 1;
+        let mut tunings = vec![];
+        let mut inner = None;
+        let mut tie = false;
+        while let Some(&c) = chars.get(*i) {
+            if c == ']' {
+                *i += 1;
+                break;
+            }
+            match pitch(chars, i) {
+                Some(pitch) => {
+                    let length = length(chars, i);
+                    inner.get_or_insert(length);
+                    tunings.extend(self.tuning(pitch));
+                }
+                None => {
+                    tie |= c == '-';
+                    *i += 1;
+                }
+            }
+        }
+        let length = length(chars, i) * inner.unwrap_or(1.0);
+        self.event(tunings, length);
+        if tie {
+            let voice = self.voice();
+            voice.tie = true;
+            if let Some(event) = voice.bar.events.last_mut() {
+                event.tie = true;
+            }
+        }
+    }
+
+    fn bar_line(&mut self, chars: &[char], i: &mut usize) {
+        let start = *i;
+        while let Some(&c) = chars.get(*i) {
+            let part = match c {
+                '|' | ':' => true,
+                ']' => *i > start,
+                '[' => *i == start && chars.get(*i + 1) == Some(&'|'),
+                _ => false,
+            };
+            if !part {
+                break;
+            }
+            *i += 1;
+        }
+        let text = chars[start..*i].iter().collect::<String>();
+        let ending = match (chars.get(*i), chars.get(*i + 1)) {
+            (Some(d), _) if d.is_ascii_digit() => Some(numbers(chars, i)),
+            (Some('['), Some(d)) if d.is_ascii_digit() => {
+                *i += 1;
+                Some(numbers(chars, i))
+            }
+            _ => None,
+        };
+
+        let repeat_end = text.starts_with(':');
+        let repeat_start = text.ends_with(':');
+        let double = text.contains("||") || text.contains("|]") || text.contains("[|");
+        let voice = self.voice();
+        voice.close_bar();
+        voice.overlay = false;
+        if repeat_end {
+            if let Some(last) = voice.bars.len().checked_sub(1) {
+                voice.marks.push((last, BarMark::RepeatEnd(2)));
+            }
+        }
+        if repeat_end || repeat_start || double {
+            voice.volta = None;
+        }
+        if repeat_start {
+            voice.marks.push((voice.bars.len(), BarMark::RepeatStart));
+        }
+        if ending.is_some() {
+            voice.volta = ending;
+        }
+    }
+
+    /// Chord symbol or annotation in quotes
+    fn symbol(&mut self, text: &str) {
+        if text.starts_with(['^', '_', '<', '>', '@']) {
+            return self.warn(
+                ImportWarningKind::Unsupported,
+                "annotation",
+                "text annotations are not kept",
+            );
+        }
+        match chord_symbol(text) {
+            Some((chord, approximated)) => {
+                if approximated {
+                    self.warn(
+                        ImportWarningKind::Approximated,
+                        text,
+                        "read as the closest chord quality",
+                    );
+                }
+                let voice = self.voice();
+                if !voice.overlay {
+                    voice.bar.chords.push((voice.bar.position, chord));
+                }
+            }
+            None => self.warn(ImportWarningKind::Invalid, text, "not a chord symbol"),
+        }
+    }
+
+    fn decoration(&mut self, name: &str) {
+        let voice = self.voice();
+        let bar = voice.bars.len();
+        let expression = &mut voice.expression;
+        let mark = match name {
+            "." | "staccato" => {
+                expression.articulations.push(Articulation::Staccato);
+                None
+            }
+            "tenuto" => {
+                expression.articulations.push(Articulation::Tenuto);
+                None
+            }
+            "L" | "accent" | ">" | "emphasis" => {
+                expression.articulations.push(Articulation::Accent);
+                None
+            }
+            "marcato" | "^" => {
+                expression.articulations.push(Articulation::Marcato);
+                None
+            }
+            "H" | "fermata" => {
+                expression.fermata = true;
+                None
+            }
+            "crescendo(" | "<(" => {
+                expression.hairpin = Some(HairpinMark::Start(Hairpin::Crescendo));
+                None
+            }
+            "diminuendo(" | ">(" => {
+                expression.hairpin = Some(HairpinMark::Start(Hairpin::Diminuendo));
+                None
+            }
+            "crescendo)" | "<)" | "diminuendo)" | ">)" => {
+                expression.hairpin = Some(HairpinMark::Stop);
+                None
+            }
+            "S" | "segno" => Some(BarMark::Segno),
+            "O" | "coda" => Some(BarMark::Coda),
+            "dacoda" => Some(BarMark::ToCoda),
+            "fine" => Some(BarMark::Fine),
+            "D.C." | "dacapo" => Some(BarMark::Jump(Jump::DaCapo)),
+            "D.C.alfine" => Some(BarMark::Jump(Jump::DaCapoAlFine)),
+            "D.C.alcoda" => Some(BarMark::Jump(Jump::DaCapoAlCoda)),
+            "D.S." => Some(BarMark::Jump(Jump::DalSegno)),
+            "D.S.alfine" => Some(BarMark::Jump(Jump::DalSegnoAlFine)),
+            "D.S.alcoda" => Some(BarMark::Jump(Jump::DalSegnoAlCoda)),
+            name => match Dynamic::from_symbol(name) {
+                Some(dynamic) => {
+                    expression.dynamic = Some(dynamic);
+                    None
+                }
+                None => {
+                    return self.warn(
+                        ImportWarningKind::Unsupported,
+                        &format!("!{name}!"),
+                        "decoration is not kept",
+                    )
+                }
+            },
+        };
+        if let Some(mark) = mark {
+            voice.marks.push((bar, mark));
+        }
+    }
+
+    /// Spelled pitch of a note, through the accidentals of the bar and the key
+    fn tuning(&mut self, (letter, alter, octave): (char, Option<i8>, i8)) -> Option<Tuning> {
+        let key = self.key.alter(letter);
+        let voice = self.voice();
+        let alter = match alter {
+            Some(alter) => {
+                voice.altered.insert((letter, octave), alter);
+                alter
+            }
+            None => voice.altered.get(&(letter, octave)).copied().unwrap_or(key),
+        };
+        let tuning = Tuning::from_spelling(letter, alter, octave)
+            .ok()
+            .filter(|_| alter.abs() <= 2 && (-1..=9).contains(&octave));
+        if tuning.is_none() {
+            self.warn(ImportWarningKind::Invalid, "note", "pitch out of range");
+        }
+        tuning
+    }
+
+    fn event(&mut self, pitches: Vec<Tuning>, multiplier: f32) {
+        let unit = self.unit();
+        let voice = self.voice();
+        if voice.overlay {
+            return;
+        }
+        let grace = voice.grace;
+        let mut length = unit * multiplier;
+        let mut tuplet = None;
+        if grace.is_none() {
+            length *= voice.broken.take().unwrap_or(1.0);
+            if let Some((p, q, left)) = voice.tuplet {
+                tuplet = Some((p, q));
+                voice.tuplet = (left > 1).then_some((p, q, left - 1));
+            }
+        }
+        let continued = grace.is_none() && std::mem::take(&mut voice.tie);
+        let note = match pitches.iter().copied().max_by_key(|t| pitch_height(*t)) {
+            Some(top) => {
+                let expression = match grace {
+                    Some(_) => Expression::default(),
+                    None => std::mem::take(&mut voice.expression),
+                };
+                Note::new(top).with_expression(Expression {
+                    grace,
+                    ..expression
+                })
+            }
+            None => Note::rest(Duration::new(DurationBase::Quarter)),
+        };
+        if !pitches.is_empty() && grace.is_none() && !continued {
+            voice.line.push((voice.bars.len(), voice.bar.events.len()));
+        }
+        let event = Event {
+            pitches,
+            length,
+            tuplet,
+            note,
+            tie: false,
+        };
+        if grace.is_none() {
+            voice.bar.position += event.actual_length();
+        }
+        voice.bar.events.push(event);
+    }
+
+    /// `w:` line under the last music line of the voice, each one a new verse
+    fn lyrics(&mut self, text: &str) {
+        let voice = self.voice();
+        voice.verse = voice.verse.saturating_add(1);
+        let verse = voice.verse;
+        let text = text.replace("\\-", "\u{0}").replace('|', " ");
+        let targets = voice.line.clone();
+        for (lyric, (bar, event)) in Lyric::parse_line(&text, verse).into_iter().zip(targets) {
+            let Some(mut lyric) = lyric else {
+                continue;
+            };
+            lyric.text = lyric.text.replace('~', " ").replace('\u{0}', "-");
+            if let Some(event) = voice.event_mut(bar, event) {
+                event.note = event.note.clone().with_lyric(lyric);
+            }
+        }
+    }
+
+    fn current_meter(&self) -> TimeSignature {
+        self.voices
+            .get(self.current)
+            .and_then(|v| v.meter)
+            .or(self.meter)
+            .unwrap_or(TimeSignature::new(4, DurationBase::Quarter))
+    }
+
+    fn finish<const N: usize>(mut self) -> Result<AbcTune<N>, MusicError> {
+        if !self.body && self.voices.is_empty() {
+            return Err(MusicError::ParseError("no ABC tune found".into()));
+        }
+        for voice in &mut self.voices {
+            voice.close_bar();
+        }
+        let meter = self
+            .meter
+            .unwrap_or(TimeSignature::new(4, DurationBase::Quarter));
+        let mut score = Score::<N>::new()
+            .with_meter(meter)
+            .with_key_signature(self.key)
+            .with_tempo(self.tempo.unwrap_or(120.0) / meter.beat_type().in_quarters());
+
+        for voice in self.voices.iter().skip(N) {
+            self.warnings.push(
+                ImportWarningKind::Unsupported,
+                &voice.id,
+                "",
+                "V",
+                format!("the score has {N} tracks"),
+            );
+        }
+        let mut tracks = vec![];
+        let mut chord_symbols = vec![];
+        for (track, voice) in self.voices.iter().take(N).enumerate() {
+            let voice_meter = voice.meter.unwrap_or(meter);
+            if &voice_meter != score.time_signature() {
+                score.set_track_time_signature(track, voice_meter)?;
+            }
+            let (measures, symbols) = voice.measures(track, &voice_meter, &mut self.warnings);
+            tracks.push(measures);
+            chord_symbols.extend(symbols);
+        }
+
+        // Chord symbols over the melody also fill the first free track, each chord lasting
+        // until the next one
+        if let Some(first) = self.voices.first().filter(|_| tracks.len() < N) {
+            if chord_symbols.iter().any(|s| s.track == 0) {
+                let mut last = None;
+                let chords = first
+                    .bars
+                    .iter()
+                    .map(|bar| match bar.chords.is_empty() {
+                        true => last
+                            .clone()
+                            .map_or(Measure::Rest, |chord| Measure::Chords(vec![chord])),
+                        false => {
+                            last = bar.chords.last().map(|(_, c)| c.clone());
+                            Measure::Chords(bar.chords.iter().map(|(_, c)| c.clone()).collect())
+                        }
+                    })
+                    .collect();
+                tracks.push(chords);
+            }
+        }
+
+        if let Some(first) = self.voices.first() {
+            for (measure, mark) in &first.marks {
+                score.mark(*measure, mark.clone());
+            }
+        }
+        let length = tracks.iter().map(Vec::len).max().unwrap_or(0);
+        for index in 0..length {
+            score.push_measures(std::array::from_fn(|track| {
+                tracks
+                    .get(track)
+                    .and_then(|measures| measures.get(index).cloned())
+                    .unwrap_or(Measure::Rest)
+            }));
+        }
+
+        Ok(AbcTune {
+            score,
+            reference: self.reference,
+            title: self.title,
+            composer: self.composer,
+            chord_symbols,
+            warnings: self.warnings.list,
+        })
+    }
+}
+
+/// Accidentals, letter and octave marks of a note, e.g. `^c'`. Nothing is consumed when
+/// there is no note.
+fn pitch(chars: &[char], i: &mut usize) -> Option<(char, Option<i8>, i8)> {
+    let mut j = *i;
+    let mut alter: Option<i8> = None;
+    while let Some(&c) = chars.get(j) {
+        alter = match c {
+            '^' => Some(alter.unwrap_or(0).max(0).saturating_add(1)),
+            '_' => Some(alter.unwrap_or(0).min(0).saturating_sub(1)),
+            '=' => Some(0),
+            _ => break,
+        };
+        j += 1;
+    }
+    let letter = *chars.get(j).filter(|c| "ABCDEFGabcdefg".contains(**c))?;
+    j += 1;
+    let mut octave: i8 = if letter.is_ascii_uppercase() { 4 } else { 5 };
+    while let Some(&c) = chars.get(j) {
+        match c {
+            '\'' => octave = octave.saturating_add(1),
+            ',' => octave = octave.saturating_sub(1),
+            _ => break,
+        }
+        j += 1;
+    }
+    *i = j;
+    Some((letter.to_ascii_uppercase(), alter, octave))
+}
+
+/// Length multiplier after a note: `2`, `3/2`, `/`, `//`, `/4`
+fn length(chars: &[char], i: &mut usize) -> f32 {
+    let mut value = number(chars, i).unwrap_or(1) as f32;
+    while chars.get(*i) == Some(&'/') {
+        *i += 1;
+        value /= number(chars, i).unwrap_or(2).max(1) as f32;
+    }
+    value
+}
+
+fn number(chars: &[char], i: &mut usize) -> Option<u32> {
+    let start = *i;
+    while chars.get(*i).is_some_and(char::is_ascii_digit) {
+        *i += 1;
+    }
+    chars[start..*i].iter().collect::<String>().parse().ok()
+}
+
+/// Ending numbers after a bar line: `1`, `1,2`, `1-3`
+fn numbers(chars: &[char], i: &mut usize) -> Vec<u8> {
+    let mut numbers = vec![];
+    while let Some(first) = number(chars, i) {
+        let mut last = first;
+        if chars.get(*i) == Some(&'-') {
+            *i += 1;
+            last = number(chars, i).unwrap_or(first);
+        }
+        numbers.extend((first..=last.max(first)).map(|n| n.min(255) as u8));
+        if chars.get(*i) != Some(&',') {
+            break;
+        }
+        *i += 1;
+    }
+    numbers
+}
+
+/// Fraction of a whole note, e.g. `1/8`
+fn fraction(value: &str) -> Option<f32> {
+    let (numerator, denominator) = value.trim().split_once('/')?;
+    let numerator = numerator.trim().parse::<f32>().ok()?;
+    let denominator = denominator.trim().parse::<f32>().ok()?;
+    (denominator > 0.0).then_some(numerator / denominator)
+}
+
+/// `6/8`, `C`, `C|` or `2+3+2/8`. `none` (free meter) has no time signature.
+pub(crate) fn meter(value: &str) -> Option<TimeSignature> {
+    match value {
+        "C" => return Some(TimeSignature::new(4, DurationBase::Quarter)),
+        "C|" => return Some(TimeSignature::new(2, DurationBase::Half)),
+        _ => {}
+    }
+    let (top, bottom) = value.split_once('/')?;
+    let groups = top
+        .trim_matches(['(', ')', ' '])
+        .split('+')
+        .map(|g| g.trim().parse::<u8>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let beat_type = DurationBase::from_whole(1.0 / bottom.trim().parse::<f32>().ok()?).ok()?;
+    let ts = TimeSignature::new(groups.iter().sum(), beat_type);
+    match groups.len() {
+        1 => Some(ts),
+        _ => ts.with_grouping(&groups).ok(),
+    }
+}
+
+/// Quarter notes per minute of `1/4=120`, or of `120` units per minute
+fn tempo(value: &str, unit: f32) -> Option<f32> {
+    // Quoted text such as "Allegro" is left out
+    let value = value
+        .split('"')
+        .enumerate()
+        .filter(|(i, _)| i % 2 == 0)
+        .map(|(_, part)| part)
+        .collect::<String>();
+    match value.split_once('=') {
+        Some((beats, bpm)) => {
+            let whole = beats
+                .split_whitespace()
+                .map(fraction)
+                .sum::<Option<f32>>()?;
+            Some(bpm.trim().parse::<f32>().ok()? * whole * 4.0)
+        }
+        None => Some(value.trim().parse::<f32>().ok()? * unit),
+    }
+}
+
+/// Key signature of a `K:` field and whether explicit accidentals were left out
+fn key(value: &str) -> Option<(KeySignature, bool)> {
+    let words = value
+        .split_whitespace()
+        .filter(|w| !w.contains('='))
+        .collect::<Vec<_>>();
+    let extra = words.iter().skip(1).any(|w| w.starts_with(['^', '_']));
+    let Some(first) = words.first() else {
+        return Some((KeySignature::default(), false));
+    };
+    match *first {
+        "none" | "HP" => return Some((KeySignature::default(), extra)),
+        "Hp" => return Some((KeySignature::new(2), extra)),
+        _ => {}
+    }
+    let letter = first.chars().next().filter(|c| ('A'..='G').contains(c))?;
+    let rest = &first[1..];
+    let (alter, rest) = match rest.chars().next() {
+        Some('#') => (1, &rest[1..]),
+        Some('b') => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    let mode = match (rest, words.get(1)) {
+        ("", Some(word)) if !word.starts_with(['^', '_']) => word.to_ascii_lowercase(),
+        _ => rest.to_ascii_lowercase(),
+    };
+    let scale_type = match mode.get(..3).unwrap_or(&mode) {
+        "" | "maj" | "ion" => ScaleType::Major,
+        "m" | "min" | "aeo" => ScaleType::NaturalMinor,
+        "dor" => ScaleType::Dorian,
+        "phr" => ScaleType::Phrygian,
+        "lyd" => ScaleType::Lydian,
+        "mix" => ScaleType::Mixolydian,
+        "loc" => ScaleType::Locrian,
+        _ => return None,
+    };
+    let tonic = Tuning::from_spelling(letter, alter, 4).ok()?;
+    let scale = Scale::new(tonic, scale_type).ok()?;
+    Some((KeySignature::from_scale(&scale).ok()?, extra))
+}
+
+/// Chord of a symbol and whether it is only the closest one, e.g. `G7/B` or `D9`
+pub(crate) fn chord_symbol(text: &str) -> Option<(Chord, bool)> {
+    let (text, bass) = match text.split_once('/') {
+        Some((chord, _)) => (chord.trim(), true),
+        None => (text.trim(), false),
+    };
+    let mut chars = text.chars().peekable();
+    let root = Tuning::take(&mut chars).ok()?;
+    let (quality, approximated) = match chars.collect::<String>().as_str() {
+        "6" => (ChordQuality::Major6, false),
+        "o" => (ChordQuality::Diminished, false),
+        "o7" | "dim7" => (ChordQuality::Diminished7, false),
+        "+" => (ChordQuality::Augmented, false),
+        "+7" | "7#5" => (ChordQuality::Augmented7, false),
+        "m7b5" | "ø" | "ø7" | "Ø7" => (ChordQuality::HalfDiminished7, false),
+        "mmaj7" | "m(maj7)" => (ChordQuality::MinorMajor7, false),
+        "sus" => (ChordQuality::Suspended4, false),
+        "9" | "11" | "13" | "7b9" | "7#9" => (ChordQuality::Dominant7, true),
+        "maj9" | "M9" => (ChordQuality::Major7, true),
+        "m9" | "m11" => (ChordQuality::Minor7, true),
+        "5" | "add9" | "6/9" | "69" => (ChordQuality::Major, true),
+        "madd9" => (ChordQuality::Minor, true),
+        quality => (ChordQuality::from_str(quality).ok()?, false),
+    };
+    Some((Chord::new(root, quality).ok()?, approximated || bass))
+}
+
+/// Duration of a written length in quarters, as a plain or dotted value
+pub(crate) fn notated(quarters: f32, tuplet: Option<(u8, u8)>) -> Option<Duration> {
+    (0..=2).find_map(|dots| {
+        let base = DurationBase::from_quarters(quarters / (2.0 - 0.5f32.powi(dots))).ok()?;
+        let duration = Duration::new(base).dotted(dots as u8);
+        match tuplet {
+            Some((p, q)) => Tuplet::new(p, q, base)
+                .ok()
+                .map(|t| duration.with_tuplet(t)),
+            None => Some(duration),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TUNE: &str = "\
+%abc-2.1
+X:7
+T:Morning Dew
+T:The Second Title
+C:Trad.
+M:4/4
+L:1/8
+Q:1/4=100
+K:G
+|:\"G\"GABc d2 e2|\"D7\"f2 d2 (3efg a2|1 g4 z4:|2 !fermata!g8|]
+w:one two three four five six se-ven eight nine ten _
+
+X:8
+T:Another
+K:D
+DEF|
+";
+
+    #[test]
+    fn test_import_tune() {
+        let tunes = Abc::tunes(TUNE);
+        assert_eq!(tunes.len(), 2);
+        let tune = Abc::import::<2>(tunes[0]).unwrap();
+        assert_eq!(tune.reference, Some(7));
+        assert_eq!(tune.title.as_deref(), Some("Morning Dew"));
+        assert_eq!(tune.composer.as_deref(), Some("Trad."));
+
+        let score = &tune.score;
+        assert_eq!(score.key_signature().fifths(), 1);
+        assert_eq!(score.tempo(), 100.0);
+        assert_eq!(score.measure_count(), 4);
+        assert_eq!(score.marks(0), &[BarMark::RepeatStart]);
+        assert!(score.marks(2).contains(&BarMark::Volta(vec![1])));
+        assert!(score.marks(2).contains(&BarMark::RepeatEnd(2)));
+        assert_eq!(score.marks(3), &[BarMark::Volta(vec![2])]);
+
+        let Measure::Note(notes) = &score.get_tracks()[0].get_measures()[1] else {
+            panic!("melody measure expected");
+        };
+        // F is sharp in G major
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('F', 1, 5));
+        assert_eq!(notes[0].duration(), Duration::new(DurationBase::Quarter));
+        let triplet = notes[2].duration().tuplet.unwrap();
+        assert_eq!((triplet.actual_notes, triplet.base_notes), (3, 2));
+        assert_eq!(notes[0].lyric(1).unwrap().text, "se");
+        assert_eq!(notes[1].lyric(1).unwrap().syllabic, Syllabic::End);
+        assert!(notes[4].lyric(1).unwrap().extend);
+
+        // Chord symbols over the melody, and the chord track made of them
+        assert_eq!(tune.chord_symbols.len(), 2);
+        assert_eq!(tune.chord_symbols[1].chord.to_string(), "D7");
+        let chords = score.get_tracks()[1].get_measures();
+        assert!(matches!(&chords[2], Measure::Chords(c) if c[0].to_string() == "D7"));
+
+        let Measure::Note(last) = &score.get_tracks()[0].get_measures()[3] else {
+            panic!("melody measure expected");
+        };
+        assert!(last[0].expression().fermata);
+        assert!(last[0].lyrics().is_empty());
+    }
+
+    #[test]
+    fn test_import_approximations() {
+        let tune = Abc::import::<1>(
+            "X:1\nM:3/4\nL:1/4\nK:Am\n\"Am\"[ACE]3|c-c/ c/>B/ !trill!A/|Z2|^c c =c d &e|\n",
+        )
+        .unwrap();
+        let measures = tune.score.get_tracks()[0].get_measures();
+        assert_eq!(measures.len(), 5);
+        assert!(matches!(&measures[0], Measure::Chords(c) if c.len() == 1));
+
+        // The tied quarter and eighth merge into a dotted quarter, the broken rhythm makes
+        // a dotted eighth and a sixteenth
+        let Measure::Note(notes) = &measures[1] else {
+            panic!("melody measure expected");
+        };
+        assert_eq!(
+            notes[0].duration(),
+            Duration::new(DurationBase::Quarter).dotted(1)
+        );
+        assert_eq!(
+            notes[1].duration(),
+            Duration::new(DurationBase::Eighth).dotted(1)
+        );
+        assert_eq!(notes[2].duration(), Duration::new(DurationBase::Sixteenth));
+        assert!(matches!(measures[2], Measure::Rest));
+        assert!(matches!(measures[3], Measure::Rest));
+
+        // Accidentals last until the bar line, the overlay is skipped
+        let Measure::Note(notes) = &measures[4] else {
+            panic!("melody measure expected");
+        };
+        let letters = notes
+            .iter()
+            .map(|n| n.tuning().unwrap().spelling())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            letters,
+            vec![('C', 1, 5), ('C', 1, 5), ('C', 0, 5), ('D', 0, 5)]
+        );
+
+        let elements = tune
+            .warnings
+            .iter()
+            .map(|w| w.element.as_str())
+            .collect::<Vec<_>>();
+        assert!(elements.contains(&"!trill!"));
+        assert!(elements.contains(&"&"));
+
+        // Runs of accidentals, octave marks and verses do not overflow
+        let degenerate = format!(
+            "X:1\nK:C\n{}c{} d|\n{}",
+            "^".repeat(300),
+            "'".repeat(300),
+            "w:la\n".repeat(300)
+        );
+        let tune = Abc::import::<1>(&degenerate).unwrap();
+        assert!(tune.warnings.iter().any(|w| w.element == "note"));
+    }
+
+    /// Notes of a melody measure
+    fn melody<const N: usize>(tune: &AbcTune<N>, track: usize, measure: usize) -> Vec<Note> {
+        match &tune.score.get_tracks()[track].get_measures()[measure] {
+            Measure::Note(notes) => notes.clone(),
+            _ => panic!("melody measure expected"),
+        }
+    }
+
+    fn spellings(notes: &[Note]) -> Vec<(char, i8, i8)> {
+        notes
+            .iter()
+            .map(|n| n.tuning().unwrap().spelling())
+            .collect()
+    }
+
+    #[test]
+    fn test_key_modes_and_accidentals() {
+        // A dorian has the signature of G major
+        let tune = Abc::import::<1>("X:1\nL:1/4\nK:Ador\n=f f g f|f a b c|\n").unwrap();
+        assert_eq!(tune.score.key_signature().fifths(), 1);
+        // The natural holds for the rest of the bar, the next bar is back to the key
+        assert_eq!(
+            spellings(&melody(&tune, 0, 0)),
+            vec![('F', 0, 5), ('F', 0, 5), ('G', 0, 5), ('F', 0, 5)]
+        );
+        assert_eq!(
+            melody(&tune, 0, 1)[0].tuning().unwrap().spelling(),
+            ('F', 1, 5)
+        );
+
+        for (key, fifths) in [("D mix", 1), ("Bb", -2), ("F#m", 3), ("E phrygian", 0)] {
+            let tune = Abc::import::<1>(&format!("X:1\nK:{key}\nC|\n")).unwrap();
+            assert_eq!(tune.score.key_signature().fifths(), fifths, "K:{key}");
+        }
+    }
+
+    #[test]
+    fn test_default_unit_length() {
+        // A sixteenth below 3/4, an eighth from 3/4 on
+        for (meter, base) in [
+            ("2/4", DurationBase::Sixteenth),
+            ("3/4", DurationBase::Eighth),
+            ("6/8", DurationBase::Eighth),
+        ] {
+            let tune = Abc::import::<1>(&format!("X:1\nM:{meter}\nK:C\nCD|\n")).unwrap();
+            assert_eq!(
+                melody(&tune, 0, 0)[0].duration(),
+                Duration::new(base),
+                "M:{meter}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_broken_rhythm_tuplets_and_chords() {
+        let tune = Abc::import::<1>("X:1\nM:4/4\nL:1/8\nK:C\nA>B A<B (3cde [CEG]2|\n").unwrap();
+        let notes = melody(&tune, 0, 0);
+        let lengths = notes.iter().map(|n| n.duration()).collect::<Vec<_>>();
+        let eighth = Duration::new(DurationBase::Eighth);
+        let sixteenth = Duration::new(DurationBase::Sixteenth);
+        assert_eq!(
+            lengths[..4],
+            [eighth.dotted(1), sixteenth, sixteenth, eighth.dotted(1)]
+        );
+        for note in &notes[4..7] {
+            let tuplet = note.duration().tuplet.unwrap();
+            assert_eq!((tuplet.actual_notes, tuplet.base_notes), (3, 2));
+        }
+        // The chord keeps its top note
+        assert_eq!(notes[7].tuning().unwrap().spelling(), ('G', 0, 4));
+        assert_eq!(notes[7].duration(), Duration::new(DurationBase::Quarter));
+        assert!(tune
+            .warnings
+            .iter()
+            .any(|w| w.element == "[" && w.kind == ImportWarningKind::Approximated));
+    }
+
+    #[test]
+    fn test_repeats_and_endings() {
+        let tune = Abc::import::<1>("X:1\nL:1/4\nK:C\n|:C4|[1 D4:|[2 E4|]\n").unwrap();
+        let score = &tune.score;
+        assert_eq!(score.measure_count(), 3);
+        assert_eq!(score.marks(0), &[BarMark::RepeatStart]);
+        assert!(score.marks(1).contains(&BarMark::Volta(vec![1])));
+        assert!(score.marks(1).contains(&BarMark::RepeatEnd(2)));
+        assert_eq!(score.marks(2), &[BarMark::Volta(vec![2])]);
+    }
+
+    #[test]
+    fn test_voices_and_grouped_meter() {
+        let tune =
+            Abc::import::<2>("X:1\nM:(3+2+2)/8\nL:1/8\nK:C\nV:1\nCDEFGAB|\nV:2\nC,7|\n").unwrap();
+        assert_eq!(tune.score.time_signature().grouping(), vec![3, 2, 2]);
+        assert_eq!(tune.score.track_time_signature(1).grouping(), vec![3, 2, 2]);
+        assert_eq!(melody(&tune, 0, 0).len(), 7);
+        let low = melody(&tune, 1, 0);
+        assert_eq!(spellings(&low), vec![('C', 0, 3)]);
+        assert_eq!(
+            low[0].duration(),
+            Duration::new(DurationBase::Half).dotted(2)
+        );
+
+        // The second voice has no track in a score of one
+        let tune = Abc::import::<1>("X:1\nK:C\nV:1\nC|\nV:2\nE|\n").unwrap();
+        assert!(tune.warnings.iter().any(|w| w.element == "V"));
+    }
+
+    #[test]
+    fn test_ties() {
+        let tune = Abc::import::<1>("X:1\nM:4/4\nL:1/8\nK:C\nc2-c d3-d2|\n").unwrap();
+        let notes = melody(&tune, 0, 0);
+        // A quarter and an eighth make a dotted quarter, five eighths have no single value
+        let dotted = Duration::new(DurationBase::Quarter).dotted(1);
+        assert_eq!(notes.len(), 3);
+        assert_eq!(notes[0].duration(), dotted);
+        assert_eq!(notes[1].duration(), dotted);
+        assert_eq!(notes[2].duration(), Duration::new(DurationBase::Quarter));
+        let tie = tune.warnings.iter().find(|w| w.element == "-").unwrap();
+        assert_eq!(tie.kind, ImportWarningKind::Approximated);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let c = |letter, alter, octave| Tuning::from_spelling(letter, alter, octave).unwrap();
+        let eighth = Duration::new(DurationBase::Eighth);
+        let triplet = eighth.with_tuplet(Tuplet::new(3, 2, DurationBase::Eighth).unwrap());
+        let mut score = Score::<1>::new()
+            .with_tempo(90.0)
+            .with_key_signature(KeySignature::new(-1));
+        score.push_measures([Measure::Note(vec![
+            Note::new(c('B', -1, 4)).with_duration(eighth.dotted(1)),
+            Note::new(c('C', 0, 5)).with_duration(Duration::new(DurationBase::Sixteenth)),
+            Note::new(c('D', 0, 5)).with_duration(triplet),
+            Note::new(c('E', 0, 5)).with_duration(triplet),
+            Note::new(c('F', 1, 5)).with_duration(triplet),
+            Note::rest(Duration::new(DurationBase::Quarter)),
+            Note::new(c('B', 0, 3))
+                .with_duration(Duration::new(DurationBase::Quarter))
+                .with_lyric(Lyric::new("end")),
+        ])]);
+        let chord = Chord::new(c('F', 0, 4), ChordQuality::Dominant7).unwrap();
+        score.push_measures([Measure::Chords(vec![chord])]);
+        score.mark(0, BarMark::RepeatStart);
+        score.mark(0, BarMark::repeat_end());
+
+        let text = Abc::new().with_title("Round").export(&score);
+        assert!(text.contains("K:F\n"));
+        assert!(text.contains("|:B3/2c/ (3de^f z2 =B,2:|"), "{text}");
+        assert!(text.contains("\"F7\"[FAc_e]8|]"), "{text}");
+        assert!(text.contains("w:* * * * * end"), "{text}");
+
+        let tune = Abc::import::<1>(&text).unwrap();
+        assert_eq!(tune.title.as_deref(), Some("Round"));
+        assert_eq!(tune.score.tempo(), 90.0);
+        assert_eq!(tune.score.marks(0), score.marks(0));
+        let measures = tune.score.get_tracks()[0].get_measures();
+        let (Measure::Note(read), Measure::Note(written)) =
+            (&measures[0], &score.get_tracks()[0].get_measures()[0])
+        else {
+            panic!("melody measures expected");
+        };
+        for (read, written) in read.iter().zip(written) {
+            assert_eq!(read.is_rest(), written.is_rest());
+            assert_eq!(read.duration(), written.duration());
+            if !read.is_rest() {
+                assert_eq!(
+                    read.tuning().unwrap().spelling(),
+                    written.tuning().unwrap().spelling()
+                );
+            }
+        }
+        assert_eq!(read[6].lyric(1).unwrap().text, "end");
+        assert!(matches!(&measures[1], Measure::Chords(c) if c[0].to_string() == "F7"));
+    }
+}
@@ -0,0 +1,672 @@
+//! ChordPro lead sheets
+//!
+//! Reads ChordPro songs into a `LeadSheet`: metadata directives (`{title}`, `{key}`,
+//! `{capo}`, …), `[Am]` chords inline with the lyrics, comments, chorus references and
+//! sections (`{start_of_chorus}` … `{end_of_chorus}`, tab and grid blocks kept verbatim).
+//!
+//! Writes them back as ChordPro or as chords over lyrics, transposed and renamed for a
+//! capo, with a diagram of every chord for a `StringedFretboard`.
+
+use super::abc::chord_symbol;
+use crate::{
+    Chord, ChordFingeringGenerator, FingeringGenerator, FretboardDiagramGenerator, MusicError,
+    StringedFretboard,
+};
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Chord symbol of a lead sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetChord {
+    /// As written, e.g. `G7/B`
+    pub symbol: String,
+    /// Chord of the symbol, its closest one for symbols `Chord` cannot spell
+    pub chord: Option<Chord>,
+}
+
+impl SheetChord {
+    pub fn new(symbol: impl Into<String>) -> Self {
+        let symbol = symbol.into();
+        let chord = Chord::from_str(&symbol)
+            .ok()
+            .or_else(|| chord_symbol(&symbol).map(|(chord, _)| chord));
+        SheetChord { symbol, chord }
+    }
+
+    /// Symbol moved by `semitones`, the root and bass spelled with flats or sharps
+    pub fn transposed(&self, semitones: i8, flats: bool) -> Self {
+        let transpose = |text: &str| match root(text) {
+            Some((pitch, rest)) => pitch_name(pitch as i32 + semitones as i32, flats) + rest,
+            None => text.to_string(),
+        };
+        let symbol = match self.symbol.split_once('/') {
+            Some((chord, bass)) => format!("{}/{}", transpose(chord), transpose(bass)),
+            None => transpose(&self.symbol),
+        };
+        SheetChord::new(symbol)
+    }
+}
+
+/// Lyrics following a chord, up to the next one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChordedText {
+    pub chord: Option<SheetChord>,
+    pub text: String,
+}
+
+/// Line of a section
+#[derive(Debug, Clone, PartialEq)]
+pub enum SheetLine {
+    /// Lyrics with inline chords
+    Lyrics(Vec<ChordedText>),
+    Comment(String),
+    /// Repeat of the chorus, with an optional label
+    Chorus(Option<String>),
+    /// Verbatim text of tab and grid sections
+    Literal(String),
+    Empty,
+}
+
+/// Kind of a section
+#[derive(Debug, Clone, PartialEq)]
+pub enum SectionKind {
+    /// Lines outside any environment
+    Plain,
+    Verse,
+    Chorus,
+    Bridge,
+    Tab,
+    Grid,
+    Other(String),
+}
+
+impl SectionKind {
+    fn name(&self) -> &str {
+        match self {
+            SectionKind::Plain => "",
+            SectionKind::Verse => "verse",
+            SectionKind::Chorus => "chorus",
+            SectionKind::Bridge => "bridge",
+            SectionKind::Tab => "tab",
+            SectionKind::Grid => "grid",
+            SectionKind::Other(name) => name,
+        }
+    }
+
+    fn from_name(name: &str) -> Self {
+        match name {
+            "verse" | "v" => SectionKind::Verse,
+            "chorus" | "c" => SectionKind::Chorus,
+            "bridge" | "b" => SectionKind::Bridge,
+            "tab" | "t" => SectionKind::Tab,
+            "grid" | "g" => SectionKind::Grid,
+            name => SectionKind::Other(name.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub label: Option<String>,
+    pub lines: Vec<SheetLine>,
+}
+
+/// Song of a ChordPro file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LeadSheet {
+    pub title: Option<String>,
+    pub subtitles: Vec<String>,
+    pub artist: Option<String>,
+    pub composer: Option<String>,
+    /// Key of the chords as written, e.g. `G` or `Em`
+    pub key: Option<String>,
+    /// Fret of the capo the chords are played with
+    pub capo: u8,
+    pub tempo: Option<f32>,
+    pub time: Option<String>,
+    /// Other directives with a value, in order
+    pub metadata: Vec<(String, String)>,
+    pub sections: Vec<Section>,
+}
+
+impl LeadSheet {
+    /// Distinct chords in order of appearance
+    pub fn chords(&self) -> Vec<&SheetChord> {
+        let mut chords: Vec<&SheetChord> = vec![];
+        let lines = self.sections.iter().flat_map(|s| &s.lines);
+        for line in lines {
+            if let SheetLine::Lyrics(segments) = line {
+                for chord in segments.iter().filter_map(|s| s.chord.as_ref()) {
+                    if !chords.iter().any(|c| c.symbol == chord.symbol) {
+                        chords.push(chord);
+                    }
+                }
+            }
+        }
+        chords
+    }
+}
+
+/// ChordPro reader and writer, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct ChordPro {
+    transpose: i8,
+    capo: Option<u8>,
+    diagrams: Option<StringedFretboard>,
+}
+
+impl ChordPro {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the song by `semitones`
+    pub fn with_transpose(self, semitones: i8) -> Self {
+        ChordPro {
+            transpose: semitones,
+            ..self
+        }
+    }
+
+    /// Rename the chords for a capo at `fret`, keeping the song's pitch
+    pub fn with_capo(self, fret: u8) -> Self {
+        ChordPro {
+            capo: Some(fret),
+            ..self
+        }
+    }
+
+    /// Draw a diagram of every chord on `fretboard` after the rendered song
+    pub fn with_diagrams(self, fretboard: StringedFretboard) -> Self {
+        ChordPro {
+            diagrams: Some(fretboard),
+            ..self
+        }
+    }
+
+    /// Read a ChordPro song
+    pub fn parse(text: &str) -> Result<LeadSheet, MusicError> {
+        let mut sheet = LeadSheet::default();
+        let mut section = Section {
+            kind: SectionKind::Plain,
+            label: None,
+            lines: vec![],
+        };
+        let push = |sheet: &mut LeadSheet, section: &mut Section, kind, label| {
+            let next = Section {
+                kind,
+                label,
+                lines: vec![],
+            };
+            let done = std::mem::replace(section, next);
+            if !done.lines.is_empty() || done.kind != SectionKind::Plain {
+                sheet.sections.push(done);
+            }
+        };
+
+        for (number, line) in text.lines().enumerate() {
+            let error =
+                |message: &str| MusicError::ParseError(format!("line {}: {message}", number + 1));
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') {
+                continue;
+            }
+            if let Some(directive) = trimmed.strip_prefix('{') {
+                let directive = directive
+                    .strip_suffix('}')
+                    .ok_or_else(|| error("unterminated directive"))?;
+                let (name, value) = match directive.split_once([':', ' ']) {
+                    Some((name, value)) => (name.trim(), Some(value.trim().to_string())),
+                    None => (directive.trim(), None),
+                };
+                let name = name.to_lowercase();
+                let value = value.filter(|v| !v.is_empty());
+                match name.as_str() {
+                    "title" | "t" => sheet.title = value,
+                    "subtitle" | "st" => sheet.subtitles.extend(value),
+                    "artist" => sheet.artist = value,
+                    "composer" => sheet.composer = value,
+                    "key" => sheet.key = value,
+                    "capo" => {
+                        sheet.capo = value
+                            .as_deref()
+                            .and_then(|v| v.parse().ok())
+                            .ok_or_else(|| error("capo is not a fret number"))?
+                    }
+                    "tempo" => sheet.tempo = value.and_then(|v| v.parse().ok()),
+                    "time" => sheet.time = value,
+                    "comment" | "c" | "comment_italic" | "ci" | "comment_box" | "cb"
+                    | "highlight" => section.lines.extend(value.map(SheetLine::Comment)),
+                    "chorus" => section.lines.push(SheetLine::Chorus(value)),
+                    name => {
+                        let short = |prefix: &str| {
+                            name.strip_prefix(prefix)
+                                .filter(|kind| kind.len() == 1)
+                                .map(SectionKind::from_name)
+                        };
+                        if let Some(kind) = name
+                            .strip_prefix("start_of_")
+                            .map(SectionKind::from_name)
+                            .or_else(|| short("so"))
+                        {
+                            push(&mut sheet, &mut section, kind, value);
+                        } else if name.starts_with("end_of_") || short("eo").is_some() {
+                            push(&mut sheet, &mut section, SectionKind::Plain, None);
+                        } else if let Some(value) = value {
+                            sheet.metadata.push((name.to_string(), value));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            let line = match (&section.kind, trimmed.is_empty()) {
+                (SectionKind::Tab | SectionKind::Grid, _) => SheetLine::Literal(line.to_string()),
+                (_, true) => SheetLine::Empty,
+                _ => SheetLine::Lyrics(lyrics(line).ok_or_else(|| error("unterminated chord"))?),
+            };
+            section.lines.push(line);
+        }
+        push(&mut sheet, &mut section, SectionKind::Plain, None);
+        Ok(sheet)
+    }
+
+    /// ChordPro source of a song, transposed and renamed for the capo
+    pub fn export(&self, sheet: &LeadSheet) -> String {
+        let (shift, flats) = self.shift(sheet);
+        let mut out = String::new();
+        for (name, value) in header(sheet, shift, flats, self.capo.unwrap_or(sheet.capo)) {
+            let _ = writeln!(out, "{{{name}: {value}}}");
+        }
+        for (name, value) in &sheet.metadata {
+            let _ = writeln!(out, "{{{name}: {value}}}");
+        }
+
+        for section in &sheet.sections {
+            let kind = section.kind.name();
+            if section.kind != SectionKind::Plain {
+                match &section.label {
+                    Some(label) => {
+                        let _ = writeln!(out, "{{start_of_{kind}: {label}}}");
+                    }
+                    None => {
+                        let _ = writeln!(out, "{{start_of_{kind}}}");
+                    }
+                }
+            }
+            for line in &section.lines {
+                match line {
+                    SheetLine::Lyrics(segments) => {
+                        for segment in segments {
+                            if let Some(chord) = &segment.chord {
+                                let _ = write!(out, "[{}]", chord.transposed(shift, flats).symbol);
+                            }
+                            out.push_str(&segment.text);
+                        }
+                    }
+                    SheetLine::Comment(text) => {
+                        let _ = write!(out, "{{comment: {text}}}");
+                    }
+                    SheetLine::Chorus(Some(label)) => {
+                        let _ = write!(out, "{{chorus: {label}}}");
+                    }
+                    SheetLine::Chorus(None) => out.push_str("{chorus}"),
+                    SheetLine::Literal(text) => out.push_str(text),
+                    SheetLine::Empty => {}
+                }
+                out.push('\n');
+            }
+            if section.kind != SectionKind::Plain {
+                let _ = writeln!(out, "{{end_of_{kind}}}");
+            }
+        }
+        out
+    }
+
+    /// Printable song: header, chords over lyrics, then the chord diagrams
+    pub fn render(&self, sheet: &LeadSheet) -> String {
+        let (shift, flats) = self.shift(sheet);
+        let mut out = String::new();
+        if let Some(title) = &sheet.title {
+            let _ = writeln!(out, "{title}");
+        }
+        for subtitle in &sheet.subtitles {
+            let _ = writeln!(out, "{subtitle}");
+        }
+        let details = header(sheet, shift, flats, self.capo.unwrap_or(sheet.capo))
+            .into_iter()
+            .filter(|(name, _)| !matches!(*name, "title" | "subtitle"))
+            .map(|(name, value)| format!("{}: {value}", capitalize(name)))
+            .collect::<Vec<_>>();
+        if !details.is_empty() {
+            let _ = writeln!(out, "{}", details.join("  "));
+        }
+
+        for section in &sheet.sections {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            let heading = match (&section.kind, &section.label) {
+                (_, Some(label)) => Some(label.clone()),
+                (SectionKind::Plain, None) => None,
+                (kind, None) => Some(capitalize(kind.name())).filter(|name| !name.is_empty()),
+            };
+            if let Some(heading) = heading {
+                let _ = writeln!(out, "{heading}:");
+            }
+            for line in &section.lines {
+                match line {
+                    SheetLine::Lyrics(segments) => {
+                        let (chords, lyrics) = chords_over_lyrics(segments, shift, flats);
+                        for line in [chords, lyrics] {
+                            let line = line.trim_end();
+                            if !line.is_empty() {
+                                let _ = writeln!(out, "{line}");
+                            }
+                        }
+                    }
+                    SheetLine::Comment(text) => {
+                        let _ = writeln!(out, "({text})");
+                    }
+                    SheetLine::Chorus(label) => {
+                        let _ = writeln!(out, "{}", label.as_deref().unwrap_or("Chorus"));
+                    }
+                    SheetLine::Literal(text) => {
+                        let _ = writeln!(out, "{text}");
+                    }
+                    SheetLine::Empty => out.push('\n'),
+                }
+            }
+        }
+
+        if let Some(fretboard) = &self.diagrams {
+            let generator = FretboardDiagramGenerator::new();
+            // Voiced from the octave above the lowest string
+            let octave = fretboard.string_tuning(0).map_or(3, |t| t.octave() + 1);
+            for chord in sheet.chords() {
+                let chord = chord.transposed(shift, flats);
+                let diagram = chord.chord.as_ref().and_then(|c| {
+                    let voiced = Chord::new(c.root().with_octave(octave), c.quality()).ok()?;
+                    let fingerings = ChordFingeringGenerator::new()
+                        .generate_chord_fingerings(fretboard, &voiced)
+                        .ok()?;
+                    generator
+                        .generate_diagram(fretboard, fingerings.first()?)
+                        .ok()
+                });
+                if let Some(diagram) = diagram {
+                    let _ = write!(out, "\n{}\n{diagram}", chord.symbol);
+                }
+            }
+        }
+        out
+    }
+
+    /// Semitones the chords move by and whether they are spelled with flats
+    fn shift(&self, sheet: &LeadSheet) -> (i8, bool) {
+        let capo = self.capo.unwrap_or(sheet.capo) as i32;
+        let shift = (sheet.capo as i32 + self.transpose as i32 - capo).rem_euclid(12) as i8;
+        let flats = match &sheet.key {
+            Some(key) => match root(key) {
+                Some((pitch, rest)) => {
+                    let minor = rest.starts_with('m') && !rest.starts_with("maj");
+                    let tonic = (pitch + shift).rem_euclid(12) + if minor { 3 } else { 0 };
+                    // F, Bb, Eb, Ab, Db and Gb major and their relative minors
+                    matches!(tonic % 12, 5 | 10 | 3 | 8 | 1 | 6)
+                }
+                None => false,
+            },
+            None => sheet
+                .chords()
+                .iter()
+                .any(|c| c.symbol.chars().nth(1) == Some('b')),
+        };
+        (shift, flats)
+    }
+}
+
+/// Name with its first letter upper case
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    chars.next().map_or(String::new(), |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// Directives of the song's details, the key moved along with the chords
+fn header(sheet: &LeadSheet, shift: i8, flats: bool, capo: u8) -> Vec<(&'static str, String)> {
+    let mut header = vec![];
+    header.extend(sheet.title.clone().map(|v| ("title", v)));
+    header.extend(sheet.subtitles.iter().map(|v| ("subtitle", v.clone())));
+    header.extend(sheet.artist.clone().map(|v| ("artist", v)));
+    header.extend(sheet.composer.clone().map(|v| ("composer", v)));
+    if let Some(key) = &sheet.key {
+        let key = SheetChord::new(key.as_str()).transposed(shift, flats);
+        header.push(("key", key.symbol));
+    }
+    if capo > 0 {
+        header.push(("capo", capo.to_string()));
+    }
+    header.extend(sheet.tempo.map(|v| ("tempo", v.to_string())));
+    header.extend(sheet.time.clone().map(|v| ("time", v)));
+    header
+}
+
+/// Segments of a lyrics line, `None` for an unterminated chord
+fn lyrics(line: &str) -> Option<Vec<ChordedText>> {
+    let mut segments = vec![];
+    let mut rest = line;
+    let mut chord = None;
+    loop {
+        match rest.find('[') {
+            Some(open) => {
+                let text = &rest[..open];
+                if !text.is_empty() || chord.is_some() {
+                    segments.push(ChordedText {
+                        chord: chord.take(),
+                        text: text.to_string(),
+                    });
+                }
+                let close = rest[open..].find(']')? + open;
+                chord = Some(SheetChord::new(rest[open + 1..close].trim()));
+                rest = &rest[close + 1..];
+            }
+            None => {
+                if !rest.is_empty() || chord.is_some() {
+                    segments.push(ChordedText {
+                        chord,
+                        text: rest.to_string(),
+                    });
+                }
+                return Some(segments);
+            }
+        }
+    }
+}
+
+/// Chord line aligned above its lyrics line
+fn chords_over_lyrics(segments: &[ChordedText], shift: i8, flats: bool) -> (String, String) {
+    let width = |s: &String| s.chars().count();
+    let mut chords = String::new();
+    let mut lyrics = String::new();
+    for segment in segments {
+        if let Some(chord) = &segment.chord {
+            // Keep chord symbols apart, a hyphen stretches a word split by a chord
+            let column = match chords.is_empty() {
+                true => 0,
+                false => width(&chords) + 1,
+            };
+            let split = lyrics.ends_with(char::is_alphabetic)
+                && segment.text.starts_with(char::is_alphabetic);
+            let fill = if split { '-' } else { ' ' };
+            while width(&lyrics) < column {
+                lyrics.push(fill);
+            }
+            while width(&chords) < width(&lyrics) {
+                chords.push(' ');
+            }
+            chords.push_str(&chord.transposed(shift, flats).symbol);
+        }
+        lyrics.push_str(&segment.text);
+    }
+    (chords, lyrics)
+}
+
+/// Pitch class of the root note starting `text` and the text after it
+fn root(text: &str) -> Option<(i8, &str)> {
+    let mut chars = text.chars();
+    let pitch = match chars.next()? {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    Some(match rest.chars().next() {
+        Some('#') => (pitch + 1, &rest[1..]),
+        Some('b') => (pitch - 1, &rest[1..]),
+        _ => (pitch, rest),
+    })
+}
+
+fn pitch_name(pitch: i32, flats: bool) -> String {
+    const SHARPS: [&str; 12] = [
+        "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+    ];
+    const FLATS: [&str; 12] = [
+        "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
+    ];
+    let names = if flats { FLATS } else { SHARPS };
+    names[pitch.rem_euclid(12) as usize].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InstrumentPresets;
+
+    const SONG: &str = "\
+{title: Amazing Grace}
+{artist: John Newton}
+{key: G}
+{capo: 2}
+{meta: ccli 22025}
+# a comment line
+{start_of_verse: Verse 1}
+A[G]mazing [G7]grace, how [C]sweet the [G]sound
+That [G]saved a [Em]wretch like [D]me
+{end_of_verse}
+
+{c: Slowly}
+{soc}
+[C]My chains are [G]gone
+{eoc}
+{chorus}
+";
+
+    #[test]
+    fn test_parse() {
+        let sheet = ChordPro::parse(SONG).unwrap();
+        assert_eq!(sheet.title.as_deref(), Some("Amazing Grace"));
+        assert_eq!(sheet.artist.as_deref(), Some("John Newton"));
+        assert_eq!(sheet.key.as_deref(), Some("G"));
+        assert_eq!(sheet.capo, 2);
+        assert_eq!(sheet.metadata, vec![("meta".into(), "ccli 22025".into())]);
+        assert_eq!(sheet.sections.len(), 4);
+
+        let verse = &sheet.sections[0];
+        assert_eq!(verse.kind, SectionKind::Verse);
+        assert_eq!(verse.label.as_deref(), Some("Verse 1"));
+        let SheetLine::Lyrics(segments) = &verse.lines[0] else {
+            panic!("{:?}", verse.lines);
+        };
+        assert_eq!(segments[0].chord, None);
+        assert_eq!(segments[0].text, "A");
+        let g7 = segments[2].chord.as_ref().unwrap();
+        assert_eq!(g7.chord, Some(Chord::from_str("G7").unwrap()));
+        assert_eq!(segments[2].text, "grace, how ");
+
+        assert_eq!(
+            sheet.sections[1].lines[1],
+            SheetLine::Comment("Slowly".into())
+        );
+        assert_eq!(sheet.sections[2].kind, SectionKind::Chorus);
+        assert_eq!(sheet.sections[3].lines, vec![SheetLine::Chorus(None)]);
+        let chords = sheet
+            .chords()
+            .iter()
+            .map(|c| c.symbol.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(chords, vec!["G", "G7", "C", "Em", "D"]);
+
+        assert!(ChordPro::parse("{title: Open").is_err());
+        assert!(ChordPro::parse("[Am").is_err());
+    }
+
+    #[test]
+    fn test_transpose_and_capo() {
+        let sheet = ChordPro::parse(SONG).unwrap();
+
+        // Sounding in A, played in A without the capo
+        let text = ChordPro::new().with_capo(0).export(&sheet);
+        assert!(text.contains("{key: A}"), "{text}");
+        assert!(!text.contains("{capo"));
+        assert!(text.contains("A[A]mazing [A7]grace, how [D]sweet the [A]sound"));
+        assert_eq!(ChordPro::parse(&text).unwrap().capo, 0);
+
+        // Up a semitone to Bb, spelled with flats for the key
+        let text = ChordPro::new()
+            .with_transpose(1)
+            .with_capo(0)
+            .export(&sheet);
+        assert!(text.contains("{key: Bb}"));
+        assert!(
+            text.contains("[Bb]mazing [Bb7]grace, how [Eb]sweet"),
+            "{text}"
+        );
+
+        let rendered = ChordPro::new().render(&sheet);
+        assert!(rendered.contains("Artist: John Newton  Key: G  Capo: 2"));
+        let verse = "Verse 1:\n G      G7         C         G\nAmazing grace, how sweet the sound";
+        assert!(rendered.contains(verse), "{rendered}");
+    }
+
+    #[test]
+    fn test_unusual_input() {
+        // Empty and non-ASCII section names
+        let sheet = ChordPro::parse(
+            "{start_of_}\n[C]one\n{end_of_}\n{start_of_éclat}\n[G]two\n{end_of_éclat}",
+        )
+        .unwrap();
+        let rendered = ChordPro::new().render(&sheet);
+        assert!(rendered.starts_with("C\none\n"), "{rendered}");
+        assert!(rendered.contains("Éclat:\nG\ntwo"), "{rendered}");
+
+        // Capo and transposition far out of range wrap around the octave
+        let sheet = ChordPro::parse("{capo: 200}\n[C]la").unwrap();
+        let text = ChordPro::new()
+            .with_transpose(127)
+            .with_capo(0)
+            .export(&sheet);
+        assert!(text.contains("[D#]la"), "{text}");
+    }
+
+    #[test]
+    fn test_diagrams() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let sheet = ChordPro::parse("[Am]Hal[Fmaj7]le[C]lu[G]jah").unwrap();
+        let rendered = ChordPro::new().with_diagrams(guitar).render(&sheet);
+        assert!(
+            rendered.starts_with("Am Fmaj7 C G\nHalle----lujah\n"),
+            "{rendered}"
+        );
+        for chord in ["\nAm\n", "\nFmaj7\n", "\nC\n", "\nG\n"] {
+            assert!(rendered.contains(chord), "{rendered}");
+        }
+    }
+}
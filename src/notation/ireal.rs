@@ -0,0 +1,589 @@
+//! iReal Pro charts
+//!
+//! Reads the `irealb://` links iReal Pro shares, one song or a whole playlist, and the
+//! older plain `irealbook://` links. The music of `irealb://` songs is scrambled: it is
+//! unscrambled first, then read as a chart:
+//! - sections `*A`, `*B`, verse `*V` and intro `*i`, time signatures `T44`, `T34`, `T12`
+//! - bar lines `|`, `[`, `]`, `Z`, repeats `{` `}`, endings `N1` `N2`, segno `S`, coda `Q`
+//!   and jumps written as comments (`<D.C. al Coda>`)
+//! - chord symbols in iReal's shorthand (`^7`, `-7`, `h7`, `o7`, `7sus`), turned into
+//!   common symbols and read as `SheetChord`s through `Chord::from_str`
+//! - slashes `p` repeating the chord, `x` and `r` repeating one or two measures, and `n`
+//!   for no chord
+//!
+//! Alternate chords in parentheses, fermatas, spacers and text other than jumps are left
+//! out. Chords of a measure share it evenly when turned into a score.
+
+use super::chordpro::SheetChord;
+use crate::{
+    BarMark, Chord, DurationBase, Jump, KeySignature, Measure, MusicError, Score, TimeSignature,
+    Tuning,
+};
+
+/// Start of the music of `irealb://` songs
+const MUSIC_PREFIX: &str = "1r34LbKcu7";
+
+/// One measure of a chart
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IRealMeasure {
+    /// Chords in order, `N.C.` has no chord
+    pub chords: Vec<SheetChord>,
+    /// Meter change at this measure
+    pub time_signature: Option<TimeSignature>,
+    /// Repeats, endings, segno, coda and jumps
+    pub marks: Vec<BarMark>,
+}
+
+/// Measures under a rehearsal mark
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IRealSection {
+    /// `A`, `B`, `Verse`, `Intro`, none before the first mark
+    pub label: Option<String>,
+    pub measures: Vec<IRealMeasure>,
+}
+
+/// A song of iReal Pro
+#[derive(Debug, Clone, PartialEq)]
+pub struct IRealChart {
+    pub title: String,
+    /// As iReal stores it, last name first
+    pub composer: String,
+    /// Accompaniment style, e.g. `Medium Swing`
+    pub style: String,
+    pub key_signature: Option<KeySignature>,
+    /// Quarter notes per minute, `None` for the style's default
+    pub tempo: Option<f32>,
+    pub time_signature: TimeSignature,
+    pub sections: Vec<IRealSection>,
+}
+
+impl IRealChart {
+    /// All measures in written order
+    pub fn measures(&self) -> impl Iterator<Item = &IRealMeasure> {
+        self.sections.iter().flat_map(|s| s.measures.iter())
+    }
+
+    /// Score with one chord track, in the first time signature. Repeats, endings and
+    /// jumps become `BarMark`s, see `Score::unroll`. Measures without a chord are rests.
+    pub fn to_score(&self) -> Score<1> {
+        let ts = &self.time_signature;
+        let mut score = Score::<1>::new()
+            .with_time_signature(ts.beats_per_measure(), ts.beat_type())
+            .with_key_signature(self.key_signature.unwrap_or_default())
+            .with_tempo(self.tempo.unwrap_or(120.0) / ts.beat_type().in_quarters());
+        for (index, measure) in self.measures().enumerate() {
+            let chords = measure
+                .chords
+                .iter()
+                .filter_map(|c| c.chord.clone())
+                .collect::<Vec<Chord>>();
+            score.push_measures([match chords.is_empty() {
+                true => Measure::Rest,
+                false => Measure::Chords(chords),
+            }]);
+            for mark in &measure.marks {
+                score.mark(index, mark.clone());
+            }
+        }
+        score
+    }
+}
+
+/// iReal Pro link reader, see the module documentation
+pub struct IReal;
+
+impl IReal {
+    /// Songs of an `irealb://` or `irealbook://` link
+    pub fn import(link: &str) -> Result<Vec<IRealChart>, MusicError> {
+        let link = link.trim();
+        let (scrambled, body) = match (
+            link.strip_prefix("irealb://"),
+            link.strip_prefix("irealbook://"),
+        ) {
+            (Some(body), _) => (true, body),
+            (None, Some(body)) => (false, body),
+            _ => return Err(MusicError::ParseError("not an iReal Pro link".into())),
+        };
+        // Links escape the separators too
+        let body = percent_decode(body);
+        let charts = match scrambled {
+            true => body
+                .split("===")
+                .filter_map(|song| {
+                    let fields = song.split('=').collect::<Vec<_>>();
+                    let music = fields.get(6)?.strip_prefix(MUSIC_PREFIX)?;
+                    let tempo = fields.get(8).and_then(|t| t.parse::<f32>().ok());
+                    Some(chart(&fields, 3, &unscramble(music), tempo))
+                })
+                .collect::<Vec<_>>(),
+            false => {
+                let fields = body.split('=').collect::<Vec<_>>();
+                fields
+                    .chunks(6)
+                    .filter(|song| song.len() == 6)
+                    .map(|song| chart(song, 2, song[5], None))
+                    .collect()
+            }
+        };
+        match charts.is_empty() {
+            true => Err(MusicError::ParseError("no iReal Pro song found".into())),
+            false => Ok(charts),
+        }
+    }
+}
+
+/// Chart of the fields of a song: title, composer, the style at `style` and the key after
+/// it
+fn chart(fields: &[&str], style: usize, music: &str, tempo: Option<f32>) -> IRealChart {
+    let field = |i: usize| {
+        fields
+            .get(i)
+            .map_or(String::new(), |f| f.trim().to_string())
+    };
+    let (time_signature, sections) = read_music(music);
+    IRealChart {
+        title: field(0),
+        composer: field(1),
+        style: field(style),
+        key_signature: key(&field(style + 1)),
+        tempo: tempo.filter(|t| *t > 0.0),
+        time_signature,
+        sections,
+    }
+}
+
+/// `%20` and the other escapes of a link
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Music of an `irealb://` song in plain text
+fn unscramble(music: &str) -> String {
+    let mut chars = music.chars().collect::<Vec<_>>();
+    let mut out = String::new();
+    while chars.len() > 51 {
+        let rest = chars.split_off(50);
+        out.extend(obfuscate(&chars));
+        chars = rest;
+    }
+    out.extend(chars);
+    out.replace("Kcl", "| x")
+        .replace("LZ", " |")
+        .replace("XyQ", "   ")
+}
+
+/// Swaps the ends of a block of 50 characters, its own inverse
+fn obfuscate(block: &[char]) -> Vec<char> {
+    let mut out = block.to_vec();
+    for i in (0..5).chain(10..24) {
+        out[i] = block[49 - i];
+        out[49 - i] = block[i];
+    }
+    out
+}
+
+/// `C`, `Bb`, `F#-` (minor)
+fn key(text: &str) -> Option<KeySignature> {
+    let (text, minor) = match text.strip_suffix('-') {
+        Some(text) => (text, true),
+        None => (text, false),
+    };
+    let mut chars = text.chars().peekable();
+    let tonic = Tuning::take(&mut chars).ok()?;
+    KeySignature::from_tonic(tonic.with_octave(4), minor).ok()
+}
+
+/// Time signature of `T44`, `T68` or `T12` (12/8)
+fn time_signature(digits: &str) -> Option<TimeSignature> {
+    let (beats, beat) = match digits {
+        "12" => (12, 8),
+        _ => {
+            let mut chars = digits.chars().map(|c| c.to_digit(10));
+            (chars.next()??, chars.next()??)
+        }
+    };
+    let beat_type = DurationBase::from_whole(1.0 / beat as f32).ok()?;
+    (beats > 0).then(|| TimeSignature::new(beats as u8, beat_type))
+}
+
+/// Common spelling of an iReal chord symbol, e.g. `C^7` as `Cmaj7`, `Bh7/F` as `Bm7b5/F`
+fn symbol(text: &str) -> String {
+    let (chord, bass) = match text.split_once('/') {
+        Some((chord, bass)) => (chord, Some(bass)),
+        None => (text, None),
+    };
+    let split = chord
+        .char_indices()
+        .nth(match chord[1..].starts_with(['b', '#']) {
+            true => 2,
+            false => 1,
+        })
+        .map_or(chord.len(), |(i, _)| i);
+    let (root, quality) = chord.split_at(split);
+    let quality = match quality {
+        "^" => "maj7".to_string(),
+        "-^7" | "-^" => "mM7".to_string(),
+        "h" | "h7" => "m7b5".to_string(),
+        "o" => "dim".to_string(),
+        "7sus" | "sus" => "sus4".to_string(),
+        "2" => "sus2".to_string(),
+        quality => quality.replace('^', "maj").replace('-', "m"),
+    };
+    match bass {
+        Some(bass) => format!("{root}{quality}/{bass}"),
+        None => format!("{root}{quality}"),
+    }
+}
+
+/// Sheet chord of an iReal symbol, extended chords read as the seventh or suspended chord
+/// they extend
+fn sheet_chord(text: &str) -> SheetChord {
+    let mut chord = SheetChord::new(symbol(text));
+    if chord.chord.is_none() {
+        // 7b9, 13#11, 7alt...
+        let (root, quality) = text.split_at(match text[1..].starts_with(['b', '#']) {
+            true => 2,
+            false => 1,
+        });
+        let quality = quality.split('/').next().unwrap_or_default();
+        let reduced = match quality.chars().next() {
+            // 9sus and 13sus like 7sus
+            _ if quality.ends_with("sus") => Some("sus4"),
+            Some('7' | '9' | '1') => Some("7"),
+            Some('-') => Some("m7"),
+            Some('^') => Some("maj7"),
+            Some('h') => Some("m7b5"),
+            Some('o') => Some("dim7"),
+            _ => None,
+        };
+        chord.chord = reduced.and_then(|q| SheetChord::new(format!("{root}{q}")).chord);
+    }
+    chord
+}
+
+/// Meter and sections of the unscrambled music
+fn read_music(music: &str) -> (TimeSignature, Vec<IRealSection>) {
+    let chars = music.chars().collect::<Vec<_>>();
+    let mut reader = Reader::default();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        match c {
+            'T' => {
+                let digits = chars[i..].iter().take(2).collect::<String>();
+                i += 2;
+                if let Some(ts) = time_signature(&digits) {
+                    reader.time_signature.get_or_insert(ts);
+                    reader.measure.time_signature = Some(ts);
+                }
+            }
+            '*' => {
+                let label = match chars.get(i) {
+                    Some('V') => "Verse".to_string(),
+                    Some('i') => "Intro".to_string(),
+                    Some(c) => c.to_string(),
+                    None => break,
+                };
+                i += 1;
+                reader.ending = None;
+                reader.sections.push(IRealSection {
+                    label: Some(label),
+                    measures: vec![],
+                });
+            }
+            '|' | '[' | ']' | '{' | '}' | 'Z' => reader.bar(c),
+            'N' => {
+                if let Some(number) = chars.get(i).and_then(|c| c.to_digit(10)) {
+                    i += 1;
+                    reader.ending = (number > 0).then_some(number as u8);
+                }
+            }
+            'S' => reader.measure.marks.push(BarMark::Segno),
+            'Q' => {
+                let mark = match reader.coda {
+                    false => BarMark::ToCoda,
+                    true => BarMark::Coda,
+                };
+                reader.coda = true;
+                reader.measure.marks.push(mark);
+            }
+            '<' => {
+                let text = chars[i..]
+                    .iter()
+                    .take_while(|c| **c != '>')
+                    .collect::<String>();
+                i += text.chars().count() + 1;
+                reader.comment(&text);
+            }
+            '(' => {
+                // Alternate chord
+                i += chars[i..].iter().take_while(|c| **c != ')').count() + 1;
+            }
+            'x' => {
+                reader.measure.chords = reader.previous(1);
+            }
+            'r' => reader.repeat_two = true,
+            'n' => reader.measure.chords.push(SheetChord::new("N.C.")),
+            'p' | 'W' => {
+                let last = reader.measure.chords.last().cloned();
+                if let Some(chord) = last.or_else(|| reader.previous(1).pop()) {
+                    reader.measure.chords.push(chord);
+                }
+                // An invisible root keeps its bass note out too
+                if c == 'W' && chars.get(i) == Some(&'/') {
+                    i += 1 + chars[i + 1..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_alphabetic() || **c == '#')
+                        .count();
+                }
+            }
+            'A'..='G' => {
+                let length = chars[i..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit() || "^-+#bhosuadltimj/ABCDEFG".contains(**c))
+                    .count();
+                let text = chars[i - 1..i + length].iter().collect::<String>();
+                i += length;
+                reader.measure.chords.push(sheet_chord(&text));
+            }
+            // Spacers, small and large chords, fermatas, stops and separators
+            _ => {}
+        }
+    }
+    reader.bar('Z');
+    (
+        reader
+            .time_signature
+            .unwrap_or(TimeSignature::new(4, DurationBase::Quarter)),
+        reader
+            .sections
+            .into_iter()
+            .filter(|s| !s.measures.is_empty())
+            .collect(),
+    )
+}
+
+#[derive(Default)]
+struct Reader {
+    time_signature: Option<TimeSignature>,
+    sections: Vec<IRealSection>,
+    measure: IRealMeasure,
+    /// `{` before the next measure
+    repeat_start: bool,
+    /// Number of the open ending
+    ending: Option<u8>,
+    /// `r`, the measure and the next one repeat the two before them
+    repeat_two: bool,
+    /// A coda sign was seen, the next one starts the coda
+    coda: bool,
+}
+
+impl Reader {
+    /// Chords of the measure `back` measures ago
+    fn previous(&self, back: usize) -> Vec<SheetChord> {
+        let measures = self
+            .sections
+            .iter()
+            .flat_map(|s| s.measures.iter())
+            .collect::<Vec<_>>();
+        measures
+            .len()
+            .checked_sub(back)
+            .map_or(vec![], |i| measures[i].chords.clone())
+    }
+
+    fn comment(&mut self, text: &str) {
+        let text = text.to_lowercase();
+        let mark = match () {
+            _ if text.contains("d.c. al coda") => BarMark::Jump(Jump::DaCapoAlCoda),
+            _ if text.contains("d.c. al fine") => BarMark::Jump(Jump::DaCapoAlFine),
+            _ if text.contains("d.c.") => BarMark::Jump(Jump::DaCapo),
+            _ if text.contains("d.s. al coda") => BarMark::Jump(Jump::DalSegnoAlCoda),
+            _ if text.contains("d.s. al fine") => BarMark::Jump(Jump::DalSegnoAlFine),
+            _ if text.contains("d.s.") => BarMark::Jump(Jump::DalSegno),
+            _ if text.trim() == "fine" => BarMark::Fine,
+            _ => return,
+        };
+        self.measure.marks.push(mark);
+    }
+
+    /// Closes the measure at a bar line
+    fn bar(&mut self, line: char) {
+        if self.repeat_two {
+            // The two-measure repeat spans this measure and the next, empty one
+            self.measure.chords = self.previous(2);
+            self.repeat_two = false;
+            self.push();
+            self.measure.chords = self.previous(2);
+        }
+        if line == '}' {
+            self.measure.marks.push(BarMark::repeat_end());
+        }
+        if !self.measure.chords.is_empty() || !self.measure.marks.is_empty() {
+            self.push();
+        }
+        if line == '{' {
+            self.repeat_start = true;
+        }
+        if line != '|' {
+            self.ending = None;
+        }
+    }
+
+    fn push(&mut self) {
+        let mut measure = std::mem::take(&mut self.measure);
+        if std::mem::take(&mut self.repeat_start) {
+            measure.marks.insert(0, BarMark::RepeatStart);
+        }
+        if let Some(number) = self.ending {
+            measure.marks.push(BarMark::volta(number));
+        }
+        if self.sections.is_empty() {
+            self.sections.push(IRealSection::default());
+        }
+        if let Some(section) = self.sections.last_mut() {
+            section.measures.push(measure);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChordQuality;
+
+    /// Scrambles music the way iReal Pro stores it
+    fn scramble(music: &str) -> String {
+        let mut chars = music.chars().collect::<Vec<_>>();
+        let mut out = String::new();
+        while chars.len() > 51 {
+            let rest = chars.split_off(50);
+            out.extend(obfuscate(&chars));
+            chars = rest;
+        }
+        out.extend(chars);
+        out
+    }
+
+    #[test]
+    fn test_unscramble() {
+        let music = "{*AT44D-7XyQ|G7XyQ|C^7XyQ|Kcl LZN1Bh7 E7b9 }XyQXyQ|N2C6 p Z ";
+        let plain = unscramble(&scramble(music));
+        assert!(plain.starts_with("{*AT44D-7   |G7   |C^7   || x  |N1"));
+        assert_eq!(symbol("Bh7"), "Bm7b5");
+        assert_eq!(symbol("Eb^7/G"), "Ebmaj7/G");
+        assert_eq!(symbol("F#-7"), "F#m7");
+
+        // Extended chords are read as the nearest chord of their family
+        let chord = |text: &str| sheet_chord(text).chord;
+        let spelled = |text: &str| SheetChord::new(text).chord;
+        assert!(["C7b9", "Ch9", "Co^7", "C9sus"]
+            .iter()
+            .all(|text| chord(text).is_some()));
+        assert_eq!(chord("C7b9"), spelled("C7"));
+        assert_eq!(chord("Ch9"), spelled("Cm7b5"));
+        assert_eq!(chord("Co^7"), spelled("Cdim7"));
+        assert!(spelled("Csus4").is_some());
+        assert_eq!(chord("C7sus"), spelled("Csus4"));
+        assert_eq!(chord("C9sus"), spelled("Csus4"));
+        assert_eq!(chord("Eb13sus"), spelled("Ebsus4"));
+    }
+
+    #[test]
+    fn test_import() {
+        let music = "[T44*AC^7 A-7 |D-7 G7 |x |r| |*BE-7XyQ|<D.C. al Fine>A7alt Z";
+        let song = |title: &str| {
+            format!(
+                "{title}=Composer Some==Medium Swing=C==1r34LbKcu7{}=Swing=160=3",
+                scramble(music)
+            )
+        };
+        // Shared links escape everything but letters and digits, the separators too
+        let body = format!("{}==={}===Playlist", song("One"), song("Two"))
+            .bytes()
+            .map(|b| match b.is_ascii_alphanumeric() {
+                true => (b as char).to_string(),
+                false => format!("%{b:02X}"),
+            })
+            .collect::<String>();
+        let link = format!("irealb://{body}");
+        assert!(!link.contains('='));
+        let charts = IReal::import(&link).unwrap();
+        assert_eq!(charts.len(), 2);
+        let chart = &charts[0];
+        assert_eq!(chart.title, "One");
+        assert_eq!(chart.composer, "Composer Some");
+        assert_eq!(chart.style, "Medium Swing");
+        assert_eq!(chart.tempo, Some(160.0));
+        assert_eq!(chart.key_signature.map(|k| k.fifths()), Some(0));
+        assert_eq!(chart.sections.len(), 2);
+        assert_eq!(chart.sections[0].label.as_deref(), Some("A"));
+
+        let measures = chart.measures().collect::<Vec<_>>();
+        assert_eq!(measures.len(), 7);
+        let symbols = |m: &IRealMeasure| {
+            m.chords
+                .iter()
+                .map(|c| c.symbol.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(symbols(measures[0]), ["Cmaj7", "Am7"]);
+        assert_eq!(symbols(measures[2]), ["Dm7", "G7"], "one-measure repeat");
+        assert_eq!(symbols(measures[3]), ["Dm7", "G7"], "two-measure repeat");
+        assert_eq!(symbols(measures[4]), ["Dm7", "G7"]);
+        assert_eq!(measures[6].marks, [BarMark::Jump(Jump::DaCapoAlFine)]);
+        let alt = measures[6].chords[0].chord.as_ref().unwrap();
+        assert_eq!(alt.quality(), ChordQuality::Dominant7);
+
+        let score = chart.to_score();
+        assert_eq!(score.measure_count(), 7);
+        assert!(
+            matches!(&score.get_tracks()[0].get_measures()[1], Measure::Chords(c) if c.len() == 2)
+        );
+    }
+
+    #[test]
+    fn test_repeats_and_endings() {
+        let link = "irealbook://Blues=Someone=Jazz=F-=n={*AT34F-7 |Bb7 |N1C7 }|N2Db^7 |C7 Z";
+        let charts = IReal::import(link).unwrap();
+        let chart = &charts[0];
+        assert!(chart.key_signature.unwrap().is_minor());
+        assert_eq!(chart.time_signature.beats_per_measure(), 3);
+        let marks = chart
+            .measures()
+            .map(|m| m.marks.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            marks,
+            [
+                vec![BarMark::RepeatStart],
+                vec![],
+                vec![BarMark::repeat_end(), BarMark::volta(1)],
+                vec![BarMark::volta(2)],
+                vec![BarMark::volta(2)],
+            ]
+        );
+        let score = chart.to_score();
+        assert_eq!(score.unroll(), [0, 1, 2, 0, 1, 3, 4]);
+    }
+}
@@ -0,0 +1,722 @@
+//! Jianpu (简谱, numbered musical notation)
+//!
+//! Writes and reads the plain-text form of jianpu, relative to the tonic of a key:
+//! - a header line `1=G 3/4`, `1=bB` for flat tonics
+//! - degrees `1` to `7`, `0` for rests, `#` and `b` before the degree
+//! - octave dots as `'` above and `,` below (or the combining dots U+0307 and U+0323)
+//! - underlines as `_` per line, an eighth in 4/4 is `1_` and a sixteenth `1__` (or the
+//!   combining low lines U+0332 and U+0333)
+//! - `-` holds the previous note one more beat, `.` is an augmentation dot
+//! - `~` ties to the next note, `(3` starts a triplet, `|` ends a measure
+//!
+//! `1` is the tonic of the major key of the signature in octave 4, minor keys are written
+//! with `6` as their tonic. Accidentals apply to their own note only.
+//!
+//! The writer puts underlined notes of one beat together, chord measures as their root
+//! under the chord symbol in quotes, and drum measures as rests. Grace notes are left out.
+//! The reader ignores quoted text and lines that are not jianpu (titles, lyrics). Ties are
+//! merged within a measure when their sum is a single value, the others are kept as
+//! separate notes and reported as an `ImportWarning`.
+
+use super::abc::{default_ratio, meter, meter_text, notated};
+use super::layout::{layout, Content};
+use super::musicxml::letter_index;
+use super::musicxml_import::Warnings;
+use crate::{
+    Duration, DurationBase, ImportWarning, ImportWarningKind, KeySignature, Measure, MusicError,
+    Note, Score, TimeSignature, Track, Tuning,
+};
+use std::fmt::Write;
+
+/// Written for a track without measures
+static REST: Measure = Measure::Rest;
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+
+/// Jianpu writer and reader, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Jianpu {
+    title: Option<String>,
+    key_signature: KeySignature,
+    time_signature: TimeSignature,
+    measures_per_line: usize,
+    unicode: bool,
+}
+
+impl Default for Jianpu {
+    fn default() -> Self {
+        Jianpu {
+            title: None,
+            key_signature: KeySignature::default(),
+            time_signature: TimeSignature::new(4, DurationBase::Quarter),
+            measures_per_line: 4,
+            unicode: false,
+        }
+    }
+}
+
+/// Jianpu read from text, see `Jianpu::import`
+#[derive(Clone)]
+pub struct JianpuImport {
+    pub title: Option<String>,
+    pub key_signature: KeySignature,
+    pub time_signature: TimeSignature,
+    pub track: Track,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl Jianpu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        Jianpu {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Key of written tracks and of imported text without a `1=` header, C by default
+    pub fn with_key_signature(self, key_signature: KeySignature) -> Self {
+        Jianpu {
+            key_signature,
+            ..self
+        }
+    }
+
+    /// Meter of written tracks and of imported text without one in the header, 4/4 by
+    /// default
+    pub fn with_time_signature(self, time_signature: TimeSignature) -> Self {
+        Jianpu {
+            time_signature,
+            ..self
+        }
+    }
+
+    pub fn with_measures_per_line(self, measures_per_line: usize) -> Self {
+        Jianpu {
+            measures_per_line: measures_per_line.max(1),
+            ..self
+        }
+    }
+
+    /// Octave dots and underlines as combining characters instead of `'`, `,` and `_`
+    pub fn with_unicode_marks(self, unicode: bool) -> Self {
+        Jianpu { unicode, ..self }
+    }
+
+    /// Jianpu of a score, one part per track after a `V:` line
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let ts = score.time_signature();
+        let key = score.key_signature();
+        let mut out = self.header(key, ts);
+        for track in 0..N {
+            let meter = score.track_time_signature(track);
+            if N > 1 {
+                let _ = match &meter == ts {
+                    true => writeln!(out, "V:{}", track + 1),
+                    false => writeln!(out, "V:{} {}", track + 1, meter_text(&meter)),
+                };
+            }
+            self.write_part(
+                &mut out,
+                score.get_tracks()[track].get_measures(),
+                &meter,
+                key,
+            );
+        }
+        out
+    }
+
+    /// Jianpu of a single track, in its own time signature if it has one
+    pub fn export_track(&self, track: &Track) -> String {
+        let ts = track.time_signature().unwrap_or(&self.time_signature);
+        let mut out = self.header(&self.key_signature, ts);
+        self.write_part(&mut out, track.get_measures(), ts, &self.key_signature);
+        out
+    }
+
+    fn header(&self, key: &KeySignature, ts: &TimeSignature) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "{title}");
+        }
+        let (letter, alter, _) = tonic(key).spelling();
+        let accidental = match alter {
+            1 => "#",
+            -1 => "b",
+            _ => "",
+        };
+        let _ = writeln!(out, "1={accidental}{letter} {}", meter_text(ts));
+        out
+    }
+
+    fn write_part(
+        &self,
+        out: &mut String,
+        measures: &[Measure],
+        ts: &TimeSignature,
+        key: &KeySignature,
+    ) {
+        let measures = match measures.is_empty() {
+            true => std::slice::from_ref(&REST),
+            false => measures,
+        };
+        let count = measures.len();
+        for (line, chunk) in measures.chunks(self.measures_per_line).enumerate() {
+            let text = chunk
+                .iter()
+                .map(|measure| self.measure(measure, ts, key))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            let end = (line + 1) * self.measures_per_line >= count;
+            let _ = writeln!(out, "{text} {}", if end { "||" } else { "|" });
+        }
+    }
+
+    fn measure(&self, measure: &Measure, ts: &TimeSignature, key: &KeySignature) -> String {
+        let beat = ts.beat_type().in_quarters();
+        let mut text = String::new();
+        let mut position = 0.0;
+        let mut underlined = false;
+        let mut tuplet_left = 0;
+        for slot in layout(measure, ts) {
+            if slot.is_grace() {
+                continue;
+            }
+            let tuning = match &slot.content {
+                Content::Note(_, tuning) => Some(*tuning),
+                Content::Chord(chord) => {
+                    if !slot.continued {
+                        let _ = write!(
+                            text,
+                            "{}\"{chord}\"",
+                            if text.is_empty() { "" } else { " " }
+                        );
+                        underlined = false;
+                    }
+                    Some(chord.root())
+                }
+                Content::Drums(_) | Content::Rest | Content::MeasureRest => None,
+            };
+            let duration = slot.duration;
+            let (token, lines) = self.token(tuning, duration, beat, key);
+            let joined = underlined && lines > 0 && (position / beat).fract() > 1e-3;
+            if !text.is_empty() && !joined && !text.ends_with('"') {
+                text.push(' ');
+            }
+            if let Some(tuplet) = duration.tuplet {
+                if tuplet_left == 0 {
+                    let _ = write!(text, "({} ", tuplet.actual_notes);
+                    tuplet_left = tuplet.actual_notes;
+                }
+                tuplet_left -= 1;
+            }
+            text.push_str(&token);
+            if slot.tie {
+                text.push('~');
+            }
+            underlined = lines > 0;
+            position += duration.in_quarters();
+        }
+        text
+    }
+
+    /// Written value and its number of underlines
+    fn token(
+        &self,
+        tuning: Option<Tuning>,
+        duration: Duration,
+        beat: f32,
+        key: &KeySignature,
+    ) -> (String, u32) {
+        let mut token = String::new();
+        match tuning {
+            Some(tuning) => {
+                let (degree, accidental, octave) = degree(tuning, key);
+                let sign = if accidental > 0 { '#' } else { 'b' };
+                token.extend(std::iter::repeat_n(
+                    sign,
+                    accidental.unsigned_abs() as usize,
+                ));
+                token.push(char::from(b'0' + degree));
+                let mark = match (octave > 0, self.unicode) {
+                    (true, true) => '\u{307}',
+                    (true, false) => '\'',
+                    (false, true) => '\u{323}',
+                    (false, false) => ',',
+                };
+                token.extend(std::iter::repeat_n(mark, octave.unsigned_abs() as usize));
+            }
+            None => token.push('0'),
+        }
+
+        let nominal = duration.base.in_quarters() / beat;
+        let beats = nominal * (2.0 - 0.5f32.powi(duration.dots as i32));
+        let whole = duration.tuplet.is_none() && beats >= 1.0 && beats.fract() < 1e-3;
+        let (lines, dots, held) = match (whole, nominal >= 1.0) {
+            (true, _) => (0, 0, beats as usize - 1),
+            (false, true) => (0, duration.dots, nominal as usize - 1),
+            (false, false) => ((1.0 / nominal).log2().round() as u32, duration.dots, 0),
+        };
+        match self.unicode {
+            true => {
+                for _ in 0..lines / 2 {
+                    token.push('\u{333}');
+                }
+                if lines % 2 == 1 {
+                    token.push('\u{332}');
+                }
+            }
+            false => token.extend(std::iter::repeat_n('_', lines as usize)),
+        }
+        // Rests are held by more rests
+        let hold = if tuning.is_some() { " -" } else { " 0" };
+        for _ in 0..held {
+            token.push_str(hold);
+        }
+        token.extend(std::iter::repeat_n('.', dots as usize));
+        (token, lines)
+    }
+
+    /// Read plain-text jianpu into a track of notes. The key and meter come from the
+    /// `1=` header line when there is one. Only the first part of a text with `V:` lines
+    /// is read.
+    pub fn import(&self, text: &str) -> Result<JianpuImport, MusicError> {
+        let mut title = None;
+        let mut key_signature = self.key_signature;
+        let mut time_signature = self.time_signature;
+        let mut music = vec![];
+        let mut parts = 0;
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            let line = line.trim();
+            if let Some(voice) = line.strip_prefix("V:") {
+                parts += 1;
+                if parts > 1 {
+                    break;
+                }
+                if let Some(ts) = voice.split_whitespace().find_map(meter) {
+                    time_signature = ts;
+                }
+            } else if line.contains("1=") {
+                for field in line.split_whitespace() {
+                    if let Some(tonic) = field.strip_prefix("1=") {
+                        key_signature = key(tonic).ok_or_else(|| {
+                            MusicError::ParseError(format!("unknown jianpu key {field}"))
+                        })?;
+                    } else if let Some(ts) = meter(field) {
+                        time_signature = ts;
+                    }
+                }
+            } else if is_music(line) {
+                music.push(line);
+            } else if music.is_empty() && title.is_none() && !line.is_empty() {
+                title = Some(line.to_string());
+            }
+        }
+        if music.is_empty() {
+            return Err(MusicError::ParseError("no jianpu found".into()));
+        }
+
+        let mut reader = Reader {
+            key: key_signature,
+            ts: time_signature,
+            track: Track::new(),
+            events: vec![],
+            accidental: 0,
+            dot: 0.0,
+            tuplet: None,
+            warnings: Warnings::default(),
+        };
+        for line in music {
+            reader.line(line);
+        }
+        reader.bar();
+        Ok(JianpuImport {
+            title,
+            key_signature,
+            time_signature,
+            track: reader.track,
+            warnings: reader.warnings.list,
+        })
+    }
+}
+
+/// `1` of a key, the tonic of its major key in octave 4
+fn tonic(key: &KeySignature) -> Tuning {
+    KeySignature::new(key.fifths()).tonic()
+}
+
+/// Degree, accidental against the major scale and octave of a pitch
+fn degree(tuning: Tuning, key: &KeySignature) -> (u8, i8, i8) {
+    let (letter, alter, octave) = tuning.spelling();
+    let (tonic_letter, _, tonic_octave) = tonic(key).spelling();
+    let steps = letter_index(letter) as i32 + 7 * octave as i32
+        - letter_index(tonic_letter) as i32
+        - 7 * tonic_octave as i32;
+    let accidental = alter - KeySignature::new(key.fifths()).alter(letter);
+    (
+        steps.rem_euclid(7) as u8 + 1,
+        accidental,
+        steps.div_euclid(7) as i8,
+    )
+}
+
+/// Pitch of a degree, see `degree`
+fn pitch(degree: u8, accidental: i8, octave: i8, key: &KeySignature) -> Option<Tuning> {
+    let (tonic_letter, _, tonic_octave) = tonic(key).spelling();
+    let step =
+        letter_index(tonic_letter) as i32 + 7 * (tonic_octave + octave) as i32 + degree as i32 - 1;
+    let letter = LETTERS[step.rem_euclid(7) as usize];
+    let alter = KeySignature::new(key.fifths()).alter(letter) + accidental;
+    Tuning::from_spelling(letter, alter, step.div_euclid(7) as i8).ok()
+}
+
+/// Major key of `G`, `bB`, `Bb`, `#F` or `F#`
+fn key(tonic: &str) -> Option<KeySignature> {
+    let sign = |c: char| match c {
+        '#' | '♯' => Some(1),
+        'b' | '♭' => Some(-1),
+        _ => None,
+    };
+    let mut chars = tonic.chars().peekable();
+    let mut alter = 0;
+    while let Some(a) = chars.peek().and_then(|c| sign(*c)) {
+        alter += a;
+        chars.next();
+    }
+    let letter = chars
+        .next()
+        .filter(|c| c.is_ascii_alphabetic())?
+        .to_ascii_uppercase();
+    alter += chars.map(sign).sum::<Option<i8>>()?;
+    let tonic = Tuning::from_spelling(letter, alter, 4).ok()?;
+    KeySignature::from_tonic(tonic, false).ok()
+}
+
+/// A line of degrees and bar lines, quoted chord symbols left out
+fn is_music(line: &str) -> bool {
+    let mut degrees = false;
+    for c in line.split('"').step_by(2).flat_map(str::chars) {
+        match c {
+            '0'..='7' => degrees = true,
+            // Tuplet numbers such as (3 go up to 9
+            '8' | '9' => {}
+            '#' | 'b' | '♯' | '♭' | '\'' | ',' | '_' | '.' | '-' | '~' | '|' | ':' | '(' => {}
+            '\u{307}' | '\u{323}' | '\u{332}' | '\u{333}' => {}
+            c if c.is_whitespace() => {}
+            _ => return false,
+        }
+    }
+    degrees
+}
+
+/// One written value of a measure being read
+struct Event {
+    /// Degree, accidental and octave, `None` for a rest
+    pitch: Option<(u8, i8, i8)>,
+    /// Nominal length in quarters
+    quarters: f32,
+    tuplet: Option<(u8, u8)>,
+    tie: bool,
+}
+
+/// Reads the lines of a part into measures
+struct Reader {
+    key: KeySignature,
+    ts: TimeSignature,
+    track: Track,
+    events: Vec<Event>,
+    /// Accidental of the next degree
+    accidental: i8,
+    /// Length the next dot adds
+    dot: f32,
+    /// Ratio and notes left of the current tuplet
+    tuplet: Option<((u8, u8), u8)>,
+    warnings: Warnings,
+}
+
+impl Reader {
+    fn line(&mut self, line: &str) {
+        let beat = self.ts.beat_type().in_quarters();
+        let chars = line
+            .split('"')
+            .step_by(2)
+            .flat_map(str::chars)
+            .collect::<Vec<_>>();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            match c {
+                '#' | '♯' => self.accidental += 1,
+                'b' | '♭' => self.accidental -= 1,
+                '0'..='7' => {
+                    let degree = c as u8 - b'0';
+                    let tuplet = self.tuplet.take().map(|(ratio, left)| {
+                        if left > 1 {
+                            self.tuplet = Some((ratio, left - 1));
+                        }
+                        ratio
+                    });
+                    self.events.push(Event {
+                        pitch: (degree > 0).then_some((degree, self.accidental, 0)),
+                        quarters: beat,
+                        tuplet,
+                        tie: false,
+                    });
+                    self.accidental = 0;
+                    self.dot = beat / 2.0;
+                }
+                '\'' | '\u{307}' => self.octave(1),
+                ',' | '\u{323}' => self.octave(-1),
+                '_' | '\u{332}' => self.shorten(1),
+                '\u{333}' => self.shorten(2),
+                '.' => {
+                    if let Some(event) = self.events.last_mut() {
+                        event.quarters += self.dot;
+                        self.dot /= 2.0;
+                    }
+                }
+                '-' => {
+                    if let Some(event) = self.events.last_mut() {
+                        event.quarters += beat;
+                        self.dot = event.quarters / 2.0;
+                    }
+                }
+                '~' => {
+                    if let Some(event) = self.events.last_mut() {
+                        event.tie = true;
+                    }
+                }
+                '(' => {
+                    let digits = chars[i..].iter().take_while(|c| c.is_ascii_digit());
+                    let count = digits.clone().count();
+                    let p = digits.collect::<String>().parse::<u8>().unwrap_or(3);
+                    i += count;
+                    self.tuplet = (p > 1).then(|| ((p, default_ratio(p, &self.ts)), p));
+                }
+                '|' => self.bar(),
+                _ => {}
+            }
+        }
+    }
+
+    fn octave(&mut self, shift: i8) {
+        if let Some(Event {
+            pitch: Some((_, _, octave)),
+            ..
+        }) = self.events.last_mut()
+        {
+            *octave += shift;
+        }
+    }
+
+    fn shorten(&mut self, lines: i32) {
+        if let Some(event) = self.events.last_mut() {
+            event.quarters /= 2f32.powi(lines);
+            self.dot = event.quarters / 2.0;
+        }
+    }
+
+    /// Ends the measure read so far
+    fn bar(&mut self) {
+        if self.events.is_empty() {
+            return;
+        }
+        let mut events: Vec<Event> = vec![];
+        for event in self.events.drain(..) {
+            match events.last_mut() {
+                Some(last)
+                    if last.tie
+                        && last.pitch.is_some()
+                        && last.pitch == event.pitch
+                        && last.tuplet.is_none()
+                        && event.tuplet.is_none()
+                        && notated(last.quarters + event.quarters, None).is_some() =>
+                {
+                    last.quarters += event.quarters;
+                    last.tie = event.tie;
+                }
+                _ => events.push(event),
+            }
+        }
+        let measure = (self.track.get_measures().len() + 1).to_string();
+        let split = |e: &Event| e.tie && e.pitch.is_some();
+        if events.last().is_some_and(split) {
+            self.warnings.push(
+                ImportWarningKind::Approximated,
+                "",
+                &measure,
+                "~",
+                "ties across a barline are split into two notes",
+            );
+        }
+        if events.iter().rev().skip(1).any(split) {
+            self.warnings.push(
+                ImportWarningKind::Approximated,
+                "",
+                &measure,
+                "~",
+                "tied notes without a single value are kept as two notes",
+            );
+        }
+        if events.iter().all(|e| e.pitch.is_none()) {
+            self.track.push(Measure::Rest);
+            return;
+        }
+        let notes = events
+            .iter()
+            .map(|event| {
+                let duration = notated(event.quarters, event.tuplet)
+                    .unwrap_or_else(|| Duration::from_quarters(event.quarters));
+                let tuning = event.pitch.and_then(|(degree, accidental, octave)| {
+                    pitch(degree, accidental, octave, &self.key)
+                });
+                match tuning {
+                    Some(tuning) => Note::new(tuning).with_duration(duration),
+                    None => Note::rest(duration),
+                }
+            })
+            .collect();
+        self.track.push(Measure::Note(notes));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chord, ChordQuality, PitchClass, Tuplet};
+
+    fn note(class: PitchClass, octave: i8, duration: Duration) -> Note {
+        Note::new(Tuning::new(class, octave)).with_duration(duration)
+    }
+
+    #[test]
+    fn test_export() {
+        let quarter = Duration::new(DurationBase::Quarter);
+        let eighth = Duration::new(DurationBase::Eighth);
+        let mut score = Score::<1>::new()
+            .with_time_signature(4, DurationBase::Quarter)
+            .with_key_signature(KeySignature::new(1));
+        score.push_measures([Measure::Note(vec![
+            note(PitchClass::G, 4, quarter),
+            note(PitchClass::A, 4, eighth),
+            note(PitchClass::B, 4, eighth),
+            note(PitchClass::D, 5, Duration::new(DurationBase::Half)),
+        ])]);
+        score.push_measures([Measure::Note(vec![
+            note(PitchClass::C, 5, quarter.dotted(1)),
+            note(PitchClass::Fs, 4, eighth),
+            note(PitchClass::F, 4, eighth),
+            note(PitchClass::D, 4, Duration::new(DurationBase::Sixteenth)),
+            note(PitchClass::G, 3, Duration::new(DurationBase::Sixteenth)),
+            Note::rest(quarter),
+        ])]);
+        score.push_measures([Measure::Rest]);
+        let text = Jianpu::new().with_title("小曲").export(&score);
+        assert_eq!(
+            text,
+            "小曲\n1=G 4/4\n1 2_3_ 5 - | 4. 7,_ b7,_5,__1,__ 0 | 0 0 0 0 ||\n"
+        );
+
+        let unicode = Jianpu::new().with_unicode_marks(true).export(&score);
+        assert!(unicode.contains("7\u{323}\u{332}"));
+
+        // Chords are written as their root under the symbol, triplets with their number
+        let chord = Chord::new(Tuning::new(PitchClass::C, 4), ChordQuality::Minor).unwrap();
+        let triplet = Duration::new(DurationBase::Eighth)
+            .with_tuplet(Tuplet::new(3, 2, DurationBase::Eighth).unwrap());
+        let mut track = Track::new();
+        track.push(Measure::Chords(vec![chord]));
+        track.push(Measure::Note(vec![
+            note(PitchClass::C, 4, triplet),
+            note(PitchClass::D, 4, triplet),
+            note(PitchClass::E, 4, triplet),
+        ]));
+        let text = Jianpu::new()
+            .with_time_signature(TimeSignature::new(1, DurationBase::Quarter))
+            .export_track(&track);
+        assert_eq!(text, "1=C 1/4\n\"Cm\"1 | (3 1_2_3_ ||\n");
+    }
+
+    #[test]
+    fn test_import() {
+        let text = "\
+茉莉花
+1=bB 3/4
+3 \"F\"2_3_ 5'~ | 5' 6_.1'__ 0 |
+#4,_5,_ (3 1_2_3_ 0 | 0 0 0 ||
+";
+        let import = Jianpu::new().import(text).unwrap();
+        assert_eq!(import.title.as_deref(), Some("茉莉花"));
+        assert_eq!(import.key_signature.fifths(), -2);
+        assert_eq!(import.time_signature.beats_per_measure(), 3);
+        let measures = import.track.get_measures();
+        assert_eq!(measures.len(), 4);
+        assert!(matches!(measures[3], Measure::Rest));
+
+        let Measure::Note(notes) = &measures[0] else {
+            panic!("notes expected")
+        };
+        // 3 of B flat major is D
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('D', 0, 5));
+        assert_eq!(notes[1].tuning().unwrap().spelling(), ('C', 0, 5));
+        assert_eq!(notes[1].duration(), Duration::new(DurationBase::Eighth));
+        assert_eq!(notes[3].tuning().unwrap().spelling(), ('F', 0, 6));
+
+        // The tie over the barline is split into two notes, with a warning
+        let Measure::Note(notes) = &measures[1] else {
+            panic!("notes expected")
+        };
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('F', 0, 6));
+        assert_eq!(import.warnings.len(), 1);
+        let tie = &import.warnings[0];
+        assert_eq!(
+            (tie.kind, tie.measure.as_str(), tie.element.as_str()),
+            (ImportWarningKind::Approximated, "1", "~")
+        );
+        assert_eq!(
+            notes[1].duration(),
+            Duration::new(DurationBase::Eighth).dotted(1)
+        );
+        assert_eq!(notes[2].tuning().unwrap().spelling(), ('B', -1, 5));
+        assert!(notes[3].is_rest());
+
+        let Measure::Note(notes) = &measures[2] else {
+            panic!("notes expected")
+        };
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('E', 0, 4));
+        assert!(notes[2].duration().tuplet.is_some());
+
+        // A written score reads back to the same notes
+        let mut score = Score::<1>::new()
+            .with_time_signature(3, DurationBase::Quarter)
+            .with_key_signature(KeySignature::new(-3));
+        score.push_measures([measures[1].clone()]);
+        let written = Jianpu::new().export(&score);
+        let read = Jianpu::new().import(&written).unwrap();
+        assert_eq!(read.key_signature.fifths(), -3);
+        let (Measure::Note(a), Measure::Note(b)) = (&measures[1], &read.track.get_measures()[0])
+        else {
+            panic!("notes expected")
+        };
+        let pitches = |notes: &[Note]| {
+            notes
+                .iter()
+                .map(|n| (n.tuning().map(|t| t.number()), n.duration()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(pitches(a), pitches(b));
+        assert!(read.warnings.is_empty());
+
+        // A half tied to an eighth has no single value
+        let read = Jianpu::new().import("1=C 4/4\n1-~1_ 0_ 0 |").unwrap();
+        let Measure::Note(notes) = &read.track.get_measures()[0] else {
+            panic!("notes expected")
+        };
+        assert_eq!(notes.len(), 4);
+        assert_eq!(read.warnings[0].element, "~");
+    }
+}
@@ -0,0 +1,891 @@
+//! Humdrum **kern
+//!
+//! Reads and writes the **kern representation of the Humdrum toolkit, the format of
+//! analytical corpora such as the Bach chorales:
+//! - one `**kern` spine per part, from the lowest part on the left. Track 0 is the
+//!   rightmost spine, the way scores list their parts from the top.
+//! - pitches by letter case, `c` for C4, `cc` for C5, `C` for C3, `CC` for C2, with
+//!   `#`, `-` and `n` accidentals
+//! - reciprocal durations with dots, `4.` for a dotted quarter, `12` for a triplet eighth,
+//!   `3%2` for other lengths, `0` and `00` for a breve and a longa, `r` for rests
+//! - ties `[`, `_` and `]`, barlines `=`, grace notes `q` and `Q`
+//! - the interpretations `*k[..]`, `*G:` (lowercase for minor), `*M3/4` and `*MM`, and the
+//!   reference records `!!!OTL` (title) and `!!!COM` (composer)
+//!
+//! As for ABC, tied notes are merged within a measure when their sum is a single value,
+//! otherwise, and across barlines, kept as two notes. Chords within a spine keep their
+//! top note, and a split spine (`*^`) keeps its first voice. Other spines such as
+//! `**dynam` or `**text` are skipped. Everything left out is reported as an
+//! `ImportWarning`.
+//!
+//! The writer aligns the tracks measure by measure in the score's meter, and writes chord
+//! measures as stacked notes and drum tracks as rests.
+
+use super::abc::{meter, notated};
+use super::layout::{layout, Content, Slot};
+use super::musicxml::{beat_type_number, low_register};
+use super::musicxml_import::{pitch_height, Warnings};
+use crate::{
+    Duration, DurationBase, Grace, ImportWarning, ImportWarningKind, KeySignature, Measure,
+    MusicError, Note, Score, TimeSignature, Tuning, Tuplet,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Stands in for the measures missing at the end of a track
+static REST: Measure = Measure::Rest;
+
+const SHARPS: [char; 7] = ['f', 'c', 'g', 'd', 'a', 'e', 'b'];
+
+/// **kern writer and reader, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct Kern {
+    title: Option<String>,
+    composer: Option<String>,
+}
+
+/// Result of reading a **kern file
+#[derive(Clone)]
+pub struct KernImport<const N: usize> {
+    pub score: Score<N>,
+    pub title: Option<String>,
+    pub composer: Option<String>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl Kern {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        Kern {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    pub fn with_composer(self, composer: impl Into<String>) -> Self {
+        Kern {
+            composer: Some(composer.into()),
+            ..self
+        }
+    }
+
+    /// **kern file of a score, one spine per track
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let mut out = String::new();
+        if let Some(composer) = &self.composer {
+            let _ = writeln!(out, "!!!COM: {composer}");
+        }
+        if let Some(title) = &self.title {
+            let _ = writeln!(out, "!!!OTL: {title}");
+        }
+        // Lowest part on the left
+        let spines = (0..N).rev().collect::<Vec<_>>();
+        let record = |token: &dyn Fn(usize) -> String| {
+            spines
+                .iter()
+                .map(|t| token(*t))
+                .collect::<Vec<_>>()
+                .join("\t")
+                + "\n"
+        };
+
+        out += &record(&|_| "**kern".into());
+        out += &record(&|t| {
+            let measures = score.get_tracks()[t].get_measures();
+            match measures.iter().any(|m| matches!(m, Measure::Percussion(_))) {
+                true => "*clefX".into(),
+                false if low_register(measures) => "*clefF4".into(),
+                false => "*clefG2".into(),
+            }
+        });
+        let key = score.key_signature();
+        out += &record(&|_| key_signature(key));
+        out += &record(&|_| designation(key));
+        out += &record(&|t| {
+            let ts = score.track_time_signature(t);
+            format!(
+                "*M{}/{}",
+                ts.beats_per_measure(),
+                beat_type_number(ts.beat_type())
+            )
+        });
+        let beat = score.time_signature().beat_type().in_quarters();
+        let tempo = (score.tempo() * beat).round();
+        out += &record(&|_| format!("*MM{tempo}"));
+
+        for m in 0..score.measure_count().max(1) {
+            out += &record(&|_| format!("={}", m + 1));
+            // Onset in ticks, then the order of the grace notes before a note
+            let mut slices: BTreeMap<(i64, usize), Vec<String>> = BTreeMap::new();
+            for (column, track) in spines.iter().enumerate() {
+                let ts = score.track_time_signature(*track);
+                let measure = score.get_tracks()[*track]
+                    .get_measures()
+                    .get(m)
+                    .unwrap_or(&REST);
+                let mut position = 0.0;
+                let mut graces = 0;
+                for slot in layout(measure, &ts) {
+                    let ticks = (position * 960.0f32).round() as i64;
+                    let order = match slot.is_grace() {
+                        true => {
+                            graces += 1;
+                            graces - 1
+                        }
+                        false => {
+                            graces = 0;
+                            usize::MAX
+                        }
+                    };
+                    let tokens = slices
+                        .entry((ticks, order))
+                        .or_insert_with(|| vec![".".to_string(); N]);
+                    tokens[column] = slot_token(&slot);
+                    if !slot.is_grace() {
+                        position += slot.duration.in_quarters();
+                    }
+                }
+            }
+            for tokens in slices.into_values() {
+                out += &(tokens.join("\t") + "\n");
+            }
+        }
+        out += &record(&|_| "==".into());
+        out += &record(&|_| "*-".into());
+        out
+    }
+
+    /// Read a **kern file into a score of `N` tracks, track 0 from the rightmost spine.
+    /// Spines beyond `N` (the lowest parts) are dropped with a warning.
+    pub fn import<const N: usize>(text: &str) -> Result<KernImport<N>, MusicError> {
+        let mut reader = Reader::default();
+        for line in text.trim_start_matches('\u{feff}').lines() {
+            reader.line(line.trim_end_matches('\r'));
+        }
+        reader.finish()
+    }
+}
+
+/// `*k[f#c#]`
+fn key_signature(key: &KeySignature) -> String {
+    let count = key.fifths().unsigned_abs() as usize;
+    let accidentals = match key.fifths() > 0 {
+        true => SHARPS[..count]
+            .iter()
+            .map(|l| format!("{l}#"))
+            .collect::<String>(),
+        false => SHARPS[7 - count..]
+            .iter()
+            .rev()
+            .map(|l| format!("{l}-"))
+            .collect(),
+    };
+    format!("*k[{accidentals}]")
+}
+
+/// `*G:`, `*e:` or `*B-:`
+fn designation(key: &KeySignature) -> String {
+    let (letter, alter, _) = key.tonic().spelling();
+    let letter = match key.is_minor() {
+        true => letter.to_ascii_lowercase(),
+        false => letter,
+    };
+    format!("*{letter}{}:", accidentals(alter))
+}
+
+fn accidentals(alter: i8) -> String {
+    let sign = if alter > 0 { "#" } else { "-" };
+    sign.repeat(alter.unsigned_abs() as usize)
+}
+
+/// Reciprocal duration, e.g. `8.` or `12`
+fn recip(duration: Duration) -> String {
+    let whole = duration.base.in_whole();
+    let (actual, normal) = duration
+        .tuplet
+        .map_or((1, 1), |t| (t.actual_notes as u32, t.base_notes as u32));
+    let number = match whole > 1.0 {
+        true if actual == normal => "0".repeat(whole.log2().round() as usize),
+        true => format!("{actual}%{}", normal * whole as u32),
+        false => {
+            let n = (1.0 / whole).round() as u32 * actual;
+            match n % normal {
+                0 => (n / normal).to_string(),
+                _ => format!("{n}%{normal}"),
+            }
+        }
+    };
+    number + &".".repeat(duration.dots as usize)
+}
+
+/// `cc#`, `B-` or `CC`
+fn kern_pitch(tuning: Tuning) -> String {
+    let (letter, alter, octave) = tuning.spelling();
+    let name = match octave >= 4 {
+        true => letter
+            .to_ascii_lowercase()
+            .to_string()
+            .repeat((octave - 3) as usize),
+        false => letter.to_string().repeat((4 - octave) as usize),
+    };
+    name + &accidentals(alter)
+}
+
+fn slot_token(slot: &Slot) -> String {
+    let recip = recip(slot.duration);
+    let note = |tuning: Tuning| {
+        let (open, close) = match (slot.tie, slot.continued) {
+            (true, false) => ("[", ""),
+            (true, true) => ("", "_"),
+            (false, true) => ("", "]"),
+            (false, false) => ("", ""),
+        };
+        format!("{open}{recip}{}{close}", kern_pitch(tuning))
+    };
+    match &slot.content {
+        Content::Note(n, tuning) => match n.expression().grace {
+            Some(Grace::Acciaccatura) => format!("{recip}{}q", kern_pitch(*tuning)),
+            Some(Grace::Appoggiatura) => format!("{recip}{}Q", kern_pitch(*tuning)),
+            None => note(*tuning),
+        },
+        Content::Chord(chord) => chord
+            .components()
+            .into_iter()
+            .map(note)
+            .collect::<Vec<_>>()
+            .join(" "),
+        Content::Drums(_) | Content::Rest | Content::MeasureRest => format!("{recip}r"),
+    }
+}
+
+/// A note or rest read from a token
+#[derive(Debug, Clone)]
+struct KernNote {
+    tuning: Option<Tuning>,
+    /// Written length in quarters and tuplet ratio
+    quarters: f32,
+    tuplet: Option<(u8, u8)>,
+    dots: u8,
+    /// `[` or `_`, tied to the next note
+    tie: bool,
+    /// `_` or `]`, tied from the previous note
+    tied: bool,
+    grace: Option<Grace>,
+}
+
+impl KernNote {
+    fn duration(&self) -> Duration {
+        let plain = self.quarters * (2.0 - 0.5f32.powi(self.dots as i32));
+        notated(plain, self.tuplet).unwrap_or_else(|| Duration::from_quarters(plain))
+    }
+
+    fn note(&self) -> Note {
+        let duration = self.duration();
+        match (self.tuning, self.grace) {
+            (Some(tuning), Some(grace)) => {
+                Note::new(tuning).with_duration(duration).with_grace(grace)
+            }
+            (Some(tuning), None) => Note::new(tuning).with_duration(duration),
+            (None, _) => Note::rest(duration),
+        }
+    }
+}
+
+/// Reads one subtoken such as `[4.cc#` or `8rL`
+fn kern_note(token: &str) -> Option<KernNote> {
+    let chars = token.chars().collect::<Vec<_>>();
+    let mut recip = String::new();
+    let mut dots = 0;
+    let mut letters = String::new();
+    let mut alter = 0;
+    let mut note = KernNote {
+        tuning: None,
+        quarters: 1.0,
+        tuplet: None,
+        dots: 0,
+        tie: false,
+        tied: false,
+        grace: None,
+    };
+    let mut rest = false;
+    for c in chars {
+        match c {
+            '0'..='9' | '%' if letters.is_empty() && !rest => recip.push(c),
+            '.' => dots += 1,
+            'a'..='g' | 'A'..='G' => letters.push(c),
+            '#' => alter = i8::saturating_add(alter, 1),
+            '-' => alter = i8::saturating_sub(alter, 1),
+            'r' => rest = true,
+            '[' => note.tie = true,
+            '_' => (note.tie, note.tied) = (true, true),
+            ']' => note.tied = true,
+            'q' => note.grace = Some(Grace::Acciaccatura),
+            'Q' => note.grace = Some(Grace::Appoggiatura),
+            // Beams, articulations, slurs, stems and editorial marks
+            _ => {}
+        }
+    }
+    note.dots = dots;
+
+    let (quarters, tuplet) = match recip.split_once('%') {
+        Some((a, b)) => (4.0 * b.parse::<f32>().ok()? / a.parse::<f32>().ok()?, None),
+        None if recip.is_empty() => (0.5, None),
+        None if recip.chars().all(|c| c == '0') => (4.0 * 2f32.powi(recip.len() as i32), None),
+        None => {
+            let n = recip.parse::<u32>().ok().filter(|n| *n > 0)?;
+            let power = 1 << n.ilog2();
+            let base = 4.0 / power as f32;
+            let ratio = (n != power).then(|| {
+                let g = gcd(n, power);
+                ((n / g) as u8, (power / g) as u8)
+            });
+            (base, ratio)
+        }
+    };
+    // Zero and overflowing lengths, and lengths no `DurationBase` can write
+    let shortest = DurationBase::SixtyFourth.in_quarters();
+    let longest = DurationBase::Maxima.in_quarters();
+    if !(shortest..2.0 * longest).contains(&quarters) {
+        return None;
+    }
+    note.quarters = quarters;
+    note.tuplet = tuplet.filter(|(p, q)| {
+        DurationBase::from_quarters(quarters)
+            .and_then(|base| Tuplet::new(*p, *q, base))
+            .is_ok()
+    });
+
+    if !rest {
+        let letter = letters.chars().next()?;
+        if !letters.chars().all(|c| c == letter) {
+            return None;
+        }
+        let count = i8::try_from(letters.len()).ok()?;
+        let octave = match letter.is_ascii_lowercase() {
+            true => count.checked_add(3)?,
+            false => 4i8.checked_sub(count)?,
+        };
+        if alter.abs() > 2 || !(-1..=9).contains(&octave) {
+            return None;
+        }
+        note.tuning = Some(Tuning::from_spelling(letter, alter, octave).ok()?);
+    }
+    Some(note)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b),
+    }
+}
+
+/// A column of the file and the part it belongs to
+#[derive(Debug, Clone)]
+struct Column {
+    /// Index of the **kern spine from the left, `None` for other spines
+    part: Option<usize>,
+    /// The first voice of a split spine
+    primary: bool,
+}
+
+#[derive(Default)]
+struct Part {
+    measures: Vec<Measure>,
+    notes: Vec<KernNote>,
+    meter: Option<TimeSignature>,
+}
+
+#[derive(Default)]
+struct Reader {
+    columns: Vec<Column>,
+    parts: Vec<Part>,
+    title: Option<String>,
+    composer: Option<String>,
+    fifths: Option<i8>,
+    minor: Option<(Tuning, bool)>,
+    tempo: Option<f32>,
+    /// Measure number of the last barline, for warnings
+    measure: String,
+    started: bool,
+    warnings: Warnings,
+}
+
+impl Reader {
+    fn line(&mut self, line: &str) {
+        if let Some((key, value)) = line.strip_prefix("!!!").and_then(|r| r.split_once(':')) {
+            let value = Some(value.trim().to_string());
+            match key.trim() {
+                "OTL" if self.title.is_none() => self.title = value,
+                "COM" if self.composer.is_none() => self.composer = value,
+                _ => {}
+            }
+            return;
+        }
+        if line.is_empty() || line.starts_with('!') {
+            return;
+        }
+        let tokens = line.split('\t').collect::<Vec<_>>();
+        if !self.started && !tokens[0].starts_with("**") {
+            return;
+        }
+        if tokens[0].starts_with("**") {
+            self.started = true;
+            for token in tokens {
+                let part = (token == "**kern").then(|| {
+                    self.parts.push(Part::default());
+                    self.parts.len() - 1
+                });
+                self.columns.push(Column {
+                    part,
+                    primary: true,
+                });
+            }
+        } else if tokens[0].starts_with('*') {
+            self.interpretation(&tokens);
+        } else if tokens[0].starts_with('=') {
+            self.measure = tokens[0].trim_start_matches('=').to_string();
+            self.bar();
+        } else {
+            self.data(&tokens);
+        }
+    }
+
+    fn interpretation(&mut self, tokens: &[&str]) {
+        let mut columns = vec![];
+        let mut i = 0;
+        while i < tokens.len().min(self.columns.len()) {
+            let column = self.columns[i].clone();
+            let token = tokens[i];
+            i += 1;
+            match token {
+                "*^" => {
+                    if column.part.is_some() {
+                        self.warn(
+                            ImportWarningKind::Approximated,
+                            "*^",
+                            "the first voice of a split spine is kept",
+                        );
+                    }
+                    columns.push(column.clone());
+                    columns.push(Column {
+                        primary: false,
+                        ..column
+                    });
+                }
+                "*v" => {
+                    // Joins with the following columns that are joined too
+                    let mut primary = column.primary;
+                    while tokens.get(i) == Some(&"*v") && i < self.columns.len() {
+                        primary |= self.columns[i].primary;
+                        i += 1;
+                    }
+                    columns.push(Column { primary, ..column });
+                }
+                "*-" => {}
+                "*x" => {
+                    self.warn(
+                        ImportWarningKind::Unsupported,
+                        "*x",
+                        "spine exchanges are ignored",
+                    );
+                    columns.push(column);
+                }
+                _ => {
+                    if let (Some(part), true) = (column.part, column.primary) {
+                        self.interpret(part, token);
+                    }
+                    columns.push(column);
+                }
+            }
+        }
+        self.columns = columns;
+    }
+
+    fn interpret(&mut self, part: usize, token: &str) {
+        let Some(value) = token.strip_prefix('*') else {
+            return;
+        };
+        if let Some(tempo) = value.strip_prefix("MM") {
+            if let Ok(tempo) = tempo.parse::<f32>() {
+                self.tempo.get_or_insert(tempo);
+            }
+        } else if let Some(ts) = value.strip_prefix('M').and_then(meter) {
+            self.parts[part].meter.get_or_insert(ts);
+        } else if let Some(accidentals) = value.strip_prefix("k[") {
+            let sharps = accidentals.matches('#').count() as i8;
+            let flats = accidentals.matches('-').count() as i8;
+            self.fifths.get_or_insert(sharps - flats);
+        } else if let Some(tonic) = value.strip_suffix(':') {
+            let mut chars = tonic.chars();
+            let letter = chars.next().filter(|c| "abcdefgABCDEFG".contains(*c));
+            let alter = chars
+                .map(|c| match c {
+                    '#' => Some(1),
+                    '-' => Some(-1),
+                    _ => None,
+                })
+                .sum::<Option<i8>>();
+            if let (Some(letter), Some(alter)) = (letter, alter) {
+                if let Ok(tuning) = Tuning::from_spelling(letter, alter, 4) {
+                    self.minor
+                        .get_or_insert((tuning, letter.is_ascii_lowercase()));
+                }
+            }
+        }
+    }
+
+    fn data(&mut self, tokens: &[&str]) {
+        for (column, token) in self.columns.clone().iter().zip(tokens) {
+            let Some(part) = column.part else {
+                continue;
+            };
+            if *token == "." {
+                continue;
+            }
+            if !column.primary {
+                self.warn(
+                    ImportWarningKind::Unsupported,
+                    "*^",
+                    "the second voice of a split spine is skipped",
+                );
+                continue;
+            }
+            let mut notes = token.split(' ').filter_map(kern_note).collect::<Vec<_>>();
+            if notes.len() < token.split(' ').count() {
+                self.warn(ImportWarningKind::Invalid, token, "unreadable token");
+            }
+            if notes.len() > 1 {
+                self.warn(
+                    ImportWarningKind::Approximated,
+                    "chord",
+                    "chords within a spine keep their top note",
+                );
+                notes.sort_by_key(|n| n.tuning.map_or(i32::MIN, pitch_height));
+            }
+            if let Some(note) = notes.pop() {
+                self.push(part, note);
+            }
+        }
+    }
+
+    /// Adds a note to a part, merging it into the previous one when tied
+    fn push(&mut self, part: usize, note: KernNote) {
+        let notes = &mut self.parts[part].notes;
+        if let Some(last) = notes.last_mut() {
+            let sum = last.duration().in_quarters() + note.duration().in_quarters();
+            let same = last.tuning.is_some()
+                && last.tuning.map(pitch_height) == note.tuning.map(pitch_height);
+            if last.tie && note.tied && same {
+                match notated(sum, None).filter(|_| last.tuplet.is_none() && note.tuplet.is_none())
+                {
+                    Some(duration) => {
+                        last.quarters = duration.base.in_quarters();
+                        last.dots = duration.dots;
+                        last.tie = note.tie;
+                        return;
+                    }
+                    None => self.warn(
+                        ImportWarningKind::Approximated,
+                        "tie",
+                        "tied notes without a single value are kept as two notes",
+                    ),
+                }
+            }
+        }
+        self.parts[part].notes.push(note);
+    }
+
+    fn bar(&mut self) {
+        if self.parts.iter().all(|p| p.notes.is_empty()) {
+            return;
+        }
+        if self
+            .parts
+            .iter()
+            .any(|p| p.notes.last().is_some_and(|n| n.tie && n.tuning.is_some()))
+        {
+            self.warn(
+                ImportWarningKind::Approximated,
+                "tie",
+                "ties across a barline are split into two notes",
+            );
+        }
+        for part in &mut self.parts {
+            let notes = std::mem::take(&mut part.notes);
+            part.measures
+                .push(match notes.iter().all(|n| n.tuning.is_none()) {
+                    true => Measure::Rest,
+                    false => Measure::Note(notes.iter().map(KernNote::note).collect()),
+                });
+        }
+    }
+
+    fn warn(&mut self, kind: ImportWarningKind, element: &str, message: &str) {
+        let measure = self.measure.clone();
+        self.warnings.push(kind, "", &measure, element, message);
+    }
+
+    fn finish<const N: usize>(mut self) -> Result<KernImport<N>, MusicError> {
+        if self.parts.is_empty() {
+            return Err(MusicError::ParseError("no **kern spine found".into()));
+        }
+        self.bar();
+        if self.parts.len() > N {
+            let message = format!("the score has {N} tracks");
+            self.warn(ImportWarningKind::Unsupported, "**kern", &message);
+        }
+
+        let meter = self
+            .parts
+            .iter()
+            .rev()
+            .find_map(|p| p.meter)
+            .unwrap_or(TimeSignature::new(4, DurationBase::Quarter));
+        let key = match (self.fifths, self.minor) {
+            (Some(fifths), Some((_, true))) => KeySignature::minor(fifths),
+            (Some(fifths), _) => KeySignature::new(fifths),
+            (None, Some((tonic, minor))) => match KeySignature::from_tonic(tonic, minor) {
+                Ok(key) => key,
+                Err(error) => {
+                    let (letter, alter, _) = tonic.spelling();
+                    let letter = match minor {
+                        true => letter.to_ascii_lowercase(),
+                        false => letter,
+                    };
+                    let designation = format!("*{letter}{}:", accidentals(alter));
+                    self.warn(ImportWarningKind::Invalid, &designation, &error.to_string());
+                    KeySignature::default()
+                }
+            },
+            (None, None) => KeySignature::default(),
+        };
+        let mut score = Score::<N>::new()
+            .with_time_signature(meter.beats_per_measure(), meter.beat_type())
+            .with_key_signature(key)
+            .with_tempo(self.tempo.unwrap_or(120.0) / meter.beat_type().in_quarters());
+
+        // Rightmost spine first
+        let parts = self.parts.iter().rev().take(N).collect::<Vec<_>>();
+        for (track, part) in parts.iter().enumerate() {
+            if let Some(ts) = part.meter.filter(|ts| ts != score.time_signature()) {
+                score.set_track_time_signature(track, ts)?;
+            }
+        }
+        let length = parts.iter().map(|p| p.measures.len()).max().unwrap_or(0);
+        for index in 0..length {
+            score.push_measures(std::array::from_fn(|track| {
+                parts
+                    .get(track)
+                    .and_then(|part| part.measures.get(index).cloned())
+                    .unwrap_or(Measure::Rest)
+            }));
+        }
+
+        Ok(KernImport {
+            score,
+            title: self.title,
+            composer: self.composer,
+            warnings: self.warnings.list,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chord, ChordQuality, PitchClass};
+
+    const CHORALE: &str = "\
+!!!COM: Bach, Johann Sebastian
+!!!OTL: Aus meines Herzens Grunde
+**kern\t**kern\t**dynam\t**kern
+*clefF4\t*clefG2\t*\t*clefG2
+*k[f#]\t*k[f#]\t*\t*k[f#]
+*G:\t*G:\t*\t*G:
+*M3/4\t*M3/4\t*\t*M3/4
+*MM100\t*MM100\t*\t*MM100
+4GG\t4d\tp\t4g
+=1\t=1\t=1\t=1
+4G\t4d\t.\t8g\\L
+.\t.\t.\t8a\\J
+4F#\t[4d\t.\t4b 4dd
+4E\t8d]\t.\t4cc
+.\t8c#\t.\t.
+=2\t=2\t=2\t=2
+*\t*^\t*\t*
+2.D\t2.d\t2.B\t.\t2.a;
+*\t*v\t*v\t*\t*
+==\t==\t==\t==
+*-\t*-\t*-\t*-
+";
+
+    #[test]
+    fn test_import() {
+        let import = Kern::import::<3>(CHORALE).unwrap();
+        assert_eq!(import.title.as_deref(), Some("Aus meines Herzens Grunde"));
+        assert_eq!(import.composer.as_deref(), Some("Bach, Johann Sebastian"));
+        let score = &import.score;
+        assert_eq!(score.key_signature().fifths(), 1);
+        assert_eq!(score.time_signature().beats_per_measure(), 3);
+        assert_eq!(score.tempo(), 100.0);
+        // The pickup, one full measure and the last one
+        assert_eq!(score.measure_count(), 3);
+
+        // Track 0 is the soprano, the rightmost spine
+        let Measure::Note(soprano) = &score.get_tracks()[0].get_measures()[1] else {
+            panic!("notes expected")
+        };
+        assert_eq!(soprano.len(), 4);
+        assert_eq!(soprano[0].tuning().unwrap().spelling(), ('G', 0, 4));
+        assert_eq!(soprano[0].duration(), Duration::new(DurationBase::Eighth));
+        assert_eq!(
+            soprano[2].tuning().unwrap().spelling(),
+            ('D', 0, 5),
+            "top of the chord"
+        );
+
+        // The tied d is merged into a dotted quarter
+        let Measure::Note(alto) = &score.get_tracks()[1].get_measures()[1] else {
+            panic!("notes expected")
+        };
+        assert_eq!(
+            alto[1].duration(),
+            Duration::new(DurationBase::Quarter).dotted(1)
+        );
+        assert_eq!(alto[2].tuning().unwrap().spelling(), ('C', 1, 4));
+
+        let Measure::Note(bass) = &score.get_tracks()[2].get_measures()[0] else {
+            panic!("notes expected")
+        };
+        assert_eq!(bass[0].tuning().unwrap().spelling(), ('G', 0, 2));
+
+        let elements = import
+            .warnings
+            .iter()
+            .map(|w| w.element.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(elements, ["chord", "*^"]);
+
+        // A tie over the barline is reported, a run of letters past any octave is refused
+        let text = format!(
+            "**kern\n*M2/4\n=1\n[2c\n=2\n2c]\n=3\n2{}\n==\n*-\n",
+            "c".repeat(200)
+        );
+        let import = Kern::import::<1>(&text).unwrap();
+        let warnings = import
+            .warnings
+            .iter()
+            .map(|w| (w.kind, w.element.as_str()))
+            .collect::<Vec<_>>();
+        assert!(warnings.contains(&(ImportWarningKind::Approximated, "tie")));
+        assert!(warnings
+            .iter()
+            .any(|(kind, _)| *kind == ImportWarningKind::Invalid));
+
+        // Zero, overflowing and out of range lengths are unreadable tokens
+        let zeros = format!("{}c", "0".repeat(50));
+        for token in [
+            "0%1c",
+            "1%9999c",
+            zeros.as_str(),
+            "3%0c",
+            "4000000000c",
+            "128c",
+        ] {
+            let import =
+                Kern::import::<1>(&format!("**kern\n*M2/4\n=1\n{token}\n4c\n*-\n")).unwrap();
+            let warning = import.warnings.iter().find(|w| w.element == token);
+            assert_eq!(
+                warning.map(|w| w.kind),
+                Some(ImportWarningKind::Invalid),
+                "{token}"
+            );
+        }
+
+        // A designation without a key signature falls back to C major
+        let import = Kern::import::<1>("**kern\n*G#:\n*M2/4\n=1\n2c\n*-\n").unwrap();
+        assert_eq!(import.score.key_signature(), &KeySignature::default());
+        assert!(import
+            .warnings
+            .iter()
+            .any(|w| w.element == "*G#:" && w.kind == ImportWarningKind::Invalid));
+    }
+
+    #[test]
+    fn test_export() {
+        let quarter = Duration::new(DurationBase::Quarter);
+        let triplet = Duration::new(DurationBase::Eighth)
+            .with_tuplet(Tuplet::new(3, 2, DurationBase::Eighth).unwrap());
+        let mut score = Score::<2>::new()
+            .with_time_signature(2, DurationBase::Quarter)
+            .with_key_signature(KeySignature::minor(-1))
+            .with_tempo(90);
+        let note =
+            |class, octave, duration| Note::new(Tuning::new(class, octave)).with_duration(duration);
+        score.push_measures([
+            Measure::Note(vec![
+                note(PitchClass::Bb, 4, quarter.dotted(1)),
+                note(PitchClass::C, 5, Duration::new(DurationBase::Eighth)),
+            ]),
+            Measure::Chords(vec![Chord::new(
+                Tuning::new(PitchClass::D, 3),
+                ChordQuality::Minor,
+            )
+            .unwrap()]),
+        ]);
+        score.push_measures([
+            Measure::Note(vec![
+                note(PitchClass::D, 5, triplet),
+                note(PitchClass::Cs, 5, triplet),
+                note(PitchClass::D, 5, triplet),
+                Note::rest(quarter),
+            ]),
+            Measure::Rest,
+        ]);
+        let text = Kern::new().with_title("Study").export(&score);
+        assert_eq!(
+            text,
+            "\
+!!!OTL: Study
+**kern\t**kern
+*clefF4\t*clefG2
+*k[b-]\t*k[b-]
+*d:\t*d:
+*M2/4\t*M2/4
+*MM90\t*MM90
+=1\t=1
+2D 2F 2A\t4.b-
+.\t8cc
+=2\t=2
+2r\t12dd
+.\t12cc#
+.\t12dd
+.\t4r
+==\t==
+*-\t*-
+"
+        );
+
+        // And back
+        let import = Kern::import::<2>(&text).unwrap();
+        assert_eq!(import.score.key_signature().fifths(), -1);
+        assert!(import.score.key_signature().is_minor());
+        let (Measure::Note(a), Measure::Note(b)) = (
+            &score.get_tracks()[0].get_measures()[1],
+            &import.score.get_tracks()[0].get_measures()[1],
+        ) else {
+            panic!("notes expected")
+        };
+        let notes = |notes: &[Note]| {
+            notes
+                .iter()
+                .map(|n| (n.tuning().map(|t| t.number()), n.duration()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(notes(a), notes(b));
+    }
+}
@@ -0,0 +1,206 @@
+//! Measure layout shared by the text exporters
+//!
+//! Turns a `Measure` into a sequence of notated values: notes as written, chord and
+//! percussion spans split along the meter into tied values, and rests filling the end of
+//! the measure.
+
+use crate::{
+    Chord, Duration, DurationBase, Measure, Note, PercussionNote, TimeSignature, Tuning, Tuplet,
+};
+
+/// What sounds during a slot
+#[derive(Debug, Clone)]
+pub(crate) enum Content<'a> {
+    /// Pitched note, rests of a melody are `Rest`
+    Note(&'a Note, Tuning),
+    Chord(&'a Chord),
+    /// Hits starting together
+    Drums(Vec<PercussionNote>),
+    Rest,
+    /// Rest filling a whole measure
+    MeasureRest,
+}
+
+/// One notated value of a measure
+#[derive(Debug, Clone)]
+pub(crate) struct Slot<'a> {
+    pub content: Content<'a>,
+    pub duration: Duration,
+    /// Tied to the next slot
+    pub tie: bool,
+    /// Tied from the previous slot
+    pub continued: bool,
+}
+
+impl Slot<'_> {
+    pub fn is_grace(&self) -> bool {
+        matches!(self.content, Content::Note(note, _) if note.is_grace())
+    }
+}
+
+/// Slots of a measure in `ts`, in order
+pub(crate) fn layout<'a>(measure: &'a Measure, ts: &TimeSignature) -> Vec<Slot<'a>> {
+    let beat = ts.beat_type().in_quarters();
+    let length = ts.beats_per_measure() as f32;
+    match measure {
+        Measure::Rest => vec![Slot {
+            content: Content::MeasureRest,
+            duration: Duration::from_quarters(ts.measure_quarters()),
+            tie: false,
+            continued: false,
+        }],
+        Measure::Note(notes) => {
+            let mut slots = vec![];
+            let mut position = 0.0;
+            for note in notes {
+                slots.push(Slot {
+                    content: match note.tuning() {
+                        Some(tuning) => Content::Note(note, tuning),
+                        None => Content::Rest,
+                    },
+                    duration: note.duration(),
+                    tie: false,
+                    continued: false,
+                });
+                if !note.is_grace() {
+                    position += note.duration().in_quarters() / beat;
+                }
+            }
+            if position < length - 1e-3 {
+                slots.extend(span(Content::Rest, position, length - position, ts));
+            }
+            slots
+        }
+        Measure::Chords(chords) => {
+            let share = length / chords.len().max(1) as f32;
+            chords
+                .iter()
+                .enumerate()
+                .flat_map(|(i, chord)| span(Content::Chord(chord), share * i as f32, share, ts))
+                .collect()
+        }
+        Measure::Percussion(hits) => {
+            let mut starts = hits
+                .iter()
+                .map(|hit| hit.position())
+                .filter(|p| *p >= 0.0 && *p < length)
+                .collect::<Vec<_>>();
+            starts.sort_by(|a, b| a.total_cmp(b));
+            starts.dedup_by(|a, b| (*a - *b).abs() < 1e-3);
+
+            let mut slots = vec![];
+            if starts.first().is_none_or(|first| *first > 1e-3) {
+                let end = starts.first().copied().unwrap_or(length);
+                slots.extend(span(Content::Rest, 0.0, end, ts));
+            }
+            for (i, start) in starts.iter().enumerate() {
+                let end = starts.get(i + 1).copied().unwrap_or(length);
+                let together = hits
+                    .iter()
+                    .filter(|hit| (hit.position() - start).abs() < 1e-3)
+                    .copied()
+                    .collect::<Vec<_>>();
+                // A hit does not ring through the following rest of the span
+                let mut hit = span(Content::Drums(together), *start, end - start, ts);
+                if let Some((first, rest)) = hit.split_first_mut() {
+                    first.tie = false;
+                    for slot in rest {
+                        slot.content = Content::Rest;
+                        slot.tie = false;
+                        slot.continued = false;
+                    }
+                }
+                slots.extend(hit);
+            }
+            slots
+        }
+    }
+}
+
+/// Tied values covering `length` beats from `start`, or a single tuplet value when the
+/// span is not notatable in the meter (three chords in a 4/4 bar)
+fn span<'a>(content: Content<'a>, start: f32, length: f32, ts: &TimeSignature) -> Vec<Slot<'a>> {
+    let durations = ts
+        .decompose(start, length)
+        .unwrap_or_else(|_| vec![tuplet_value(length * ts.beat_type().in_quarters())]);
+    let count = durations.len();
+    durations
+        .into_iter()
+        .enumerate()
+        .map(|(i, duration)| Slot {
+            content: content.clone(),
+            duration,
+            tie: i + 1 < count && !matches!(content, Content::Rest),
+            continued: i > 0 && !matches!(content, Content::Rest),
+        })
+        .collect()
+}
+
+/// Value of `quarters` as a triplet (or other tuplet) of a plain value
+fn tuplet_value(quarters: f32) -> Duration {
+    for actual in [3u8, 5, 6, 7] {
+        let normal = match actual {
+            3 => 2,
+            _ => 4,
+        };
+        let nominal = quarters * actual as f32 / normal as f32;
+        if let Ok(base) = DurationBase::from_quarters(nominal) {
+            if let Ok(tuplet) = Tuplet::new(actual, normal, base) {
+                return Duration::new(base).with_tuplet(tuplet);
+            }
+        }
+    }
+    Duration::from_quarters(quarters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChordQuality, DrumSound, PitchClass, Tuning};
+
+    #[test]
+    fn test_layout() {
+        let ts = TimeSignature::new(4, DurationBase::Quarter);
+        let c = Tuning::new(PitchClass::C, 4);
+
+        // Notes are kept, the rest of the measure is filled
+        let measure = Measure::Note(vec![
+            Note::new(c).with_duration(Duration::new(DurationBase::Half))
+        ]);
+        let slots = layout(&measure, &ts);
+        assert_eq!(slots.len(), 2);
+        assert!(matches!(slots[1].content, Content::Rest));
+        assert_eq!(slots[1].duration, Duration::new(DurationBase::Half));
+
+        // Two chords in 3/4: a dotted quarter, then an eighth tied over the middle beat
+        let three = TimeSignature::new(3, DurationBase::Quarter);
+        let chord = Chord::new(c, ChordQuality::Major).unwrap();
+        let measure = Measure::Chords(vec![chord.clone(), chord.clone()]);
+        let slots = layout(&measure, &three);
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0].duration.dots, 1);
+        assert!(!slots[0].tie && slots[1].tie && slots[2].continued && !slots[2].tie);
+
+        // Three chords in 4/4 become half-note triplets
+        let measure = Measure::Chords(vec![chord.clone(), chord.clone(), chord]);
+        let slots = layout(&measure, &ts);
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots[0].duration.base, DurationBase::Half);
+        assert!(slots[0].duration.tuplet.is_some());
+
+        // Hits sound until the next one, a leading rest is added
+        let measure = Measure::Percussion(vec![
+            PercussionNote::new(DrumSound::BassDrum).at(1.0),
+            PercussionNote::new(DrumSound::ClosedHiHat).at(1.0),
+            PercussionNote::new(DrumSound::AcousticSnare).at(2.0),
+        ]);
+        let slots = layout(&measure, &ts);
+        assert!(matches!(slots[0].content, Content::Rest));
+        assert!(matches!(&slots[1].content, Content::Drums(hits) if hits.len() == 2));
+        assert_eq!(
+            slots[2].duration,
+            Duration::new(DurationBase::Half),
+            "snare on beat 3 fills the second half"
+        );
+    }
+}
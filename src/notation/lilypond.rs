@@ -0,0 +1,851 @@
+//! LilyPond export
+//!
+//! Writes a `Score` as a LilyPond 2.24 file for engraving:
+//! - one staff per track, pitches in LilyPond's default (Dutch) names as the `Tuning`
+//!   spells them, durations, ties and `\tuplet` groups from the measure layout
+//! - chord measures as stacked notes under `\chordmode` chord names
+//! - key, time signature with its beat grouping, and tempo. A track in its own meter
+//!   gets its own time signature, a track tuplet is scaled with `\scaleDurations`.
+//! - drum tracks on a `DrumStaff`
+//! - a `TabStaff` under each track given a `StringedFretboard`: notes take the string
+//!   closest to the previous fret, chords the easiest fingering of the
+//!   `ChordFingeringGenerator`
+//! - dynamics, hairpins, slurs, articulations, fermatas, grace notes, lyrics, repeats,
+//!   voltas, segno, coda and jumps
+
+use super::layout::{layout, Content, Slot};
+use super::musicxml::{beat_type_number, low_register};
+use crate::{
+    Articulation, BarMark, Chord, ChordFingeringGenerator, ChordQuality, DrumSound, Duration,
+    DurationBase, FingeringGenerator, Fretboard, Grace, Hairpin, HairpinMark, Jump, Measure, Note,
+    Score, SlurMark, StringedFretboard, Syllabic, TimeSignature, Tuning,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Stands in for the measures missing at the end of a track
+static REST: Measure = Measure::Rest;
+
+/// LilyPond writer, see the module documentation
+#[derive(Debug, Clone, Default)]
+pub struct LilyPond {
+    title: Option<String>,
+    composer: Option<String>,
+    part_names: Vec<String>,
+    tablature: Vec<(usize, StringedFretboard)>,
+}
+
+impl LilyPond {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        LilyPond {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    pub fn with_composer(self, composer: impl Into<String>) -> Self {
+        LilyPond {
+            composer: Some(composer.into()),
+            ..self
+        }
+    }
+
+    /// Instrument names of the staves in track order
+    pub fn with_part_names(self, names: &[&str]) -> Self {
+        LilyPond {
+            part_names: names.iter().map(|n| n.to_string()).collect(),
+            ..self
+        }
+    }
+
+    /// Add a tablature staff under `track`, fingered on `fretboard`
+    pub fn with_tablature(mut self, track: usize, fretboard: StringedFretboard) -> Self {
+        self.tablature.retain(|(t, _)| *t != track);
+        self.tablature.push((track, fretboard));
+        self
+    }
+
+    /// LilyPond source of a score
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let mut out = String::from("\\version \"2.24.0\"\n\n");
+        if self.title.is_some() || self.composer.is_some() {
+            out.push_str("\\header {\n");
+            if let Some(title) = &self.title {
+                let _ = writeln!(out, "  title = {}", quoted(title));
+            }
+            if let Some(composer) = &self.composer {
+                let _ = writeln!(out, "  composer = {}", quoted(composer));
+            }
+            out.push_str("}\n\n");
+        }
+
+        let key = score.key_signature();
+        let _ = writeln!(
+            out,
+            "global = {{\n  \\key {} \\{}\n  {}\n}}\n",
+            pitch_class_name(key.tonic()),
+            if key.is_minor() { "minor" } else { "major" },
+            time_command(score.time_signature())
+        );
+
+        let mut staves = String::new();
+        for track in 0..N {
+            let name = letters(track);
+            let measures = score.get_tracks()[track].get_measures();
+            let drums = measures.iter().any(|m| matches!(m, Measure::Percussion(_)));
+            let staff = TrackWriter::new(score, track, None).write();
+            let _ = writeln!(out, "track{name} = {}\n", staff.music);
+
+            if measures.iter().any(|m| matches!(m, Measure::Chords(_))) {
+                let _ = writeln!(out, "chords{name} = {}\n", chord_names(score, track));
+                let _ = writeln!(staves, "    \\new ChordNames \\chords{name}");
+            }
+
+            let instrument = self
+                .part_names
+                .get(track)
+                .cloned()
+                .unwrap_or_else(|| format!("Track {}", track + 1));
+            let (context, clef) = match (drums, low_register(measures)) {
+                (true, _) => ("DrumStaff", None),
+                (false, true) => ("Staff", Some("bass")),
+                (false, false) => ("Staff", Some("treble")),
+            };
+            let _ = write!(
+                staves,
+                "    \\new {context} = \"track{name}\" \\with {{ instrumentName = {} }} {{ ",
+                quoted(&instrument)
+            );
+            if let Some(clef) = clef {
+                let _ = write!(staves, "\\clef \"{clef}\" ");
+            }
+            let _ = writeln!(staves, "\\track{name} }}");
+            for verse in &staff.verses {
+                let _ = writeln!(
+                    staves,
+                    "    \\addlyrics {{ {} }}",
+                    lyrics(&staff.sung, *verse)
+                );
+            }
+
+            if let Some((_, fretboard)) = self.tablature.iter().find(|(t, _)| *t == track) {
+                let tab = TrackWriter::new(score, track, Some(fretboard)).write();
+                let _ = writeln!(out, "tab{name} = {}\n", tab.music);
+                let tunings = (0..fretboard.string_count())
+                    .filter_map(|s| fretboard.string_tuning(s))
+                    .map(|t| pitch_name(*t))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let _ = writeln!(
+                    staves,
+                    "    \\new TabStaff \\with {{ stringTunings = \\stringTuning <{tunings}> }} {{ \\tab{name} }}"
+                );
+            }
+        }
+
+        out.push_str("\\score {\n  <<\n");
+        out.push_str(&staves);
+        out.push_str("  >>\n  \\layout {");
+        // Each staff keeps its own measures when the tracks are in different meters
+        if (0..N).any(|t| &score.track_time_signature(t) != score.time_signature()) {
+            out.push_str(
+                "\n    \\context { \\Score \\remove \"Timing_translator\" \\remove \"Default_bar_line_engraver\" }\n    \\context { \\Staff \\consists \"Timing_translator\" \\consists \"Default_bar_line_engraver\" }\n  ",
+            );
+        }
+        out.push_str("}\n  \\midi { }\n}\n");
+        out
+    }
+}
+
+/// Music of one track
+struct TrackMusic<'a> {
+    music: String,
+    /// Notes that take a syllable, in order
+    sung: Vec<&'a Note>,
+    verses: BTreeSet<u8>,
+}
+
+/// Writes the music of a track for a staff, or for a tablature staff when given a
+/// fretboard
+struct TrackWriter<'a, const N: usize> {
+    score: &'a Score<N>,
+    track: usize,
+    out: String,
+    /// Quarters left in the open `\tuplet`
+    tuplet: Option<f32>,
+    fretboard: Option<&'a StringedFretboard>,
+    /// Fret of the last note on the fretboard
+    fret: u32,
+    fingerings: HashMap<String, Vec<(Tuning, usize)>>,
+    sung: Vec<&'a Note>,
+}
+
+impl<'a, const N: usize> TrackWriter<'a, N> {
+    fn new(score: &'a Score<N>, track: usize, fretboard: Option<&'a StringedFretboard>) -> Self {
+        TrackWriter {
+            score,
+            track,
+            out: String::new(),
+            tuplet: None,
+            fretboard,
+            fret: 0,
+            fingerings: HashMap::new(),
+            sung: vec![],
+        }
+    }
+
+    fn write(mut self) -> TrackMusic<'a> {
+        let score = self.score;
+        let track = &score.get_tracks()[self.track];
+        let measures = track.get_measures();
+        let ts = score.track_time_signature(self.track);
+        let drums = measures.iter().any(|m| matches!(m, Measure::Percussion(_)));
+        // Score-wide marks are written once, in the first staff
+        let first = self.track == 0 && self.fretboard.is_none();
+
+        self.out
+            .push_str(if drums { "\\drummode {\n" } else { "{\n" });
+        match drums {
+            true => {
+                let _ = writeln!(self.out, "  {}", time_command(&ts));
+            }
+            false => {
+                self.out.push_str("  \\global\n");
+                if &ts != score.time_signature() {
+                    let _ = writeln!(self.out, "  {}", time_command(&ts));
+                }
+            }
+        }
+        if first {
+            let _ = writeln!(
+                self.out,
+                "  \\tempo {} = {}",
+                beat_type_number(score.time_signature().beat_type()),
+                // LilyPond only takes whole beats per minute
+                score.tempo().round().max(1.0) as u32
+            );
+        }
+        if let Some((actual, normal)) = track.tuplet() {
+            let _ = writeln!(self.out, "  \\scaleDurations {normal}/{actual} {{");
+        }
+
+        let count = score.measure_count();
+        if score.marks(0).contains(&BarMark::RepeatStart) {
+            self.out.push_str("  \\bar \".|:\"\n");
+        }
+        for m in 0..count {
+            self.out.push_str("  ");
+            if first {
+                self.left_marks(m);
+            }
+            for slot in layout(measures.get(m).unwrap_or(&REST), &ts) {
+                self.slot(&slot, &ts);
+            }
+            if first {
+                self.right_marks(m);
+            }
+            self.out.push_str("|\n");
+            self.bar_line(m);
+        }
+
+        if track.tuplet().is_some() {
+            self.out.push_str("  }\n");
+        }
+        self.out.push('}');
+        let verses = self
+            .sung
+            .iter()
+            .flat_map(|n| n.lyrics().iter().map(|l| l.verse))
+            .collect();
+        TrackMusic {
+            music: self.out,
+            sung: self.sung,
+            verses,
+        }
+    }
+
+    fn left_marks(&mut self, m: usize) {
+        let current = volta(self.score, m);
+        let previous = m.checked_sub(1).and_then(|p| volta(self.score, p));
+        if current.is_some() && current != previous {
+            let mut commands = vec![];
+            if previous.is_some() {
+                commands.push("(volta #f)".to_string());
+            }
+            if let Some(numbers) = current {
+                let text = numbers.iter().map(|n| format!("{n}.")).collect::<String>();
+                commands.push(format!("(volta \"{text}\")"));
+            }
+            let _ = write!(
+                self.out,
+                "\\set Score.repeatCommands = #'({}) ",
+                commands.join(" ")
+            );
+        }
+        for mark in self.score.marks(m) {
+            match mark {
+                BarMark::Segno => self
+                    .out
+                    .push_str("\\mark \\markup { \\musicglyph \"scripts.segno\" } "),
+                BarMark::Coda => self
+                    .out
+                    .push_str("\\mark \\markup { \\musicglyph \"scripts.coda\" } "),
+                _ => {}
+            }
+        }
+    }
+
+    fn right_marks(&mut self, m: usize) {
+        for mark in self.score.marks(m) {
+            let words = match mark {
+                BarMark::ToCoda => "To Coda",
+                BarMark::Fine => "Fine",
+                BarMark::Jump(jump) => jump_words(*jump),
+                _ => continue,
+            };
+            let _ = write!(self.out, "\\mark \\markup {{ \\italic \"{words}\" }} ");
+        }
+        let current = volta(self.score, m);
+        if current.is_some() && volta(self.score, m + 1).is_none() {
+            self.out
+                .push_str("\\set Score.repeatCommands = #'((volta #f)) ");
+        }
+    }
+
+    fn bar_line(&mut self, m: usize) {
+        let last = m + 1 == self.score.measure_count();
+        let end = self
+            .score
+            .marks(m)
+            .iter()
+            .any(|mark| matches!(mark, BarMark::RepeatEnd(_)));
+        let start = !last && self.score.marks(m + 1).contains(&BarMark::RepeatStart);
+        let bar = match (end, start) {
+            (true, true) => ":|.|:",
+            (true, false) => ":|.",
+            (false, true) => ".|:",
+            _ if last => "|.",
+            _ => return,
+        };
+        let _ = writeln!(self.out, "  \\bar \"{bar}\"");
+    }
+
+    fn slot(&mut self, slot: &Slot<'a>, ts: &TimeSignature) {
+        self.open_tuplet(slot);
+        let duration = duration_text(slot.duration);
+        let tie = if slot.tie { "~" } else { "" };
+        match &slot.content {
+            Content::Note(note, tuning) => {
+                let expression = note.expression();
+                match expression.grace {
+                    Some(Grace::Acciaccatura) => self.out.push_str("\\acciaccatura "),
+                    Some(Grace::Appoggiatura) => self.out.push_str("\\appoggiatura "),
+                    None => {}
+                }
+                let string = self.string(*tuning);
+                let _ = write!(self.out, "{}{duration}{string}{tie}", pitch_name(*tuning));
+                if let Some(dynamic) = expression.dynamic {
+                    let _ = write!(self.out, "\\{}", dynamic.symbol());
+                }
+                match expression.hairpin {
+                    Some(HairpinMark::Start(Hairpin::Crescendo)) => self.out.push_str("\\<"),
+                    Some(HairpinMark::Start(Hairpin::Diminuendo)) => self.out.push_str("\\>"),
+                    Some(HairpinMark::Stop) => self.out.push_str("\\!"),
+                    None => {}
+                }
+                for articulation in &expression.articulations {
+                    self.out.push_str(match articulation {
+                        Articulation::Staccato => "-.",
+                        Articulation::Tenuto => "--",
+                        Articulation::Accent => "->",
+                        Articulation::Marcato => "-^",
+                    });
+                }
+                if expression.fermata {
+                    self.out.push_str("\\fermata");
+                }
+                match expression.slur {
+                    Some(SlurMark::Start) => self.out.push('('),
+                    Some(SlurMark::Stop) => self.out.push(')'),
+                    None => {}
+                }
+                if !note.is_grace() {
+                    self.sung.push(note);
+                }
+            }
+            Content::Chord(chord) => {
+                let pitches = self.chord(chord);
+                let _ = write!(self.out, "<{pitches}>{duration}{tie}");
+            }
+            Content::Drums(hits) => {
+                let names = hits
+                    .iter()
+                    .map(|hit| drum_name(hit.sound()))
+                    .collect::<Vec<_>>();
+                match names.as_slice() {
+                    [name] => {
+                        let _ = write!(self.out, "{name}{duration}");
+                    }
+                    names => {
+                        let _ = write!(self.out, "<{}>{duration}", names.join(" "));
+                    }
+                }
+            }
+            Content::Rest => {
+                let _ = write!(self.out, "r{duration}");
+            }
+            Content::MeasureRest => {
+                let _ = write!(self.out, "R{}", measure_length(ts));
+            }
+        }
+        self.out.push(' ');
+        self.close_tuplet(slot);
+    }
+
+    fn open_tuplet(&mut self, slot: &Slot) {
+        let Some(tuplet) = slot.duration.tuplet.filter(|_| !slot.is_grace()) else {
+            return;
+        };
+        if self.tuplet.is_none() {
+            let _ = write!(
+                self.out,
+                "\\tuplet {}/{} {{ ",
+                tuplet.actual_notes, tuplet.base_notes
+            );
+            self.tuplet = Some(tuplet.base_notes as f32 * tuplet.base_duration.in_quarters());
+        }
+    }
+
+    fn close_tuplet(&mut self, slot: &Slot) {
+        if slot.is_grace() {
+            return;
+        }
+        if let Some(remaining) = self.tuplet {
+            let remaining = remaining - slot.duration.in_quarters();
+            if remaining < 1e-3 {
+                self.out.push_str("} ");
+                self.tuplet = None;
+            } else {
+                self.tuplet = Some(remaining);
+            }
+        }
+    }
+
+    /// String number of a note on the fretboard, e.g. `\\6`
+    fn string(&mut self, tuning: Tuning) -> String {
+        let Some(fretboard) = self.fretboard else {
+            return String::new();
+        };
+        let fret = self.fret;
+        let position = fretboard
+            .positions_for_tuning(&tuning)
+            .into_iter()
+            .min_by_key(|p| (p.fret.abs_diff(fret), p.fret));
+        match position {
+            Some(position) => {
+                self.fret = position.fret;
+                let string = fretboard.string_count() - position.string as usize;
+                format!("\\{string}")
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Pitches of a chord, as fingered on the fretboard when there is one
+    fn chord(&mut self, chord: &Chord) -> String {
+        let Some(fretboard) = self.fretboard else {
+            return chord
+                .components()
+                .iter()
+                .map(|t| pitch_name(*t))
+                .collect::<Vec<_>>()
+                .join(" ");
+        };
+        let key = chord.to_string();
+        let fingered = self.fingerings.entry(key).or_insert_with(|| {
+            let fingering = ChordFingeringGenerator::new()
+                .generate_chord_fingerings(fretboard, chord)
+                .ok()
+                .and_then(|fingerings| fingerings.into_iter().next());
+            let mut pitches = fingering
+                .map(|f| {
+                    f.positions
+                        .iter()
+                        .filter_map(|p| {
+                            let tuning = fretboard.tuning_at_position(&p.position)?;
+                            Some((
+                                tuning,
+                                fretboard.string_count() - p.position.string as usize,
+                            ))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            pitches.sort_by_key(|(_, string)| std::cmp::Reverse(*string));
+            pitches
+        });
+        match fingered.is_empty() {
+            true => chord
+                .components()
+                .iter()
+                .map(|t| pitch_name(*t))
+                .collect::<Vec<_>>()
+                .join(" "),
+            false => fingered
+                .iter()
+                .map(|(tuning, string)| format!("{}\\{string}", pitch_name(*tuning)))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// `\chordmode` names of the chord measures of a track, skips elsewhere
+fn chord_names<const N: usize>(score: &Score<N>, track: usize) -> String {
+    let ts = score.track_time_signature(track);
+    let rest = Measure::Rest;
+    let measures = score.get_tracks()[track].get_measures();
+    let mut out = String::from("\\chordmode {\n");
+    for m in 0..score.measure_count() {
+        out.push_str("  ");
+        match measures.get(m).unwrap_or(&rest) {
+            measure @ Measure::Chords(_) => {
+                let mut tuplet: Option<f32> = None;
+                for slot in layout(measure, &ts) {
+                    if let (Some(t), None) = (slot.duration.tuplet, tuplet) {
+                        let _ = write!(out, "\\tuplet {}/{} {{ ", t.actual_notes, t.base_notes);
+                        tuplet = Some(t.base_notes as f32 * t.base_duration.in_quarters());
+                    }
+                    let duration = duration_text(slot.duration);
+                    match (&slot.content, slot.continued) {
+                        (Content::Chord(chord), false) => {
+                            let _ = write!(
+                                out,
+                                "{}{duration}{} ",
+                                pitch_class_name(chord.root()),
+                                chord_modifier(chord.quality())
+                            );
+                        }
+                        _ => {
+                            let _ = write!(out, "s{duration} ");
+                        }
+                    }
+                    if let Some(remaining) = tuplet {
+                        let remaining = remaining - slot.duration.in_quarters();
+                        tuplet = (remaining > 1e-3).then_some(remaining);
+                        if tuplet.is_none() {
+                            out.push_str("} ");
+                        }
+                    }
+                }
+            }
+            _ => {
+                let _ = write!(out, "s{} ", measure_length(&ts));
+            }
+        }
+        out.push_str("|\n");
+    }
+    out.push('}');
+    out
+}
+
+/// Lyrics of a verse, a skip `_` for the notes without a syllable
+fn lyrics(notes: &[&Note], verse: u8) -> String {
+    notes
+        .iter()
+        .map(|note| match note.lyric(verse) {
+            Some(lyric) => {
+                let mut text = match lyric.text.chars().all(|c| c.is_alphabetic() || c == '\'') {
+                    true => lyric.text.clone(),
+                    false => quoted(&lyric.text),
+                };
+                if matches!(lyric.syllabic, Syllabic::Begin | Syllabic::Middle) {
+                    text.push_str(" --");
+                }
+                if lyric.extend {
+                    text.push_str(" __");
+                }
+                text
+            }
+            None => "_".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn volta<const N: usize>(score: &Score<N>, m: usize) -> Option<Vec<u8>> {
+    score.marks(m).iter().find_map(|mark| match mark {
+        BarMark::Volta(numbers) => Some(numbers.clone()),
+        _ => None,
+    })
+}
+
+fn jump_words(jump: Jump) -> &'static str {
+    match jump {
+        Jump::DaCapo => "D.C.",
+        Jump::DaCapoAlFine => "D.C. al Fine",
+        Jump::DaCapoAlCoda => "D.C. al Coda",
+        Jump::DalSegno => "D.S.",
+        Jump::DalSegnoAlFine => "D.S. al Fine",
+        Jump::DalSegnoAlCoda => "D.S. al Coda",
+    }
+}
+
+/// `\time 4/4`, or `\time 3,3,2 8/8` for an irregular grouping
+fn time_command(ts: &TimeSignature) -> String {
+    let default = TimeSignature::new(ts.beats_per_measure(), ts.beat_type());
+    let grouping = match ts.grouping() == default.grouping() {
+        true => String::new(),
+        false => {
+            let groups = ts
+                .grouping()
+                .iter()
+                .map(|g| g.to_string())
+                .collect::<Vec<_>>();
+            format!("{} ", groups.join(","))
+        }
+    };
+    format!(
+        "\\time {grouping}{}/{}",
+        ts.beats_per_measure(),
+        beat_type_number(ts.beat_type())
+    )
+}
+
+/// Length of a full measure, e.g. `4*3` in 3/4
+fn measure_length(ts: &TimeSignature) -> String {
+    let beat = beat_type_number(ts.beat_type());
+    match ts.beats_per_measure() {
+        1 => beat.to_string(),
+        beats => format!("{beat}*{beats}"),
+    }
+}
+
+fn duration_text(duration: Duration) -> String {
+    let base = match duration.base {
+        DurationBase::Maxima => "\\maxima".to_string(),
+        DurationBase::Longa => "\\longa".to_string(),
+        DurationBase::Breve => "\\breve".to_string(),
+        base => beat_type_number(base).to_string(),
+    };
+    base + &".".repeat(duration.dots as usize)
+}
+
+/// Pitch in LilyPond's Dutch names, `c'` being middle C
+fn pitch_name(tuning: Tuning) -> String {
+    let octave = tuning.spelling().2 - 3;
+    let marks = match octave {
+        0 => String::new(),
+        o if o > 0 => "'".repeat(o as usize),
+        o => ",".repeat(o.unsigned_abs() as usize),
+    };
+    pitch_class_name(tuning) + &marks
+}
+
+/// Note name without octave, e.g. `fis` or `as`
+fn pitch_class_name(tuning: Tuning) -> String {
+    let (letter, alter, _) = tuning.spelling();
+    let letter = letter.to_ascii_lowercase();
+    let suffix = match (letter, alter) {
+        ('e' | 'a', -1) => "s",
+        ('e' | 'a', -2) => "ses",
+        (_, i8::MIN..=-2) => "eses",
+        (_, -1) => "es",
+        (_, 0) => "",
+        (_, 1) => "is",
+        _ => "isis",
+    };
+    format!("{letter}{suffix}")
+}
+
+fn chord_modifier(quality: ChordQuality) -> &'static str {
+    match quality {
+        ChordQuality::Major => "",
+        ChordQuality::Minor => ":m",
+        ChordQuality::Diminished => ":dim",
+        ChordQuality::Augmented => ":aug",
+        ChordQuality::Major7 => ":maj7",
+        ChordQuality::Dominant7 => ":7",
+        ChordQuality::Minor7 => ":m7",
+        ChordQuality::MinorMajor7 => ":m7+",
+        ChordQuality::HalfDiminished7 => ":m7.5-",
+        ChordQuality::Diminished7 => ":dim7",
+        ChordQuality::Augmented7 => ":aug7",
+        ChordQuality::AugmentedMajor7 => ":maj7.5+",
+        ChordQuality::Major6 => ":6",
+        ChordQuality::Minor6 => ":m6",
+        ChordQuality::Suspended2 => ":sus2",
+        ChordQuality::Suspended4 => ":sus4",
+    }
+}
+
+/// Name of a drum in `\drummode`
+fn drum_name(sound: DrumSound) -> &'static str {
+    match sound {
+        DrumSound::AcousticBassDrum => "bda",
+        DrumSound::BassDrum => "bd",
+        DrumSound::SideStick => "ss",
+        DrumSound::AcousticSnare => "sna",
+        DrumSound::HandClap => "hc",
+        DrumSound::ElectricSnare => "sne",
+        DrumSound::LowFloorTom => "tomfl",
+        DrumSound::ClosedHiHat => "hhc",
+        DrumSound::HighFloorTom => "tomfh",
+        DrumSound::PedalHiHat => "hhp",
+        DrumSound::LowTom => "toml",
+        DrumSound::OpenHiHat => "hho",
+        DrumSound::LowMidTom => "tomml",
+        DrumSound::HighMidTom => "tommh",
+        DrumSound::CrashCymbal => "cymca",
+        DrumSound::HighTom => "tomh",
+        DrumSound::RideCymbal => "cymra",
+        DrumSound::ChineseCymbal => "cymch",
+        DrumSound::RideBell => "rb",
+        DrumSound::Tambourine => "tamb",
+        DrumSound::SplashCymbal => "cyms",
+        DrumSound::Cowbell => "cb",
+        DrumSound::CrashCymbal2 => "cymcb",
+        DrumSound::Vibraslap => "vibs",
+        DrumSound::RideCymbal2 => "cymrb",
+        DrumSound::HighBongo => "boh",
+        DrumSound::LowBongo => "bol",
+        DrumSound::MuteHighConga => "cghm",
+        DrumSound::OpenHighConga => "cgho",
+        DrumSound::LowConga => "cgl",
+        DrumSound::HighTimbale => "timh",
+        DrumSound::LowTimbale => "timl",
+        DrumSound::HighAgogo => "agh",
+        DrumSound::LowAgogo => "agl",
+        DrumSound::Cabasa => "cab",
+        DrumSound::Maracas => "mar",
+        DrumSound::ShortWhistle => "whs",
+        DrumSound::LongWhistle => "whl",
+        DrumSound::ShortGuiro => "guis",
+        DrumSound::LongGuiro => "guil",
+        DrumSound::Claves => "cl",
+        DrumSound::HighWoodBlock => "wbh",
+        DrumSound::LowWoodBlock => "wbl",
+        DrumSound::MuteCuica => "cuim",
+        DrumSound::OpenCuica => "cuio",
+        DrumSound::MuteTriangle => "trim",
+        DrumSound::OpenTriangle => "tri",
+    }
+}
+
+/// Identifiers may only hold letters: 0 is `A`, 25 is `Z`, 26 is `AA`
+fn letters(mut index: usize) -> String {
+    let mut name = vec![];
+    loop {
+        name.push((b'A' + (index % 26) as u8) as char);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.iter().rev().collect()
+}
+
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Dynamic, InstrumentPresets, KeySignature, Lyric, PercussionNote, PitchClass, Tuplet,
+    };
+
+    #[test]
+    fn test_export() {
+        let c = |letter, alter, octave| Tuning::from_spelling(letter, alter, octave).unwrap();
+        let eighth = Duration::new(DurationBase::Eighth);
+        let triplet = eighth.with_tuplet(Tuplet::new(3, 2, DurationBase::Eighth).unwrap());
+        let mut score = Score::<3>::new()
+            .with_tempo(90.0)
+            .with_key_signature(KeySignature::minor(-1));
+        let chord = Chord::new(Tuning::new(PitchClass::C, 4), ChordQuality::Dominant7).unwrap();
+        score.push_measures([
+            Measure::Note(vec![
+                Note::new(c('E', -1, 5))
+                    .with_duration(Duration::new(DurationBase::Half))
+                    .with_dynamic(Dynamic::MezzoForte)
+                    .with_lyric(Lyric::new("Hel").with_syllabic(Syllabic::Begin)),
+                Note::new(c('D', 0, 5))
+                    .with_duration(triplet)
+                    .with_lyric(Lyric::new("lo")),
+                Note::new(c('E', 0, 5)).with_duration(triplet),
+                Note::new(c('F', 1, 5)).with_duration(triplet),
+            ]),
+            Measure::Chords(vec![chord]),
+            Measure::Percussion(vec![
+                PercussionNote::new(DrumSound::BassDrum).at(0.0),
+                PercussionNote::new(DrumSound::ClosedHiHat).at(0.0),
+                PercussionNote::new(DrumSound::AcousticSnare).at(2.0),
+            ]),
+        ]);
+        score.push_measures([Measure::Rest, Measure::Rest, Measure::Rest]);
+        score.mark(0, BarMark::RepeatStart);
+        score.mark(0, BarMark::repeat_end());
+
+        let ly = LilyPond::new().with_title("Test").export(&score);
+        assert!(ly.contains("title = \"Test\""));
+        assert!(ly.contains("\\key d \\minor"));
+        assert!(ly.contains("\\time 4/4"));
+        assert!(ly.contains("\\tempo 4 = 90"));
+        assert!(
+            ly.contains("es''2\\mf \\tuplet 3/2 { d''8 e''8 fis''8 } r4 |"),
+            "{ly}"
+        );
+        assert!(ly.contains("\\addlyrics { Hel -- lo _ _ }"), "{ly}");
+        assert!(ly.contains("<c' e' g' bes'>1 |"), "{ly}");
+        assert!(ly.contains("c1:7 |"), "{ly}");
+        assert!(ly.contains("<bd hhc>2 sna2 |"), "{ly}");
+        assert!(ly.contains("R4*4 |"));
+        assert!(ly.contains("\\bar \".|:\"") && ly.contains("\\bar \":|.\""));
+        assert!(ly.contains("\\new DrumStaff"));
+        assert!(!ly.contains("Timing_translator"));
+
+        // A second meter moves timing and bar lines to the staves, tempo in whole beats
+        score
+            .set_track_time_signature(1, TimeSignature::new(3, DurationBase::Quarter))
+            .unwrap();
+        let ly = LilyPond::new().export(&score.with_tempo(92.5));
+        assert!(ly.contains("\\tempo 4 = 93"), "{ly}");
+        assert!(ly.contains(
+            "\\Score \\remove \"Timing_translator\" \\remove \"Default_bar_line_engraver\""
+        ));
+        assert!(ly.contains(
+            "\\Staff \\consists \"Timing_translator\" \\consists \"Default_bar_line_engraver\""
+        ));
+    }
+
+    #[test]
+    fn test_tablature() {
+        let guitar = StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap();
+        let quarter = Duration::new(DurationBase::Quarter);
+        let mut score = Score::<1>::new();
+        score.push_measures([Measure::Note(vec![
+            Note::new(Tuning::new(PitchClass::E, 2)).with_duration(quarter),
+            Note::new(Tuning::new(PitchClass::C, 4)).with_duration(quarter),
+        ])]);
+        let chord = Chord::new(Tuning::new(PitchClass::C, 3), ChordQuality::Major).unwrap();
+        score.push_measures([Measure::Chords(vec![chord])]);
+
+        let ly = LilyPond::new().with_tablature(0, guitar).export(&score);
+        assert!(ly.contains("\\stringTuning <e, a, d g b e'>"), "{ly}");
+        assert!(ly.contains("\\new TabStaff"));
+        // The C takes the fret closest to the open E string
+        assert!(ly.contains("e,4\\6 c'4\\2"), "{ly}");
+        let tab = &ly[ly.find("tabA =").unwrap()..];
+        let chord_line = tab.lines().nth(3).unwrap();
+        assert!(
+            chord_line.trim_start().starts_with('<') && chord_line.contains("\\5"),
+            "{tab}"
+        );
+    }
+}
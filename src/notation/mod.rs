@@ -0,0 +1,27 @@
+//! Text notation formats
+//!
+//! Exporters and importers between a `Score` and the formats of notation software, and an SVG staff preview.
+
+mod abc;
+mod chordpro;
+mod ireal;
+mod jianpu;
+mod kern;
+mod layout;
+mod lilypond;
+mod musicxml;
+mod musicxml_import;
+mod staff;
+mod tab;
+mod zip;
+
+pub use abc::*;
+pub use chordpro::*;
+pub use ireal::*;
+pub use jianpu::*;
+pub use kern::*;
+pub use lilypond::*;
+pub use musicxml::*;
+pub use musicxml_import::*;
+pub use staff::*;
+pub use tab::*;
@@ -0,0 +1,851 @@
+//! MusicXML export
+//!
+//! Writes a `Score` as a MusicXML 4.0 partwise document, one part per track, that
+//! notation editors (MuseScore, Finale, Dorico, Sibelius) can open:
+//! - pitches are spelled as the `Tuning` is, accidentals follow the key signature and
+//!   earlier notes of the measure
+//! - chord measures become `<harmony>` symbols over stacked chord notes, tied where the
+//!   meter splits them
+//! - tuplets, dots, grace notes, dynamics, hairpins, slurs, articulations, fermatas and
+//!   lyrics are kept
+//! - repeats, voltas, segno, coda and jumps become barlines and directions
+//!
+//! A track in its own meter gets its own time signature. Track tuplets (polyrhythm
+//! against the score) have no MusicXML equivalent and are written as plain values.
+
+use super::layout::{layout, Content, Slot};
+use crate::{
+    Articulation, BarMark, Chord, ChordQuality, DrumSound, Duration, DurationBase, Grace, Hairpin,
+    HairpinMark, Jump, Lyric, Measure, Note, PercussionNote, Score, SlurMark, Syllabic,
+    TimeSignature, Tuning,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// MusicXML writer, see the module documentation
+#[derive(Debug, Clone)]
+pub struct MusicXml {
+    title: Option<String>,
+    part_names: Vec<String>,
+    chord_notes: bool,
+}
+
+impl Default for MusicXml {
+    fn default() -> Self {
+        MusicXml {
+            title: None,
+            part_names: vec![],
+            chord_notes: true,
+        }
+    }
+}
+
+impl MusicXml {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        MusicXml {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Names of the parts in track order, missing ones are "Track n"
+    pub fn with_part_names(self, names: &[&str]) -> Self {
+        MusicXml {
+            part_names: names.iter().map(|n| n.to_string()).collect(),
+            ..self
+        }
+    }
+
+    /// Write the notes of chord measures under their symbols (default), or only the
+    /// symbols over rests, as in a lead sheet
+    pub fn with_chord_notes(self, chord_notes: bool) -> Self {
+        MusicXml {
+            chord_notes,
+            ..self
+        }
+    }
+
+    /// MusicXML document of a score
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let mut out = String::new();
+        out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+        out.push_str(
+            "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" \
+             \"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+        );
+        out.push_str("<score-partwise version=\"4.0\">\n");
+        if let Some(title) = &self.title {
+            let _ = writeln!(
+                out,
+                "  <work>\n    <work-title>{}</work-title>\n  </work>",
+                escape(title)
+            );
+        }
+
+        out.push_str("  <part-list>\n");
+        for (i, track) in score.get_tracks().iter().enumerate() {
+            let name = self
+                .part_names
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| format!("Track {}", i + 1));
+            let _ = writeln!(out, "    <score-part id=\"P{}\">", i + 1);
+            let _ = writeln!(out, "      <part-name>{}</part-name>", escape(&name));
+            for sound in drum_sounds(track.get_measures()) {
+                let _ = writeln!(
+                    out,
+                    "      <score-instrument id=\"P{}-I{}\"><instrument-name>{}</instrument-name></score-instrument>",
+                    i + 1,
+                    sound.midi_number(),
+                    escape(sound.name())
+                );
+            }
+            for sound in drum_sounds(track.get_measures()) {
+                let _ = writeln!(
+                    out,
+                    "      <midi-instrument id=\"P{0}-I{1}\"><midi-channel>10</midi-channel><midi-unpitched>{2}</midi-unpitched></midi-instrument>",
+                    i + 1,
+                    sound.midi_number(),
+                    sound.midi_number() as u16 + 1
+                );
+            }
+            out.push_str("    </score-part>\n");
+        }
+        out.push_str("  </part-list>\n");
+
+        for track in 0..N {
+            self.write_part(&mut out, score, track);
+        }
+        out.push_str("</score-partwise>\n");
+        out
+    }
+
+    fn write_part<const N: usize>(&self, out: &mut String, score: &Score<N>, track: usize) {
+        let ts = score.track_time_signature(track);
+        let rest = Measure::Rest;
+        let measures = score.get_tracks()[track].get_measures();
+        let slots = (0..score.measure_count())
+            .map(|m| layout(measures.get(m).unwrap_or(&rest), &ts))
+            .collect::<Vec<_>>();
+        let divisions = divisions(slots.iter().flatten());
+        let percussion = measures.iter().any(|m| matches!(m, Measure::Percussion(_)));
+
+        let _ = writeln!(out, "  <part id=\"P{}\">", track + 1);
+        for (m, measure) in slots.iter().enumerate() {
+            let _ = writeln!(out, "    <measure number=\"{}\">", m + 1);
+            let marks = score.marks(m);
+            write_left_barline(out, score, m);
+
+            if m == 0 {
+                out.push_str("      <attributes>\n");
+                let _ = writeln!(out, "        <divisions>{divisions}</divisions>");
+                if !percussion {
+                    let key = score.key_signature();
+                    let mode = if key.is_minor() { "minor" } else { "major" };
+                    let _ = writeln!(
+                        out,
+                        "        <key><fifths>{}</fifths><mode>{mode}</mode></key>",
+                        key.fifths()
+                    );
+                }
+                let _ = writeln!(
+                    out,
+                    "        <time><beats>{}</beats><beat-type>{}</beat-type></time>",
+                    beats_text(&ts),
+                    beat_type_number(ts.beat_type())
+                );
+                let clef = match (percussion, low_register(measures)) {
+                    (true, _) => "<sign>percussion</sign>",
+                    (false, true) => "<sign>F</sign><line>4</line>",
+                    (false, false) => "<sign>G</sign><line>2</line>",
+                };
+                let _ = writeln!(out, "        <clef>{clef}</clef>");
+                out.push_str("      </attributes>\n");
+
+                if track == 0 {
+                    let beat = score.time_signature().beat_type();
+                    let _ = writeln!(
+                        out,
+                        "      <direction placement=\"above\"><direction-type><metronome><beat-unit>{}</beat-unit><per-minute>{}</per-minute></metronome></direction-type><sound tempo=\"{}\"/></direction>",
+                        type_name(beat),
+                        score.tempo(),
+                        score.tempo() * beat.in_quarters()
+                    );
+                }
+            }
+
+            if track == 0 {
+                for mark in marks {
+                    match mark {
+                        BarMark::Segno => direction(out, "<segno/>"),
+                        BarMark::Coda => direction(out, "<coda/>"),
+                        _ => {}
+                    }
+                }
+            }
+
+            let mut writer = MeasureWriter {
+                out,
+                divisions,
+                key: ['C', 'D', 'E', 'F', 'G', 'A', 'B'].map(|l| score.key_signature().alter(l)),
+                altered: HashMap::new(),
+                tuplet: None,
+                chord_notes: self.chord_notes,
+                part: track + 1,
+            };
+            for slot in measure {
+                writer.slot(slot);
+            }
+
+            if track == 0 {
+                for mark in marks {
+                    let words = match mark {
+                        BarMark::ToCoda => "To Coda",
+                        BarMark::Fine => "Fine",
+                        BarMark::Jump(jump) => jump_words(*jump),
+                        _ => continue,
+                    };
+                    direction(out, &format!("<words>{words}</words>"));
+                }
+            }
+            write_right_barline(out, score, m);
+            out.push_str("    </measure>\n");
+        }
+        out.push_str("  </part>\n");
+    }
+}
+
+/// Writes the notes of one measure, keeping accidental and tuplet state
+struct MeasureWriter<'a> {
+    out: &'a mut String,
+    divisions: u32,
+    /// Alteration of each letter in the key signature
+    key: [i8; 7],
+    /// Alterations already shown in the measure, by letter and octave
+    altered: HashMap<(char, i8), i8>,
+    /// Quarters left in the open tuplet bracket
+    tuplet: Option<f32>,
+    chord_notes: bool,
+    part: usize,
+}
+
+/// Everything written inside one `<note>`
+#[derive(Default)]
+struct NoteXml<'a> {
+    /// `<pitch>`, `<unpitched>` or `<rest>` element
+    pitch: String,
+    chord: bool,
+    grace: Option<Grace>,
+    instrument: Option<String>,
+    accidental: Option<i8>,
+    notehead: Option<&'static str>,
+    notations: Vec<String>,
+    lyrics: &'a [Lyric],
+    measure_rest: bool,
+}
+
+impl MeasureWriter<'_> {
+    fn slot(&mut self, slot: &Slot) {
+        let tuplet = self.tuplet_marks(slot);
+        match &slot.content {
+            Content::Note(note, tuning) => self.note(slot, note, *tuning, tuplet),
+            Content::Chord(chord) => {
+                if !slot.continued {
+                    self.harmony(chord);
+                }
+                if !self.chord_notes {
+                    return self.rest(slot, tuplet, false);
+                }
+                for (i, tuning) in chord.components().iter().enumerate() {
+                    let xml = NoteXml {
+                        chord: i > 0,
+                        ..self.pitched(*tuning, slot.continued)
+                    };
+                    self.write(slot, xml, tuplet.clone());
+                }
+            }
+            Content::Drums(hits) => {
+                // A flam is a slashed grace stroke before the main one
+                if !slot.continued {
+                    let grace = Slot {
+                        duration: Duration::new(DurationBase::Eighth),
+                        tie: false,
+                        ..slot.clone()
+                    };
+                    for (i, hit) in hits.iter().filter(|h| h.is_flam()).enumerate() {
+                        let xml = NoteXml {
+                            grace: Some(Grace::Acciaccatura),
+                            chord: i > 0,
+                            ..self.unpitched(hit)
+                        };
+                        self.write(&grace, xml, vec![]);
+                    }
+                }
+                for (i, hit) in hits.iter().enumerate() {
+                    let xml = NoteXml {
+                        chord: i > 0,
+                        ..self.unpitched(hit)
+                    };
+                    self.write(slot, xml, tuplet.clone());
+                }
+            }
+            Content::Rest => self.rest(slot, tuplet, false),
+            Content::MeasureRest => self.rest(slot, tuplet, true),
+        }
+    }
+
+    /// Drum hit on its line of the percussion staff
+    fn unpitched(&self, hit: &PercussionNote) -> NoteXml<'static> {
+        let (step, octave, notehead) = drum_display(hit.sound());
+        NoteXml {
+            pitch: format!(
+                "<unpitched><display-step>{step}</display-step><display-octave>{octave}</display-octave></unpitched>"
+            ),
+            instrument: Some(format!("P{}-I{}", self.part, hit.sound().midi_number())),
+            notehead,
+            ..NoteXml::default()
+        }
+    }
+
+    fn note(&mut self, slot: &Slot, note: &Note, tuning: Tuning, tuplet: Vec<String>) {
+        let expression = note.expression();
+        if let Some(dynamic) = expression.dynamic {
+            direction(
+                self.out,
+                &format!("<dynamics><{}/></dynamics>", dynamic.symbol()),
+            );
+        }
+        if let Some(hairpin) = expression.hairpin {
+            let kind = match hairpin {
+                HairpinMark::Start(Hairpin::Crescendo) => "crescendo",
+                HairpinMark::Start(Hairpin::Diminuendo) => "diminuendo",
+                HairpinMark::Stop => "stop",
+            };
+            direction(self.out, &format!("<wedge type=\"{kind}\"/>"));
+        }
+
+        let mut notations = vec![];
+        if let Some(slur) = expression.slur {
+            let kind = match slur {
+                SlurMark::Start => "start",
+                SlurMark::Stop => "stop",
+            };
+            notations.push(format!("<slur type=\"{kind}\" number=\"1\"/>"));
+        }
+        let articulations = expression
+            .articulations
+            .iter()
+            .map(|a| match a {
+                Articulation::Staccato => "<staccato/>",
+                Articulation::Tenuto => "<tenuto/>",
+                Articulation::Accent => "<accent/>",
+                Articulation::Marcato => "<strong-accent/>",
+            })
+            .collect::<String>();
+        if !articulations.is_empty() {
+            notations.push(format!("<articulations>{articulations}</articulations>"));
+        }
+        if expression.fermata {
+            notations.push("<fermata type=\"upright\"/>".into());
+        }
+
+        let xml = NoteXml {
+            grace: expression.grace,
+            notations,
+            lyrics: note.lyrics(),
+            ..self.pitched(tuning, false)
+        };
+        self.write(slot, xml, tuplet);
+    }
+
+    fn rest(&mut self, slot: &Slot, tuplet: Vec<String>, measure: bool) {
+        let xml = NoteXml {
+            pitch: match measure {
+                true => "<rest measure=\"yes\"/>".into(),
+                false => "<rest/>".into(),
+            },
+            measure_rest: measure,
+            ..NoteXml::default()
+        };
+        self.write(slot, xml, tuplet);
+    }
+
+    /// Pitch of a tuning with the accidental it needs at this point of the measure
+    fn pitched(&mut self, tuning: Tuning, tied: bool) -> NoteXml<'static> {
+        let (step, alter, octave) = tuning.spelling();
+        let expected = self
+            .altered
+            .get(&(step, octave))
+            .copied()
+            .unwrap_or(self.key[letter_index(step)]);
+        let accidental = (alter != expected && !tied).then_some(alter);
+        self.altered.insert((step, octave), alter);
+
+        let alter = match alter {
+            0 => String::new(),
+            a => format!("<alter>{a}</alter>"),
+        };
+        NoteXml {
+            pitch: format!("<pitch><step>{step}</step>{alter}<octave>{octave}</octave></pitch>"),
+            accidental,
+            ..NoteXml::default()
+        }
+    }
+
+    fn harmony(&mut self, chord: &Chord) {
+        let (step, alter, _) = chord.root().spelling();
+        let alter = match alter {
+            0 => String::new(),
+            a => format!("<root-alter>{a}</root-alter>"),
+        };
+        let _ = writeln!(
+            self.out,
+            "      <harmony><root><root-step>{step}</root-step>{alter}</root><kind text=\"{}\">{}</kind></harmony>",
+            escape(&chord.quality().to_string()),
+            harmony_kind(chord.quality())
+        );
+    }
+
+    /// `<tuplet>` start and stop elements of a slot, opening and closing brackets
+    fn tuplet_marks(&mut self, slot: &Slot) -> Vec<String> {
+        let Some(tuplet) = slot.duration.tuplet.filter(|_| !slot.is_grace()) else {
+            return vec![];
+        };
+        let mut marks = vec![];
+        let remaining = match self.tuplet {
+            Some(remaining) => remaining,
+            None => {
+                marks.push("<tuplet type=\"start\" bracket=\"yes\"/>".to_string());
+                tuplet.base_notes as f32 * tuplet.base_duration.in_quarters()
+            }
+        };
+        let remaining = remaining - slot.duration.in_quarters();
+        if remaining < 1e-3 {
+            marks.push("<tuplet type=\"stop\"/>".to_string());
+            self.tuplet = None;
+        } else {
+            self.tuplet = Some(remaining);
+        }
+        marks
+    }
+
+    fn write(&mut self, slot: &Slot, xml: NoteXml, tuplet: Vec<String>) {
+        let out = &mut *self.out;
+        let duration = slot.duration;
+        out.push_str("      <note>");
+        match xml.grace {
+            Some(Grace::Acciaccatura) => out.push_str("<grace slash=\"yes\"/>"),
+            Some(Grace::Appoggiatura) => out.push_str("<grace/>"),
+            None => {}
+        }
+        if xml.chord {
+            out.push_str("<chord/>");
+        }
+        out.push_str(&xml.pitch);
+        if xml.grace.is_none() {
+            let value = (duration.in_quarters() * self.divisions as f32).round() as u32;
+            let _ = write!(out, "<duration>{value}</duration>");
+        }
+        let is_rest = matches!(slot.content, Content::Rest | Content::MeasureRest);
+        if slot.continued && !is_rest {
+            out.push_str("<tie type=\"stop\"/>");
+        }
+        if slot.tie {
+            out.push_str("<tie type=\"start\"/>");
+        }
+        if let Some(instrument) = &xml.instrument {
+            let _ = write!(out, "<instrument id=\"{instrument}\"/>");
+        }
+        out.push_str("<voice>1</voice>");
+        if !xml.measure_rest {
+            let _ = write!(out, "<type>{}</type>", type_name(duration.base));
+            for _ in 0..duration.dots {
+                out.push_str("<dot/>");
+            }
+        }
+        if let Some(alter) = xml.accidental {
+            let _ = write!(out, "<accidental>{}</accidental>", accidental_name(alter));
+        }
+        if let Some(t) = duration.tuplet {
+            let _ = write!(
+                out,
+                "<time-modification><actual-notes>{}</actual-notes><normal-notes>{}</normal-notes></time-modification>",
+                t.actual_notes, t.base_notes
+            );
+        }
+        if let Some(notehead) = xml.notehead {
+            let _ = write!(out, "<notehead>{notehead}</notehead>");
+        }
+
+        let mut notations = vec![];
+        if slot.continued && !is_rest {
+            notations.push("<tied type=\"stop\"/>".to_string());
+        }
+        if slot.tie {
+            notations.push("<tied type=\"start\"/>".to_string());
+        }
+        // Brackets are drawn once, on the first note of a stacked chord
+        if !xml.chord {
+            notations.extend(tuplet);
+        }
+        notations.extend(xml.notations);
+        if !notations.is_empty() {
+            let _ = write!(out, "<notations>{}</notations>", notations.concat());
+        }
+
+        for lyric in xml.lyrics {
+            let syllabic = match lyric.syllabic {
+                Syllabic::Single => "single",
+                Syllabic::Begin => "begin",
+                Syllabic::Middle => "middle",
+                Syllabic::End => "end",
+            };
+            let _ = write!(
+                out,
+                "<lyric number=\"{}\"><syllabic>{syllabic}</syllabic><text>{}</text>{}</lyric>",
+                lyric.verse,
+                escape(&lyric.text),
+                if lyric.extend { "<extend/>" } else { "" }
+            );
+        }
+        out.push_str("</note>\n");
+    }
+}
+
+fn write_left_barline<const N: usize>(out: &mut String, score: &Score<N>, m: usize) {
+    let marks = score.marks(m);
+    let repeat = marks.contains(&BarMark::RepeatStart);
+    let ending =
+        volta(score, m).filter(|numbers| volta(score, m.wrapping_sub(1)).as_ref() != Some(numbers));
+    if !repeat && ending.is_none() {
+        return;
+    }
+    out.push_str("      <barline location=\"left\">");
+    if repeat {
+        out.push_str("<bar-style>heavy-light</bar-style>");
+    }
+    if let Some(numbers) = ending {
+        let _ = write!(
+            out,
+            "<ending number=\"{}\" type=\"start\">{}.</ending>",
+            join_numbers(&numbers, ", "),
+            join_numbers(&numbers, ".")
+        );
+    }
+    if repeat {
+        out.push_str("<repeat direction=\"forward\"/>");
+    }
+    out.push_str("</barline>\n");
+}
+
+fn write_right_barline<const N: usize>(out: &mut String, score: &Score<N>, m: usize) {
+    let marks = score.marks(m);
+    let times = marks.iter().find_map(|mark| match mark {
+        BarMark::RepeatEnd(times) => Some(*times),
+        _ => None,
+    });
+    let ending = volta(score, m).filter(|numbers| volta(score, m + 1).as_ref() != Some(numbers));
+    let last = m + 1 == score.measure_count();
+    if times.is_none() && ending.is_none() && !last {
+        return;
+    }
+    out.push_str("      <barline location=\"right\">");
+    if times.is_some() || last {
+        out.push_str("<bar-style>light-heavy</bar-style>");
+    }
+    if let Some(numbers) = ending {
+        let kind = if times.is_some() {
+            "stop"
+        } else {
+            "discontinue"
+        };
+        let _ = write!(
+            out,
+            "<ending number=\"{}\" type=\"{kind}\"/>",
+            join_numbers(&numbers, ", ")
+        );
+    }
+    match times {
+        Some(times) if times > 2 => {
+            let _ = write!(out, "<repeat direction=\"backward\" times=\"{times}\"/>");
+        }
+        Some(_) => out.push_str("<repeat direction=\"backward\"/>"),
+        None => {}
+    }
+    out.push_str("</barline>\n");
+}
+
+fn volta<const N: usize>(score: &Score<N>, m: usize) -> Option<Vec<u8>> {
+    score.marks(m).iter().find_map(|mark| match mark {
+        BarMark::Volta(numbers) => Some(numbers.clone()),
+        _ => None,
+    })
+}
+
+fn join_numbers(numbers: &[u8], separator: &str) -> String {
+    numbers
+        .iter()
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn direction(out: &mut String, content: &str) {
+    let _ = writeln!(
+        out,
+        "      <direction placement=\"above\"><direction-type>{content}</direction-type></direction>"
+    );
+}
+
+fn jump_words(jump: Jump) -> &'static str {
+    match jump {
+        Jump::DaCapo => "D.C.",
+        Jump::DaCapoAlFine => "D.C. al Fine",
+        Jump::DaCapoAlCoda => "D.C. al Coda",
+        Jump::DalSegno => "D.S.",
+        Jump::DalSegnoAlFine => "D.S. al Fine",
+        Jump::DalSegnoAlCoda => "D.S. al Coda",
+    }
+}
+
+/// Smallest number of divisions per quarter that makes every value a whole number
+fn divisions<'a>(slots: impl Iterator<Item = &'a Slot<'a>>) -> u32 {
+    let quarters = slots
+        .filter(|slot| !slot.is_grace())
+        .map(|slot| slot.duration.in_quarters())
+        .collect::<Vec<_>>();
+    (1..=960)
+        .find(|d| {
+            quarters.iter().all(|q| {
+                let value = q * *d as f32;
+                (value - value.round()).abs() < 1e-3
+            })
+        })
+        .unwrap_or(960)
+}
+
+/// `3+3+2` for an irregular grouping, the plain beat count otherwise
+fn beats_text(ts: &TimeSignature) -> String {
+    let default = TimeSignature::new(ts.beats_per_measure(), ts.beat_type());
+    match ts.grouping() == default.grouping() {
+        true => ts.beats_per_measure().to_string(),
+        false => ts
+            .grouping()
+            .iter()
+            .map(|g| g.to_string())
+            .collect::<Vec<_>>()
+            .join("+"),
+    }
+}
+
+pub(crate) fn beat_type_number(base: DurationBase) -> u32 {
+    (1.0 / base.in_whole()).round().max(1.0) as u32
+}
+
+pub(crate) fn type_name(base: DurationBase) -> &'static str {
+    match base {
+        DurationBase::Maxima => "maxima",
+        DurationBase::Longa => "long",
+        DurationBase::Breve => "breve",
+        DurationBase::Whole => "whole",
+        DurationBase::Half => "half",
+        DurationBase::Quarter => "quarter",
+        DurationBase::Eighth => "eighth",
+        DurationBase::Sixteenth => "16th",
+        DurationBase::ThirtySecond => "32nd",
+        DurationBase::SixtyFourth => "64th",
+    }
+}
+
+/// Inverse of `type_name`
+pub(crate) fn type_base(name: &str) -> Option<DurationBase> {
+    [
+        DurationBase::Maxima,
+        DurationBase::Longa,
+        DurationBase::Breve,
+        DurationBase::Whole,
+        DurationBase::Half,
+        DurationBase::Quarter,
+        DurationBase::Eighth,
+        DurationBase::Sixteenth,
+        DurationBase::ThirtySecond,
+        DurationBase::SixtyFourth,
+    ]
+    .into_iter()
+    .find(|base| type_name(*base) == name)
+}
+
+fn accidental_name(alter: i8) -> &'static str {
+    match alter {
+        i8::MIN..=-2 => "flat-flat",
+        -1 => "flat",
+        0 => "natural",
+        1 => "sharp",
+        _ => "double-sharp",
+    }
+}
+
+pub(crate) fn harmony_kind(quality: ChordQuality) -> &'static str {
+    match quality {
+        ChordQuality::Major => "major",
+        ChordQuality::Minor => "minor",
+        ChordQuality::Diminished => "diminished",
+        ChordQuality::Augmented => "augmented",
+        ChordQuality::Major7 => "major-seventh",
+        ChordQuality::Dominant7 => "dominant",
+        ChordQuality::Minor7 => "minor-seventh",
+        ChordQuality::MinorMajor7 => "major-minor",
+        ChordQuality::HalfDiminished7 => "half-diminished",
+        ChordQuality::Diminished7 => "diminished-seventh",
+        ChordQuality::Augmented7 => "augmented-seventh",
+        ChordQuality::AugmentedMajor7 => "augmented-major-seventh",
+        ChordQuality::Major6 => "major-sixth",
+        ChordQuality::Minor6 => "minor-sixth",
+        ChordQuality::Suspended2 => "suspended-second",
+        ChordQuality::Suspended4 => "suspended-fourth",
+    }
+}
+
+pub(crate) fn letter_index(letter: char) -> usize {
+    "CDEFGAB".find(letter).unwrap_or(0)
+}
+
+/// Staff position and notehead of a drum on a five-line percussion staff
+pub(crate) fn drum_display(sound: DrumSound) -> (char, u8, Option<&'static str>) {
+    use DrumSound::*;
+    match sound {
+        AcousticBassDrum | BassDrum => ('F', 4, None),
+        LowFloorTom | HighFloorTom => ('A', 4, None),
+        LowTom | LowMidTom => ('D', 5, None),
+        HighMidTom | HighTom => ('E', 5, None),
+        SideStick => ('C', 5, Some("x")),
+        AcousticSnare | ElectricSnare | HandClap => ('C', 5, None),
+        ClosedHiHat | OpenHiHat => ('G', 5, Some("x")),
+        PedalHiHat => ('D', 4, Some("x")),
+        RideCymbal | RideCymbal2 | RideBell => ('F', 5, Some("x")),
+        CrashCymbal | CrashCymbal2 | ChineseCymbal | SplashCymbal => ('A', 5, Some("x")),
+        Cowbell | Tambourine => ('E', 5, Some("triangle")),
+        _ => ('B', 4, None),
+    }
+}
+
+/// Drum sounds used by a track, in MIDI order
+fn drum_sounds(measures: &[Measure]) -> BTreeSet<DrumSound> {
+    measures
+        .iter()
+        .flat_map(|m| match m {
+            Measure::Percussion(hits) => hits.iter().map(|h| h.sound()).collect(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+/// Whether the notes of a track mostly lie below middle C
+pub(crate) fn low_register(measures: &[Measure]) -> bool {
+    let octaves = measures
+        .iter()
+        .flat_map(|m| match m {
+            Measure::Note(notes) => notes
+                .iter()
+                .filter_map(|n| n.tuning())
+                .map(|t| t.octave())
+                .collect(),
+            Measure::Chords(chords) => chords.iter().map(|c| c.root().octave()).collect(),
+            _ => vec![],
+        })
+        .collect::<Vec<_>>();
+    !octaves.is_empty()
+        && octaves.iter().map(|o| *o as f32).sum::<f32>() / (octaves.len() as f32) < 3.5
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChordQuality, Duration, KeySignature, PitchClass, Tuplet};
+
+    #[test]
+    fn test_export_notes() {
+        let mut score = Score::<1>::new().with_key_signature(KeySignature::new(-1));
+        let triplet = Tuplet::new(3, 2, DurationBase::Eighth).unwrap();
+        let eighth = Duration::new(DurationBase::Eighth).with_tuplet(triplet);
+        score.new_measures(|m| {
+            m[0].note(vec![
+                Note::new(Tuning::new(PitchClass::Bb, 4))
+                    .with_duration(Duration::new(DurationBase::Half)),
+                Note::new(Tuning::new(PitchClass::B, 4)).with_duration(eighth),
+                Note::new(Tuning::new(PitchClass::B, 4)).with_duration(eighth),
+                Note::new(Tuning::new(PitchClass::Cs, 5)).with_duration(eighth),
+            ])
+        });
+        score.set_lyrics(0, 1, "Hel-lo world").unwrap();
+
+        let xml = MusicXml::new().with_title("Test & co").export(&score);
+        assert!(xml.contains("<work-title>Test &amp; co</work-title>"));
+        assert!(xml.contains("<key><fifths>-1</fifths><mode>major</mode></key>"));
+        assert!(xml.contains("<divisions>3</divisions>"));
+        // B flat comes from the key, the natural is written once
+        assert!(xml.contains(
+            "<step>B</step><alter>-1</alter><octave>4</octave></pitch><duration>6</duration>"
+        ));
+        assert_eq!(xml.matches("<accidental>natural</accidental>").count(), 1);
+        assert_eq!(xml.matches("<accidental>sharp</accidental>").count(), 1);
+        assert_eq!(xml.matches("<tuplet type=\"start\"").count(), 1);
+        assert_eq!(xml.matches("<tuplet type=\"stop\"/>").count(), 1);
+        assert!(xml.contains("<normal-notes>2</normal-notes>"));
+        // The quarter left in the measure is a rest
+        assert!(xml.contains("<rest/><duration>3</duration>"));
+        assert!(xml.contains("<syllabic>begin</syllabic><text>Hel</text>"));
+    }
+
+    #[test]
+    fn test_export_chords_and_repeats() {
+        let mut score = Score::<2>::new().with_time_signature(3, DurationBase::Quarter);
+        let c = Chord::new(Tuning::new(PitchClass::C, 4), ChordQuality::Minor7).unwrap();
+        let g = Chord::new(Tuning::new(PitchClass::G, 3), ChordQuality::Dominant7).unwrap();
+        score.new_measures(|m| {
+            m[0].chords(vec![c.clone(), g.clone()]);
+            m[1].rest();
+        });
+        score.new_measures(|m| m[0].chord(c.clone()));
+        score.mark(0, BarMark::RepeatStart);
+        score.mark(1, BarMark::repeat_end());
+
+        let xml = MusicXml::new().export(&score);
+        assert!(xml.contains("<part id=\"P2\">"));
+        assert!(xml.contains("<kind text=\"m7\">minor-seventh</kind>"));
+        assert!(xml.contains("<root-step>G</root-step></root><kind text=\"7\">dominant</kind>"));
+        // Each chord lasts a beat and a half, the second one is tied over the middle beat
+        assert_eq!(xml.matches("<tie type=\"start\"/>").count(), 4);
+        assert!(xml.contains("<type>quarter</type><dot/>"));
+        assert!(xml.contains("<repeat direction=\"forward\"/>"));
+        assert!(xml.contains("<repeat direction=\"backward\"/>"));
+        assert!(xml.contains("<rest measure=\"yes\"/>"));
+
+        let lead_sheet = MusicXml::new().with_chord_notes(false).export(&score);
+        assert!(!lead_sheet.contains("<chord/>"));
+        assert!(lead_sheet.contains("<harmony>"));
+
+        // A flam is a slashed grace note before the snare stroke
+        let mut drums = Score::<1>::new();
+        drums.push_measures([Measure::Percussion(vec![PercussionNote::new(
+            DrumSound::AcousticSnare,
+        )
+        .with_flam()])]);
+        let xml = MusicXml::new().export(&drums);
+        let grace = xml.find("<note><grace slash=\"yes\"/><unpitched>").unwrap();
+        assert!(grace < xml.find("<note><unpitched>").unwrap());
+        assert_eq!(xml.matches("<display-step>C</display-step>").count(), 2);
+        assert!(!xml.contains("<tremolo"));
+    }
+}
@@ -0,0 +1,1356 @@
+//! MusicXML import
+//!
+//! Reads partwise and timewise MusicXML, plain or compressed (`.mxl`), into a `Score`.
+//! The score model is smaller than MusicXML, so the import keeps:
+//! - the first voice of each part, and the top note of stacked chords
+//! - tied notes of a measure as one note when their sum is a single value, otherwise as
+//!   separate notes. Ties over a barline are always split.
+//! - gaps left by `<forward>` as rests
+//! - measures holding only chord symbols over their chord notes (or rests) as
+//!   `Measure::Chords`; chord symbols over a melody are listed in
+//!   `MusicXmlImport::chord_symbols`
+//! - unpitched notes as `Measure::Percussion`, through the MIDI numbers of the part's
+//!   instruments
+//! - the first key, meter and tempo. A part in another meter than the first one gets its
+//!   own time signature.
+//!
+//! Everything else is reported as an `ImportWarning`, once per part and element, instead
+//! of being dropped silently.
+
+use super::abc::notated;
+use super::musicxml::{harmony_kind, type_base};
+use super::zip::Archive;
+use crate::{
+    Articulation, BarMark, Chord, ChordQuality, DrumSound, Duration, DurationBase, Dynamic, Grace,
+    Hairpin, HairpinMark, Jump, KeySignature, Lyric, Measure, MusicError, MusicXml, Note,
+    PercussionNote, Score, SlurMark, Syllabic, TimeSignature, Tuning, Tuplet,
+};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportWarningKind {
+    /// The element has no place in the score model and was skipped
+    Unsupported,
+    /// The element was kept approximately, e.g. a ninth chord read as a seventh
+    Approximated,
+    /// The element could not be read and was skipped
+    Invalid,
+}
+
+/// Something the import skipped or changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportWarning {
+    pub kind: ImportWarningKind,
+    /// Part id, e.g. `P1`
+    pub part: String,
+    /// Measure number as written in the document
+    pub measure: String,
+    /// Name of the element, e.g. `ornaments`
+    pub element: String,
+    pub message: String,
+}
+
+impl Display for ImportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} <{}> in part {} measure {}: {}",
+            self.kind, self.element, self.part, self.measure, self.message
+        )
+    }
+}
+
+/// Chord symbol written over a melody
+#[derive(Debug, Clone)]
+pub struct ChordSymbol {
+    pub track: usize,
+    pub measure: usize,
+    /// Position in beats of the track from the start of the measure
+    pub position: f32,
+    pub chord: Chord,
+}
+
+/// Result of a MusicXML import
+#[derive(Clone)]
+pub struct MusicXmlImport<const N: usize> {
+    pub score: Score<N>,
+    pub title: Option<String>,
+    /// Part names in track order
+    pub part_names: Vec<String>,
+    pub chord_symbols: Vec<ChordSymbol>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+impl MusicXml {
+    /// Read a partwise or timewise MusicXML document into a score of `N` tracks, one per
+    /// part. Parts beyond `N` are dropped with a warning.
+    pub fn import<const N: usize>(xml: &str) -> Result<MusicXmlImport<N>, MusicError> {
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        };
+        let xml = xml.trim_start_matches('\u{feff}');
+        let document = Document::parse_with_options(xml, options)
+            .map_err(|e| MusicError::ParseError(e.to_string()))?;
+        import(&document)
+    }
+
+    /// Read a compressed MusicXML archive (`.mxl`)
+    pub fn import_mxl<const N: usize>(data: &[u8]) -> Result<MusicXmlImport<N>, MusicError> {
+        let archive = Archive::new(data)?;
+        // The container names the score, otherwise take the first XML file
+        let path = archive
+            .read("META-INF/container.xml")
+            .ok()
+            .and_then(|container| {
+                let container = String::from_utf8(container).ok()?;
+                let document = Document::parse(&container).ok()?;
+                let path = document
+                    .descendants()
+                    .find(|n| n.has_tag_name("rootfile"))?
+                    .attribute("full-path")?;
+                Some(path.to_string())
+            })
+            .or_else(|| {
+                archive
+                    .names()
+                    .find(|n| {
+                        !n.starts_with("META-INF")
+                            && (n.ends_with(".xml") || n.ends_with(".musicxml"))
+                    })
+                    .map(str::to_string)
+            })
+            .ok_or_else(|| MusicError::ParseError("no score in the archive".into()))?;
+        let xml = archive.read(&path)?;
+        Self::import(&String::from_utf8_lossy(&xml))
+    }
+
+    /// Read either a compressed archive or a plain document, told apart by the zip
+    /// signature
+    pub fn import_bytes<const N: usize>(data: &[u8]) -> Result<MusicXmlImport<N>, MusicError> {
+        match data.starts_with(b"PK\x03\x04") {
+            true => Self::import_mxl(data),
+            false => Self::import(&String::from_utf8_lossy(data)),
+        }
+    }
+}
+
+/// A part of the document, whatever its layout
+struct Part<'a, 'input> {
+    id: String,
+    name: String,
+    /// Measure numbers with the element holding the measure content
+    measures: Vec<(String, Node<'a, 'input>)>,
+    /// Drum sound of each instrument id
+    drums: HashMap<String, DrumSound>,
+}
+
+fn import<const N: usize>(document: &Document) -> Result<MusicXmlImport<N>, MusicError> {
+    let root = document.root_element();
+    let timewise = match root.tag_name().name() {
+        "score-partwise" => false,
+        "score-timewise" => true,
+        other => {
+            return Err(MusicError::ParseError(format!(
+                "<{other}> is not a MusicXML score"
+            )))
+        }
+    };
+
+    let mut parts = vec![];
+    for score_part in children(root, "part-list").flat_map(|list| children(list, "score-part")) {
+        let drums = children(score_part, "midi-instrument")
+            .filter_map(|instrument| {
+                let number = child_text(instrument, "midi-unpitched")?
+                    .parse::<u8>()
+                    .ok()?;
+                let sound = DrumSound::from_midi_number(number.checked_sub(1)?)?;
+                Some((instrument.attribute("id")?.to_string(), sound))
+            })
+            .collect();
+        parts.push(Part {
+            id: score_part.attribute("id").unwrap_or_default().to_string(),
+            name: child_text(score_part, "part-name")
+                .unwrap_or_default()
+                .to_string(),
+            measures: vec![],
+            drums,
+        });
+    }
+
+    let mut add_measure = |id: &str, number: &str, node| {
+        let index = match parts.iter().position(|p| p.id == id) {
+            Some(index) => index,
+            None => {
+                parts.push(Part {
+                    id: id.to_string(),
+                    name: String::new(),
+                    measures: vec![],
+                    drums: HashMap::new(),
+                });
+                parts.len() - 1
+            }
+        };
+        parts[index].measures.push((number.to_string(), node));
+    };
+    if timewise {
+        for measure in children(root, "measure") {
+            let number = measure.attribute("number").unwrap_or_default();
+            for part in children(measure, "part") {
+                add_measure(part.attribute("id").unwrap_or_default(), number, part);
+            }
+        }
+    } else {
+        for part in children(root, "part") {
+            let id = part.attribute("id").unwrap_or_default();
+            for measure in children(part, "measure") {
+                add_measure(id, measure.attribute("number").unwrap_or_default(), measure);
+            }
+        }
+    }
+
+    let mut warnings = Warnings::default();
+    for part in parts.iter().skip(N) {
+        warnings.push(
+            ImportWarningKind::Unsupported,
+            &part.id,
+            "",
+            "part",
+            format!("the score has {N} tracks"),
+        );
+    }
+
+    let mut read = vec![];
+    for (track, part) in parts.iter().take(N).enumerate() {
+        let mut reader = PartReader::new(part, track, &mut warnings);
+        let measures = part
+            .measures
+            .iter()
+            .enumerate()
+            .map(|(index, (number, node))| reader.measure(index, number, *node))
+            .collect::<Vec<_>>();
+        read.push((measures, reader.finish()));
+    }
+
+    let first = read
+        .first()
+        .map(|(_, part)| part.clone())
+        .unwrap_or_default();
+    let time = first
+        .time
+        .unwrap_or(TimeSignature::new(4, DurationBase::Quarter));
+    let mut score = Score::<N>::new()
+        .with_meter(time)
+        .with_key_signature(first.key.unwrap_or_default())
+        .with_tempo(first.tempo.unwrap_or(120.0) / time.beat_type().in_quarters());
+
+    let mut chord_symbols = vec![];
+    for (track, (_, part)) in read.iter().enumerate() {
+        let meter = part.time.unwrap_or(time);
+        if &meter != score.time_signature() {
+            score.set_track_time_signature(track, meter)?;
+        }
+        chord_symbols.extend(part.chord_symbols.iter().cloned());
+    }
+    for (measure, mark) in &first.marks {
+        score.mark(*measure, mark.clone());
+    }
+
+    let length = read.iter().map(|(m, _)| m.len()).max().unwrap_or(0);
+    for index in 0..length {
+        score.push_measures(std::array::from_fn(|track| {
+            read.get(track)
+                .and_then(|(measures, _)| measures.get(index).cloned())
+                .unwrap_or(Measure::Rest)
+        }));
+    }
+
+    let title = document
+        .descendants()
+        .find(|n| n.has_tag_name("work-title") || n.has_tag_name("movement-title"))
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string());
+
+    Ok(MusicXmlImport {
+        score,
+        title,
+        part_names: parts.iter().take(N).map(|p| p.name.clone()).collect(),
+        chord_symbols,
+        warnings: warnings.list,
+    })
+}
+
+/// Warnings of an import, reported once per part and element
+#[derive(Default)]
+pub(crate) struct Warnings {
+    pub list: Vec<ImportWarning>,
+    seen: HashSet<(String, String)>,
+}
+
+impl Warnings {
+    pub fn push(
+        &mut self,
+        kind: ImportWarningKind,
+        part: &str,
+        measure: &str,
+        element: &str,
+        message: impl Into<String>,
+    ) {
+        if self.seen.insert((part.to_string(), element.to_string())) {
+            self.list.push(ImportWarning {
+                kind,
+                part: part.to_string(),
+                measure: measure.to_string(),
+                element: element.to_string(),
+                message: message.into(),
+            });
+        }
+    }
+}
+
+/// Part-wide values found while reading the measures
+#[derive(Clone, Default)]
+struct PartSummary {
+    time: Option<TimeSignature>,
+    key: Option<KeySignature>,
+    /// Quarter notes per minute
+    tempo: Option<f32>,
+    marks: Vec<(usize, BarMark)>,
+    chord_symbols: Vec<ChordSymbol>,
+}
+
+/// A note, chord or rest of the imported voice
+struct Event {
+    offset: f32,
+    /// Sounding length in quarter notes
+    quarters: f32,
+    sound: Sound,
+    note: Note,
+    /// Tied to the next note
+    tie: bool,
+    /// Tied from the previous note
+    tied: bool,
+}
+
+enum Sound {
+    Pitched(Vec<Tuning>),
+    Unpitched(Vec<DrumSound>),
+    Rest,
+}
+
+struct PartReader<'p, 'a, 'input> {
+    part: &'p Part<'a, 'input>,
+    track: usize,
+    warnings: &'p mut Warnings,
+    measure: String,
+    divisions: f32,
+    voice: Option<String>,
+    summary: PartSummary,
+    /// Dynamic and hairpin waiting for the next note
+    dynamic: Option<Dynamic>,
+    hairpin: Option<HairpinMark>,
+}
+
+impl<'p, 'a, 'input> PartReader<'p, 'a, 'input> {
+    fn new(part: &'p Part<'a, 'input>, track: usize, warnings: &'p mut Warnings) -> Self {
+        PartReader {
+            part,
+            track,
+            warnings,
+            measure: String::new(),
+            divisions: 1.0,
+            voice: None,
+            summary: PartSummary::default(),
+            dynamic: None,
+            hairpin: None,
+        }
+    }
+
+    fn finish(self) -> PartSummary {
+        self.summary
+    }
+
+    fn warn(&mut self, kind: ImportWarningKind, element: &str, message: impl Into<String>) {
+        self.warnings
+            .push(kind, &self.part.id, &self.measure, element, message);
+    }
+
+    fn measure(&mut self, index: usize, number: &str, node: Node) -> Measure {
+        self.measure = number.to_string();
+        let mut position = 0.0f32;
+        let mut events: Vec<Event> = vec![];
+        let mut harmonies: Vec<(f32, Chord)> = vec![];
+
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "attributes" => self.attributes(child),
+                "note" => self.note(child, &mut position, &mut events),
+                "backup" => position -= self.quarters(child),
+                "forward" => position += self.quarters(child),
+                "harmony" => {
+                    let offset = child_text(child, "offset")
+                        .and_then(|o| o.parse::<f32>().ok())
+                        .unwrap_or(0.0)
+                        / self.divisions;
+                    if let Some(chord) = self.harmony(child) {
+                        harmonies.push((position + offset, chord));
+                    }
+                }
+                "direction" => self.direction(child, index),
+                "barline" => self.barline(child, index),
+                "sound" => self.sound(child),
+                "print" | "bookmark" | "link" => {}
+                other => self.warn(ImportWarningKind::Unsupported, other, "skipped"),
+            }
+        }
+
+        let ts = self
+            .summary
+            .time
+            .unwrap_or(TimeSignature::new(4, DurationBase::Quarter));
+        self.build(index, &ts, events, harmonies)
+    }
+
+    fn build(
+        &mut self,
+        index: usize,
+        ts: &TimeSignature,
+        events: Vec<Event>,
+        harmonies: Vec<(f32, Chord)>,
+    ) -> Measure {
+        let beat = ts.beat_type().in_quarters();
+        if events
+            .iter()
+            .any(|e| matches!(e.sound, Sound::Unpitched(_)))
+        {
+            let hits = events
+                .iter()
+                .flat_map(|event| match &event.sound {
+                    Sound::Unpitched(sounds) => sounds
+                        .iter()
+                        .map(|sound| PercussionNote::new(*sound).at(event.offset / beat))
+                        .collect(),
+                    _ => vec![],
+                })
+                .collect();
+            return Measure::Percussion(hits);
+        }
+
+        let pitched = events
+            .iter()
+            .filter_map(|e| match &e.sound {
+                Sound::Pitched(tunings) => Some(tunings.len()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let only_chord_notes = pitched.iter().all(|count| *count > 1);
+        if !harmonies.is_empty() && only_chord_notes {
+            let share = ts.measure_quarters() / harmonies.len() as f32;
+            let even = harmonies
+                .iter()
+                .enumerate()
+                .all(|(i, (offset, _))| (offset - share * i as f32).abs() < 1e-3);
+            if !even {
+                self.warn(
+                    ImportWarningKind::Approximated,
+                    "harmony",
+                    "chord symbols are spread evenly over the measure",
+                );
+            }
+            return Measure::Chords(harmonies.into_iter().map(|(_, chord)| chord).collect());
+        }
+        if pitched.is_empty() {
+            return Measure::Rest;
+        }
+
+        for (offset, chord) in harmonies {
+            self.summary.chord_symbols.push(ChordSymbol {
+                track: self.track,
+                measure: index,
+                position: offset / beat,
+                chord,
+            });
+        }
+        if pitched.iter().any(|count| *count > 1) {
+            self.warn(
+                ImportWarningKind::Approximated,
+                "chord",
+                "only the top note of a chord is kept",
+            );
+        }
+        let mut end = 0.0;
+        let mut notes = vec![];
+        for event in self.tie(events) {
+            // A gap left by <forward> is a rest
+            let gap = event.offset - end;
+            if gap > 1e-3 {
+                match ts.decompose(end / beat, gap / beat) {
+                    Ok(values) => notes.extend(values.into_iter().map(Note::rest)),
+                    Err(_) => notes.push(Note::rest(Duration::from_quarters(gap))),
+                }
+            }
+            end = end.max(event.offset + event.quarters);
+            notes.push(match event.sound {
+                Sound::Pitched(tunings) if tunings.len() > 1 => {
+                    let top = tunings.iter().copied().max_by_key(|t| pitch_height(*t));
+                    let top = top.unwrap_or(tunings[0]);
+                    let note = &event.note;
+                    let mut top = Note::new(top)
+                        .with_duration(note.duration())
+                        .with_velocity(note.velocity())
+                        .with_expression(note.expression().clone());
+                    for lyric in note.lyrics() {
+                        top = top.with_lyric(lyric.clone());
+                    }
+                    top
+                }
+                _ => event.note,
+            });
+        }
+        Measure::Note(notes)
+    }
+
+    /// Merges tied notes that follow each other into one note when their sum is a single
+    /// value
+    fn tie(&mut self, events: Vec<Event>) -> Vec<Event> {
+        let plain = |e: &Event| e.note.duration().tuplet.is_none() && !e.note.is_grace();
+        let mut merged: Vec<Event> = vec![];
+        for event in events {
+            if let Some(last) = merged.last_mut() {
+                let same = matches!(
+                    (&last.sound, &event.sound),
+                    (Sound::Pitched(a), Sound::Pitched(b)) if a == b
+                );
+                let adjacent = (last.offset + last.quarters - event.offset).abs() < 1e-3;
+                let mergeable =
+                    last.tie && event.tied && same && adjacent && plain(last) && plain(&event);
+                if let Some(duration) =
+                    notated(last.quarters + event.quarters, None).filter(|_| mergeable)
+                {
+                    last.quarters += event.quarters;
+                    last.note = last.note.clone().with_duration(duration);
+                    last.tie = event.tie;
+                    continue;
+                }
+            }
+            merged.push(event);
+        }
+        if merged
+            .iter()
+            .any(|e| e.tie && matches!(e.sound, Sound::Pitched(_)))
+        {
+            self.warn(
+                ImportWarningKind::Approximated,
+                "tie",
+                "tied notes are kept as separate notes",
+            );
+        }
+        merged
+    }
+
+    /// `<duration>` of an element in quarter notes
+    fn quarters(&self, node: Node) -> f32 {
+        child_text(node, "duration")
+            .and_then(|d| d.parse::<f32>().ok())
+            .unwrap_or(0.0)
+            / self.divisions
+    }
+
+    fn attributes(&mut self, node: Node) {
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "divisions" => match child.text().and_then(|t| t.trim().parse::<f32>().ok()) {
+                    Some(divisions) if divisions > 0.0 => self.divisions = divisions,
+                    _ => self.warn(ImportWarningKind::Invalid, "divisions", "not a number"),
+                },
+                "key" => {
+                    let fifths = child_text(child, "fifths").and_then(|f| f.parse::<i8>().ok());
+                    let Some(fifths) = fifths else {
+                        self.warn(
+                            ImportWarningKind::Unsupported,
+                            "key",
+                            "only traditional keys are read",
+                        );
+                        continue;
+                    };
+                    let key = match child_text(child, "mode") {
+                        Some("minor") | Some("aeolian") => KeySignature::minor(fifths),
+                        _ => KeySignature::new(fifths),
+                    };
+                    match self.summary.key {
+                        None => self.summary.key = Some(key),
+                        Some(first) if first != key => self.warn(
+                            ImportWarningKind::Unsupported,
+                            "key",
+                            "key changes are not kept",
+                        ),
+                        Some(_) => {}
+                    }
+                }
+                "time" => {
+                    let Some(time) = self.time(child) else {
+                        continue;
+                    };
+                    match self.summary.time {
+                        None => self.summary.time = Some(time),
+                        Some(first) if first != time => self.warn(
+                            ImportWarningKind::Unsupported,
+                            "time",
+                            "meter changes are not kept",
+                        ),
+                        Some(_) => {}
+                    }
+                }
+                "staves" if child.text().map(str::trim) != Some("1") => self.warn(
+                    ImportWarningKind::Unsupported,
+                    "staves",
+                    "staves are merged into one track",
+                ),
+                "clef" | "staves" | "instruments" | "part-symbol" => {}
+                other => self.warn(ImportWarningKind::Unsupported, other, "skipped"),
+            }
+        }
+    }
+
+    fn time(&mut self, node: Node) -> Option<TimeSignature> {
+        let beats = child_text(node, "beats");
+        let beat_type = child_text(node, "beat-type").and_then(|t| t.parse::<f32>().ok());
+        let (Some(beats), Some(beat_type)) = (beats, beat_type) else {
+            self.warn(
+                ImportWarningKind::Unsupported,
+                "time",
+                "only numeric time signatures are read",
+            );
+            return None;
+        };
+        let groups = beats
+            .split('+')
+            .map(|g| g.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>();
+        let base = DurationBase::from_whole(1.0 / beat_type);
+        let (Ok(groups), Ok(base)) = (groups, base) else {
+            self.warn(
+                ImportWarningKind::Invalid,
+                "time",
+                format!("{beats}/{beat_type}"),
+            );
+            return None;
+        };
+        let total = groups.iter().map(|g| *g as u32).sum::<u32>().min(255) as u8;
+        let time = TimeSignature::new(total, base);
+        match groups.len() {
+            1 => Some(time),
+            _ => time.with_grouping(&groups).ok(),
+        }
+    }
+
+    fn note(&mut self, node: Node, position: &mut f32, events: &mut Vec<Event>) {
+        let grace = node.children().find(|n| n.has_tag_name("grace"));
+        let chord = has_child(node, "chord");
+        let quarters = self.quarters(node);
+        let offset = match chord {
+            // A chord note starts with the previous note, which already moved on
+            true => events.last().map_or(*position, |e| e.offset),
+            false => *position,
+        };
+        if grace.is_none() && !chord {
+            *position += quarters;
+        }
+
+        let voice = child_text(node, "voice").unwrap_or("1").to_string();
+        match &self.voice {
+            None => self.voice = Some(voice),
+            Some(first) if *first != voice => {
+                return self.warn(
+                    ImportWarningKind::Unsupported,
+                    "voice",
+                    "only the first voice of a part is read",
+                )
+            }
+            Some(_) => {}
+        }
+
+        let sound = if let Some(pitch) = node.children().find(|n| n.has_tag_name("pitch")) {
+            match self.pitch(pitch) {
+                Some(tuning) => Sound::Pitched(vec![tuning]),
+                None => return,
+            }
+        } else if has_child(node, "unpitched") {
+            let instrument = node
+                .children()
+                .find(|n| n.has_tag_name("instrument"))
+                .and_then(|n| n.attribute("id"))
+                .and_then(|id| self.part.drums.get(id).copied());
+            match instrument {
+                Some(sound) => Sound::Unpitched(vec![sound]),
+                None => {
+                    return self.warn(
+                        ImportWarningKind::Invalid,
+                        "unpitched",
+                        "no MIDI instrument for the note",
+                    )
+                }
+            }
+        } else {
+            Sound::Rest
+        };
+
+        if chord {
+            match (events.last_mut().map(|e| &mut e.sound), sound) {
+                (Some(Sound::Pitched(tunings)), Sound::Pitched(more)) => tunings.extend(more),
+                (Some(Sound::Unpitched(sounds)), Sound::Unpitched(more)) => sounds.extend(more),
+                _ => {}
+            }
+            return;
+        }
+
+        let duration = self.duration(node, quarters);
+        let mut note = match sound {
+            Sound::Rest => Note::rest(duration),
+            Sound::Pitched(ref tunings) => Note::new(tunings[0]).with_duration(duration),
+            Sound::Unpitched(_) => Note::rest(duration),
+        };
+        if let Some(grace) = grace {
+            note = note.with_grace(match grace.attribute("slash") {
+                Some("yes") => Grace::Acciaccatura,
+                _ => Grace::Appoggiatura,
+            });
+        }
+        if !matches!(sound, Sound::Rest) {
+            if let Some(dynamic) = self.dynamic.take() {
+                note = note.with_dynamic(dynamic);
+            }
+            if let Some(hairpin) = self.hairpin.take() {
+                note = note.with_hairpin(hairpin);
+            }
+        }
+        let (mut tie, mut tied) = (false, false);
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "notations" => note = self.notations(child, note),
+                "lyric" => {
+                    if let Some(lyric) = self.lyric(child) {
+                        note = note.with_lyric(lyric);
+                    }
+                }
+                "tie" => match child.attribute("type") {
+                    Some("start") => tie = true,
+                    Some("stop") => tied = true,
+                    _ => {}
+                },
+                "play" | "cue" => {
+                    let name = child.tag_name().name().to_string();
+                    self.warn(ImportWarningKind::Unsupported, &name, "skipped")
+                }
+                _ => {}
+            }
+        }
+        events.push(Event {
+            offset,
+            quarters: if grace.is_some() { 0.0 } else { quarters },
+            sound,
+            note,
+            tie,
+            tied,
+        });
+    }
+
+    fn pitch(&mut self, node: Node) -> Option<Tuning> {
+        let step = child_text(node, "step")?.chars().next()?;
+        let alter = child_text(node, "alter")
+            .and_then(|a| a.parse::<f32>().ok())
+            .unwrap_or(0.0);
+        if alter.round().abs() > 2.0 {
+            self.warn(ImportWarningKind::Invalid, "alter", alter.to_string());
+            return None;
+        }
+        if alter.fract() != 0.0 {
+            self.warn(
+                ImportWarningKind::Approximated,
+                "alter",
+                "microtones are rounded to semitones",
+            );
+        }
+        let octave = child_text(node, "octave")?.parse::<i8>().ok()?;
+        match Tuning::from_spelling(step, alter.round() as i8, octave) {
+            Ok(tuning) => Some(tuning),
+            Err(_) => {
+                self.warn(
+                    ImportWarningKind::Invalid,
+                    "pitch",
+                    format!("{step}{octave}"),
+                );
+                None
+            }
+        }
+    }
+
+    /// Notated value of a note, from `<type>` when present or its length otherwise
+    fn duration(&mut self, node: Node, quarters: f32) -> Duration {
+        let base = child_text(node, "type").and_then(type_base);
+        let Some(base) = base else {
+            return match has_child(node, "grace") {
+                true => Duration::new(DurationBase::Eighth),
+                false => Duration::from_quarters(quarters),
+            };
+        };
+        let dots = node.children().filter(|n| n.has_tag_name("dot")).count() as u8;
+        let mut duration = Duration::new(base).dotted(dots);
+
+        if let Some(modification) = node
+            .children()
+            .find(|n| n.has_tag_name("time-modification"))
+        {
+            let number = |name| child_text(modification, name).and_then(|n| n.parse::<u8>().ok());
+            match (number("actual-notes"), number("normal-notes")) {
+                (Some(actual), Some(normal)) => match Tuplet::new(actual, normal, base) {
+                    Ok(tuplet) => duration = duration.with_tuplet(tuplet),
+                    Err(_) => {
+                        self.warn(
+                            ImportWarningKind::Approximated,
+                            "time-modification",
+                            format!("{actual}:{normal} read as a plain value"),
+                        );
+                        duration = Duration::from_quarters(quarters);
+                    }
+                },
+                _ => self.warn(ImportWarningKind::Invalid, "time-modification", "skipped"),
+            }
+        }
+        duration
+    }
+
+    fn notations(&mut self, node: Node, mut note: Note) -> Note {
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "slur" => match child.attribute("type") {
+                    Some("start") => note = note.with_slur(SlurMark::Start),
+                    Some("stop") => note = note.with_slur(SlurMark::Stop),
+                    _ => {}
+                },
+                "articulations" => {
+                    for articulation in child.children().filter(Node::is_element) {
+                        let articulation = match articulation.tag_name().name() {
+                            "staccato" => Articulation::Staccato,
+                            "tenuto" => Articulation::Tenuto,
+                            "accent" => Articulation::Accent,
+                            "strong-accent" => Articulation::Marcato,
+                            other => {
+                                self.warn(ImportWarningKind::Unsupported, other, "skipped");
+                                continue;
+                            }
+                        };
+                        note = note.with_articulation(articulation);
+                    }
+                }
+                "fermata" => note = note.with_fermata(),
+                "dynamics" => {
+                    if let Some(dynamic) = self.dynamics(child) {
+                        note = note.with_dynamic(dynamic);
+                    }
+                }
+                "tied" | "tuplet" => {}
+                other => self.warn(ImportWarningKind::Unsupported, other, "skipped"),
+            }
+        }
+        note
+    }
+
+    fn dynamics(&mut self, node: Node) -> Option<Dynamic> {
+        let name = node.first_element_child()?.tag_name().name();
+        let dynamic = Dynamic::from_symbol(name);
+        if dynamic.is_none() {
+            self.warn(
+                ImportWarningKind::Unsupported,
+                "dynamics",
+                format!("{name} is not a dynamic level"),
+            );
+        }
+        dynamic
+    }
+
+    fn lyric(&mut self, node: Node) -> Option<Lyric> {
+        let text = child_text(node, "text")?;
+        let syllabic = match child_text(node, "syllabic") {
+            Some("begin") => Syllabic::Begin,
+            Some("middle") => Syllabic::Middle,
+            Some("end") => Syllabic::End,
+            _ => Syllabic::Single,
+        };
+        if has_child(node, "elision") {
+            self.warn(
+                ImportWarningKind::Approximated,
+                "elision",
+                "only the first syllable is kept",
+            );
+        }
+        let verse = node
+            .attribute("number")
+            .and_then(|n| n.parse::<u8>().ok())
+            .unwrap_or(1);
+        let mut lyric = Lyric::new(text).with_verse(verse).with_syllabic(syllabic);
+        if has_child(node, "extend") {
+            lyric = lyric.with_extend();
+        }
+        Some(lyric)
+    }
+
+    fn harmony(&mut self, node: Node) -> Option<Chord> {
+        let Some(root) = node.children().find(|n| n.has_tag_name("root")) else {
+            self.warn(
+                ImportWarningKind::Unsupported,
+                "harmony",
+                "only chord symbols with a root are read",
+            );
+            return None;
+        };
+        let step = child_text(root, "root-step")?;
+        let alter = child_text(root, "root-alter")
+            .and_then(|a| a.parse::<f32>().ok())
+            .unwrap_or(0.0)
+            .round() as i32;
+        if alter.abs() > 2 {
+            self.warn(ImportWarningKind::Invalid, "root-alter", alter.to_string());
+            return None;
+        }
+        let accidental = match alter {
+            a if a < 0 => "b".repeat(a.unsigned_abs() as usize),
+            a => "#".repeat(a as usize),
+        };
+
+        let kind = node.children().find(|n| n.has_tag_name("kind"))?;
+        let value = kind.text().unwrap_or_default().trim();
+        if value == "none" {
+            return None;
+        }
+        let (quality, approximated) = match ChordQuality::iter().find(|q| harmony_kind(*q) == value)
+        {
+            Some(quality) => (Some(quality), false),
+            None => (approximate_kind(value), true),
+        };
+        for element in ["bass", "degree", "inversion"] {
+            if has_child(node, element) {
+                self.warn(
+                    ImportWarningKind::Approximated,
+                    element,
+                    "chord read without it",
+                );
+            }
+        }
+
+        let symbol = match quality {
+            Some(quality) => format!("{step}{accidental}{quality}"),
+            None => format!(
+                "{step}{accidental}{}",
+                kind.attribute("text").unwrap_or(value)
+            ),
+        };
+        match Chord::from_symbol(&symbol) {
+            Ok(chord) => {
+                if approximated && quality.is_some() {
+                    self.warn(
+                        ImportWarningKind::Approximated,
+                        "kind",
+                        format!("{value} read as {symbol}"),
+                    );
+                }
+                Some(chord)
+            }
+            Err(_) => {
+                self.warn(
+                    ImportWarningKind::Invalid,
+                    "kind",
+                    format!("{value} ({symbol})"),
+                );
+                None
+            }
+        }
+    }
+
+    fn direction(&mut self, node: Node, index: usize) {
+        for kind in
+            children(node, "direction-type").flat_map(|t| t.children().filter(Node::is_element))
+        {
+            match kind.tag_name().name() {
+                "dynamics" => self.dynamic = self.dynamics(kind).or(self.dynamic),
+                "wedge" => {
+                    self.hairpin = match kind.attribute("type") {
+                        Some("crescendo") => Some(HairpinMark::Start(Hairpin::Crescendo)),
+                        Some("diminuendo") => Some(HairpinMark::Start(Hairpin::Diminuendo)),
+                        Some("stop") => Some(HairpinMark::Stop),
+                        _ => self.hairpin,
+                    }
+                }
+                "segno" => self.mark(index, BarMark::Segno),
+                "coda" => self.mark(index, BarMark::Coda),
+                "words" => {
+                    let words = kind.text().unwrap_or_default().trim().to_lowercase();
+                    let mark = match words.as_str() {
+                        "fine" => BarMark::Fine,
+                        "to coda" => BarMark::ToCoda,
+                        "d.c." | "da capo" => BarMark::Jump(Jump::DaCapo),
+                        "d.c. al fine" => BarMark::Jump(Jump::DaCapoAlFine),
+                        "d.c. al coda" => BarMark::Jump(Jump::DaCapoAlCoda),
+                        "d.s." | "dal segno" => BarMark::Jump(Jump::DalSegno),
+                        "d.s. al fine" => BarMark::Jump(Jump::DalSegnoAlFine),
+                        "d.s. al coda" => BarMark::Jump(Jump::DalSegnoAlCoda),
+                        _ => {
+                            self.warn(ImportWarningKind::Unsupported, "words", "text is skipped");
+                            continue;
+                        }
+                    };
+                    self.mark(index, mark);
+                }
+                "metronome" => {
+                    let per_minute =
+                        child_text(kind, "per-minute").and_then(|p| p.parse::<f32>().ok());
+                    let unit = child_text(kind, "beat-unit").and_then(type_base);
+                    if let (Some(per_minute), Some(unit), None) =
+                        (per_minute, unit, self.summary.tempo)
+                    {
+                        let dotted = match has_child(kind, "beat-unit-dot") {
+                            true => 1.5,
+                            false => 1.0,
+                        };
+                        self.summary.tempo = Some(per_minute * unit.in_quarters() * dotted);
+                    }
+                }
+                other => self.warn(ImportWarningKind::Unsupported, other, "skipped"),
+            }
+        }
+        if let Some(sound) = children(node, "sound").next() {
+            self.sound(sound);
+        }
+    }
+
+    fn sound(&mut self, node: Node) {
+        if let Some(tempo) = node.attribute("tempo").and_then(|t| t.parse::<f32>().ok()) {
+            match self.summary.tempo {
+                None => self.summary.tempo = Some(tempo),
+                Some(first) if (first - tempo).abs() > 1e-3 => self.warn(
+                    ImportWarningKind::Unsupported,
+                    "sound",
+                    "tempo changes are not kept",
+                ),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn barline(&mut self, node: Node, index: usize) {
+        if let Some(repeat) = children(node, "repeat").next() {
+            match repeat.attribute("direction") {
+                Some("forward") => self.mark(index, BarMark::RepeatStart),
+                _ => {
+                    let times = repeat
+                        .attribute("times")
+                        .and_then(|t| t.parse::<u8>().ok())
+                        .unwrap_or(2);
+                    self.mark(index, BarMark::RepeatEnd(times));
+                }
+            }
+        }
+        if let Some(ending) = children(node, "ending").next() {
+            if ending.attribute("type") == Some("start") {
+                let numbers = ending
+                    .attribute("number")
+                    .unwrap_or_default()
+                    .split([',', ' '])
+                    .filter_map(|n| n.trim().parse::<u8>().ok())
+                    .collect::<Vec<_>>();
+                self.mark(index, BarMark::Volta(numbers));
+            }
+        }
+    }
+
+    fn mark(&mut self, index: usize, mark: BarMark) {
+        // Navigation belongs to the score, the first part carries it
+        if self.track == 0 {
+            self.summary.marks.push((index, mark));
+        }
+    }
+}
+
+/// Closest quality for the chord kinds the model has no quality for
+fn approximate_kind(kind: &str) -> Option<ChordQuality> {
+    match kind {
+        "dominant-ninth" | "dominant-11th" | "dominant-13th" => Some(ChordQuality::Dominant7),
+        "major-ninth" | "major-11th" | "major-13th" => Some(ChordQuality::Major7),
+        "minor-ninth" | "minor-11th" | "minor-13th" => Some(ChordQuality::Minor7),
+        "major-minor-seventh" => Some(ChordQuality::MinorMajor7),
+        "power" => Some(ChordQuality::Major),
+        _ => None,
+    }
+}
+
+/// Continuous pitch height for comparing spelled pitches
+pub(crate) fn pitch_height(tuning: Tuning) -> i32 {
+    let (letter, alter, octave) = tuning.spelling();
+    let semitones = [0, 2, 4, 5, 7, 9, 11][super::musicxml::letter_index(letter)];
+    octave as i32 * 12 + semitones + alter as i32
+}
+
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+fn has_child(node: Node, name: &str) -> bool {
+    node.children().any(|n| n.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .and_then(|n| n.text())
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PARTWISE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE score-partwise PUBLIC "-//Recordare//DTD MusicXML 4.0 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd">
+<score-partwise version="4.0">
+  <work><work-title>Air</work-title></work>
+  <part-list><score-part id="P1"><part-name>Flute</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>6</divisions>
+        <key><fifths>2</fifths><mode>major</mode></key>
+        <time><beats>3</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <direction><direction-type><dynamics><mp/></dynamics></direction-type><sound tempo="90"/></direction>
+      <harmony><root><root-step>B</root-step></root><kind>minor-seventh</kind></harmony>
+      <note><pitch><step>F</step><alter>1</alter><octave>5</octave></pitch><duration>6</duration><voice>1</voice><type>quarter</type>
+        <lyric number="1"><syllabic>begin</syllabic><text>Ah</text></lyric></note>
+      <note><rest/><duration>2</duration><voice>1</voice><type>eighth</type><time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification></note>
+      <note><pitch><step>E</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>eighth</type><time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification></note>
+      <note><pitch><step>D</step><octave>5</octave></pitch><duration>2</duration><voice>1</voice><type>eighth</type><time-modification><actual-notes>3</actual-notes><normal-notes>2</normal-notes></time-modification><notations><ornaments><trill-mark/></ornaments></notations></note>
+      <note><pitch><step>C</step><alter>1</alter><octave>5</octave></pitch><duration>6</duration><voice>1</voice><type>quarter</type></note>
+      <backup><duration>18</duration></backup>
+      <note><pitch><step>D</step><octave>4</octave></pitch><duration>18</duration><voice>2</voice><type>half</type><dot/></note>
+    </measure>
+    <measure number="2">
+      <barline location="left"><repeat direction="forward"/></barline>
+      <harmony><root><root-step>E</root-step><root-alter>-1</root-alter></root><kind text="7">dominant</kind></harmony>
+      <note><pitch><step>E</step><alter>-1</alter><octave>4</octave></pitch><duration>18</duration><voice>1</voice><type>half</type><dot/></note>
+      <note><chord/><pitch><step>G</step><octave>4</octave></pitch><duration>18</duration><voice>1</voice><type>half</type><dot/></note>
+      <barline location="right"><repeat direction="backward" times="3"/></barline>
+    </measure>
+  </part>
+</score-partwise>"#;
+
+    #[test]
+    fn test_import_partwise() {
+        let import = MusicXml::import::<1>(PARTWISE).unwrap();
+        assert_eq!(import.title.as_deref(), Some("Air"));
+        assert_eq!(import.part_names, vec!["Flute"]);
+        let score = &import.score;
+        assert_eq!(score.key_signature().fifths(), 2);
+        assert_eq!(score.time_signature().beats_per_measure(), 3);
+        assert_eq!(score.tempo(), 90.0);
+        assert_eq!(
+            score.marks(1),
+            &[BarMark::RepeatStart, BarMark::RepeatEnd(3)]
+        );
+
+        let measures = score.get_tracks()[0].get_measures();
+        let Measure::Note(notes) = &measures[0] else {
+            panic!("melody measure expected");
+        };
+        assert_eq!(notes.len(), 5);
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('F', 1, 5));
+        assert_eq!(notes[0].expression().dynamic, Some(Dynamic::MezzoPiano));
+        assert_eq!(notes[0].lyric(1).unwrap().text, "Ah");
+        assert!(notes[1].is_rest());
+        assert_eq!(notes[2].duration().tuplet.unwrap().actual_notes, 3);
+        assert!((notes[2].duration().in_quarters() - 1.0 / 3.0).abs() < 1e-5);
+
+        // The chord symbol of a melody measure is listed apart
+        assert_eq!(import.chord_symbols.len(), 1);
+        assert_eq!(import.chord_symbols[0].chord.to_string(), "Bm7");
+        // A chord symbol over its own chord notes is a chord measure
+        let Measure::Chords(chords) = &measures[1] else {
+            panic!("chord measure expected");
+        };
+        assert_eq!(chords[0].root().spelling(), ('E', -1, 4));
+        assert_eq!(chords[0].quality(), ChordQuality::Dominant7);
+
+        let elements = import
+            .warnings
+            .iter()
+            .map(|w| w.element.as_str())
+            .collect::<Vec<_>>();
+        assert!(elements.contains(&"voice"));
+        assert!(elements.contains(&"ornaments"));
+        let ornaments = import
+            .warnings
+            .iter()
+            .find(|w| w.element == "ornaments")
+            .unwrap();
+        assert_eq!(ornaments.kind, ImportWarningKind::Unsupported);
+        assert_eq!(ornaments.measure, "1");
+    }
+
+    #[test]
+    fn test_import_ties_chords_and_voices() {
+        let note = |step: &str, octave: u8, duration: u8, kind: &str, extra: &str| {
+            format!("<note>{extra}<pitch><step>{step}</step><octave>{octave}</octave></pitch><duration>{duration}</duration><voice>1</voice><type>{kind}</type></note>")
+        };
+        let start = r#"<tie type="start"/>"#;
+        let stop = r#"<tie type="stop"/>"#;
+        let first = [
+            r#"<attributes><divisions>2</divisions><time><beats>4</beats><beat-type>4</beat-type></time></attributes>"#.to_string(),
+            note("C", 4, 2, "quarter", start),
+            note("C", 4, 1, "eighth", stop),
+            note("E", 4, 1, "eighth", ""),
+            note("C", 5, 1, "eighth", "<chord/>"),
+            note("G", 4, 1, "eighth", "<chord/>"),
+            "<forward><duration>2</duration></forward>".to_string(),
+            note("D", 4, 2, "quarter", start),
+            "<backup><duration>8</duration></backup>".to_string(),
+            r#"<note><pitch><step>A</step><octave>3</octave></pitch><duration>8</duration><voice>2</voice><type>whole</type></note>"#.to_string(),
+        ]
+        .concat();
+        let second = [
+            note("D", 4, 2, "quarter", stop),
+            note("E", 4, 2, "quarter", start),
+            note("E", 4, 3, "quarter", &format!("{stop}<dot/>")),
+            "<note><rest/><duration>1</duration><voice>1</voice><type>eighth</type></note>"
+                .to_string(),
+        ]
+        .concat();
+        let partwise = format!(
+            r#"<score-partwise version="4.0"><part-list><score-part id="P1"/></part-list><part id="P1">
+<measure number="1">{first}</measure><measure number="2">{second}</measure></part></score-partwise>"#
+        );
+        let timewise = format!(
+            r#"<score-timewise version="4.0"><part-list><score-part id="P1"/></part-list>
+<measure number="1"><part id="P1">{first}</part></measure><measure number="2"><part id="P1">{second}</part></measure></score-timewise>"#
+        );
+
+        for xml in [partwise, timewise] {
+            let import = MusicXml::import::<1>(&xml).unwrap();
+            let measures = import.score.get_tracks()[0].get_measures();
+            let notes = |index: usize| {
+                let Measure::Note(notes) = &measures[index] else {
+                    panic!("melody measure expected");
+                };
+                notes
+                    .iter()
+                    .map(|n| {
+                        let pitch = n.tuning().map(|t| t.spelling());
+                        (pitch, n.duration().in_quarters())
+                    })
+                    .collect::<Vec<_>>()
+            };
+            // The tied C merges into a dotted quarter, the chord keeps its top note, the
+            // forward is a rest and the second voice after the backup is left out
+            assert_eq!(
+                notes(0),
+                vec![
+                    (Some(('C', 0, 4)), 1.5),
+                    (Some(('C', 0, 5)), 0.5),
+                    (None, 1.0),
+                    (Some(('D', 0, 4)), 1.0),
+                ]
+            );
+            // The tie over the barline and the tie without a single value stay split
+            assert_eq!(
+                notes(1),
+                vec![
+                    (Some(('D', 0, 4)), 1.0),
+                    (Some(('E', 0, 4)), 1.0),
+                    (Some(('E', 0, 4)), 1.5),
+                    (None, 0.5),
+                ]
+            );
+
+            let warning = |element: &str| {
+                import
+                    .warnings
+                    .iter()
+                    .find(|w| w.element == element)
+                    .map(|w| (w.kind, w.measure.as_str()))
+            };
+            assert_eq!(
+                warning("chord"),
+                Some((ImportWarningKind::Approximated, "1"))
+            );
+            assert_eq!(
+                warning("voice"),
+                Some((ImportWarningKind::Unsupported, "1"))
+            );
+            // Reported once, at the first split tie
+            assert_eq!(warning("tie"), Some((ImportWarningKind::Approximated, "1")));
+        }
+    }
+
+    #[test]
+    fn test_import_timewise_and_mxl() {
+        let timewise = r#"<score-timewise version="4.0">
+  <part-list>
+    <score-part id="P1"><part-name>Voice</part-name></score-part>
+    <score-part id="P2"><part-name>Drums</part-name>
+      <midi-instrument id="P2-I36"><midi-channel>10</midi-channel><midi-unpitched>37</midi-unpitched></midi-instrument>
+    </score-part>
+  </part-list>
+  <measure number="1">
+    <part id="P1">
+      <attributes><divisions>2</divisions><time><beats>3+2</beats><beat-type>8</beat-type></time></attributes>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>3</duration><type>quarter</type><dot/></note>
+    </part>
+    <part id="P2">
+      <note><unpitched><display-step>F</display-step><display-octave>4</display-octave></unpitched><duration>2</duration><instrument id="P2-I36"/><type>quarter</type></note>
+    </part>
+  </measure>
+</score-timewise>"#;
+        let import = MusicXml::import::<2>(timewise).unwrap();
+        let score = &import.score;
+        assert_eq!(score.track_time_signature(0).grouping(), vec![3, 2]);
+        let tracks = score.get_tracks();
+        assert!(matches!(&tracks[0].get_measures()[0], Measure::Note(n) if n.len() == 1));
+        let Measure::Percussion(hits) = &tracks[1].get_measures()[0] else {
+            panic!("percussion measure expected");
+        };
+        assert_eq!(hits[0].sound(), DrumSound::BassDrum);
+
+        // Only one track: the drums are dropped with a warning
+        let import = MusicXml::import::<1>(timewise).unwrap();
+        assert_eq!(import.warnings[0].element, "part");
+
+        // The same score exported, compressed and read back
+        let exported = MusicXml::new().export(&import.score);
+        let container = br#"<container><rootfiles><rootfile full-path="score.musicxml"/></rootfiles></container>"#;
+        let mxl = super::super::zip::deflated(&[
+            ("mimetype", b"application/vnd.recordare.musicxml"),
+            ("META-INF/container.xml", container),
+            ("score.musicxml", exported.as_bytes()),
+        ]);
+        let reread = MusicXml::import_bytes::<1>(&mxl).unwrap();
+        let Measure::Note(notes) = &reread.score.get_tracks()[0].get_measures()[0] else {
+            panic!("melody measure expected");
+        };
+        assert_eq!(notes[0].tuning().unwrap().spelling(), ('A', 0, 4));
+        assert_eq!(
+            notes[0].duration(),
+            Duration::new(DurationBase::Quarter).dotted(1)
+        );
+        assert!(MusicXml::import::<1>("<html/>").is_err());
+
+        // An absurd alteration is rejected, not spelled out
+        let harmony = r#"<score-partwise version="4.0"><part id="P1"><measure number="1">
+  <harmony><root><root-step>C</root-step><root-alter>1000000000</root-alter></root><kind>major</kind></harmony>
+</measure></part></score-partwise>"#;
+        let import = MusicXml::import::<1>(harmony).unwrap();
+        assert!(import.chord_symbols.is_empty());
+        assert!(import.warnings.iter().any(|w| w.element == "root-alter"));
+    }
+}
@@ -0,0 +1,1306 @@
+//! SVG staff notation
+//!
+//! A small engraver to preview measures, tracks and scores without external tools:
+//! - treble, bass, alto and percussion staves with ledger lines, key and time signature
+//! - noteheads, stems, flags, dots and ties, beams grouped along the `TimeSignature`
+//! - accidentals from the spelling of each `Tuning`, carried through the measure
+//! - rests, tuplet numbers and chord symbols above the staff
+//!
+//! Measures are spaced by duration and wrapped into systems of a fixed width. All glyphs
+//! are drawn as paths, no music font is needed.
+
+use super::layout::{layout, Content};
+use super::musicxml::{beat_type_number, drum_display, low_register};
+use crate::fret::svg::SvgWriter;
+use crate::{Duration, DurationBase, KeySignature, Measure, Score, TimeSignature, Track, Tuning};
+use std::collections::HashMap;
+
+/// Stands in for the measures missing at the end of a track
+static REST: Measure = Measure::Rest;
+
+/// Steps of the sharps of a key signature above the bottom line of a treble staff
+const SHARPS: [i32; 7] = [8, 5, 9, 6, 3, 7, 4];
+/// Steps of the flats of a key signature above the bottom line of a treble staff
+const FLATS: [i32; 7] = [4, 7, 3, 6, 2, 5, 1];
+/// Staff spaces taken by one staff with the room above and below it
+const STAFF_HEIGHT: f32 = 14.0;
+
+/// Clef of a staff
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clef {
+    Treble,
+    Bass,
+    Alto,
+    /// Neutral clef of drum staves
+    Percussion,
+}
+
+impl Clef {
+    /// Diatonic step of the bottom line
+    fn bottom(&self) -> i32 {
+        match self {
+            Clef::Treble | Clef::Percussion => step('E', 4),
+            Clef::Bass => step('G', 2),
+            Clef::Alto => step('F', 3),
+        }
+    }
+
+    /// Steps the key signature is moved down from its treble positions
+    fn key_shift(&self) -> i32 {
+        match self {
+            Clef::Bass => 2,
+            Clef::Alto => 1,
+            Clef::Treble | Clef::Percussion => 0,
+        }
+    }
+}
+
+/// Diatonic step of a letter in an octave, counted from C0
+fn step(letter: char, octave: i8) -> i32 {
+    "CDEFGAB".find(letter).unwrap_or(0) as i32 + 7 * octave as i32
+}
+
+/// Steps and alteration of the accidentals of a key signature on a staff
+fn key_steps(clef: Clef, key: &KeySignature) -> Vec<(i32, i8)> {
+    let count = key.fifths().unsigned_abs() as usize;
+    let (steps, alter) = match key.fifths() > 0 {
+        true => (&SHARPS, 1),
+        false => (&FLATS, -1),
+    };
+    steps
+        .iter()
+        .take(count)
+        .map(|step| (clef.bottom() + step - clef.key_shift(), alter))
+        .collect()
+}
+
+/// Steps of the ledger lines a head needs below or above the staff
+fn ledgers(clef: Clef, step: i32) -> Vec<i32> {
+    let bottom = clef.bottom();
+    let below = (step..=bottom - 2).rev().step_by(2);
+    let above = (bottom + 10..=step).step_by(2);
+    below
+        .chain(above)
+        .map(|ledger| ledger - (ledger - bottom).rem_euclid(2))
+        .collect()
+}
+
+/// SVG staff renderer, see the module documentation
+#[derive(Debug, Clone)]
+pub struct StaffSvg {
+    title: Option<String>,
+    clef: Option<Clef>,
+    key_signature: KeySignature,
+    width: f32,
+    space: f32,
+    color: String,
+    font_family: String,
+}
+
+impl Default for StaffSvg {
+    fn default() -> Self {
+        StaffSvg {
+            title: None,
+            clef: None,
+            key_signature: KeySignature::default(),
+            width: 800.0,
+            space: 8.0,
+            color: "#000000".to_string(),
+            font_family: "serif".to_string(),
+        }
+    }
+}
+
+impl StaffSvg {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(self, title: impl Into<String>) -> Self {
+        StaffSvg {
+            title: Some(title.into()),
+            ..self
+        }
+    }
+
+    /// Clef of the pitched staves, chosen from their register by default
+    pub fn with_clef(self, clef: Clef) -> Self {
+        StaffSvg {
+            clef: Some(clef),
+            ..self
+        }
+    }
+
+    /// Key signature of rendered measures and tracks, scores bring their own
+    pub fn with_key_signature(self, key_signature: KeySignature) -> Self {
+        StaffSvg {
+            key_signature,
+            ..self
+        }
+    }
+
+    /// Width of the systems in pixels
+    pub fn with_width(self, width: f32) -> Self {
+        StaffSvg { width, ..self }
+    }
+
+    /// Distance between two staff lines in pixels, everything scales with it
+    pub fn with_staff_space(self, space: f32) -> Self {
+        StaffSvg { space, ..self }
+    }
+
+    pub fn with_color(self, color: impl Into<String>) -> Self {
+        StaffSvg {
+            color: color.into(),
+            ..self
+        }
+    }
+
+    pub fn with_font(self, font_family: impl Into<String>) -> Self {
+        StaffSvg {
+            font_family: font_family.into(),
+            ..self
+        }
+    }
+
+    /// SVG of a single measure
+    pub fn render_measure(&self, measure: &Measure, time_signature: &TimeSignature) -> String {
+        let part = self.part(std::slice::from_ref(measure), *time_signature);
+        self.engrave(&[part], 1, &self.key_signature)
+    }
+
+    /// SVG of a track, in its own time signature if it has one
+    pub fn render_track(&self, track: &Track, time_signature: &TimeSignature) -> String {
+        let ts = track.time_signature().copied().unwrap_or(*time_signature);
+        let measures = track.get_measures();
+        let part = self.part(measures, ts);
+        self.engrave(&[part], measures.len().max(1), &self.key_signature)
+    }
+
+    /// SVG of a score, one staff per track in systems
+    pub fn export<const N: usize>(&self, score: &Score<N>) -> String {
+        let parts = (0..N)
+            .map(|t| {
+                let measures = score.get_tracks()[t].get_measures();
+                self.part(measures, score.track_time_signature(t))
+            })
+            .collect::<Vec<_>>();
+        self.engrave(&parts, score.measure_count().max(1), score.key_signature())
+    }
+
+    fn part<'a>(&self, measures: &'a [Measure], ts: TimeSignature) -> Part<'a> {
+        let drums = measures.iter().any(|m| matches!(m, Measure::Percussion(_)));
+        let clef = match (drums, self.clef) {
+            (true, _) => Clef::Percussion,
+            (false, Some(clef)) => clef,
+            (false, None) if low_register(measures) => Clef::Bass,
+            (false, None) => Clef::Treble,
+        };
+        Part { measures, ts, clef }
+    }
+
+    fn engrave(&self, parts: &[Part], count: usize, key: &KeySignature) -> String {
+        let s = self.space;
+        let key = match parts.iter().all(|p| p.clef == Clef::Percussion) {
+            true => KeySignature::default(),
+            false => *key,
+        };
+        let events = parts
+            .iter()
+            .map(|part| {
+                (0..count)
+                    .map(|m| events(part.measures.get(m).unwrap_or(&REST), part, &key))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        // Widths in staff spaces, the most crowded staff sets the width of a measure
+        let natural = (0..count)
+            .map(|m| {
+                events
+                    .iter()
+                    .map(|part| 1.5 + part[m].iter().map(Event::width).sum::<f32>())
+                    .fold(0.0, f32::max)
+            })
+            .collect::<Vec<_>>();
+        let margin = 2.0;
+        let prefix = |first: bool| {
+            4.0 + key.fifths().unsigned_abs() as f32 * 1.1 + if first { 3.0 } else { 0.0 }
+        };
+        let room = |first: bool| (self.width / s - 2.0 * margin - prefix(first)).max(1.0);
+        let mut systems: Vec<Vec<usize>> = vec![];
+        let mut used = 0.0;
+        for (m, width) in natural.iter().enumerate() {
+            let first = systems.len() == 1;
+            match systems.last_mut() {
+                Some(system) if used + width <= room(first) => system.push(m),
+                _ => {
+                    systems.push(vec![m]);
+                    used = 0.0;
+                }
+            }
+            used += width;
+        }
+
+        let title = if self.title.is_some() { 4.0 } else { 0.0 };
+        let system_height = parts.len() as f32 * STAFF_HEIGHT + 2.0;
+        let height = (2.0 * margin + title + systems.len() as f32 * system_height) * s;
+        let mut pen = Pen {
+            svg: SvgWriter::new(self.width, height),
+            s,
+            color: &self.color,
+            font: &self.font_family,
+        };
+        if let Some(title) = &self.title {
+            pen.text(self.width / 2.0, (margin + 2.5) * s, title, 2.5, "middle");
+        }
+
+        let left = margin * s;
+        let right = self.width - margin * s;
+        for (index, system) in systems.iter().enumerate() {
+            let first = index == 0;
+            let last = index + 1 == systems.len();
+            let y = (margin + title + index as f32 * system_height) * s;
+            let tops = (0..parts.len())
+                .map(|p| y + (5.0 + p as f32 * STAFF_HEIGHT) * s)
+                .collect::<Vec<_>>();
+            let total = system.iter().map(|m| natural[*m]).sum::<f32>();
+            // The last system keeps its natural spacing when it is much shorter
+            let stretch = match last && total < room(first) * 0.75 {
+                true => 1.0,
+                false => room(first) / total,
+            };
+            let end = match stretch {
+                1.0 => left + (prefix(first) + total) * s,
+                _ => right,
+            };
+
+            for (part, top) in parts.iter().zip(&tops) {
+                for line in 0..5 {
+                    let ly = top + line as f32 * s;
+                    pen.svg.line(left, ly, end, ly, &self.color, s * 0.1);
+                }
+                pen.clef(left + 0.6 * s, *top, part.clef);
+                if part.clef != Clef::Percussion {
+                    pen.key_signature(left + 4.0 * s, *top, part.clef, &key);
+                }
+                if first {
+                    let x = left + prefix(false) * s + 1.0 * s;
+                    pen.time_signature(x, *top, &part.ts);
+                }
+            }
+            if parts.len() > 1 {
+                let bottom = tops[tops.len() - 1] + 4.0 * s;
+                pen.svg
+                    .line(left, tops[0], left, bottom, &self.color, s * 0.15);
+            }
+
+            let mut x = left + prefix(first) * s;
+            let mut ties = vec![vec![]; parts.len()];
+            for &m in system {
+                let width = natural[m] * stretch * s;
+                for (p, (part, top)) in parts.iter().zip(&tops).enumerate() {
+                    let events = &events[p][m];
+                    let inner = events.iter().map(Event::width).sum::<f32>();
+                    let scale = (width / s - 1.5) / inner.max(1e-3);
+                    let mut xs = vec![];
+                    let mut cursor = x + 1.0 * s;
+                    for event in events {
+                        xs.push(match event.kind {
+                            Kind::MeasureRest => x + width / 2.0,
+                            _ => cursor + event.lead() * s,
+                        });
+                        cursor += event.width() * scale * s;
+                    }
+                    pen.measure(events, &xs, part, *top, &mut ties[p]);
+                    let bar = x + width;
+                    let final_bar = last && m + 1 == count;
+                    match final_bar {
+                        true => {
+                            pen.svg.line(
+                                bar - 0.7 * s,
+                                *top,
+                                bar - 0.7 * s,
+                                top + 4.0 * s,
+                                &self.color,
+                                s * 0.12,
+                            );
+                            pen.svg
+                                .rect(bar - 0.4 * s, *top, 0.4 * s, 4.0 * s, &self.color);
+                        }
+                        false => pen
+                            .svg
+                            .line(bar, *top, bar, top + 4.0 * s, &self.color, s * 0.12),
+                    }
+                }
+                x += width;
+            }
+            // Ties into the next system run to the end of this one
+            for (pending, (part, top)) in ties.iter().zip(parts.iter().zip(&tops)) {
+                for tie in pending {
+                    pen.tie(tie, end - 0.3 * s, *top, part.clef);
+                }
+            }
+        }
+        pen.svg.finish()
+    }
+}
+
+/// Measures of one staff
+struct Part<'a> {
+    measures: &'a [Measure],
+    ts: TimeSignature,
+    clef: Clef,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Notes,
+    Rest,
+    MeasureRest,
+}
+
+#[derive(Debug, Clone)]
+struct Head {
+    step: i32,
+    /// Alteration to print in front of the head
+    accidental: Option<i8>,
+    /// Cross notehead of cymbals and hi-hats
+    cross: bool,
+}
+
+/// One notated value of a staff
+#[derive(Debug, Clone)]
+struct Event {
+    kind: Kind,
+    /// Lowest first
+    heads: Vec<Head>,
+    duration: Duration,
+    /// Quarters from the barline
+    start: f32,
+    tie: bool,
+    grace: bool,
+    symbol: Option<String>,
+}
+
+impl Event {
+    /// Space before the heads, in staff spaces
+    fn lead(&self) -> f32 {
+        match self.heads.iter().any(|h| h.accidental.is_some()) {
+            true => 2.2,
+            false => 0.8,
+        }
+    }
+
+    /// Natural width in staff spaces
+    fn width(&self) -> f32 {
+        if self.kind == Kind::MeasureRest {
+            return 6.0;
+        }
+        let body = match self.grace {
+            true => 1.2,
+            false => 1.4 + 2.2 * self.duration.in_quarters().sqrt().min(2.0),
+        };
+        self.lead() + body + 0.6 * self.duration.dots as f32
+    }
+
+    fn filled(&self) -> bool {
+        self.duration.base.in_quarters() < 2.0
+    }
+
+    fn stemmed(&self) -> bool {
+        self.duration.base.in_quarters() < 4.0
+    }
+
+    fn flags(&self) -> usize {
+        match self.duration.base {
+            DurationBase::Eighth => 1,
+            DurationBase::Sixteenth => 2,
+            DurationBase::ThirtySecond => 3,
+            DurationBase::SixtyFourth => 4,
+            _ => 0,
+        }
+    }
+}
+
+/// Events of a measure, accidentals printed when the spelling differs from the key or
+/// from an earlier note of the measure on the same line
+fn events(measure: &Measure, part: &Part, key: &KeySignature) -> Vec<Event> {
+    let mut altered: HashMap<(char, i8), i8> = HashMap::new();
+    let mut head = |tuning: Tuning, continued: bool| {
+        let (letter, alter, octave) = tuning.spelling();
+        let current = altered
+            .insert((letter, octave), alter)
+            .unwrap_or_else(|| key.alter(letter));
+        Head {
+            step: step(letter, octave),
+            accidental: (alter != current && !continued).then_some(alter),
+            cross: false,
+        }
+    };
+
+    let mut events = vec![];
+    let mut start = 0.0;
+    for slot in layout(measure, &part.ts) {
+        let (kind, mut heads, symbol) = match &slot.content {
+            Content::Note(_, tuning) => (Kind::Notes, vec![head(*tuning, slot.continued)], None),
+            Content::Chord(chord) => (
+                Kind::Notes,
+                chord
+                    .components()
+                    .into_iter()
+                    .map(|t| head(t, slot.continued))
+                    .collect(),
+                (!slot.continued).then(|| chord.to_string()),
+            ),
+            Content::Drums(hits) => (
+                Kind::Notes,
+                hits.iter()
+                    .map(|hit| {
+                        let (letter, octave, notehead) = drum_display(hit.sound());
+                        Head {
+                            step: step(letter, octave as i8),
+                            accidental: None,
+                            cross: notehead.is_some(),
+                        }
+                    })
+                    .collect(),
+                None,
+            ),
+            Content::Rest => (Kind::Rest, vec![], None),
+            Content::MeasureRest => (Kind::MeasureRest, vec![], None),
+        };
+        heads.sort_by_key(|h| h.step);
+        heads.dedup_by_key(|h| h.step);
+        let grace = slot.is_grace();
+        events.push(Event {
+            kind,
+            heads,
+            duration: slot.duration,
+            start,
+            tie: slot.tie,
+            grace,
+            symbol,
+        });
+        if !grace {
+            start += slot.duration.in_quarters();
+        }
+    }
+    events
+}
+
+/// Beamed groups of a measure: flagged notes within one beat, or within one beat group
+/// in meters counted in eighths and shorter
+fn beams(events: &[Event], ts: &TimeSignature) -> Vec<Vec<usize>> {
+    let beat = ts.beat_type().in_quarters();
+    let mut ends = vec![];
+    let mut position = 0.0;
+    match beat < 1.0 {
+        true => {
+            for group in ts.grouping() {
+                position += group as f32 * beat;
+                ends.push(position);
+            }
+        }
+        false => {
+            for _ in 0..ts.beats_per_measure() {
+                position += beat;
+                ends.push(position);
+            }
+        }
+    }
+    let unit = |event: &Event| {
+        let end = event.start + event.duration.in_quarters();
+        let index = ends.iter().position(|e| event.start < e - 1e-3)?;
+        (end <= ends[index] + 1e-3).then_some(index)
+    };
+
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut current: Option<usize> = None;
+    for (i, event) in events.iter().enumerate() {
+        let beamable = event.kind == Kind::Notes && !event.grace && event.flags() > 0;
+        let index = unit(event).filter(|_| beamable);
+        match (index, current) {
+            (Some(index), Some(open)) if index == open => groups.last_mut().unwrap().push(i),
+            (Some(_), _) => groups.push(vec![i]),
+            (None, _) => {}
+        }
+        current = index;
+    }
+    groups.retain(|g| g.len() > 1);
+    groups
+}
+
+/// Runs of events under one tuplet number
+fn tuplets(events: &[Event]) -> Vec<(Vec<usize>, u8)> {
+    let mut runs = vec![];
+    let mut open: Option<(Vec<usize>, f32, f32, u8)> = None;
+    for (i, event) in events.iter().enumerate() {
+        let Some(tuplet) = event.duration.tuplet.filter(|_| !event.grace) else {
+            open = None;
+            continue;
+        };
+        let (mut members, filled, span, actual) = open.take().unwrap_or_else(|| {
+            let span = tuplet.base_notes as f32 * tuplet.base_duration.in_quarters();
+            (vec![], 0.0, span, tuplet.actual_notes)
+        });
+        members.push(i);
+        let filled = filled + event.duration.in_quarters();
+        match filled >= span - 1e-3 {
+            true => runs.push((members, actual)),
+            false => open = Some((members, filled, span, actual)),
+        }
+    }
+    runs
+}
+
+/// Heads left tied over a barline: x, step and stem direction
+#[derive(Debug, Clone)]
+struct Tie {
+    x: f32,
+    step: i32,
+    up: bool,
+}
+
+/// Draws on a staff, all sizes in staff spaces
+struct Pen<'a> {
+    svg: SvgWriter,
+    s: f32,
+    color: &'a str,
+    font: &'a str,
+}
+
+impl Pen<'_> {
+    fn text(&mut self, x: f32, y: f32, text: &str, size: f32, anchor: &str) {
+        let size = size * self.s;
+        self.svg
+            .text(x, y, text, size, self.font, self.color, anchor);
+    }
+
+    fn stroke(&mut self, d: &str, width: f32) {
+        let width = width * self.s;
+        self.svg.path(d, self.color, width);
+    }
+
+    fn line(&mut self, (x1, y1): (f32, f32), (x2, y2): (f32, f32), width: f32) {
+        let width = width * self.s;
+        self.svg.line(x1, y1, x2, y2, self.color, width);
+    }
+
+    /// Path through points given in staff spaces around `(x, y)`
+    fn curve(&self, x: f32, y: f32, d: &[(&str, &[(f32, f32)])]) -> String {
+        d.iter()
+            .map(|(command, points)| {
+                let points = points
+                    .iter()
+                    .map(|(px, py)| format!("{:.1} {:.1}", x + px * self.s, y + py * self.s))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{command} {points}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn y(&self, top: f32, clef: Clef, step: i32) -> f32 {
+        top + 4.0 * self.s - (step - clef.bottom()) as f32 * self.s / 2.0
+    }
+
+    fn clef(&mut self, x: f32, top: f32, clef: Clef) {
+        let s = self.s;
+        match clef {
+            Clef::Treble => {
+                // Curled around the G line
+                let d = self.curve(
+                    x + 1.2 * s,
+                    top + 3.0 * s,
+                    &[
+                        ("M", &[(0.35, 3.3)]),
+                        ("L", &[(0.05, -3.6)]),
+                        ("C", &[(0.9, -2.8), (0.9, -1.6), (-0.4, -0.6)]),
+                        ("C", &[(-1.3, 0.1), (-0.9, 1.4), (0.2, 1.3)]),
+                        ("C", &[(1.2, 1.2), (1.1, -0.3), (0.2, -0.35)]),
+                        ("C", &[(-0.4, -0.35), (-0.5, 0.35), (-0.1, 0.6)]),
+                        ("M", &[(0.35, 3.3)]),
+                        ("C", &[(0.4, 4.1), (-0.6, 4.1), (-0.55, 3.5)]),
+                    ],
+                );
+                self.stroke(&d, 0.22);
+                self.svg.circle(
+                    x + 0.85 * s,
+                    top + 6.45 * s,
+                    0.32 * s,
+                    self.color,
+                    "none",
+                    0.0,
+                );
+            }
+            Clef::Bass => {
+                let d = self.curve(
+                    x + 0.5 * s,
+                    top + s,
+                    &[
+                        ("M", &[(0.0, 0.0)]),
+                        ("C", &[(0.0, -1.2), (1.7, -1.3), (1.7, 0.2)]),
+                        ("C", &[(1.7, 1.6), (0.7, 2.6), (-0.4, 3.0)]),
+                    ],
+                );
+                self.stroke(&d, 0.3);
+                self.svg
+                    .circle(x + 0.5 * s, top + s, 0.38 * s, self.color, "none", 0.0);
+                for dot in [0.5, 1.5] {
+                    self.svg.circle(
+                        x + 2.7 * s,
+                        top + dot * s,
+                        0.18 * s,
+                        self.color,
+                        "none",
+                        0.0,
+                    );
+                }
+            }
+            Clef::Alto => {
+                self.svg
+                    .rect(x + 0.3 * s, top, 0.45 * s, 4.0 * s, self.color);
+                self.line((x + 1.05 * s, top), (x + 1.05 * s, top + 4.0 * s), 0.15);
+                for sign in [-1.0, 1.0] {
+                    let d = self.curve(
+                        x + 1.1 * s,
+                        top + 2.0 * s,
+                        &[
+                            ("M", &[(0.0, 0.0)]),
+                            (
+                                "C",
+                                &[(1.5, sign * 0.4), (1.7, sign * 2.2), (0.5, sign * 1.7)],
+                            ),
+                        ],
+                    );
+                    self.stroke(&d, 0.3);
+                }
+            }
+            Clef::Percussion => {
+                for bar in [0.8, 1.6] {
+                    self.svg
+                        .rect(x + bar * s, top + s, 0.4 * s, 2.0 * s, self.color);
+                }
+            }
+        }
+    }
+
+    fn key_signature(&mut self, x: f32, top: f32, clef: Clef, key: &KeySignature) {
+        for (i, (step, alter)) in key_steps(clef, key).into_iter().enumerate() {
+            let y = self.y(top, clef, step);
+            self.accidental(x + (0.5 + i as f32 * 1.1) * self.s, y, alter);
+        }
+    }
+
+    fn time_signature(&mut self, x: f32, top: f32, ts: &TimeSignature) {
+        let s = self.s;
+        let beats = ts.beats_per_measure().to_string();
+        let beat = beat_type_number(ts.beat_type()).to_string();
+        self.text(x, top + 1.85 * s, &beats, 2.6, "middle");
+        self.text(x, top + 3.85 * s, &beat, 2.6, "middle");
+    }
+
+    fn accidental(&mut self, x: f32, y: f32, alter: i8) {
+        let s = self.s;
+        match alter {
+            0 => {
+                self.line((x - 0.3 * s, y - 1.2 * s), (x - 0.3 * s, y + 0.6 * s), 0.12);
+                self.line((x + 0.3 * s, y - 0.6 * s), (x + 0.3 * s, y + 1.2 * s), 0.12);
+                for dy in [-0.4, 0.4] {
+                    let (from, to) = (
+                        (x - 0.3 * s, y + dy * s + 0.1 * s),
+                        (x + 0.3 * s, y + dy * s - 0.1 * s),
+                    );
+                    self.line(from, to, 0.25);
+                }
+            }
+            1 => {
+                for dx in [-0.25, 0.25] {
+                    let (from, to) = ((x + dx * s, y - 1.2 * s), (x + dx * s, y + 1.2 * s));
+                    self.line(from, to, 0.12);
+                }
+                for dy in [-0.4, 0.4] {
+                    let (from, to) = (
+                        (x - 0.6 * s, y + dy * s + 0.15 * s),
+                        (x + 0.6 * s, y + dy * s - 0.15 * s),
+                    );
+                    self.line(from, to, 0.25);
+                }
+            }
+            2 => {
+                for dx in [-0.4, 0.4] {
+                    self.line((x - dx * s, y - 0.4 * s), (x + dx * s, y + 0.4 * s), 0.2);
+                }
+            }
+            alter if alter < 0 => {
+                for i in 0..(-alter).min(2) {
+                    let x = x - i as f32 * 0.8 * s;
+                    self.line((x - 0.3 * s, y - 1.7 * s), (x - 0.3 * s, y + 0.5 * s), 0.12);
+                    let d = self.curve(
+                        x - 0.3 * s,
+                        y + 0.5 * s,
+                        &[
+                            ("M", &[(0.0, 0.0)]),
+                            ("C", &[(1.0, -0.6), (0.8, -1.4), (0.0, -0.7)]),
+                        ],
+                    );
+                    self.stroke(&d, 0.18);
+                }
+            }
+            _ => {
+                // Triple sharps are rare enough to show as a double sharp after a sharp
+                self.accidental(x - 1.0 * s, y, 1);
+                self.accidental(x, y, 2);
+            }
+        }
+    }
+
+    fn rest(&mut self, x: f32, top: f32, event: &Event) {
+        let s = self.s;
+        let base = event.duration.base.in_quarters();
+        match (event.kind, event.flags()) {
+            (Kind::MeasureRest, _) => {
+                self.svg
+                    .rect(x - 0.6 * s, top + s, 1.2 * s, 0.5 * s, self.color)
+            }
+            _ if base >= 4.0 => self
+                .svg
+                .rect(x - 0.6 * s, top + s, 1.2 * s, 0.5 * s, self.color),
+            _ if base >= 2.0 => {
+                self.svg
+                    .rect(x - 0.6 * s, top + 1.5 * s, 1.2 * s, 0.5 * s, self.color)
+            }
+            (_, 0) => {
+                let d = self.curve(
+                    x,
+                    top,
+                    &[
+                        ("M", &[(-0.3, 0.8)]),
+                        ("L", &[(0.4, 1.7), (-0.2, 2.3), (0.4, 3.0)]),
+                        ("Q", &[(-0.6, 2.7), (0.0, 3.6)]),
+                    ],
+                );
+                self.stroke(&d, 0.3);
+            }
+            (_, flags) => {
+                let bottom = 1.2 + (flags as f32 + 1.0);
+                self.line(
+                    (x + 0.6 * s, top + 1.2 * s),
+                    (x - 0.2 * s, top + bottom * s),
+                    0.15,
+                );
+                for i in 0..flags {
+                    let along = 1.2 + i as f32;
+                    let stem = x + 0.6 * s - (along - 1.2) / (bottom - 1.2) * 0.8 * s;
+                    self.svg.circle(
+                        stem - 0.7 * s,
+                        top + along * s + 0.1 * s,
+                        0.28 * s,
+                        self.color,
+                        "none",
+                        0.0,
+                    );
+                    let d = self.curve(
+                        stem,
+                        top + along * s,
+                        &[("M", &[(0.0, 0.0)]), ("Q", &[(-0.4, 0.4), (-0.7, 0.1)])],
+                    );
+                    self.stroke(&d, 0.12);
+                }
+            }
+        }
+        for dot in 0..event.duration.dots {
+            let dx = 1.1 + dot as f32 * 0.5;
+            self.svg
+                .circle(x + dx * s, top + 1.5 * s, 0.16 * s, self.color, "none", 0.0);
+        }
+    }
+
+    /// A measure of one staff, `xs` are the positions of the heads
+    fn measure(
+        &mut self,
+        events: &[Event],
+        xs: &[f32],
+        part: &Part,
+        top: f32,
+        ties: &mut Vec<Tie>,
+    ) {
+        let s = self.s;
+        let clef = part.clef;
+        let middle = clef.bottom() + 4;
+        let y = |step: i32| top + 4.0 * s - (step - clef.bottom()) as f32 * s / 2.0;
+        let scale = |event: &Event| if event.grace { 0.65 } else { 1.0 };
+
+        // Stem directions and tips, beamed groups share theirs
+        let mut up = events
+            .iter()
+            .map(|e| match (e.heads.first(), e.heads.last()) {
+                _ if e.grace => true,
+                (Some(low), Some(high)) => low.step + high.step < 2 * middle,
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        let stem_x = |event: &Event, x: f32, up: bool| match up {
+            true => x + 0.6 * s * scale(event),
+            false => x - 0.6 * s * scale(event),
+        };
+        let mut tips = events
+            .iter()
+            .zip(xs)
+            .zip(&up)
+            .map(|((event, _), up)| {
+                let (Some(low), Some(high)) = (event.heads.first(), event.heads.last()) else {
+                    return top;
+                };
+                let length = if event.grace { 2.5 } else { 3.5 } * s;
+                match up {
+                    true => (y(high.step) - length).min(y(middle)),
+                    false => (y(low.step) + length).max(y(middle)),
+                }
+            })
+            .collect::<Vec<_>>();
+        let groups = beams(events, &part.ts);
+        let mut beamed = vec![false; events.len()];
+        for group in &groups {
+            let steps = group
+                .iter()
+                .flat_map(|&i| events[i].heads.iter().map(|h| h.step));
+            let (sum, count) = steps.fold((0, 0), |(sum, count), step| (sum + step, count + 1));
+            let group_up = sum < middle * count;
+            let (first, last) = (group[0], group[group.len() - 1]);
+            let points = group
+                .iter()
+                .map(|&i| {
+                    let event = &events[i];
+                    let x = stem_x(event, xs[i], group_up);
+                    let (low, high) =
+                        (event.heads[0].step, event.heads[event.heads.len() - 1].step);
+                    (x, y(low), y(high))
+                })
+                .collect::<Vec<_>>();
+            let ideal = |(_, low, high): (f32, f32, f32)| match group_up {
+                true => high - 3.5 * s,
+                false => low + 3.5 * s,
+            };
+            let (x0, x1) = (points[0].0, points[points.len() - 1].0);
+            let y0 = ideal(points[0]);
+            let rise = (ideal(points[points.len() - 1]) - y0).clamp(-s, s);
+            let line = |x: f32| y0 + rise * (x - x0) / (x1 - x0).max(1e-3);
+            // Keep every stem at least 2.5 spaces long
+            let shift = points
+                .iter()
+                .map(|&(x, low, high)| match group_up {
+                    true => (line(x) - (high - 2.5 * s)).max(0.0),
+                    false => ((low + 2.5 * s) - line(x)).max(0.0),
+                })
+                .fold(0.0, f32::max);
+            let shift = if group_up { -shift } else { shift };
+            for &i in group {
+                up[i] = group_up;
+                tips[i] = line(stem_x(&events[i], xs[i], group_up)) + shift;
+                beamed[i] = true;
+            }
+            let levels = group.iter().map(|&i| events[i].flags()).max().unwrap_or(1);
+            let sign = if group_up { 1.0 } else { -1.0 };
+            let beam = |pen: &mut Self, a: f32, b: f32, level: usize| {
+                let offset = level as f32 * 0.75 * s * sign;
+                let (ya, yb) = (line(a) + shift + offset, line(b) + shift + offset);
+                let thick = 0.45 * s * sign;
+                pen.svg.polygon(
+                    &[(a, ya), (b, yb), (b, yb + thick), (a, ya + thick)],
+                    pen.color,
+                );
+            };
+            beam(
+                self,
+                stem_x(&events[first], xs[first], group_up),
+                stem_x(&events[last], xs[last], group_up),
+                0,
+            );
+            for level in 1..levels {
+                for (k, &i) in group.iter().enumerate() {
+                    if events[i].flags() <= level {
+                        continue;
+                    }
+                    let x = stem_x(&events[i], xs[i], group_up);
+                    let next = group.get(k + 1).filter(|&&j| events[j].flags() > level);
+                    let previous = k
+                        .checked_sub(1)
+                        .map(|p| group[p])
+                        .filter(|&j| events[j].flags() > level);
+                    match (previous, next) {
+                        (_, Some(&j)) => beam(self, x, stem_x(&events[j], xs[j], group_up), level),
+                        (Some(_), None) => {}
+                        (None, None) if k + 1 < group.len() => beam(self, x, x + s, level),
+                        (None, None) => beam(self, x - s, x, level),
+                    }
+                }
+            }
+        }
+
+        for (i, event) in events.iter().enumerate() {
+            let x = xs[i];
+            if event.kind != Kind::Notes {
+                self.rest(x, top, event);
+                ties.clear();
+                continue;
+            }
+            let k = scale(event);
+            let (rx, ry) = (0.62 * s * k, 0.42 * s * k);
+            // A head a second above the previous one moves to the other side of the stem
+            let mut offsets = vec![0.0; event.heads.len()];
+            for h in 1..event.heads.len() {
+                if event.heads[h].step == event.heads[h - 1].step + 1 && offsets[h - 1] == 0.0 {
+                    offsets[h] = if up[i] { 2.0 * rx } else { -2.0 * rx };
+                }
+            }
+            // Ties from the previous event end at the matching heads
+            for tie in ties.drain(..) {
+                if let Some(h) = event.heads.iter().position(|h| h.step == tie.step) {
+                    self.tie(&tie, x + offsets[h] - rx, top, clef);
+                }
+            }
+
+            let mut accidentals = 0;
+            for (head, offset) in event.heads.iter().zip(&offsets).rev() {
+                let (hx, hy) = (x + offset, y(head.step));
+                for ledger in ledgers(clef, head.step) {
+                    let ly = y(ledger);
+                    self.line((hx - 1.6 * rx, ly), (hx + 1.6 * rx, ly), 0.12);
+                }
+                match (head.cross, event.filled()) {
+                    (true, _) => {
+                        self.line((hx - rx, hy - ry), (hx + rx, hy + ry), 0.15);
+                        self.line((hx - rx, hy + ry), (hx + rx, hy - ry), 0.15);
+                    }
+                    (false, true) => self
+                        .svg
+                        .ellipse(hx, hy, rx, ry, -20.0, self.color, "none", 0.0),
+                    (false, false) => self.svg.ellipse(
+                        hx,
+                        hy,
+                        rx * 0.9,
+                        ry * 0.8,
+                        -20.0,
+                        "none",
+                        self.color,
+                        0.18 * s,
+                    ),
+                }
+                if let Some(alter) = head.accidental {
+                    let ax = x - rx - (1.0 + (accidentals % 2) as f32 * 1.1) * s;
+                    self.accidental(ax, hy, alter);
+                    accidentals += 1;
+                }
+                let on_line = (head.step - clef.bottom()).rem_euclid(2) == 0;
+                for dot in 0..event.duration.dots {
+                    let dx = rx + 0.55 * s + dot as f32 * 0.5 * s;
+                    let dy = if on_line { -0.5 * s } else { 0.0 };
+                    let right = x + offsets.iter().copied().fold(0.0, f32::max);
+                    self.svg
+                        .circle(right + dx, hy + dy, 0.16 * s, self.color, "none", 0.0);
+                }
+            }
+
+            if event.stemmed() {
+                let sx = stem_x(event, x, up[i]);
+                let far = match up[i] {
+                    true => y(event.heads[0].step),
+                    false => y(event.heads[event.heads.len() - 1].step),
+                };
+                self.line((sx, far), (sx, tips[i]), 0.12);
+                if !beamed[i] {
+                    let sign = if up[i] { 1.0 } else { -1.0 };
+                    for flag in 0..event.flags() {
+                        let fy = tips[i] + sign * flag as f32 * 0.8 * s * k;
+                        let d = self.curve(
+                            sx,
+                            fy,
+                            &[
+                                ("M", &[(0.0, 0.0)]),
+                                (
+                                    "C",
+                                    &[
+                                        (0.2 * k, sign * 1.0 * k),
+                                        (1.3 * k, sign * 1.3 * k),
+                                        (0.8 * k, sign * 2.6 * k),
+                                    ],
+                                ),
+                            ],
+                        );
+                        self.stroke(&d, 0.2 * k);
+                    }
+                    if event.grace && event.flags() > 0 {
+                        self.line(
+                            (sx - 0.6 * s, tips[i] + 1.6 * s),
+                            (sx + 0.8 * s, tips[i] + 0.6 * s),
+                            0.1,
+                        );
+                    }
+                }
+            }
+            if let Some(symbol) = &event.symbol {
+                self.text(x - rx, top - 2.8 * s, symbol, 1.5, "start");
+            }
+            if event.tie {
+                ties.extend(event.heads.iter().zip(&offsets).map(|(head, offset)| Tie {
+                    x: x + offset + rx,
+                    step: head.step,
+                    up: up[i],
+                }));
+            }
+        }
+
+        for (members, actual) in tuplets(events) {
+            let (first, last) = (members[0], members[members.len() - 1]);
+            let highest = members
+                .iter()
+                .filter(|&&i| events[i].kind == Kind::Notes)
+                .map(|&i| {
+                    if up[i] {
+                        tips[i]
+                    } else {
+                        y(events[i].heads[events[i].heads.len() - 1].step)
+                    }
+                })
+                .fold(top, f32::min);
+            let x = (xs[first] + xs[last]) / 2.0;
+            self.text(x, highest - 0.8 * s, &actual.to_string(), 1.3, "middle");
+        }
+    }
+
+    /// Tie from a head to `to`, curved away from the stem
+    fn tie(&mut self, tie: &Tie, to: f32, top: f32, clef: Clef) {
+        let s = self.s;
+        let y = self.y(top, clef, tie.step);
+        let sign = if tie.up { 1.0 } else { -1.0 };
+        let (from, y) = (tie.x + 0.2 * s, y + sign * 0.6 * s);
+        let d = format!(
+            "M {from:.1} {y:.1} Q {:.1} {:.1} {to:.1} {y:.1}",
+            (from + to) / 2.0,
+            y + sign * 0.9 * s
+        );
+        self.svg.path(&d, self.color, 0.15 * s);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chord, ChordQuality, DrumSound, Note, PercussionNote, PitchClass, Tuplet};
+
+    fn note(class: PitchClass, octave: i8, base: DurationBase) -> Note {
+        Note::new(Tuning::new(class, octave)).with_duration(Duration::new(base))
+    }
+
+    #[test]
+    fn test_measure() {
+        let ts = TimeSignature::new(4, DurationBase::Quarter);
+        // Two beamed pairs, a sharp carried through the measure, a dotted middle C and the
+        // eighth rest filling the measure
+        let measure = Measure::Note(vec![
+            note(PitchClass::Fs, 4, DurationBase::Eighth),
+            note(PitchClass::G, 4, DurationBase::Eighth),
+            note(PitchClass::Fs, 4, DurationBase::Eighth),
+            note(PitchClass::A, 4, DurationBase::Eighth),
+            Note::new(Tuning::new(PitchClass::C, 4))
+                .with_duration(Duration::new(DurationBase::Quarter).dotted(1)),
+        ]);
+        let svg = StaffSvg::new().render_measure(&measure, &ts);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<ellipse").count(), 5);
+        assert_eq!(svg.matches("<polygon").count(), 2);
+        // Staff lines, one sharp, the ledger line of C, five stems, the rest and the bar line
+        assert_eq!(svg.matches("<line").count(), 5 + 4 + 1 + 5 + 1 + 1);
+        // Treble clef, augmentation dot and the rest
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches(">4</text>").count(), 2);
+    }
+
+    #[test]
+    fn test_score_clefs_and_symbols() {
+        let mut score = Score::<3>::new()
+            .with_time_signature(3, DurationBase::Quarter)
+            .with_key_signature(KeySignature::new(-2));
+        let chord = Chord::new(Tuning::new(PitchClass::C, 3), ChordQuality::Minor7).unwrap();
+        let triplet = Tuplet::new(3, 2, DurationBase::Eighth).unwrap();
+        let eighth = Duration::new(DurationBase::Eighth).with_tuplet(triplet);
+        for _ in 0..12 {
+            score.push_measures([
+                Measure::Note(
+                    (0..3)
+                        .map(|_| Note::new(Tuning::new(PitchClass::D, 5)).with_duration(eighth))
+                        .collect(),
+                ),
+                Measure::Chords(vec![chord.clone()]),
+                Measure::Percussion(vec![
+                    PercussionNote::new(DrumSound::BassDrum),
+                    PercussionNote::new(DrumSound::ClosedHiHat).at(1.0),
+                ]),
+            ]);
+        }
+        let svg = StaffSvg::new()
+            .with_title("Etude")
+            .with_width(600.0)
+            .export(&score);
+        assert!(svg.contains(">Etude</text>"));
+        assert!(svg.contains(">Cm7</text>"));
+        assert!(svg.contains(">3</text>"));
+        // Wrapped into several systems of three staves
+        let staff_lines = svg.matches(r#"stroke-width="0.8""#).count();
+        assert!(
+            staff_lines > 15 && staff_lines.is_multiple_of(15),
+            "{staff_lines}"
+        );
+        // The low chords get a bass clef unless one is forced
+        assert!(svg.contains(r#"r="3.0""#), "bass clef");
+        let alto = StaffSvg::new().with_clef(Clef::Alto).export(&score);
+        assert!(!alto.contains(r#"r="3.0""#));
+    }
+
+    fn part(measures: &[Measure], ts: TimeSignature, clef: Clef) -> Part<'_> {
+        Part { measures, ts, clef }
+    }
+
+    #[test]
+    fn test_positions_per_clef() {
+        let pen = Pen {
+            svg: SvgWriter::new(100.0, 100.0),
+            s: 10.0,
+            color: "black",
+            font: "serif",
+        };
+        // Middle C below the treble staff, on the middle line of the alto staff and
+        // above the bass staff, with the top line at y = 0
+        let middle_c = step('C', 4);
+        assert_eq!(pen.y(0.0, Clef::Treble, middle_c), 50.0);
+        assert_eq!(pen.y(0.0, Clef::Alto, middle_c), 20.0);
+        assert_eq!(pen.y(0.0, Clef::Bass, middle_c), -10.0);
+        assert_eq!(pen.y(0.0, Clef::Treble, step('F', 5)), 0.0);
+        assert_eq!(pen.y(0.0, Clef::Bass, step('G', 2)), 40.0);
+    }
+
+    #[test]
+    fn test_key_signature_positions() {
+        let letters = |clef, fifths| {
+            key_steps(clef, &KeySignature::new(fifths))
+                .into_iter()
+                .map(|(step, alter)| {
+                    (
+                        "CDEFGAB".as_bytes()[step as usize % 7] as char,
+                        step / 7,
+                        alter,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let sharps = |clef| letters(clef, 4);
+        let flats = |clef| letters(clef, -4);
+        assert_eq!(
+            sharps(Clef::Treble),
+            vec![('F', 5, 1), ('C', 5, 1), ('G', 5, 1), ('D', 5, 1)]
+        );
+        assert_eq!(
+            sharps(Clef::Bass),
+            vec![('F', 3, 1), ('C', 3, 1), ('G', 3, 1), ('D', 3, 1)]
+        );
+        assert_eq!(
+            sharps(Clef::Alto),
+            vec![('F', 4, 1), ('C', 4, 1), ('G', 4, 1), ('D', 4, 1)]
+        );
+        assert_eq!(
+            flats(Clef::Treble),
+            vec![('B', 4, -1), ('E', 5, -1), ('A', 4, -1), ('D', 5, -1)]
+        );
+        assert_eq!(
+            flats(Clef::Bass),
+            vec![('B', 2, -1), ('E', 3, -1), ('A', 2, -1), ('D', 3, -1)]
+        );
+        assert_eq!(
+            flats(Clef::Alto),
+            vec![('B', 3, -1), ('E', 4, -1), ('A', 3, -1), ('D', 4, -1)]
+        );
+        assert!(key_steps(Clef::Treble, &KeySignature::default()).is_empty());
+    }
+
+    #[test]
+    fn test_ledger_lines() {
+        let count = |clef, letter, octave| ledgers(clef, step(letter, octave)).len();
+        assert_eq!(count(Clef::Treble, 'D', 4), 0);
+        assert_eq!(count(Clef::Treble, 'C', 4), 1);
+        assert_eq!(count(Clef::Treble, 'B', 3), 1);
+        assert_eq!(count(Clef::Treble, 'A', 3), 2);
+        assert_eq!(count(Clef::Treble, 'G', 5), 0);
+        assert_eq!(count(Clef::Treble, 'A', 5), 1);
+        assert_eq!(count(Clef::Treble, 'C', 6), 2);
+        assert_eq!(count(Clef::Bass, 'C', 4), 1);
+        assert_eq!(count(Clef::Bass, 'E', 2), 1);
+        assert_eq!(count(Clef::Alto, 'C', 4), 0);
+        // Ledger lines are drawn on line steps only
+        assert_eq!(ledgers(Clef::Treble, step('B', 3)), vec![step('C', 4)]);
+    }
+
+    #[test]
+    fn test_beams_follow_the_meter() {
+        let eighths = Measure::Note(
+            (0..6)
+                .map(|_| note(PitchClass::A, 4, DurationBase::Eighth))
+                .collect(),
+        );
+        let key = KeySignature::default();
+        let groups = |ts: TimeSignature| {
+            let measures = std::slice::from_ref(&eighths);
+            let part = part(measures, ts, Clef::Treble);
+            beams(&events(&eighths, &part, &key), &ts)
+        };
+        // Two groups of three in 6/8, three pairs in 3/4
+        assert_eq!(
+            groups(TimeSignature::new(6, DurationBase::Eighth)),
+            vec![vec![0, 1, 2], vec![3, 4, 5]]
+        );
+        assert_eq!(
+            groups(TimeSignature::new(3, DurationBase::Quarter)),
+            vec![vec![0, 1], vec![2, 3], vec![4, 5]]
+        );
+    }
+
+    #[test]
+    fn test_ties_and_tuplets() {
+        let key = KeySignature::default();
+        // Two chords in 3/4: the second one is an eighth tied over the middle beat
+        let ts = TimeSignature::new(3, DurationBase::Quarter);
+        let chord = Chord::new(Tuning::new(PitchClass::C, 4), ChordQuality::Major).unwrap();
+        let held = Measure::Chords(vec![chord.clone(), chord]);
+        let measures = std::slice::from_ref(&held);
+        let tied = events(&held, &part(measures, ts, Clef::Treble), &key);
+        assert_eq!(
+            tied.iter().map(|e| e.tie).collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+        assert!(tuplets(&tied).is_empty());
+        let svg = StaffSvg::new().render_measure(&held, &ts);
+        // One tie curve for each of the three heads
+        assert_eq!(svg.matches(" Q ").count(), 3);
+
+        let ts = TimeSignature::new(4, DurationBase::Quarter);
+        let triplet = Tuplet::new(3, 2, DurationBase::Eighth).unwrap();
+        let eighth = Duration::new(DurationBase::Eighth).with_tuplet(triplet);
+        let mut notes = (0..3)
+            .map(|_| Note::new(Tuning::new(PitchClass::E, 5)).with_duration(eighth))
+            .collect::<Vec<_>>();
+        notes.push(
+            Note::new(Tuning::new(PitchClass::E, 5))
+                .with_duration(Duration::new(DurationBase::Half).dotted(1)),
+        );
+        let measure = Measure::Note(notes);
+        let measures = std::slice::from_ref(&measure);
+        let events = events(&measure, &part(measures, ts, Clef::Treble), &key);
+        assert_eq!(tuplets(&events), vec![(vec![0, 1, 2], 3)]);
+        assert!(events.iter().all(|e| !e.tie));
+        let svg = StaffSvg::new().render_measure(&measure, &ts);
+        assert!(svg.contains(">3</text>"));
+        assert!(!svg.contains(" Q "));
+    }
+}
@@ -0,0 +1,797 @@
+//! ASCII guitar tablature
+//!
+//! Writes a melody or chord track as plain-text tab for a `StringedFretboard`:
+//! - notes fingered by the `MelodicFingeringOptimizer`, chords by the
+//!   `ChordFingeringGenerator` through the `VoiceLeadingOptimizer`
+//! - notes slurred on one string become hammer-ons and pull-offs
+//! - technique marks `h`, `p`, `/` and `\` before the fret, bends as `7b9`, harmonics
+//!   as `<12>`
+//! - columns spaced by duration, bar lines, and systems wrapped at a line width
+//!
+//! Reads tab back from text, for any number of strings tuned by the labels left of the
+//! bar lines, into fingerings timed by their columns and a `Track` of notes.
+
+use super::layout::{layout, Content};
+use crate::{
+    ChordFingeringGenerator, Duration, DurationBase, FingerPosition, Fingering, Fretboard,
+    FretboardResult, InstrumentPresets, Measure, MelodicFingeringOptimizer, MusicError, Note,
+    PlayingTechnique, Score, SlurMark, StringedFretboard, StringedInstrumentConfig,
+    StringedPosition, TimeSignature, Track, Tuning, VoiceLeadingOptimizer,
+};
+use std::collections::BTreeMap;
+
+/// One column of a tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabEvent {
+    /// Strings struck, a rest when `None`
+    pub fingering: Option<Fingering<StringedPosition>>,
+    pub duration: Duration,
+    /// Holds the previous event instead of striking again
+    pub continued: bool,
+}
+
+impl TabEvent {
+    pub fn new(fingering: Fingering<StringedPosition>, duration: Duration) -> Self {
+        TabEvent {
+            fingering: Some(fingering),
+            duration,
+            continued: false,
+        }
+    }
+
+    pub fn rest(duration: Duration) -> Self {
+        TabEvent {
+            fingering: None,
+            duration,
+            continued: false,
+        }
+    }
+}
+
+/// Fingered measures of a track
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tablature {
+    pub measures: Vec<Vec<TabEvent>>,
+}
+
+impl Tablature {
+    /// Finger the notes and chords of a track on a fretboard
+    ///
+    /// Fails when a note or chord cannot be played on the fretboard.
+    pub fn from_track<const N: usize>(
+        score: &Score<N>,
+        track: usize,
+        fretboard: &StringedFretboard,
+    ) -> FretboardResult<Self> {
+        let ts = score.track_time_signature(track);
+        let rest = Measure::Rest;
+        let measures = score.get_tracks()[track].get_measures();
+        let slots = (0..score.measure_count())
+            .map(|m| layout(measures.get(m).unwrap_or(&rest), &ts))
+            .collect::<Vec<_>>();
+
+        let mut melody = vec![];
+        let mut chords = vec![];
+        for slot in slots.iter().flatten() {
+            match &slot.content {
+                Content::Note(note, tuning) if !note.is_grace() => melody.push(*tuning),
+                Content::Chord(chord) if !slot.continued => chords.push((*chord).clone()),
+                _ => {}
+            }
+        }
+        let mut notes = MelodicFingeringOptimizer::new()
+            .optimize_melody(fretboard, &melody)?
+            .into_iter();
+        let mut chords = VoiceLeadingOptimizer::new()
+            .optimize_progression(fretboard, &chords, &ChordFingeringGenerator::new())?
+            .into_iter();
+
+        let mut previous: Option<StringedPosition> = None;
+        let mut slurred = false;
+        let mut tab = Tablature::default();
+        for measure in &slots {
+            let mut events = vec![];
+            for slot in measure {
+                let event = match &slot.content {
+                    Content::Note(note, _) if !note.is_grace() => {
+                        let position = notes.next().unwrap();
+                        let technique = match &previous {
+                            Some(p) if slurred && p.string == position.string => {
+                                match position.fret.cmp(&p.fret) {
+                                    std::cmp::Ordering::Greater => PlayingTechnique::Hammer,
+                                    std::cmp::Ordering::Less => PlayingTechnique::Pull,
+                                    std::cmp::Ordering::Equal => PlayingTechnique::Standard,
+                                }
+                            }
+                            _ => PlayingTechnique::Standard,
+                        };
+                        match note.expression().slur {
+                            Some(SlurMark::Start) => slurred = true,
+                            Some(SlurMark::Stop) => slurred = false,
+                            None => {}
+                        }
+                        previous = Some(position.clone());
+                        let finger = FingerPosition::new(position, None, 1.0);
+                        TabEvent::new(Fingering::new(vec![finger], technique, 0.0), slot.duration)
+                    }
+                    Content::Chord(_) if slot.continued => TabEvent {
+                        continued: true,
+                        ..TabEvent::rest(slot.duration)
+                    },
+                    Content::Chord(_) => TabEvent::new(chords.next().unwrap(), slot.duration),
+                    _ => TabEvent::rest(slot.duration),
+                };
+                events.push(event);
+            }
+            tab.measures.push(events);
+        }
+        Ok(tab)
+    }
+
+    /// Notes of the tab, the highest string sounding when an event strikes several
+    pub fn to_track(&self, fretboard: &StringedFretboard) -> Track {
+        let mut track = Track::new();
+        for events in &self.measures {
+            let notes = events
+                .iter()
+                .map(|event| {
+                    let tuning = event
+                        .fingering
+                        .as_ref()
+                        .filter(|_| !event.continued)
+                        .and_then(|fingering| {
+                            fingering
+                                .positions
+                                .iter()
+                                .filter_map(|p| sounding(fretboard, fingering, &p.position))
+                                .max_by_key(|t| t.number())
+                        });
+                    match tuning {
+                        Some(tuning) => Note::new(tuning).with_duration(event.duration),
+                        None => Note::rest(event.duration),
+                    }
+                })
+                .collect::<Vec<_>>();
+            track.push(match notes.iter().all(|n| n.is_rest()) {
+                true => Measure::Rest,
+                false => Measure::Note(notes),
+            });
+        }
+        track
+    }
+}
+
+/// Tab read from text, see `Tab::import`
+#[derive(Debug, Clone)]
+pub struct TabImport {
+    /// Instrument tuned from the string labels
+    pub fretboard: StringedFretboard,
+    pub tablature: Tablature,
+}
+
+impl TabImport {
+    /// Notes of the tab, see `Tablature::to_track`
+    pub fn track(&self) -> Track {
+        self.tablature.to_track(&self.fretboard)
+    }
+}
+
+/// Plain-text tab writer, see the module documentation
+#[derive(Debug, Clone)]
+pub struct Tab {
+    width: usize,
+    spacing: usize,
+    time_signature: TimeSignature,
+}
+
+impl Default for Tab {
+    fn default() -> Self {
+        Tab {
+            width: 80,
+            spacing: 4,
+            time_signature: TimeSignature::new(4, DurationBase::Quarter),
+        }
+    }
+}
+
+impl Tab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Longest line of a system before the measures wrap
+    pub fn with_width(self, width: usize) -> Self {
+        Tab { width, ..self }
+    }
+
+    /// Characters per quarter note
+    pub fn with_spacing(self, spacing: usize) -> Self {
+        Tab {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Meter of imported tabs, 4/4 by default
+    pub fn with_time_signature(self, time_signature: TimeSignature) -> Self {
+        Tab {
+            time_signature,
+            ..self
+        }
+    }
+
+    /// Tab of a track fingered on `fretboard`
+    pub fn export<const N: usize>(
+        &self,
+        score: &Score<N>,
+        track: usize,
+        fretboard: &StringedFretboard,
+    ) -> FretboardResult<String> {
+        let tab = Tablature::from_track(score, track, fretboard)?;
+        Ok(self.render(&tab, fretboard))
+    }
+
+    /// Read plain-text tab, `e|---0---3--|` style
+    ///
+    /// The open strings are tuned from the labels left of the first bar line. Labels
+    /// without an octave are matched against the instrument presets, a string naming
+    /// another note than the preset's takes the octave closest to it (e.g. drop D). Lines
+    /// that are not tab, like chord names or lyrics, separate the systems. Events are
+    /// timed by their columns between the bar lines, on a sixteenth grid.
+    pub fn import(&self, text: &str) -> Result<TabImport, MusicError> {
+        let mut systems = vec![];
+        let mut system = vec![];
+        for line in text.lines() {
+            match tab_line(line) {
+                Some(line) => system.push(line),
+                None if !system.is_empty() => systems.push(std::mem::take(&mut system)),
+                None => {}
+            }
+        }
+        if !system.is_empty() {
+            systems.push(system);
+        }
+
+        let first = systems
+            .first()
+            .ok_or_else(|| MusicError::ParseError("no tab lines found".into()))?;
+        // The top line is the highest string
+        let labels = first
+            .iter()
+            .rev()
+            .map(|(label, _)| label.and_then(label_tuning))
+            .collect::<Vec<_>>();
+        let fretboard = StringedFretboard::new(instrument(&labels)?)
+            .map_err(|e| MusicError::ParseError(e.to_string()))?;
+
+        let mut tablature = Tablature::default();
+        for system in &systems {
+            if system.len() != labels.len() {
+                return Err(MusicError::ParseError(format!(
+                    "tab system of {} strings, expected {}",
+                    system.len(),
+                    labels.len()
+                )));
+            }
+            let strings = system
+                .iter()
+                .rev()
+                .map(|(_, content)| content.split('|').collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            let count = strings.iter().map(|s| s.len()).min().unwrap_or(0);
+            for m in 0..count {
+                let measure = strings.iter().map(|s| s[m]).collect::<Vec<_>>();
+                if measure.iter().all(|s| s.trim().is_empty()) {
+                    continue;
+                }
+                tablature.measures.push(self.read_measure(&measure));
+            }
+        }
+        Ok(TabImport {
+            fretboard,
+            tablature,
+        })
+    }
+
+    /// Events of one measure, the lowest string first
+    fn read_measure(&self, strings: &[&str]) -> Vec<TabEvent> {
+        let mut onsets: BTreeMap<usize, Vec<(StringedPosition, PlayingTechnique)>> =
+            BTreeMap::new();
+        for (string, text) in strings.iter().enumerate() {
+            for (column, fret, technique) in string_onsets(text) {
+                let position = StringedPosition::new(string as u32, fret);
+                onsets
+                    .entry(column)
+                    .or_default()
+                    .push((position, technique));
+            }
+        }
+
+        let quarters = self.time_signature.measure_quarters();
+        let length = strings.iter().map(|s| s.chars().count()).max().unwrap_or(0);
+        let first = onsets.keys().next().copied().unwrap_or(length);
+        // A dash or two ahead of the first fret only pads the bar line
+        let start = if first <= 2 { first } else { 0 };
+        let span = length.saturating_sub(start).max(1) as f32;
+        // The last sixteenth of the measure is the latest onset
+        let last = (quarters - 0.25).max(0.0);
+        let time = |column: usize| {
+            let t = (column.saturating_sub(start)) as f32 / span * quarters;
+            ((t * 4.0).round() / 4.0).min(last)
+        };
+
+        // Columns that round to the same sixteenth are struck together, one fret a string
+        let mut chords: Vec<(f32, Vec<(StringedPosition, PlayingTechnique)>)> = vec![];
+        for (column, positions) in onsets {
+            let t = time(column);
+            match chords.last_mut() {
+                Some((onset, chord)) if *onset == t => {
+                    for (position, technique) in positions {
+                        if chord.iter().all(|(p, _)| p.string != position.string) {
+                            chord.push((position, technique));
+                        }
+                    }
+                }
+                _ => chords.push((t, positions)),
+            }
+        }
+
+        let mut events = rests(chords.first().map_or(quarters, |(onset, _)| *onset));
+        for (i, (onset, positions)) in chords.iter().enumerate() {
+            let end = chords.get(i + 1).map_or(quarters, |(next, _)| *next);
+            let mut values = values(end - onset).into_iter();
+            let technique = positions
+                .iter()
+                .map(|(_, technique)| technique.clone())
+                .find(|technique| *technique != PlayingTechnique::Standard)
+                .unwrap_or(PlayingTechnique::Standard);
+            let fingers = positions
+                .iter()
+                .map(|(position, _)| FingerPosition::new(position.clone(), None, 1.0))
+                .collect();
+            let duration = values
+                .next()
+                .unwrap_or(Duration::new(DurationBase::Sixteenth));
+            events.push(TabEvent::new(
+                Fingering::new(fingers, technique, 0.0),
+                duration,
+            ));
+            events.extend(values.map(TabEvent::rest));
+        }
+        events
+    }
+
+    /// Text of fingered measures, the highest string on top
+    pub fn render(&self, tab: &Tablature, fretboard: &StringedFretboard) -> String {
+        let strings = fretboard.string_count();
+        let labels = string_labels(fretboard);
+        let mut last_fret = vec![0; strings];
+        let measures = tab
+            .measures
+            .iter()
+            .map(|events| self.measure(events, strings, &mut last_fret))
+            .collect::<Vec<_>>();
+
+        let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+        let mut systems = vec![];
+        let mut system: Vec<&Vec<String>> = vec![];
+        let mut length = label_width + 1;
+        for measure in &measures {
+            let measure_length = measure.first().map_or(0, |line| line.len());
+            if !system.is_empty() && length + measure_length > self.width {
+                systems.push(std::mem::take(&mut system));
+                length = label_width + 1;
+            }
+            system.push(measure);
+            length += measure_length;
+        }
+        if !system.is_empty() {
+            systems.push(system);
+        }
+
+        systems
+            .iter()
+            .map(|system| {
+                (0..strings)
+                    .rev()
+                    .map(|string| {
+                        let mut line = format!("{:<label_width$}|", labels[string]);
+                        for measure in system {
+                            line.push_str(&measure[string]);
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            + "\n"
+    }
+
+    /// One line per string of a measure, ending with its bar line
+    fn measure(&self, events: &[TabEvent], strings: usize, last_fret: &mut [u32]) -> Vec<String> {
+        let mut lines = vec![String::from("-"); strings];
+        for event in events {
+            let mut cells = vec![String::new(); strings];
+            if let Some(fingering) = event.fingering.as_ref().filter(|_| !event.continued) {
+                for finger in &fingering.positions {
+                    let string = finger.position.string as usize;
+                    let fret = finger.position.fret;
+                    if string >= strings {
+                        continue;
+                    }
+                    cells[string] = match fingering.technique {
+                        PlayingTechnique::Hammer => format!("h{fret}"),
+                        PlayingTechnique::Pull => format!("p{fret}"),
+                        PlayingTechnique::Slide if fret >= last_fret[string] => format!("/{fret}"),
+                        PlayingTechnique::Slide => format!("\\{fret}"),
+                        PlayingTechnique::Harmonic => format!("<{fret}>"),
+                        PlayingTechnique::Bend { semitones } => {
+                            format!("{fret}b{}", fret + semitones)
+                        }
+                        _ => fret.to_string(),
+                    };
+                    last_fret[string] = fret;
+                }
+            }
+            let text = cells.iter().map(|c| c.len()).max().unwrap_or(0);
+            let spaced = (event.duration.in_quarters() * self.spacing as f32).round() as usize;
+            let width = spaced.max(text + 1);
+            for (line, cell) in lines.iter_mut().zip(&cells) {
+                line.push_str(cell);
+                line.push_str(&"-".repeat(width - cell.len()));
+            }
+        }
+        for line in &mut lines {
+            line.push('|');
+        }
+        lines
+    }
+}
+
+/// Names of the open strings, lower case for a string sharing its name with a lower one
+fn string_labels(fretboard: &StringedFretboard) -> Vec<String> {
+    let names = (0..fretboard.string_count())
+        .map(|s| {
+            fretboard
+                .string_tuning(s)
+                .map_or(String::new(), |t| t.to_string())
+        })
+        .collect::<Vec<_>>();
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match names[..i].contains(name) {
+            true => name.to_lowercase(),
+            false => name.clone(),
+        })
+        .collect()
+}
+
+/// Label and the text after the first bar line of a line of tab
+fn tab_line(line: &str) -> Option<(Option<&str>, &str)> {
+    let line = line.trim();
+    let bar = line.find('|')?;
+    let (label, content) = (line[..bar].trim(), &line[bar + 1..]);
+    if !label.is_empty() && label_tuning(label).is_none() {
+        return None;
+    }
+    let dashes = content.chars().filter(|&c| c == '-').count();
+    let length = content.trim().chars().count();
+    (dashes >= 3 && dashes * 2 >= length).then_some(((!label.is_empty()).then_some(label), content))
+}
+
+/// Open string of a label like `e`, `Bb` or `E2`, and whether it names the octave
+fn label_tuning(label: &str) -> Option<(Tuning, bool)> {
+    let mut chars = label.chars();
+    let text = chars.next()?.to_ascii_uppercase().to_string() + chars.as_str();
+    let mut chars = text.chars().peekable();
+    let tuning = Tuning::take(&mut chars).ok()?;
+    let octave = chars.collect::<String>();
+    match octave.is_empty() {
+        true => Some((tuning, false)),
+        false => Some((tuning.with_octave(octave.parse().ok()?), true)),
+    }
+}
+
+/// Instrument of the string labels, the lowest string first
+fn instrument(labels: &[Option<(Tuning, bool)>]) -> Result<StringedInstrumentConfig, MusicError> {
+    let presets = InstrumentPresets::list_presets()
+        .iter()
+        .filter_map(|name| InstrumentPresets::get_stringed_preset(name))
+        .filter(|preset| preset.strings.len() == labels.len())
+        .collect::<Vec<_>>();
+    let matches = |preset: &&StringedInstrumentConfig| {
+        preset.strings.iter().zip(labels).all(|(open, label)| {
+            label.is_none_or(|(tuning, _)| tuning.class_semitones() == open.class_semitones())
+        })
+    };
+
+    if let Some(preset) = presets.iter().find(matches).or(presets.first()) {
+        let strings = preset
+            .strings
+            .iter()
+            .zip(labels)
+            .map(|(open, label)| match label {
+                Some((tuning, true)) => *tuning,
+                Some((tuning, false)) => (0..=8)
+                    .map(|octave| tuning.with_octave(octave))
+                    .min_by_key(|t| (t.number() as i32 - open.number() as i32).abs())
+                    .unwrap(),
+                None => *open,
+            })
+            .collect();
+        return Ok(StringedInstrumentConfig {
+            strings,
+            ..preset.clone()
+        });
+    }
+
+    // Rising from the second octave
+    let mut strings: Vec<Tuning> = vec![];
+    for label in labels {
+        let (tuning, octave) =
+            label.ok_or_else(|| MusicError::ParseError("unlabelled tab strings".into()))?;
+        let tuning = match (octave, strings.last()) {
+            (true, _) => tuning,
+            (false, None) => tuning.with_octave(2),
+            (false, Some(previous)) => {
+                let t = tuning.with_octave(previous.octave());
+                match t.number() > previous.number() {
+                    true => t,
+                    false => t.with_octave(previous.octave() + 1),
+                }
+            }
+        };
+        strings.push(tuning);
+    }
+    Ok(StringedInstrumentConfig::new(
+        strings, 24, 648.0, 43.0, 10.5,
+    ))
+}
+
+/// Column, fret and technique of each fret struck on a string
+fn string_onsets(text: &str) -> Vec<(usize, u32, PlayingTechnique)> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let number = |i: &mut usize| {
+        let start = *i;
+        while *i < chars.len() && *i - start < 2 && chars[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        chars[start..*i]
+            .iter()
+            .collect::<String>()
+            .parse::<u32>()
+            .ok()
+    };
+
+    let mut onsets = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let column = i;
+        let Some(fret) = number(&mut i) else {
+            break;
+        };
+        let mut technique = match column.checked_sub(1).map(|p| chars[p]) {
+            Some('h' | 'H') => PlayingTechnique::Hammer,
+            Some('p' | 'P') => PlayingTechnique::Pull,
+            Some('/' | '\\' | 's' | 'S') => PlayingTechnique::Slide,
+            Some('<') => PlayingTechnique::Harmonic,
+            _ => PlayingTechnique::Standard,
+        };
+        // A bend names its target fret and may release back, `7b9r7`
+        if chars.get(i) == Some(&'b') {
+            i += 1;
+            let semitones = number(&mut i).map_or(2, |target| target.saturating_sub(fret));
+            technique = PlayingTechnique::Bend { semitones };
+            if chars.get(i) == Some(&'r') {
+                i += 1;
+                number(&mut i);
+            }
+        }
+        onsets.push((column, fret, technique));
+    }
+    onsets
+}
+
+/// Pitch of a position, natural harmonics sounding above the fret they are touched at
+fn sounding(
+    fretboard: &StringedFretboard,
+    fingering: &Fingering<StringedPosition>,
+    position: &StringedPosition,
+) -> Option<Tuning> {
+    let stopped = match (&fingering.technique, position.fret) {
+        (PlayingTechnique::Harmonic, 12) => 12,
+        (PlayingTechnique::Harmonic, 7) => 19,
+        (PlayingTechnique::Harmonic, 5) => 24,
+        (_, fret) => fret,
+    };
+    fretboard
+        .tuning_at_position(&StringedPosition::new(position.string, stopped))
+        .or_else(|| fretboard.tuning_at_position(position))
+}
+
+/// Notated values filling `quarters`, longest first
+fn values(mut quarters: f32) -> Vec<Duration> {
+    let mut values = vec![];
+    while quarters > 1e-3 {
+        let value = [4.0, 3.0, 2.0, 1.5, 1.0, 0.75, 0.5, 0.375, 0.25]
+            .into_iter()
+            .find(|&v| v <= quarters + 1e-3)
+            .unwrap_or(0.25);
+        values.push(Duration::from_quarters(value));
+        quarters -= value;
+    }
+    values
+}
+
+/// Rests filling `quarters`
+fn rests(quarters: f32) -> Vec<TabEvent> {
+    values(quarters).into_iter().map(TabEvent::rest).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chord, ChordQuality, DurationBase, InstrumentPresets, Note, PitchClass, Tuning};
+
+    fn guitar() -> StringedFretboard {
+        StringedFretboard::new(InstrumentPresets::guitar_standard()).unwrap()
+    }
+
+    #[test]
+    fn test_melody() {
+        let eighth = Duration::new(DurationBase::Eighth);
+        let note = |class, octave| Note::new(Tuning::new(class, octave)).with_duration(eighth);
+        let mut score = Score::<1>::new();
+        score.push_measures([Measure::Note(vec![
+            note(PitchClass::C, 4).with_slur(SlurMark::Start),
+            note(PitchClass::D, 4).with_slur(SlurMark::Stop),
+            note(PitchClass::E, 4),
+            note(PitchClass::D, 4).with_slur(SlurMark::Start),
+            note(PitchClass::C, 4).with_slur(SlurMark::Stop),
+        ])]);
+        score.push_measures([Measure::Rest]);
+
+        let text = Tab::new().export(&score, 0, &guitar()).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "e|------0------------|-----------------|");
+        assert_eq!(lines[1], "B|-1-h3---3-p1-------|-----------------|");
+        assert_eq!(lines[5], "E|-------------------|-----------------|");
+    }
+
+    #[test]
+    fn test_chords_and_wrapping() {
+        let chord = |class| Chord::new(Tuning::new(class, 3), ChordQuality::Major).unwrap();
+        let mut score = Score::<1>::new();
+        for _ in 0..3 {
+            score.push_measures([Measure::Chords(vec![
+                chord(PitchClass::C),
+                chord(PitchClass::G),
+            ])]);
+        }
+
+        let text = Tab::new()
+            .with_width(40)
+            .export(&score, 0, &guitar())
+            .unwrap();
+        let systems = text.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(systems.len(), 2);
+        assert!(systems[0].lines().all(|l| l.matches('|').count() == 3));
+        let bass = systems[0].lines().nth(4).unwrap();
+        assert!(bass.starts_with("A|-3"), "{text}");
+
+        let harmonic = Tablature {
+            measures: vec![vec![TabEvent::new(
+                Fingering::new(
+                    vec![FingerPosition::open(StringedPosition::new(0, 12))],
+                    PlayingTechnique::Harmonic,
+                    0.0,
+                ),
+                Duration::new(DurationBase::Whole),
+            )]],
+        };
+        let text = Tab::new().render(&harmonic, &guitar());
+        assert!(text.contains("E|-<12>------------|"), "{text}");
+    }
+
+    #[test]
+    fn test_import() {
+        let text = "\
+Intro   Am
+e|-------0---------|--<12>-----------|
+B|-----1---1-------|-----------------|
+G|---2-------2-----|-----------------|
+D|-2-----------2h4-|-------7b9r7-----|
+A|-0---------------|-----------------|
+D|-----------------|-0---------------|
+
+e|-0---------------|
+B|-----------------|
+G|-----------------|
+D|-----------------|
+A|-----------------|
+D|-3/5-------------|
+";
+        let import = Tab::new().import(text).unwrap();
+        // Drop D from the labels
+        let low = import.fretboard.string_tuning(0).unwrap();
+        assert_eq!((low.class(), low.octave()), (PitchClass::D, 2));
+        let measures = &import.tablature.measures;
+        assert_eq!(measures.len(), 3);
+
+        let first = &measures[0][0];
+        let fingering = first.fingering.as_ref().unwrap();
+        let frets = fingering
+            .positions
+            .iter()
+            .map(|p| (p.position.string, p.position.fret))
+            .collect::<Vec<_>>();
+        assert_eq!(frets, vec![(1, 0), (2, 2)]);
+        assert_eq!(first.duration, Duration::new(DurationBase::Eighth));
+        let hammer = measures[0].last().unwrap().fingering.as_ref().unwrap();
+        assert_eq!(hammer.technique, PlayingTechnique::Hammer);
+        let bend = measures[1][2].fingering.as_ref().unwrap();
+        assert_eq!(bend.technique, PlayingTechnique::Bend { semitones: 2 });
+        let slide = measures[2][1].fingering.as_ref().unwrap();
+        assert_eq!(slide.technique, PlayingTechnique::Slide);
+
+        let track = import.track();
+        let Measure::Note(notes) = &track.get_measures()[1] else {
+            panic!("{:?}", measures[1]);
+        };
+        // The harmonic at the twelfth fret sounds an octave over the open string
+        assert_eq!(notes[1].tuning(), Some(Tuning::new(PitchClass::E, 5)));
+        assert_eq!(notes[2].tuning(), Some(Tuning::new(PitchClass::A, 3)));
+
+        // Frets a column apart in a wide measure share a sixteenth and strike together
+        let wide = format!("e|--0{}|\nB|---1{}|\n", "-".repeat(60), "-".repeat(59));
+        let import = Tab::new().import(&wide).unwrap();
+        let events = &import.tablature.measures[0];
+        let fingering = events[0].fingering.as_ref().unwrap();
+        assert_eq!(fingering.positions.len(), 2);
+        let total = events.iter().map(|e| e.duration.in_quarters()).sum::<f32>();
+        assert_eq!(total, 4.0);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let eighth = Duration::new(DurationBase::Eighth);
+        let note = |class, octave| Note::new(Tuning::new(class, octave)).with_duration(eighth);
+        let mut score = Score::<1>::new();
+        score.push_measures([Measure::Note(vec![
+            note(PitchClass::G, 3),
+            note(PitchClass::A, 3),
+            note(PitchClass::B, 3),
+            note(PitchClass::C, 4),
+            Note::new(Tuning::new(PitchClass::D, 4))
+                .with_duration(Duration::new(DurationBase::Half)),
+        ])]);
+        let bass = StringedFretboard::new(InstrumentPresets::bass_4_string()).unwrap();
+        let text = Tab::new().export(&score, 0, &guitar()).unwrap();
+        let import = Tab::new().import(&text).unwrap();
+        assert_eq!(import.fretboard.string_count(), 6);
+        let track = import.track();
+        let Measure::Note(notes) = &track.get_measures()[0] else {
+            panic!("{text}");
+        };
+        assert_eq!(notes.len(), 5);
+        assert_eq!(notes[3].tuning(), Some(Tuning::new(PitchClass::C, 4)));
+        assert_eq!(notes[4].duration(), Duration::new(DurationBase::Half));
+
+        let text = "G|----|\nD|-5--|\nA|----|\nE|-0--|";
+        let import = Tab::new().import(text).unwrap();
+        assert_eq!(import.fretboard.string_tuning(0), bass.string_tuning(0));
+        let track = import.track();
+        let Measure::Note(notes) = &track.get_measures()[0] else {
+            panic!();
+        };
+        assert_eq!(notes[0].tuning(), Some(Tuning::new(PitchClass::G, 2)));
+    }
+}
@@ -0,0 +1,188 @@
+//! Zip reader for compressed MusicXML (`.mxl`)
+//!
+//! Only what `.mxl` archives use: the central directory, stored and deflated entries.
+
+use crate::MusicError;
+
+/// Largest entry inflated, whatever size the archive declares
+const MAX_ENTRY_SIZE: usize = 64 << 20;
+
+struct Entry {
+    name: String,
+    method: u16,
+    compressed: usize,
+    uncompressed: usize,
+    header: usize,
+}
+
+pub(crate) struct Archive<'a> {
+    data: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(data: &'a [u8]) -> Result<Self, MusicError> {
+        let invalid = || MusicError::ParseError("invalid zip archive".into());
+        // End of central directory record, followed by at most 64 KiB of comment
+        let end = (0..=data.len().saturating_sub(22))
+            .rev()
+            .take(65536 + 22)
+            .find(|&i| u32_at(data, i) == Some(0x0605_4b50))
+            .ok_or_else(invalid)?;
+        let count = u16_at(data, end + 10).ok_or_else(invalid)? as usize;
+        let mut offset = u32_at(data, end + 16).ok_or_else(invalid)? as usize;
+
+        let mut entries = vec![];
+        for _ in 0..count {
+            if u32_at(data, offset) != Some(0x0201_4b50) {
+                return Err(invalid());
+            }
+            let field = |at: usize| u16_at(data, offset + at).ok_or_else(invalid);
+            let name_length = field(28)? as usize;
+            let skip = name_length + field(30)? as usize + field(32)? as usize;
+            let name = data
+                .get(offset + 46..offset + 46 + name_length)
+                .ok_or_else(invalid)?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: field(10)?,
+                compressed: u32_at(data, offset + 20).ok_or_else(invalid)? as usize,
+                uncompressed: u32_at(data, offset + 24).ok_or_else(invalid)? as usize,
+                header: u32_at(data, offset + 42).ok_or_else(invalid)? as usize,
+            });
+            offset += 46 + skip;
+        }
+        Ok(Archive { data, entries })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Uncompressed content of an entry, which must have the size the directory declares
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, MusicError> {
+        let invalid = || MusicError::ParseError(format!("cannot read {name} from the archive"));
+        let entry = self
+            .entries
+            .iter()
+            .find(|e| e.name == name)
+            .ok_or_else(invalid)?;
+        if u32_at(self.data, entry.header) != Some(0x0403_4b50) {
+            return Err(invalid());
+        }
+        let name_length = u16_at(self.data, entry.header + 26).ok_or_else(invalid)? as usize;
+        let extra_length = u16_at(self.data, entry.header + 28).ok_or_else(invalid)? as usize;
+        let start = entry.header + 30 + name_length + extra_length;
+        let content = self
+            .data
+            .get(start..start + entry.compressed)
+            .ok_or_else(invalid)?;
+        if entry.uncompressed > MAX_ENTRY_SIZE {
+            return Err(MusicError::ParseError(format!(
+                "{name} is too large to read from the archive"
+            )));
+        }
+        let content = match entry.method {
+            0 => content.to_vec(),
+            8 => miniz_oxide::inflate::decompress_to_vec_with_limit(content, entry.uncompressed)
+                .map_err(|_| invalid())?,
+            method => {
+                return Err(MusicError::ParseError(format!(
+                    "unsupported zip compression method {method}"
+                )));
+            }
+        };
+        if content.len() != entry.uncompressed {
+            return Err(invalid());
+        }
+        Ok(content)
+    }
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// Zip archive of deflated entries, for tests
+#[cfg(test)]
+pub(crate) fn deflated(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut data = vec![];
+    let mut directory = vec![];
+    for (name, content) in files {
+        let compressed = miniz_oxide::deflate::compress_to_vec(content, 6);
+        let header = data.len() as u32;
+        let sizes = [compressed.len() as u32, content.len() as u32];
+
+        data.extend(0x0403_4b50u32.to_le_bytes());
+        data.extend([20, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        sizes.iter().for_each(|s| data.extend(s.to_le_bytes()));
+        data.extend((name.len() as u16).to_le_bytes());
+        data.extend([0, 0]);
+        data.extend(name.as_bytes());
+        data.extend(&compressed);
+
+        directory.extend(0x0201_4b50u32.to_le_bytes());
+        directory.extend([20, 0, 20, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        sizes.iter().for_each(|s| directory.extend(s.to_le_bytes()));
+        directory.extend((name.len() as u16).to_le_bytes());
+        directory.extend([0; 12]);
+        directory.extend(header.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let offset = data.len() as u32;
+    let count = files.len() as u16;
+    data.extend(&directory);
+    data.extend(0x0605_4b50u32.to_le_bytes());
+    data.extend([0, 0, 0, 0]);
+    data.extend(count.to_le_bytes());
+    data.extend(count.to_le_bytes());
+    data.extend((directory.len() as u32).to_le_bytes());
+    data.extend(offset.to_le_bytes());
+    data.extend([0, 0]);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_archive() {
+        let data = deflated(&[("a.txt", b"hello hello hello"), ("b/c.xml", b"<x/>")]);
+        let archive = Archive::new(&data).unwrap();
+        assert_eq!(
+            archive.names().collect::<Vec<_>>(),
+            vec!["a.txt", "b/c.xml"]
+        );
+        assert_eq!(archive.read("b/c.xml").unwrap(), b"<x/>");
+        assert_eq!(archive.read("a.txt").unwrap(), b"hello hello hello");
+        assert!(archive.read("missing").is_err());
+        assert!(Archive::new(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_declared_size() {
+        let zeros = vec![0; 4096];
+        let data = deflated(&[("a.txt", &zeros)]);
+        let directory = (0..data.len())
+            .find(|&i| u32_at(&data, i) == Some(0x0201_4b50))
+            .unwrap();
+        let declare = |size: u32| {
+            let mut data = data.clone();
+            data[directory + 24..directory + 28].copy_from_slice(&size.to_le_bytes());
+            data
+        };
+        assert_eq!(Archive::new(&data).unwrap().read("a.txt").unwrap(), zeros);
+        // Inflating stops at the declared size
+        let small = declare(16);
+        assert!(Archive::new(&small).unwrap().read("a.txt").is_err());
+        let large = declare(8192);
+        assert!(Archive::new(&large).unwrap().read("a.txt").is_err());
+        let huge = declare(u32::MAX);
+        assert!(Archive::new(&huge).unwrap().read("a.txt").is_err());
+    }
+}
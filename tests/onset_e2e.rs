@@ -8,7 +8,7 @@ use std::f32::consts::PI;
 fn synthesize_notes(notes: &[Note], tempo: f32, sample_rate: f32) -> Vec<f32> {
     let mut samples = Vec::new();
     for note in notes {
-        let freq = note.tuning().frequency();
+        let freq = note.tuning().map_or(0.0, |t| t.frequency());
         let dur_secs = note.duration().in_seconds(tempo);
         let n = (sample_rate * dur_secs) as usize;
         let attack = (sample_rate * 0.002) as usize; // 2ms attack